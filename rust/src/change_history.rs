@@ -0,0 +1,284 @@
+//! Persistent undo/redo history of applied queries.
+//!
+//! Every query applied by `clink` is stored together with the link and name
+//! changes recorded by `ChangeRecorder` in `<stem>.history.json` next to the
+//! database. `undo` reverts entries most recent first and keeps them for
+//! `redo` until a new query is recorded.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::change_recorder::{apply_changes, revert_changes, StorageChange};
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::named_type_links::NamedTypeLinks;
+
+const HISTORY_FORMAT_VERSION: u64 = 1;
+
+/// A query together with the changes it applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry<T: LinkAddress = u32> {
+    pub query: String,
+    pub changes: Vec<StorageChange<T>>,
+}
+
+/// Applied and undone queries of a database.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeHistory<T: LinkAddress = u32> {
+    path: PathBuf,
+    limit: usize,
+    applied: Vec<HistoryEntry<T>>,
+    undone: Vec<HistoryEntry<T>>,
+}
+
+impl ChangeHistory {
+    /// Returns `<stem>.history.json` next to the database file.
+    pub fn make_history_filename<P: AsRef<Path>>(database_filename: P) -> PathBuf {
+        let path = database_filename.as_ref();
+        let filename_without_extension = path
+            .file_stem()
+            .and_then(|value| value.to_str())
+            .unwrap_or_default();
+        path.with_file_name(format!("{filename_without_extension}.history.json"))
+    }
+
+    /// Removes the history of a database whose links were rewritten without
+    /// recording changes, such as by `compact` or a snapshot restore; its
+    /// entries would no longer revert cleanly.
+    pub fn discard<P: AsRef<Path>>(database_filename: P) -> Result<()> {
+        let path = Self::make_history_filename(database_filename);
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove history file: {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: LinkAddress> ChangeHistory<T> {
+    /// Number of applied queries kept by default.
+    pub const DEFAULT_LIMIT: usize = 100;
+
+    /// Loads the history file, or starts an empty history if it is missing.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut history = Self {
+            path,
+            limit: Self::DEFAULT_LIMIT,
+            applied: Vec::new(),
+            undone: Vec::new(),
+        };
+        if !history.path.exists() {
+            return Ok(history);
+        }
+
+        let content = fs::read_to_string(&history.path)
+            .with_context(|| format!("Failed to read history file: {}", history.path.display()))?;
+        let document: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse history file: {}", history.path.display()))?;
+        if document["version"].as_u64() != Some(HISTORY_FORMAT_VERSION) {
+            bail!(
+                "Unsupported history file version in {}.",
+                history.path.display()
+            );
+        }
+        history.applied = entries_from_json(&document["applied"])?;
+        history.undone = entries_from_json(&document["undone"])?;
+        Ok(history)
+    }
+
+    /// Sets how many applied queries are kept; older ones are forgotten.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self.trim();
+        self
+    }
+
+    pub fn applied(&self) -> &[HistoryEntry<T>] {
+        &self.applied
+    }
+
+    pub fn undone(&self) -> &[HistoryEntry<T>] {
+        &self.undone
+    }
+
+    /// Records an applied query. Queries without changes are not recorded;
+    /// any other query discards the entries available for redo.
+    pub fn record(&mut self, entry: HistoryEntry<T>) {
+        if entry.changes.is_empty() {
+            return;
+        }
+        self.undone.clear();
+        self.applied.push(entry);
+        self.trim();
+    }
+
+    /// Reverts up to `count` applied queries, most recent first, and returns
+    /// the reverted entries. An entry that cannot be reverted stays in the
+    /// history; the storage may then hold part of its reverted changes and
+    /// should not be saved.
+    pub fn undo(
+        &mut self,
+        storage: &mut impl NamedTypeLinks<T>,
+        count: usize,
+    ) -> Result<Vec<HistoryEntry<T>>> {
+        let mut reverted = Vec::new();
+        for _ in 0..count {
+            let Some(entry) = self.applied.last() else {
+                break;
+            };
+            revert_changes(storage, &entry.changes)
+                .with_context(|| format!("Cannot undo '{}'", entry.query))?;
+            let entry = self.applied.pop().expect("entry was just inspected");
+            reverted.push(entry.clone());
+            self.undone.push(entry);
+        }
+        Ok(reverted)
+    }
+
+    /// Re-applies up to `count` undone queries in their original order and
+    /// returns them.
+    pub fn redo(
+        &mut self,
+        storage: &mut impl NamedTypeLinks<T>,
+        count: usize,
+    ) -> Result<Vec<HistoryEntry<T>>> {
+        let mut reapplied = Vec::new();
+        for _ in 0..count {
+            let Some(entry) = self.undone.last() else {
+                break;
+            };
+            apply_changes(storage, &entry.changes)
+                .with_context(|| format!("Cannot redo '{}'", entry.query))?;
+            let entry = self.undone.pop().expect("entry was just inspected");
+            reapplied.push(entry.clone());
+            self.applied.push(entry);
+        }
+        Ok(reapplied)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let document = json!({
+            "version": HISTORY_FORMAT_VERSION,
+            "applied": self.applied.iter().map(entry_to_json).collect::<Vec<_>>(),
+            "undone": self.undone.iter().map(entry_to_json).collect::<Vec<_>>(),
+        });
+        fs::write(&self.path, serde_json::to_string_pretty(&document)?)
+            .with_context(|| format!("Failed to write history file: {}", self.path.display()))
+    }
+
+    fn trim(&mut self) {
+        if self.applied.len() > self.limit {
+            let excess = self.applied.len() - self.limit;
+            self.applied.drain(..excess);
+        }
+    }
+}
+
+fn entry_to_json<T: LinkAddress>(entry: &HistoryEntry<T>) -> Value {
+    let changes = entry
+        .changes
+        .iter()
+        .map(|change| match change {
+            StorageChange::Link { before, after } => json!({
+                "link": { "before": link_to_json(before), "after": link_to_json(after) }
+            }),
+            StorageChange::Name {
+                link,
+                before,
+                after,
+            } => json!({
+                "name": { "link": (*link).into(), "before": before, "after": after }
+            }),
+            StorageChange::Alias { link, alias, added } => json!({
+                "alias": { "link": (*link).into(), "alias": alias, "added": added }
+            }),
+        })
+        .collect::<Vec<_>>();
+    json!({ "query": entry.query, "changes": changes })
+}
+
+fn link_to_json<T: LinkAddress>(link: &Option<Doublet<T>>) -> Value {
+    match link {
+        Some(link) => json!([link.index.into(), link.source.into(), link.target.into()]),
+        None => Value::Null,
+    }
+}
+
+fn entries_from_json<T: LinkAddress>(value: &Value) -> Result<Vec<HistoryEntry<T>>> {
+    let Some(entries) = value.as_array() else {
+        return Ok(Vec::new());
+    };
+    entries.iter().map(entry_from_json).collect()
+}
+
+fn entry_from_json<T: LinkAddress>(value: &Value) -> Result<HistoryEntry<T>> {
+    let query = value["query"]
+        .as_str()
+        .context("History entry has no query.")?
+        .to_string();
+    let changes = value["changes"]
+        .as_array()
+        .context("History entry has no changes.")?
+        .iter()
+        .map(change_from_json)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(HistoryEntry { query, changes })
+}
+
+fn change_from_json<T: LinkAddress>(value: &Value) -> Result<StorageChange<T>> {
+    if let Some(link) = value.get("link") {
+        return Ok(StorageChange::Link {
+            before: link_from_json(&link["before"])?,
+            after: link_from_json(&link["after"])?,
+        });
+    }
+    if let Some(name) = value.get("name") {
+        let link = name["link"]
+            .as_u64()
+            .and_then(|link| T::try_from(link).ok())
+            .context("Name change has no link.")?;
+        return Ok(StorageChange::Name {
+            link,
+            before: name["before"].as_str().map(str::to_string),
+            after: name["after"].as_str().map(str::to_string),
+        });
+    }
+    if let Some(alias) = value.get("alias") {
+        let link = alias["link"]
+            .as_u64()
+            .and_then(|link| T::try_from(link).ok())
+            .context("Alias change has no link.")?;
+        return Ok(StorageChange::Alias {
+            link,
+            alias: alias["alias"]
+                .as_str()
+                .context("Alias change has no alias.")?
+                .to_string(),
+            added: alias["added"]
+                .as_bool()
+                .context("Alias change does not say whether it was added.")?,
+        });
+    }
+    bail!("Unknown history change: {value}")
+}
+
+fn link_from_json<T: LinkAddress>(value: &Value) -> Result<Option<Doublet<T>>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let parts = value
+        .as_array()
+        .filter(|parts| parts.len() == 3)
+        .context("A link change must be [index, source, target].")?
+        .iter()
+        .map(|part| {
+            part.as_u64()
+                .and_then(|part| T::try_from(part).ok())
+                .context("A link change must contain link addresses.")
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(Doublet::new(parts[0], parts[1], parts[2])))
+}
@@ -0,0 +1,341 @@
+//! Recording of link and name changes so that they can be reverted.
+//!
+//! `ChangeRecorder` wraps any `NamedTypeLinks` storage and logs every
+//! mutation that passes through it, including the names implicitly moved or
+//! removed by `set_name` and `delete`. The recorded changes can be reverted or
+//! re-applied with `revert_changes` and `apply_changes`, which fail instead of
+//! overwriting links or names that were changed in the meantime.
+
+use anyhow::{bail, Result};
+
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::named_type_links::NamedTypeLinks;
+
+/// A single mutation of a links database or of its names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageChange<T: LinkAddress = u32> {
+    /// A link was created (`before` is `None`), updated, or deleted (`after`
+    /// is `None`).
+    Link {
+        before: Option<Doublet<T>>,
+        after: Option<Doublet<T>>,
+    },
+    /// The name of a link was set, replaced or removed.
+    Name {
+        link: T,
+        before: Option<String>,
+        after: Option<String>,
+    },
+    /// An alias was added to a link or removed from it.
+    Alias { link: T, alias: String, added: bool },
+}
+
+impl<T: LinkAddress> StorageChange<T> {
+    /// Returns the change that undoes this one.
+    pub fn inverted(&self) -> Self {
+        match self {
+            StorageChange::Link { before, after } => StorageChange::Link {
+                before: *after,
+                after: *before,
+            },
+            StorageChange::Name {
+                link,
+                before,
+                after,
+            } => StorageChange::Name {
+                link: *link,
+                before: after.clone(),
+                after: before.clone(),
+            },
+            StorageChange::Alias { link, alias, added } => StorageChange::Alias {
+                link: *link,
+                alias: alias.clone(),
+                added: !added,
+            },
+        }
+    }
+}
+
+/// A `NamedTypeLinks` wrapper that records every change applied through it.
+pub struct ChangeRecorder<'a, S: NamedTypeLinks<T>, T: LinkAddress = u32> {
+    storage: &'a mut S,
+    changes: Vec<StorageChange<T>>,
+}
+
+impl<'a, S: NamedTypeLinks<T>, T: LinkAddress> ChangeRecorder<'a, S, T> {
+    pub fn new(storage: &'a mut S) -> Self {
+        Self {
+            storage,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Returns the changes recorded so far in the order they were applied.
+    pub fn changes(&self) -> &[StorageChange<T>] {
+        &self.changes
+    }
+
+    pub fn into_changes(self) -> Vec<StorageChange<T>> {
+        self.changes
+    }
+
+    fn record_link(&mut self, before: Option<Doublet<T>>, after: Option<Doublet<T>>) {
+        if before != after {
+            self.changes.push(StorageChange::Link { before, after });
+        }
+    }
+
+    fn record_name(&mut self, link: T, before: Option<String>, after: Option<String>) {
+        if before != after {
+            self.changes.push(StorageChange::Name {
+                link,
+                before,
+                after,
+            });
+        }
+    }
+
+    fn record_alias(&mut self, link: T, alias: &str, added: bool) {
+        self.changes.push(StorageChange::Alias {
+            link,
+            alias: alias.to_string(),
+            added,
+        });
+    }
+}
+
+impl<S: NamedTypeLinks<T>, T: LinkAddress> NamedTypeLinks<T> for ChangeRecorder<'_, S, T> {
+    fn create(&mut self, source: T, target: T) -> T {
+        let id = self.storage.create(source, target);
+        let after = self.storage.get_link(id);
+        self.record_link(None, after);
+        id
+    }
+
+    fn ensure_created(&mut self, id: T) -> T {
+        let before = self.storage.get_link(id);
+        let id = self.storage.ensure_created(id);
+        let after = self.storage.get_link(id);
+        self.record_link(before, after);
+        id
+    }
+
+    fn get_link(&mut self, id: T) -> Option<Doublet<T>> {
+        self.storage.get_link(id)
+    }
+
+    fn exists(&mut self, id: T) -> bool {
+        self.storage.exists(id)
+    }
+
+    fn update(&mut self, id: T, source: T, target: T) -> Result<Doublet<T>> {
+        let before = self.storage.update(id, source, target)?;
+        let after = self.storage.get_link(id);
+        self.record_link(Some(before), after);
+        Ok(before)
+    }
+
+    fn delete(&mut self, id: T) -> Result<Doublet<T>> {
+        let name = self.storage.get_name(id)?;
+        let aliases = self.storage.aliases(id)?;
+        let deleted = self.storage.delete(id)?;
+        self.record_link(Some(deleted), None);
+        // Aliases are recorded before the name, so that reverting restores
+        // the primary name first.
+        let remaining_aliases = self.storage.aliases(id)?;
+        for alias in aliases
+            .iter()
+            .filter(|alias| !remaining_aliases.contains(alias))
+        {
+            self.record_alias(id, alias, false);
+        }
+        let remaining_name = self.storage.get_name(id)?;
+        self.record_name(id, name, remaining_name);
+        Ok(deleted)
+    }
+
+    fn all_links(&mut self) -> Vec<Doublet<T>> {
+        self.storage.all_links()
+    }
+
+    fn search(&mut self, source: T, target: T) -> Option<T> {
+        self.storage.search(source, target)
+    }
+
+    fn get_or_create(&mut self, source: T, target: T) -> T {
+        match self.storage.search(source, target) {
+            Some(id) => id,
+            None => self.create(source, target),
+        }
+    }
+
+    fn get_name(&mut self, id: T) -> Result<Option<String>> {
+        self.storage.get_name(id)
+    }
+
+    fn set_name(&mut self, id: T, name: &str) -> Result<T> {
+        let previous_holder = self.storage.get_by_name(name)?;
+        let holder_aliases = match previous_holder {
+            Some(holder) => self.storage.aliases(holder)?,
+            None => Vec::new(),
+        };
+        let before = self.storage.get_name(id)?;
+        let result = self.storage.set_name(id, name)?;
+
+        if let Some(holder) = previous_holder {
+            if holder_aliases.iter().any(|alias| alias == name) {
+                self.record_alias(holder, name, false);
+            } else if holder != id {
+                let after = self.storage.get_name(holder)?;
+                self.record_name(holder, Some(name.to_string()), after);
+            }
+        }
+        let after = self.storage.get_name(id)?;
+        self.record_name(id, before, after);
+        Ok(result)
+    }
+
+    fn get_by_name(&mut self, name: &str) -> Result<Option<T>> {
+        self.storage.get_by_name(name)
+    }
+
+    fn remove_name(&mut self, id: T) -> Result<()> {
+        let before = self.storage.get_name(id)?;
+        self.storage.remove_name(id)?;
+        self.record_name(id, before, None);
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.storage.save()
+    }
+
+    fn aliases(&mut self, id: T) -> Result<Vec<String>> {
+        self.storage.aliases(id)
+    }
+
+    fn add_alias(&mut self, id: T, alias: &str) -> Result<()> {
+        let before = self.storage.aliases(id)?;
+        self.storage.add_alias(id, alias)?;
+        if !before.iter().any(|existing| existing == alias)
+            && self
+                .storage
+                .aliases(id)?
+                .iter()
+                .any(|existing| existing == alias)
+        {
+            self.record_alias(id, alias, true);
+        }
+        Ok(())
+    }
+
+    fn remove_alias(&mut self, id: T, alias: &str) -> Result<()> {
+        self.storage.remove_alias(id, alias)?;
+        self.record_alias(id, alias, false);
+        Ok(())
+    }
+}
+
+/// Re-applies recorded changes in their original order.
+pub fn apply_changes<T: LinkAddress>(
+    storage: &mut impl NamedTypeLinks<T>,
+    changes: &[StorageChange<T>],
+) -> Result<()> {
+    for change in changes {
+        apply_change(storage, change)?;
+    }
+    Ok(())
+}
+
+/// Undoes recorded changes, most recent first.
+pub fn revert_changes<T: LinkAddress>(
+    storage: &mut impl NamedTypeLinks<T>,
+    changes: &[StorageChange<T>],
+) -> Result<()> {
+    for change in changes.iter().rev() {
+        apply_change(storage, &change.inverted())?;
+    }
+    Ok(())
+}
+
+/// Applies a change if the storage is in its `before` state. A change whose
+/// `after` state is already present is skipped, so that implicit effects such
+/// as `delete` removing a name do not conflict with the recorded name change.
+fn apply_change<T: LinkAddress>(
+    storage: &mut impl NamedTypeLinks<T>,
+    change: &StorageChange<T>,
+) -> Result<()> {
+    match change {
+        StorageChange::Link { before, after } => {
+            let Some(index) = before.or(*after).map(|link| link.index) else {
+                return Ok(());
+            };
+            let current = storage.get_link(index);
+            if current == *after {
+                return Ok(());
+            }
+            if current != *before {
+                bail!(
+                    "Link {index} has changed since the change was recorded: expected {}, found {}.",
+                    describe_link(before),
+                    describe_link(&current)
+                );
+            }
+            match after {
+                Some(link) => {
+                    if current.is_none() {
+                        storage.ensure_created(link.index);
+                    }
+                    storage.update(link.index, link.source, link.target)?;
+                }
+                None => {
+                    storage.delete(index)?;
+                }
+            }
+        }
+        StorageChange::Name {
+            link,
+            before,
+            after,
+        } => {
+            let current = storage.get_name(*link)?;
+            if current == *after {
+                return Ok(());
+            }
+            if current != *before {
+                bail!(
+                    "The name of link {link} has changed since the change was recorded: expected {before:?}, found {current:?}."
+                );
+            }
+            match after {
+                Some(name) => {
+                    storage.set_name(*link, name)?;
+                }
+                None => storage.remove_name(*link)?,
+            }
+        }
+        StorageChange::Alias { link, alias, added } => {
+            let present = storage
+                .aliases(*link)?
+                .iter()
+                .any(|existing| existing == alias);
+            if present == *added {
+                return Ok(());
+            }
+            if *added {
+                storage.add_alias(*link, alias)?;
+            } else {
+                storage.remove_alias(*link, alias)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn describe_link<T: LinkAddress>(link: &Option<Doublet<T>>) -> String {
+    match link {
+        Some(link) => format!("({}: {} {})", link.index, link.source, link.target),
+        None => "no link".to_string(),
+    }
+}
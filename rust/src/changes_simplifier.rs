@@ -4,14 +4,18 @@
 //! identifying chains of transformations.
 //! Corresponds to ChangesSimplifier.cs in C#
 
-use crate::link::Link;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
 use std::collections::{HashMap, HashSet};
 
+/// A link before and after a change
+type Transition<T> = (Doublet<T>, Doublet<T>);
+
 /// Simplifies a list of changes by identifying chains of transformations.
 ///
 /// If multiple final states are reachable from the same initial state, returns multiple simplified changes.
 /// If a scenario arises where no initial or final states can be identified (no-ops), returns the original transitions as-is.
-pub fn simplify_changes(changes: Vec<(Link, Link)>) -> Vec<(Link, Link)> {
+pub fn simplify_changes<T: LinkAddress>(changes: Vec<Transition<T>>) -> Vec<Transition<T>> {
     if changes.is_empty() {
         return vec![];
     }
@@ -33,25 +37,25 @@ pub fn simplify_changes(changes: Vec<(Link, Link)>) -> Vec<(Link, Link)> {
     }
 
     // Gather all 'Before' links and all 'After' links from changed states
-    let before_links: HashSet<Link> = changed_states.iter().map(|(b, _)| *b).collect();
-    let after_links: HashSet<Link> = changed_states.iter().map(|(_, a)| *a).collect();
+    let before_links: HashSet<Doublet<T>> = changed_states.iter().map(|(b, _)| *b).collect();
+    let after_links: HashSet<Doublet<T>> = changed_states.iter().map(|(_, a)| *a).collect();
 
     // Identify initial states: appear as Before but never as After
-    let initial_states: Vec<Link> = before_links
+    let initial_states: Vec<Doublet<T>> = before_links
         .iter()
         .filter(|b| !after_links.contains(b))
         .copied()
         .collect();
 
     // Identify final states: appear as After but never as Before
-    let final_states: HashSet<Link> = after_links
+    let final_states: HashSet<Doublet<T>> = after_links
         .iter()
         .filter(|a| !before_links.contains(a))
         .copied()
         .collect();
 
     // Build adjacency (Before -> possible list of After links)
-    let mut adjacency: HashMap<Link, Vec<Link>> = HashMap::new();
+    let mut adjacency: HashMap<Doublet<T>, Vec<Doublet<T>>> = HashMap::new();
     for (before, after) in changed_states.iter() {
         adjacency.entry(*before).or_default().push(*after);
     }
@@ -70,7 +74,7 @@ pub fn simplify_changes(changes: Vec<(Link, Link)>) -> Vec<(Link, Link)> {
     // Traverse each initial state with DFS
     for initial in initial_states.iter() {
         let mut stack = vec![*initial];
-        let mut visited: HashSet<Link> = HashSet::new();
+        let mut visited: HashSet<Doublet<T>> = HashSet::new();
 
         while let Some(current) = stack.pop() {
             // Skip if already visited
@@ -117,9 +121,11 @@ pub fn simplify_changes(changes: Vec<(Link, Link)>) -> Vec<(Link, Link)> {
 /// The key insight: If we have multiple transitions from the same before state,
 /// and one of them is to a "null" state (0: 0 0), we should prefer the non-null transition
 /// as it represents the actual final transformation.
-fn remove_duplicate_before_states(changes: Vec<(Link, Link)>) -> Vec<(Link, Link)> {
+fn remove_duplicate_before_states<T: LinkAddress>(
+    changes: Vec<Transition<T>>,
+) -> Vec<Transition<T>> {
     // Group changes by their before state
-    let mut grouped: HashMap<Link, Vec<(Link, Link)>> = HashMap::new();
+    let mut grouped: HashMap<Doublet<T>, Vec<Transition<T>>> = HashMap::new();
     for change in changes {
         grouped.entry(change.0).or_default().push(change);
     }
@@ -133,7 +139,7 @@ fn remove_duplicate_before_states(changes: Vec<(Link, Link)>) -> Vec<(Link, Link
         } else {
             // Multiple changes from the same before state
             // Check if any of them is to a null state (0: 0 0)
-            let null_link = Link::new(0, 0, 0);
+            let null_link = Doublet::new(T::ZERO, T::ZERO, T::ZERO);
             let has_null_transition = changes_for_this_before
                 .iter()
                 .any(|(_, after)| *after == null_link);
@@ -0,0 +1,100 @@
+//! Tokenizer shared by the `clink` command and its subcommands.
+
+use anyhow::{anyhow, Result};
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+/// A command-line option such as `--db links.db`, `--db=links.db` or `-d`.
+pub(super) struct OptionArgument {
+    pub(super) name: String,
+    inline_value: Option<String>,
+}
+
+pub(super) enum Argument {
+    Option(OptionArgument),
+    Positional(String),
+    /// `--`: every following argument is positional.
+    Separator,
+}
+
+pub(super) struct Arguments {
+    args: Peekable<IntoIter<String>>,
+}
+
+impl Arguments {
+    pub(super) fn new(args: Vec<String>) -> Self {
+        Self {
+            args: args.into_iter().peekable(),
+        }
+    }
+
+    pub(super) fn next_argument(&mut self) -> Option<Argument> {
+        let arg = self.args.next()?;
+        if arg == "--" {
+            return Some(Argument::Separator);
+        }
+        if let Some((name, value)) = arg.split_once('=').filter(|_| arg.starts_with("--")) {
+            return Some(Argument::Option(OptionArgument {
+                name: name.to_string(),
+                inline_value: Some(value.to_string()),
+            }));
+        }
+        if arg.starts_with('-') && arg.len() > 1 {
+            return Some(Argument::Option(OptionArgument {
+                name: arg,
+                inline_value: None,
+            }));
+        }
+        Some(Argument::Positional(arg))
+    }
+
+    /// Returns the remaining arguments without interpreting them.
+    pub(super) fn remaining(&mut self) -> Vec<String> {
+        self.args.by_ref().collect()
+    }
+
+    /// Returns the value of an option that requires one.
+    pub(super) fn value(&mut self, option: &OptionArgument) -> Result<String> {
+        if let Some(value) = &option.inline_value {
+            return Ok(value.clone());
+        }
+        self.args
+            .next()
+            .ok_or_else(|| anyhow!("missing value for option '{}'", option.name))
+    }
+
+    /// Returns the value of a boolean flag; a following boolean literal is
+    /// consumed as its value.
+    pub(super) fn flag(&mut self, option: &OptionArgument) -> Result<bool> {
+        if let Some(value) = &option.inline_value {
+            return bool_literal(value)
+                .ok_or_else(|| anyhow!("invalid boolean value '{value}' for {}", option.name));
+        }
+        if let Some(value) = self.args.peek().and_then(|value| bool_literal(value)) {
+            self.args.next();
+            Ok(value)
+        } else {
+            Ok(true)
+        }
+    }
+
+    /// Parses the value of an option with `FromStr`.
+    pub(super) fn parsed<T: std::str::FromStr>(
+        &mut self,
+        option: &OptionArgument,
+        kind: &str,
+    ) -> Result<T> {
+        let value = self.value(option)?;
+        value
+            .parse()
+            .map_err(|_| anyhow!("invalid {kind} '{value}' for {}", option.name))
+    }
+}
+
+fn bool_literal(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,356 @@
+//! Help texts for `clink` and its subcommands.
+
+use super::Subcommand;
+
+// Option lists are macros so that `concat!` can share them between texts.
+macro_rules! database_options {
+    () => {
+        concat!(
+            "  -d, --db <DB>, --data-source <DB>, --data <DB>\n",
+            "          Path to the links database file [default: db.links]\n",
+            "  -t, --trace\n",
+            "          Enable trace (verbose output)\n",
+        )
+    };
+}
+
+macro_rules! query_options {
+    () => {
+        concat!(
+            "  -q, --query <QUERY>, --apply <QUERY>, --do <QUERY>\n",
+            "          LiNo query for CRUD operation\n",
+            "      --auto-create-missing-references\n",
+            "          Create missing numeric and named references as self-referential point links\n",
+            "      --reuse-deleted-ids [<BOOL>]\n",
+            "          Give new links the most recently deleted IDs first, like the C# storage, and\n",
+            "          keep doing so for this database; pass false to go back to increasing IDs\n",
+            "      --address-width <32|64>\n",
+            "          Address width of a new database; 64 widens an existing 32-bit database.\n",
+            "          Other commands use the width the database records\n",
+            "  -s, --structure <STRUCTURE>\n",
+            "          ID of the link to format its structure\n",
+            "      --depth <DEPTH>\n",
+            "          Maximum nesting depth expanded by --structure\n",
+            "      --tree\n",
+            "          Print --structure as an indented tree\n",
+            "  -b, --before\n",
+            "          Print the state of the database before applying changes\n",
+            "  -c, --changes\n",
+            "          Print the changes applied by the query\n",
+            "  -a, --after, --links\n",
+            "          Print the state of the database after applying changes\n",
+            "      --out <OUT>, --lino-output <OUT>, --export <OUT>\n",
+            "          Write the complete database as a LiNo file\n",
+            "      --export-roots <LINKS>\n",
+            "          Export only these comma-separated link IDs or names\n",
+            "      --export-filter <RESTRICTION>\n",
+            "          Export only links matching a restriction pattern, e.g. '((* father))'\n",
+            "      --export-closure\n",
+            "          Also export every link reachable through source and target references\n",
+        )
+    };
+}
+
+macro_rules! help_option {
+    () => {
+        concat!("  -h, --help\n", "          Print help\n")
+    };
+}
+
+pub(super) const HELP_TEXT: &str = concat!(
+    "LiNo CLI Tool for managing links data store\n\n",
+    "Usage: clink [OPTIONS] [QUERY]\n",
+    "       clink [OPTIONS] <COMMAND> [COMMAND OPTIONS]\n\n",
+    "Commands:\n",
+    "  query         Apply a LiNo query (the default when no command is given)\n",
+    "  import        Store a JSON document as links\n",
+    "  export        Print the database as LiNo or a stored JSON document\n",
+    "  stats         Print link and name counts\n",
+    "  check         Check database integrity and optionally repair it\n",
+    "  names         List, find, rename and unset names of links\n",
+    "  alias         Add, remove and list aliases of links\n",
+    "  repl          Run queries interactively against a loaded database\n",
+    "  undo          Revert the last applied queries\n",
+    "  redo          Re-apply queries reverted by undo\n",
+    "  snapshot      Create, list, restore and compare named snapshots\n",
+    "  diff          Print the changes between two databases\n",
+    "  merge         Merge the links of another database into this one\n",
+    "  merge-driver  Merge two versions of a database file for git\n",
+    "  compact       Renumber links densely and print the moved IDs\n",
+    "  gc            Delete strings the names database no longer uses\n",
+    "  layout        Show or change where the names database is kept\n",
+    "  encoding      Show or change how names are split into Unicode symbols\n",
+    "  help          Print help for a command\n\n",
+    "Arguments:\n",
+    "  [QUERY]  LiNo query for CRUD operation\n\n",
+    "Options:\n",
+    database_options!(),
+    query_options!(),
+    help_option!(),
+    "  -V, --version\n",
+    "          Print version\n\n",
+    "Run 'clink help <COMMAND>' for the options of a command.\n",
+);
+
+/// Returns the full help text of a subcommand.
+pub(super) fn subcommand_help_text(subcommand: Subcommand) -> &'static str {
+    match subcommand {
+        Subcommand::Query => concat!(
+            "Apply a LiNo query to the database\n\n",
+            "Usage: clink query [OPTIONS] [QUERY]\n\n",
+            "Arguments:\n",
+            "  [QUERY]  LiNo query for CRUD operation\n\n",
+            "Options:\n",
+            database_options!(),
+            query_options!(),
+            help_option!(),
+        ),
+        Subcommand::Import => concat!(
+            "Store a JSON document as links and print its root link ID\n\n",
+            "Usage: clink import [OPTIONS] --json <FILE>\n\n",
+            "Options:\n",
+            "      --json <FILE>\n",
+            "          JSON document to import\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Export => concat!(
+            "Print the database as LiNo, or the JSON document stored at a link\n\n",
+            "Usage: clink export [OPTIONS]\n\n",
+            "Options:\n",
+            "      --json <LINK>\n",
+            "          Print the JSON document stored at a link ID or name\n",
+            "      --out <OUT>, --lino-output <OUT>\n",
+            "          Write LiNo to a file instead of stdout\n",
+            "      --roots <LINKS>, --export-roots <LINKS>\n",
+            "          Export only these comma-separated link IDs or names\n",
+            "      --filter <RESTRICTION>, --export-filter <RESTRICTION>\n",
+            "          Export only links matching a restriction pattern, e.g. '((* father))'\n",
+            "      --closure, --export-closure\n",
+            "          Also export every link reachable through source and target references\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Stats => concat!(
+            "Print link and name counts of the database\n\n",
+            "Usage: clink stats [OPTIONS]\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Check => concat!(
+            "Check the database and its names database for integrity problems\n\n",
+            "Usage: clink check [OPTIONS]\n\n",
+            "Reports malformed lines, references to missing links, names and aliases of\n",
+            "missing links, duplicate (source target) pairs and broken pinned types of the\n",
+            "names database.\n",
+            "Exits with a non-zero status when unrepaired problems remain.\n\n",
+            "Options:\n",
+            "      --repair\n",
+            "          Remove orphan names and aliases and merge duplicate pairs into the lowest\n",
+            "          link ID\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Names => concat!(
+            "List, find, rename and unset names of links\n\n",
+            "Usage: clink names [OPTIONS] [list] [--prefix <TEXT> | --contains <TEXT>]\n",
+            "       clink names [OPTIONS] find <PATTERN>\n",
+            "       clink names [OPTIONS] rename <OLD> <NEW>\n",
+            "       clink names [OPTIONS] unset <LINK>\n",
+            "       clink names [OPTIONS] show <LINK>\n\n",
+            "list prints named links as '<ID> <NAME>', with --prefix or --contains only\n",
+            "those with a name or alias matching the text. find prints every name and alias\n",
+            "matching a regular expression. rename changes a name or an alias without\n",
+            "touching the source and target of its link. unset removes the primary name of\n",
+            "a link, or the alias it was given by. Changes can be reverted with clink undo.\n\n",
+            "Arguments:\n",
+            "  <PATTERN>  Regular expression, matched anywhere in the name\n",
+            "  <LINK>     Link ID or name\n\n",
+            "Options:\n",
+            "      --prefix <TEXT>    List only links with a name or alias starting with TEXT\n",
+            "      --contains <TEXT>  List only links with a name or alias containing TEXT\n",
+            "      --ignore-case      Compare with TEXT ignoring case\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Alias => concat!(
+            "Add, remove and list aliases of links\n\n",
+            "Usage: clink alias [OPTIONS] add <LINK> <ALIAS>\n",
+            "       clink alias [OPTIONS] remove <LINK> <ALIAS>\n",
+            "       clink alias [OPTIONS] list [LINK]\n",
+            "       clink alias [OPTIONS] primary <LINK> <ALIAS>\n\n",
+            "An alias is another name of a link. Queries resolve it like the primary name,\n",
+            "which is the name printed for the link. primary makes an alias the primary name\n",
+            "and keeps the previous primary name as an alias. list prints '<ID> <ALIAS>'\n",
+            "lines. Changes can be reverted with clink undo.\n\n",
+            "Arguments:\n",
+            "  <LINK>   Link ID or name\n",
+            "  <ALIAS>  Name that does not belong to another link\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Repl => concat!(
+            "Run queries interactively against a database that stays loaded\n\n",
+            "Usage: clink repl [OPTIONS]\n\n",
+            "Enter '.help' in the session for the list of meta-commands.\n\n",
+            "Options:\n",
+            "      --auto-create-missing-references\n",
+            "          Create missing numeric and named references as self-referential point links\n",
+            "      --history <FILE>\n",
+            "          Command history file [default: <DB stem>.repl_history]\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Undo => concat!(
+            "Revert the last applied queries, including their name changes\n\n",
+            "Usage: clink undo [OPTIONS] [N]\n\n",
+            "Queries are recorded in <DB stem>.history.json next to the database.\n",
+            "Fails without changing anything if a link or name was modified since.\n\n",
+            "Arguments:\n",
+            "  [N]  Number of queries to revert [default: 1]\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Redo => concat!(
+            "Re-apply queries reverted by undo\n\n",
+            "Usage: clink redo [OPTIONS] [N]\n\n",
+            "Applying a new query discards the queries available for redo.\n\n",
+            "Arguments:\n",
+            "  [N]  Number of queries to re-apply [default: 1]\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Snapshot => concat!(
+            "Create, list, restore and compare named snapshots of the database\n\n",
+            "Usage: clink snapshot [OPTIONS] create <NAME>\n",
+            "       clink snapshot [OPTIONS] list\n",
+            "       clink snapshot [OPTIONS] restore <NAME>\n",
+            "       clink snapshot [OPTIONS] diff <NAME> [OTHER]\n\n",
+            "Snapshots of the database and its names database are kept in <DB stem>.snapshots\n",
+            "next to the database; unchanged parts of the files are stored only once.\n",
+            "diff prints the changes from snapshot NAME to snapshot OTHER, or to the current\n",
+            "database, in the format of --changes.\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Diff => concat!(
+            "Print the changes between two databases in the format of --changes\n\n",
+            "Usage: clink diff [OPTIONS] <BEFORE> <AFTER>\n\n",
+            "Each database is read together with its names database.\n\n",
+            "Arguments:\n",
+            "  <BEFORE>  Database to compare from\n",
+            "  <AFTER>   Database to compare to\n\n",
+            "Options:\n",
+            "      --by-name\n",
+            "          Match links by name, and unnamed links by their source and target,\n",
+            "          instead of by ID\n",
+            "  -t, --trace\n",
+            "          Enable trace (verbose output)\n",
+            help_option!(),
+        ),
+        Subcommand::Merge => concat!(
+            "Merge the links of another database into this one and print the ID mapping\n\n",
+            "Usage: clink merge [OPTIONS] <OTHER>\n\n",
+            "Every link of OTHER is copied under a new ID. Named links are unified with the\n",
+            "link of the same name, and links whose (source target) pair already exists reuse\n",
+            "that link. Each line of the output reads '<OTHER ID> -> <ID> <OUTCOME>'.\n\n",
+            "Arguments:\n",
+            "  <OTHER>  Database to merge, read together with its names database\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::MergeDriver => concat!(
+            "Merge two versions of a database file that derive from a common base\n\n",
+            "Usage: clink merge-driver [OPTIONS] <BASE> <OURS> <THEIRS> [<BASE_NAMES> <OURS_NAMES> <THEIRS_NAMES>]\n\n",
+            "Links changed on one side take that change. Links both sides added under the\n",
+            "same ID are kept apart by moving the one from THEIRS to a new ID, unless both\n",
+            "sides added a link with the same name. Names and aliases follow their links to\n",
+            "the new IDs; a link both sides named differently is a conflict. Conflicting\n",
+            "changes keep the version of OURS and are written as '# conflict: ...' comment\n",
+            "lines, which the database loader skips. The result replaces OURS; the command\n",
+            "fails when there are conflicts.\n\n",
+            "Exported LiNo files are merged by link name, or by number for unnamed links.\n",
+            "Their conflicts are written as '(conflict: (base: ...) (ours: ...) (theirs: ...))'\n",
+            "links after the line of OURS.\n\n",
+            "Use it as a git merge driver for databases in the single-file layout, whose\n",
+            "names travel in the same file as the links:\n",
+            "  git config merge.clink.driver \"clink merge-driver %O %A %B\"\n",
+            "  echo \"*.links merge=clink\" >> .gitattributes\n",
+            "  echo \"*.lino merge=clink\" >> .gitattributes\n",
+            "Git merges a separate <STEM>.names.links file on its own, which cannot follow\n",
+            "the link IDs; switch such databases to the single-file layout with 'clink layout\n",
+            "single-file', or merge them by hand with all six files.\n\n",
+            "Arguments:\n",
+            "  <BASE>          Common ancestor of both versions\n",
+            "  <OURS>          Our version, overwritten with the merge result\n",
+            "  <THEIRS>        Their version\n",
+            "  <BASE_NAMES>    Names database of BASE in the separate layout\n",
+            "  <OURS_NAMES>    Names database of OURS, overwritten with the merged names\n",
+            "  <THEIRS_NAMES>  Names database of THEIRS\n\n",
+            "Options:\n",
+            "  -t, --trace\n",
+            "          Print links moved to a new ID\n",
+            help_option!(),
+        ),
+        Subcommand::Compact => concat!(
+            "Renumber links densely and print the moved IDs\n\n",
+            "Usage: clink compact [OPTIONS]\n\n",
+            "Links move to the lowest free IDs in the order of their current IDs, and the\n",
+            "references to them and their names move along. Pinned types at the start of\n",
+            "the database keep their addresses. Each line of the output reads\n",
+            "'<OLD ID> -> <NEW ID>'. The undo history is cleared, because its entries refer\n",
+            "to the old IDs; create a snapshot first to keep a way back.\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Layout => concat!(
+            "Show or change where the names database is kept\n\n",
+            "Usage: clink layout [OPTIONS] [single-file|separate]\n\n",
+            "The separate layout keeps names in a sibling <STEM>.names.links file. The\n",
+            "single-file layout keeps the links of the names database among the links of\n",
+            "the database, under the pinned Name type, and marks the file with a '#! names'\n",
+            "line, so the two cannot drift apart or get separated. Queries, exports and\n",
+            "merges leave those links out. Without an argument the current layout is\n",
+            "printed. Moving to single-file removes the names file, and links whose IDs the\n",
+            "names database uses move to new IDs, printed as '<OLD ID> -> <NEW ID>'; the\n",
+            "undo history is then cleared. Moving to separate refuses to overwrite an\n",
+            "existing names file or to move out links the database refers to.\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Encoding => concat!(
+            "Show or change how names are split into Unicode symbols\n\n",
+            "Usage: clink encoding [OPTIONS] [utf-16|code-points|code-points-nfc]\n\n",
+            "utf-16, the default, stores one symbol per UTF-16 code unit like the C#\n",
+            "version, so characters such as emoji become two surrogate symbols. code-points\n",
+            "stores one symbol per Unicode scalar value, and code-points-nfc does so after\n",
+            "NFC normalization, so composed and decomposed spellings are the same name.\n",
+            "Names in the Basic Multilingual Plane are stored alike in every encoding. The\n",
+            "change is refused while a stored string would be split differently. Without an\n",
+            "argument the current encoding is printed.\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+        Subcommand::Gc => concat!(
+            "Delete strings the names database no longer uses\n\n",
+            "Usage: clink gc [OPTIONS]\n\n",
+            "Removed and replaced names leave their strings, Unicode sequences and symbols\n",
+            "in the names database. gc keeps the types, the names and aliases and the\n",
+            "strings the main database refers to, such as JSON strings, and deletes every\n",
+            "other link of the names database. It prints the number of reclaimed links.\n",
+            "The undo history stays valid, because it keeps names as text.\n\n",
+            "Options:\n",
+            database_options!(),
+            help_option!(),
+        ),
+    }
+}
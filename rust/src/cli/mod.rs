@@ -0,0 +1,801 @@
+//! Command-line argument parsing for the `clink` binary.
+//!
+//! `clink [OPTIONS] [QUERY]` keeps the C# command surface. The same options
+//! are available as `clink query`, next to the `import`, `export`, `stats`,
+//! `check`, `names`, `alias`, `repl`, `undo`, `redo`, `snapshot`, `diff`, `merge`,
+//! `merge-driver`, `compact`, `gc`, `layout` and `encoding` subcommands.
+//! Database options given before a subcommand apply to it.
+
+mod arguments;
+mod help;
+
+use anyhow::{bail, Result};
+use std::env;
+use std::ffi::OsString;
+
+use arguments::{Argument, Arguments};
+
+use crate::name_search::NameSearch;
+use crate::named_types::NamesLayout;
+use crate::sequences::UnicodeSymbolEncoding;
+
+const DEFAULT_DATABASE_FILENAME: &str = "db.links";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cli {
+    pub db: String,
+    pub query: Option<String>,
+    pub query_arg: Option<String>,
+    pub trace: bool,
+    pub auto_create_missing_references: bool,
+    /// Switches the database to reusing deleted ids, or back to monotonic
+    /// ids; the choice is saved in the database.
+    pub reuse_deleted_ids: Option<bool>,
+    /// Address width of a new database, or 64 to widen an existing 32-bit
+    /// database; other commands use the width the database records.
+    pub address_width: Option<u32>,
+    pub structure: Option<u64>,
+    pub depth: Option<usize>,
+    pub tree: bool,
+    pub before: bool,
+    pub changes: bool,
+    pub after: bool,
+    pub lino_output: Option<String>,
+    pub export_roots: Vec<String>,
+    pub export_filter: Option<String>,
+    pub export_closure: bool,
+}
+
+impl Default for Cli {
+    fn default() -> Self {
+        Self {
+            db: DEFAULT_DATABASE_FILENAME.to_string(),
+            query: None,
+            query_arg: None,
+            trace: false,
+            auto_create_missing_references: false,
+            reuse_deleted_ids: None,
+            address_width: None,
+            structure: None,
+            depth: None,
+            tree: false,
+            before: false,
+            changes: false,
+            after: false,
+            lino_output: None,
+            export_roots: Vec::new(),
+            export_filter: None,
+            export_closure: false,
+        }
+    }
+}
+
+/// Arguments of `clink import`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportCli {
+    pub db: String,
+    pub trace: bool,
+    pub json: String,
+}
+
+/// Arguments of `clink export`.
+///
+/// With `json` set, the JSON document stored at that link is printed;
+/// otherwise the selected links are written as LiNo to `out` or stdout.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportCli {
+    pub db: String,
+    pub trace: bool,
+    pub json: Option<String>,
+    pub out: Option<String>,
+    pub roots: Vec<String>,
+    pub filter: Option<String>,
+    pub closure: bool,
+}
+
+/// Arguments of `clink repl`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplCli {
+    pub db: String,
+    pub trace: bool,
+    pub auto_create_missing_references: bool,
+    /// History file; defaults to `<stem>.repl_history` next to the database.
+    pub history: Option<String>,
+}
+
+/// Arguments of `clink undo` and `clink redo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryCli {
+    pub db: String,
+    pub trace: bool,
+    /// Number of queries to undo or redo.
+    pub count: usize,
+}
+
+/// What `clink snapshot` does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotAction {
+    Create(String),
+    List,
+    Restore(String),
+    /// Compares a snapshot with another snapshot, or with the current
+    /// database when the second name is omitted.
+    Diff(String, Option<String>),
+}
+
+/// Arguments of `clink snapshot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotCli {
+    pub db: String,
+    pub trace: bool,
+    pub action: SnapshotAction,
+}
+
+/// Arguments of `clink diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffCli {
+    pub before: String,
+    pub after: String,
+    pub trace: bool,
+    /// Match links by name and structure instead of by id.
+    pub by_name: bool,
+}
+
+/// Arguments of `clink merge`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeCli {
+    pub db: String,
+    pub trace: bool,
+    /// Database whose links are merged into `db`.
+    pub other: String,
+}
+
+/// Arguments of `clink merge-driver`, in the order git passes them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeDriverCli {
+    pub base: String,
+    /// Our version of the file, which receives the merge result.
+    pub ours: String,
+    pub theirs: String,
+    /// Base, ours and theirs names databases of a database kept in the
+    /// separate layout, merged along with the links.
+    pub names: Option<[String; 3]>,
+    pub trace: bool,
+}
+
+/// Arguments of `clink compact`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactCli {
+    pub db: String,
+    pub trace: bool,
+}
+
+/// Arguments of `clink gc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcCli {
+    pub db: String,
+    pub trace: bool,
+}
+
+/// Arguments of `clink layout`. Without a layout it prints the current one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutCli {
+    pub db: String,
+    pub trace: bool,
+    pub layout: Option<NamesLayout>,
+}
+
+/// Arguments of `clink encoding`. Without an encoding it prints the current
+/// one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingCli {
+    pub db: String,
+    pub trace: bool,
+    pub encoding: Option<UnicodeSymbolEncoding>,
+}
+
+/// Arguments of `clink stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsCli {
+    pub db: String,
+    pub trace: bool,
+}
+
+/// Arguments of `clink check`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckCli {
+    pub db: String,
+    pub trace: bool,
+    pub repair: bool,
+}
+
+/// What `clink names` does. Links are given as IDs or names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamesAction {
+    /// Lists named links, optionally only those with a name or alias
+    /// matching a search.
+    List(Option<NameSearch>),
+    /// Lists names and aliases matching a regular expression.
+    Find(String),
+    Rename(String, String),
+    Unset(String),
+    Show(String),
+}
+
+/// Arguments of `clink names`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamesCli {
+    pub db: String,
+    pub trace: bool,
+    pub action: NamesAction,
+}
+
+/// What `clink alias` does. Links are given as IDs or names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AliasAction {
+    Add(String, String),
+    Remove(String, String),
+    /// Lists the aliases of one link, or of every link.
+    List(Option<String>),
+    /// Makes an alias the primary name of its link.
+    Primary(String, String),
+}
+
+/// Arguments of `clink alias`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasCli {
+    pub db: String,
+    pub trace: bool,
+    pub action: AliasAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subcommand {
+    Query,
+    Import,
+    Export,
+    Stats,
+    Check,
+    Names,
+    Alias,
+    Repl,
+    Undo,
+    Redo,
+    Snapshot,
+    Diff,
+    Merge,
+    MergeDriver,
+    Compact,
+    Gc,
+    Layout,
+    Encoding,
+}
+
+impl Subcommand {
+    pub const ALL: [Subcommand; 18] = [
+        Subcommand::Query,
+        Subcommand::Import,
+        Subcommand::Export,
+        Subcommand::Stats,
+        Subcommand::Check,
+        Subcommand::Names,
+        Subcommand::Alias,
+        Subcommand::Repl,
+        Subcommand::Undo,
+        Subcommand::Redo,
+        Subcommand::Snapshot,
+        Subcommand::Diff,
+        Subcommand::Merge,
+        Subcommand::MergeDriver,
+        Subcommand::Compact,
+        Subcommand::Gc,
+        Subcommand::Layout,
+        Subcommand::Encoding,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Subcommand::Query => "query",
+            Subcommand::Import => "import",
+            Subcommand::Export => "export",
+            Subcommand::Stats => "stats",
+            Subcommand::Check => "check",
+            Subcommand::Names => "names",
+            Subcommand::Alias => "alias",
+            Subcommand::Repl => "repl",
+            Subcommand::Undo => "undo",
+            Subcommand::Redo => "redo",
+            Subcommand::Snapshot => "snapshot",
+            Subcommand::Diff => "diff",
+            Subcommand::Merge => "merge",
+            Subcommand::MergeDriver => "merge-driver",
+            Subcommand::Compact => "compact",
+            Subcommand::Gc => "gc",
+            Subcommand::Layout => "layout",
+            Subcommand::Encoding => "encoding",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|subcommand| subcommand.name() == name)
+    }
+
+    pub fn help_text(self) -> &'static str {
+        help::subcommand_help_text(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    Run(Cli),
+    Import(ImportCli),
+    Export(ExportCli),
+    Stats(StatsCli),
+    Check(CheckCli),
+    Names(NamesCli),
+    Alias(AliasCli),
+    Repl(ReplCli),
+    Undo(HistoryCli),
+    Redo(HistoryCli),
+    Snapshot(SnapshotCli),
+    Diff(DiffCli),
+    Merge(MergeCli),
+    MergeDriver(MergeDriverCli),
+    Compact(CompactCli),
+    Gc(GcCli),
+    Layout(LayoutCli),
+    Encoding(EncodingCli),
+    Help,
+    CommandHelp(Subcommand),
+    Version,
+}
+
+impl Cli {
+    pub fn parse() -> Result<CliCommand> {
+        lino_arguments::init();
+        Self::parse_from(env::args_os())
+    }
+
+    pub fn parse_from<I, T>(args: I) -> Result<CliCommand>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let mut cli = Cli::default();
+        let mut arguments = Arguments::new(
+            args.into_iter()
+                .skip(1)
+                .map(|arg| arg.into().to_string_lossy().into_owned())
+                .collect(),
+        );
+        let mut query_subcommand = false;
+
+        while let Some(argument) = arguments.next_argument() {
+            let option = match argument {
+                Argument::Separator => {
+                    for value in arguments.remaining() {
+                        set_positional_query(&mut cli, value)?;
+                    }
+                    break;
+                }
+                Argument::Positional(value) => {
+                    if !query_subcommand && cli.query_arg.is_none() {
+                        if value == "help" {
+                            return parse_help_command(&mut arguments);
+                        }
+                        match Subcommand::from_name(&value) {
+                            Some(Subcommand::Query) => {
+                                query_subcommand = true;
+                                continue;
+                            }
+                            Some(subcommand) => {
+                                return parse_subcommand(subcommand, cli, &mut arguments);
+                            }
+                            None => {}
+                        }
+                    }
+                    set_positional_query(&mut cli, value)?;
+                    continue;
+                }
+                Argument::Option(option) => option,
+            };
+
+            match option.name.as_str() {
+                "-h" | "--help" if query_subcommand => {
+                    return Ok(CliCommand::CommandHelp(Subcommand::Query));
+                }
+                "-h" | "--help" => return Ok(CliCommand::Help),
+                "-V" | "--version" => return Ok(CliCommand::Version),
+                "-d" | "--db" | "--data-source" | "--data" => {
+                    cli.db = arguments.value(&option)?;
+                }
+                "-q" | "--query" | "--apply" | "--do" => {
+                    cli.query = Some(arguments.value(&option)?);
+                }
+                "-t" | "--trace" => cli.trace = arguments.flag(&option)?,
+                "--auto-create-missing-references" => {
+                    cli.auto_create_missing_references = arguments.flag(&option)?;
+                }
+                "--reuse-deleted-ids" => cli.reuse_deleted_ids = Some(arguments.flag(&option)?),
+                "--address-width" => {
+                    let width = arguments.parsed(&option, "address width")?;
+                    if width != 32 && width != 64 {
+                        bail!(
+                            "invalid address width '{width}' for {}; expected 32 or 64",
+                            option.name
+                        );
+                    }
+                    cli.address_width = Some(width);
+                }
+                "-s" | "--structure" => {
+                    cli.structure = Some(arguments.parsed(&option, "link id")?);
+                }
+                "--depth" => cli.depth = Some(arguments.parsed(&option, "depth")?),
+                "--tree" => cli.tree = arguments.flag(&option)?,
+                "-b" | "--before" => cli.before = arguments.flag(&option)?,
+                "-c" | "--changes" => cli.changes = arguments.flag(&option)?,
+                "-a" | "--after" | "--links" => cli.after = arguments.flag(&option)?,
+                "--out" | "--lino-output" | "--export" => {
+                    cli.lino_output = Some(arguments.value(&option)?);
+                }
+                "--export-roots" => {
+                    let value = arguments.value(&option)?;
+                    cli.export_roots.extend(split_references(&value));
+                }
+                "--export-filter" => cli.export_filter = Some(arguments.value(&option)?),
+                "--export-closure" => cli.export_closure = arguments.flag(&option)?,
+                name => bail!("unknown option '{name}'"),
+            }
+        }
+
+        Ok(CliCommand::Run(cli))
+    }
+
+    pub fn print_help() {
+        print!("{}", Self::help_text());
+    }
+
+    pub fn help_text() -> &'static str {
+        help::HELP_TEXT
+    }
+
+    pub fn version_text() -> String {
+        format!("clink {}", env!("CARGO_PKG_VERSION"))
+    }
+}
+
+/// Parses the arguments following `help`: `clink help [SUBCOMMAND]`.
+fn parse_help_command(arguments: &mut Arguments) -> Result<CliCommand> {
+    match arguments.next_argument() {
+        None => Ok(CliCommand::Help),
+        Some(Argument::Positional(name)) => Subcommand::from_name(&name)
+            .map(CliCommand::CommandHelp)
+            .ok_or_else(|| anyhow::anyhow!("unknown subcommand '{name}'")),
+        Some(_) => bail!("help expects a subcommand name"),
+    }
+}
+
+fn parse_subcommand(
+    subcommand: Subcommand,
+    cli: Cli,
+    arguments: &mut Arguments,
+) -> Result<CliCommand> {
+    let mut db = cli.db;
+    let mut trace = cli.trace;
+    let mut json = None;
+    let mut export = ExportCli::default();
+    let mut auto_create_missing_references = false;
+    let mut history = None;
+    let mut repair = false;
+    let mut positionals = Vec::new();
+    let mut by_name = false;
+    let mut prefix = None;
+    let mut contains = None;
+    let mut ignore_case = false;
+
+    while let Some(argument) = arguments.next_argument() {
+        let option = match argument {
+            Argument::Option(option) => option,
+            Argument::Positional(value) => {
+                positionals.push(value);
+                continue;
+            }
+            Argument::Separator => bail!("unexpected argument '--' for {}", subcommand.name()),
+        };
+
+        match (subcommand, option.name.as_str()) {
+            (_, "-h" | "--help") => return Ok(CliCommand::CommandHelp(subcommand)),
+            (Subcommand::Diff, "-d" | "--db" | "--data-source" | "--data") => {
+                bail!("diff takes the two databases as arguments instead of --db")
+            }
+            (Subcommand::MergeDriver, "-d" | "--db" | "--data-source" | "--data") => {
+                bail!("merge-driver takes the three files as arguments instead of --db")
+            }
+            (_, "-d" | "--db" | "--data-source" | "--data") => db = arguments.value(&option)?,
+            (_, "-t" | "--trace") => trace = arguments.flag(&option)?,
+            (Subcommand::Import | Subcommand::Export, "--json") => {
+                json = Some(arguments.value(&option)?);
+            }
+            (Subcommand::Export, "--out" | "--lino-output") => {
+                export.out = Some(arguments.value(&option)?);
+            }
+            (Subcommand::Export, "--roots" | "--export-roots") => {
+                let value = arguments.value(&option)?;
+                export.roots.extend(split_references(&value));
+            }
+            (Subcommand::Export, "--filter" | "--export-filter") => {
+                export.filter = Some(arguments.value(&option)?);
+            }
+            (Subcommand::Export, "--closure" | "--export-closure") => {
+                export.closure = arguments.flag(&option)?;
+            }
+            (Subcommand::Repl, "--auto-create-missing-references") => {
+                auto_create_missing_references = arguments.flag(&option)?;
+            }
+            (Subcommand::Check, "--repair") => repair = arguments.flag(&option)?,
+            (Subcommand::Diff, "--by-name") => by_name = arguments.flag(&option)?,
+            (Subcommand::Names, "--prefix") => prefix = Some(arguments.value(&option)?),
+            (Subcommand::Names, "--contains") => contains = Some(arguments.value(&option)?),
+            (Subcommand::Names, "--ignore-case") => ignore_case = arguments.flag(&option)?,
+            (Subcommand::Repl, "--history") => history = Some(arguments.value(&option)?),
+            (_, name) => bail!("unknown option '{name}' for {}", subcommand.name()),
+        }
+    }
+
+    let positional_count = match subcommand {
+        Subcommand::Undo | Subcommand::Redo | Subcommand::Layout | Subcommand::Encoding => 1,
+        Subcommand::Alias | Subcommand::Names => 3,
+        Subcommand::Snapshot => 3,
+        Subcommand::Diff => 2,
+        Subcommand::Merge => 1,
+        Subcommand::MergeDriver => 6,
+        _ => 0,
+    };
+    if let Some(value) = positionals.get(positional_count) {
+        bail!("unexpected argument '{value}' for {}", subcommand.name());
+    }
+
+    Ok(match subcommand {
+        Subcommand::Import => CliCommand::Import(ImportCli {
+            db,
+            trace,
+            json: json.ok_or_else(|| anyhow::anyhow!("missing value for option '--json'"))?,
+        }),
+        Subcommand::Export => CliCommand::Export(ExportCli {
+            db,
+            trace,
+            json,
+            ..export
+        }),
+        Subcommand::Stats => CliCommand::Stats(StatsCli { db, trace }),
+        Subcommand::Check => CliCommand::Check(CheckCli { db, trace, repair }),
+        Subcommand::Names => CliCommand::Names(NamesCli {
+            db,
+            trace,
+            action: parse_names_action(
+                positionals,
+                name_search(prefix, contains, ignore_case)?,
+            )?,
+        }),
+        Subcommand::Alias => CliCommand::Alias(AliasCli {
+            db,
+            trace,
+            action: parse_alias_action(positionals)?,
+        }),
+        Subcommand::Compact => CliCommand::Compact(CompactCli { db, trace }),
+        Subcommand::Gc => CliCommand::Gc(GcCli { db, trace }),
+        Subcommand::Layout => CliCommand::Layout(LayoutCli {
+            db,
+            trace,
+            layout: positionals
+                .first()
+                .map(|layout| match layout.as_str() {
+                    "single-file" => Ok(NamesLayout::SingleFile),
+                    "separate" => Ok(NamesLayout::Separate),
+                    other => Err(anyhow::anyhow!(
+                        "unknown layout '{other}'; expected single-file or separate"
+                    )),
+                })
+                .transpose()?,
+        }),
+        Subcommand::Encoding => CliCommand::Encoding(EncodingCli {
+            db,
+            trace,
+            encoding: positionals
+                .first()
+                .map(|encoding| match encoding.as_str() {
+                    "utf-16" => Ok(UnicodeSymbolEncoding::Utf16),
+                    "code-points" => Ok(UnicodeSymbolEncoding::CodePoints),
+                    "code-points-nfc" => Ok(UnicodeSymbolEncoding::NfcCodePoints),
+                    other => Err(anyhow::anyhow!(
+                        "unknown encoding '{other}'; expected utf-16, code-points or code-points-nfc"
+                    )),
+                })
+                .transpose()?,
+        }),
+        Subcommand::Repl => CliCommand::Repl(ReplCli {
+            db,
+            trace,
+            auto_create_missing_references,
+            history,
+        }),
+        Subcommand::Undo => CliCommand::Undo(HistoryCli {
+            db,
+            trace,
+            count: parse_count(subcommand, positionals.first())?,
+        }),
+        Subcommand::Redo => CliCommand::Redo(HistoryCli {
+            db,
+            trace,
+            count: parse_count(subcommand, positionals.first())?,
+        }),
+        Subcommand::Snapshot => CliCommand::Snapshot(SnapshotCli {
+            db,
+            trace,
+            action: parse_snapshot_action(positionals)?,
+        }),
+        Subcommand::Diff => {
+            let mut databases = positionals.into_iter();
+            let (Some(before), Some(after)) = (databases.next(), databases.next()) else {
+                bail!("diff expects two database files");
+            };
+            CliCommand::Diff(DiffCli {
+                before,
+                after,
+                trace,
+                by_name,
+            })
+        }
+        Subcommand::Merge => CliCommand::Merge(MergeCli {
+            db,
+            trace,
+            other: positionals
+                .pop()
+                .ok_or_else(|| anyhow::anyhow!("merge expects the database to merge"))?,
+        }),
+        Subcommand::MergeDriver => {
+            let mut files = positionals.into_iter();
+            let (Some(base), Some(ours), Some(theirs)) = (files.next(), files.next(), files.next())
+            else {
+                bail!("merge-driver expects the base, ours and theirs files");
+            };
+            let names = match (files.next(), files.next(), files.next()) {
+                (Some(base), Some(ours), Some(theirs)) => Some([base, ours, theirs]),
+                (None, None, None) => None,
+                _ => bail!("merge-driver expects the base, ours and theirs names files"),
+            };
+            CliCommand::MergeDriver(MergeDriverCli {
+                base,
+                ours,
+                theirs,
+                names,
+                trace,
+            })
+        }
+        Subcommand::Query => unreachable!("query options are parsed by Cli::parse_from"),
+    })
+}
+
+fn parse_count(subcommand: Subcommand, value: Option<&String>) -> Result<usize> {
+    value.map_or(Ok(1), |value| {
+        value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid count '{value}' for {}", subcommand.name()))
+    })
+}
+
+/// Parses `create NAME`, `list`, `restore NAME` and `diff NAME [OTHER]`.
+fn parse_snapshot_action(positionals: Vec<String>) -> Result<SnapshotAction> {
+    let mut positionals = positionals.into_iter();
+    let action = positionals
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("snapshot expects create, list, restore or diff"))?;
+    let mut name = || {
+        positionals
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("snapshot {action} expects a snapshot name"))
+    };
+    let parsed = match action.as_str() {
+        "create" => SnapshotAction::Create(name()?),
+        "list" => SnapshotAction::List,
+        "restore" => SnapshotAction::Restore(name()?),
+        "diff" => SnapshotAction::Diff(name()?, positionals.next()),
+        other => bail!("unknown snapshot action '{other}'"),
+    };
+    if let Some(value) = positionals.next() {
+        bail!("unexpected argument '{value}' for snapshot {action}");
+    }
+    Ok(parsed)
+}
+
+/// Builds the search of `names list` from `--prefix`, `--contains` and
+/// `--ignore-case`.
+fn name_search(
+    prefix: Option<String>,
+    contains: Option<String>,
+    ignore_case: bool,
+) -> Result<Option<NameSearch>> {
+    let search = match (prefix, contains) {
+        (Some(_), Some(_)) => bail!("--prefix and --contains cannot be combined"),
+        (Some(prefix), None) => NameSearch::prefix(&prefix),
+        (None, Some(text)) => NameSearch::substring(&text),
+        (None, None) if ignore_case => bail!("--ignore-case needs --prefix or --contains"),
+        (None, None) => return Ok(None),
+    };
+    Ok(Some(search.with_ignore_case(ignore_case)))
+}
+
+/// Parses `[list]`, `find PATTERN`, `rename OLD NEW`, `unset LINK` and
+/// `show LINK`. A search only applies to `list`.
+fn parse_names_action(positionals: Vec<String>, search: Option<NameSearch>) -> Result<NamesAction> {
+    let mut positionals = positionals.into_iter();
+    let action = positionals.next();
+    if search.is_some() && action.as_deref().is_some_and(|action| action != "list") {
+        bail!("--prefix and --contains only apply to names list");
+    }
+    let (first, second) = (positionals.next(), positionals.next());
+    Ok(match (action.as_deref(), first, second) {
+        (None, ..) | (Some("list"), None, None) => NamesAction::List(search),
+        (Some("find"), Some(pattern), None) => NamesAction::Find(pattern),
+        (Some("rename"), Some(old), Some(new)) => NamesAction::Rename(old, new),
+        (Some("unset"), Some(link), None) => NamesAction::Unset(link),
+        (Some("show"), Some(link), None) => NamesAction::Show(link),
+        (Some("find"), ..) => bail!("names find expects a pattern"),
+        (Some("rename"), ..) => bail!("names rename expects the old and the new name"),
+        (Some(action @ ("unset" | "show")), ..) => bail!("names {action} expects a link"),
+        (Some("list"), ..) => bail!("names list takes no arguments"),
+        (Some(other), ..) => bail!("unknown names action '{other}'"),
+    })
+}
+
+/// Parses `add LINK ALIAS`, `remove LINK ALIAS`, `list [LINK]` and
+/// `primary LINK ALIAS`.
+fn parse_alias_action(positionals: Vec<String>) -> Result<AliasAction> {
+    let mut positionals = positionals.into_iter();
+    let action = positionals
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("alias expects add, remove, list or primary"))?;
+    let mut link_and_alias = || match (positionals.next(), positionals.next()) {
+        (Some(link), Some(alias)) => Ok((link, alias)),
+        _ => Err(anyhow::anyhow!(
+            "alias {action} expects a link and an alias"
+        )),
+    };
+    Ok(match action.as_str() {
+        "add" => {
+            let (link, alias) = link_and_alias()?;
+            AliasAction::Add(link, alias)
+        }
+        "remove" => {
+            let (link, alias) = link_and_alias()?;
+            AliasAction::Remove(link, alias)
+        }
+        "primary" => {
+            let (link, alias) = link_and_alias()?;
+            AliasAction::Primary(link, alias)
+        }
+        "list" => match (positionals.next(), positionals.next()) {
+            (link, None) => AliasAction::List(link),
+            (_, Some(value)) => bail!("unexpected argument '{value}' for alias list"),
+        },
+        other => bail!("unknown alias action '{other}'"),
+    })
+}
+
+fn split_references(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|reference| !reference.is_empty())
+        .map(str::to_string)
+}
+
+fn set_positional_query(cli: &mut Cli, value: String) -> Result<()> {
+    if cli.query_arg.is_some() {
+        bail!("unexpected extra positional argument '{value}'");
+    }
+
+    cli.query_arg = Some(value);
+    Ok(())
+}
@@ -0,0 +1,433 @@
+//! Integrity checks for a links database and its names database.
+//!
+//! `DatabaseChecker` reports malformed lines, references to missing links,
+//! names and aliases of deleted links, duplicate `(source, target)` pairs and
+//! a broken pinned type layout in the names database. `repair` fixes orphan
+//! names, orphan aliases and duplicate pairs; the other issues, including
+//! duplicates that both have their own name, need a decision by the user.
+
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use crate::hybrid_reference::external_reference_value;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::link_storage::{DoubletStorage, LoadMode};
+use crate::named_types::{NamedTypes, NamedTypesDecorator, NamesLayout};
+
+/// Number of pinned types laid out by `UnicodeStringStorage`.
+const NAMES_DATABASE_PINNED_TYPES: u32 = 6;
+
+/// The side of a link that holds a reference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferenceRole {
+    Source,
+    Target,
+}
+
+impl fmt::Display for ReferenceRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferenceRole::Source => write!(f, "source"),
+            ReferenceRole::Target => write!(f, "target"),
+        }
+    }
+}
+
+/// A problem found by `DatabaseChecker`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IntegrityIssue<T: LinkAddress = u32> {
+    /// A line that cannot be parsed as a link.
+    MalformedLine {
+        path: PathBuf,
+        line: usize,
+        text: String,
+    },
+    /// A source or target that is neither a stored link nor an external
+    /// reference.
+    DanglingReference {
+        link: T,
+        role: ReferenceRole,
+        reference: T,
+    },
+    /// A name in the names database for a link that does not exist.
+    OrphanName { link: T, name: String },
+    /// An alias in the names database for a link that does not exist.
+    OrphanAlias { link: T, alias: String },
+    /// A link with the same source and target as a link with a lower id.
+    DuplicatePair { link: T, original: T },
+    /// A duplicate pair whose links have different names. Merging them
+    /// would make one name refer to the other link, so it is only reported.
+    NamedDuplicatePair {
+        link: T,
+        name: String,
+        original: T,
+        original_name: String,
+    },
+    /// A names database address that should hold a pinned type.
+    InvalidPinnedType {
+        address: T,
+        found: Option<Doublet<T>>,
+    },
+}
+
+impl<T: LinkAddress> IntegrityIssue<T> {
+    /// Returns true if `DatabaseChecker::repair` can fix this issue.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            IntegrityIssue::OrphanName { .. }
+                | IntegrityIssue::OrphanAlias { .. }
+                | IntegrityIssue::DuplicatePair { .. }
+        )
+    }
+}
+
+impl<T: LinkAddress> fmt::Display for IntegrityIssue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::MalformedLine { path, line, text } => {
+                write!(f, "{}:{line}: malformed line: {text}", path.display())
+            }
+            IntegrityIssue::DanglingReference {
+                link,
+                role,
+                reference,
+            } => write!(f, "link {link}: {role} {reference} does not exist"),
+            IntegrityIssue::OrphanName { link, name } => {
+                write!(f, "name '{name}' refers to missing link {link}")
+            }
+            IntegrityIssue::OrphanAlias { link, alias } => {
+                write!(f, "alias '{alias}' refers to missing link {link}")
+            }
+            IntegrityIssue::DuplicatePair { link, original } => {
+                write!(f, "link {link} duplicates link {original}")
+            }
+            IntegrityIssue::NamedDuplicatePair {
+                link,
+                name,
+                original,
+                original_name,
+            } => write!(
+                f,
+                "link {link} ('{name}') duplicates link {original} ('{original_name}'); rename or delete one of them"
+            ),
+            IntegrityIssue::InvalidPinnedType { address, found } => {
+                write!(
+                    f,
+                    "names database: pinned type {address} should be ({address}: 1 {address}), found "
+                )?;
+                match found {
+                    Some(link) => write!(f, "({}: {} {})", link.index, link.source, link.target),
+                    None => write!(f, "nothing"),
+                }
+            }
+        }
+    }
+}
+
+/// Issues found in a database and the ones fixed by a repair.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntegrityReport<T: LinkAddress = u32> {
+    pub issues: Vec<IntegrityIssue<T>>,
+    pub repaired: Vec<IntegrityIssue<T>>,
+}
+
+impl<T: LinkAddress> IntegrityReport<T> {
+    /// Returns the issues that were not repaired.
+    pub fn remaining(&self) -> Vec<&IntegrityIssue<T>> {
+        self.issues
+            .iter()
+            .filter(|issue| !self.repaired.contains(issue))
+            .collect()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.remaining().is_empty()
+    }
+}
+
+/// Checks and repairs a database file together with its names database.
+pub struct DatabaseChecker<T: LinkAddress = u32> {
+    database_path: PathBuf,
+    names_database_path: PathBuf,
+    trace: bool,
+    address: PhantomData<T>,
+}
+
+impl DatabaseChecker {
+    pub fn new<P: AsRef<Path>>(database_path: P) -> Self {
+        Self::for_database(database_path)
+    }
+}
+
+impl<T: LinkAddress> DatabaseChecker<T> {
+    /// Checks a database of any address width, such as
+    /// `DatabaseChecker::<u64>::for_database`. `DatabaseChecker::new` checks
+    /// `u32` databases.
+    pub fn for_database<P: AsRef<Path>>(database_path: P) -> Self {
+        let database_path = database_path.as_ref().to_path_buf();
+        let names_database_path = NamedTypesDecorator::make_names_database_filename(&database_path);
+        Self {
+            database_path,
+            names_database_path,
+            trace: false,
+            address: PhantomData,
+        }
+    }
+
+    pub fn with_names_database_path<P: AsRef<Path>>(mut self, names_database_path: P) -> Self {
+        self.names_database_path = names_database_path.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn with_trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Reports all issues without changing any file.
+    pub fn check(&self) -> Result<IntegrityReport<T>> {
+        let mut storage = self.open_storage()?;
+        Ok(IntegrityReport {
+            issues: self.collect_issues(&mut storage)?,
+            repaired: Vec::new(),
+        })
+    }
+
+    /// Fixes orphan names and aliases and duplicate pairs and saves the
+    /// databases.
+    ///
+    /// Duplicates are merged into the link with the lowest id: references to
+    /// a duplicate are redirected, and its name and aliases move to the kept
+    /// link. Redirected links can become duplicates in turn, so the check
+    /// runs again until no new duplicates turn up. Duplicates that both have
+    /// a name are left alone, and so are all duplicates while the names
+    /// cannot be read. A database file with malformed lines is left
+    /// untouched, because saving it would drop those lines.
+    pub fn repair(&self) -> Result<IntegrityReport<T>> {
+        let mut storage = self.open_storage()?;
+        let mut issues = self.collect_issues(&mut storage)?;
+        let is_writable = |database: &Path| {
+            !issues.iter().any(
+                |issue| matches!(issue, IntegrityIssue::MalformedLine { path, .. } if path == database),
+            )
+        };
+        let single_file = storage.names_layout() == NamesLayout::SingleFile;
+        let links_writable = is_writable(&self.database_path);
+        let names_writable = if single_file {
+            links_writable
+        } else {
+            is_writable(&self.names_database_path)
+        };
+        let names_readable = !issues
+            .iter()
+            .any(|issue| matches!(issue, IntegrityIssue::InvalidPinnedType { .. }));
+        let merges_duplicates = links_writable && names_writable && names_readable;
+
+        let mut repaired = Vec::new();
+        for issue in &issues {
+            match issue {
+                IntegrityIssue::OrphanName { link, .. } if names_writable => {
+                    storage.remove_name(*link)?;
+                }
+                IntegrityIssue::OrphanAlias { link, alias } if names_writable => {
+                    storage.remove_alias(*link, alias)?;
+                }
+                IntegrityIssue::DuplicatePair { link, original } if merges_duplicates => {
+                    merge_duplicate(&mut storage, *link, *original)?;
+                }
+                _ => continue,
+            }
+            repaired.push(issue.clone());
+        }
+        while merges_duplicates && !repaired.is_empty() {
+            let found = duplicate_pairs(&mut storage, true)?
+                .into_iter()
+                .filter(|issue| !issues.contains(issue))
+                .collect::<Vec<_>>();
+            let mut merged_any = false;
+            for issue in found {
+                if let IntegrityIssue::DuplicatePair { link, original } = issue {
+                    merge_duplicate(&mut storage, link, original)?;
+                    repaired.push(issue.clone());
+                    merged_any = true;
+                }
+                issues.push(issue);
+            }
+            if !merged_any {
+                break;
+            }
+        }
+
+        if !repaired.is_empty() {
+            if links_writable {
+                storage.links().save()?;
+            }
+            if names_writable && !single_file {
+                storage.names_links().save()?;
+            }
+        }
+        Ok(IntegrityReport { issues, repaired })
+    }
+
+    fn open_storage(&self) -> Result<NamedTypesDecorator<T>> {
+        let open = |path: &Path| {
+            DoubletStorage::with_load_mode(&path.to_string_lossy(), self.trace, LoadMode::Lenient)
+        };
+        let links = open(&self.database_path)?;
+        if links.holds_names() {
+            return Ok(NamedTypesDecorator::from_single_file_storage(links));
+        }
+        let names_links = open(&self.names_database_path)?;
+        Ok(NamedTypesDecorator::from_link_storages(links, names_links))
+    }
+
+    fn collect_issues(
+        &self,
+        storage: &mut NamedTypesDecorator<T>,
+    ) -> Result<Vec<IntegrityIssue<T>>> {
+        let mut issues = Vec::new();
+        issues.extend(storage.load_warnings().into_iter().map(|warning| {
+            IntegrityIssue::MalformedLine {
+                path: PathBuf::from(&warning.path),
+                line: warning.line,
+                text: warning.text.clone(),
+            }
+        }));
+        let pinned_type_issues = invalid_pinned_types(storage.names_links());
+        let names_readable = pinned_type_issues.is_empty();
+        issues.extend(pinned_type_issues);
+
+        for link in &sorted_links(storage) {
+            for (role, reference) in [
+                (ReferenceRole::Source, link.source),
+                (ReferenceRole::Target, link.target),
+            ] {
+                if external_reference_value(reference).is_none() && !storage.exists(reference) {
+                    issues.push(IntegrityIssue::DanglingReference {
+                        link: link.index,
+                        role,
+                        reference,
+                    });
+                }
+            }
+        }
+
+        issues.extend(duplicate_pairs(storage, names_readable)?);
+
+        // Names cannot be read without the pinned types of the names database.
+        if names_readable {
+            for (link, name) in storage.all_names()? {
+                if !storage.exists(link) {
+                    issues.push(IntegrityIssue::OrphanName { link, name });
+                }
+            }
+            for (link, alias) in storage.all_aliases()? {
+                if !storage.exists(link) {
+                    issues.push(IntegrityIssue::OrphanAlias { link, alias });
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+}
+
+/// Returns the links of the database, without those that keep the names in
+/// the single-file layout, ordered by id.
+fn sorted_links<T: LinkAddress>(storage: &mut NamedTypesDecorator<T>) -> Vec<Doublet<T>> {
+    let mut links = crate::named_type_links::NamedTypeLinks::all_links(storage);
+    links.sort_by_key(|link| link.index);
+    links
+}
+
+/// Reports each link with the same source and target as a link with a lower
+/// id. Their names are only compared when `names_readable`.
+fn duplicate_pairs<T: LinkAddress>(
+    storage: &mut NamedTypesDecorator<T>,
+    names_readable: bool,
+) -> Result<Vec<IntegrityIssue<T>>> {
+    let links = sorted_links(storage);
+    let mut issues = Vec::new();
+    let mut first_with_pair = HashMap::new();
+    for link in &links {
+        let original = *first_with_pair
+            .entry((link.source, link.target))
+            .or_insert(link.index);
+        if original == link.index {
+            continue;
+        }
+        let names = if names_readable {
+            (storage.get_name(link.index)?, storage.get_name(original)?)
+        } else {
+            (None, None)
+        };
+        issues.push(match names {
+            (Some(name), Some(original_name)) => IntegrityIssue::NamedDuplicatePair {
+                link: link.index,
+                name,
+                original,
+                original_name,
+            },
+            _ => IntegrityIssue::DuplicatePair {
+                link: link.index,
+                original,
+            },
+        });
+    }
+    Ok(issues)
+}
+
+fn invalid_pinned_types<T: LinkAddress>(names_links: &DoubletStorage<T>) -> Vec<IntegrityIssue<T>> {
+    if names_links.all().is_empty() {
+        return Vec::new();
+    }
+
+    (1..=NAMES_DATABASE_PINNED_TYPES)
+        .map(T::from)
+        .filter_map(|address| {
+            let found = names_links.get(address).copied();
+            let valid = found.is_some_and(|link| link.source == T::ONE && link.target == address);
+            (!valid).then_some(IntegrityIssue::InvalidPinnedType { address, found })
+        })
+        .collect()
+}
+
+fn merge_duplicate<T: LinkAddress>(
+    storage: &mut NamedTypesDecorator<T>,
+    duplicate: T,
+    original: T,
+) -> Result<()> {
+    let referrers = storage
+        .all()
+        .into_iter()
+        .filter(|link| link.index != duplicate)
+        .filter(|link| link.source == duplicate || link.target == duplicate)
+        .map(|link| (link.index, *link))
+        .collect::<BTreeMap<_, _>>();
+    for (index, link) in referrers {
+        let redirect = |reference: T| {
+            if reference == duplicate {
+                original
+            } else {
+                reference
+            }
+        };
+        storage.update(index, redirect(link.source), redirect(link.target))?;
+    }
+
+    let name = storage.get_name(duplicate)?;
+    let aliases = storage.aliases(duplicate)?;
+    storage.delete(duplicate)?;
+    if let Some(name) = name {
+        storage.set_name(original, &name)?;
+    }
+    for alias in aliases {
+        storage.add_alias(original, &alias)?;
+    }
+    Ok(())
+}
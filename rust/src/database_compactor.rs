@@ -0,0 +1,133 @@
+//! Dense renumbering of link ids for `clink compact`.
+//!
+//! Deleted links leave gaps in the id space and `ensure_created` can move the
+//! next id far ahead. `DatabaseCompactor` moves every link to the lowest free
+//! id in the order of the current ids, rewrites the references to moved links
+//! and moves their names along. Pinned types at the start of the database
+//! keep their deterministic addresses.
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::hybrid_reference::external_reference_value;
+use crate::link_address::LinkAddress;
+use crate::named_types::{NamedTypes, NamedTypesDecorator};
+
+/// A link that moved to a new id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MovedLink<T: LinkAddress = u32> {
+    pub old_id: T,
+    pub new_id: T,
+}
+
+impl<T: LinkAddress> fmt::Display for MovedLink<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.old_id, self.new_id)
+    }
+}
+
+/// The outcome of a compaction, with moved links ordered by their old ids.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactionReport<T: LinkAddress = u32> {
+    /// Number of pinned types that kept their addresses.
+    pub pinned_types: u32,
+    pub moved: Vec<MovedLink<T>>,
+}
+
+impl<T: LinkAddress> CompactionReport<T> {
+    /// Returns the id a link has after the compaction.
+    pub fn new_id(&self, old_id: T) -> T {
+        self.moved
+            .iter()
+            .find(|link| link.old_id == old_id)
+            .map_or(old_id, |link| link.new_id)
+    }
+}
+
+/// Renumbers the links of a database densely.
+pub struct DatabaseCompactor<'a, T: LinkAddress = u32> {
+    storage: &'a mut NamedTypesDecorator<T>,
+}
+
+impl<'a, T: LinkAddress> DatabaseCompactor<'a, T> {
+    pub fn new(storage: &'a mut NamedTypesDecorator<T>) -> Self {
+        Self { storage }
+    }
+
+    /// Moves the links and their names and returns the moved links. The
+    /// storage is left unchanged when a link refers to a missing link or a
+    /// name belongs to a missing link, because either could end up on a
+    /// moved link; `clink check --repair` handles the latter.
+    pub fn compact(&mut self) -> Result<CompactionReport<T>> {
+        let mut links = self.storage.all().into_iter().copied().collect::<Vec<_>>();
+        links.sort_by_key(|link| link.index);
+        for link in &links {
+            for reference in [link.source, link.target] {
+                if external_reference_value(reference).is_none() && !self.storage.exists(reference)
+                {
+                    bail!(
+                        "Link {} refers to missing link {reference}; it cannot be compacted.",
+                        link.index
+                    );
+                }
+            }
+        }
+        let names = self.storage.all_names()?;
+        let aliases = self.storage.all_aliases()?;
+        if let Some((link, name)) = names
+            .iter()
+            .chain(&aliases)
+            .find(|(link, _)| !self.storage.exists(*link))
+        {
+            bail!("Name '{name}' belongs to missing link {link}; run clink check --repair first.");
+        }
+
+        let ids = || std::iter::successors(Some(T::ONE), |&id| Some(id + T::ONE));
+        let pinned_types = links
+            .iter()
+            .zip(ids())
+            .take_while(|(link, address)| {
+                link.index == *address && link.source == T::ONE && link.target == *address
+            })
+            .count() as u32;
+
+        let mut mapping = HashMap::new();
+        let mut moved = Vec::new();
+        for (link, new_id) in links.iter().zip(ids()).skip(pinned_types as usize) {
+            if link.index != new_id {
+                mapping.insert(link.index, new_id);
+                moved.push(MovedLink {
+                    old_id: link.index,
+                    new_id,
+                });
+            }
+        }
+
+        let with_new_ids = |names: Vec<(T, String)>| {
+            names
+                .into_iter()
+                .filter_map(|(link, name)| mapping.get(&link).map(|&new_id| (link, new_id, name)))
+                .collect::<Vec<_>>()
+        };
+        let (moved_names, moved_aliases) = (with_new_ids(names), with_new_ids(aliases));
+        for (old_id, _, _) in &moved_names {
+            self.storage.remove_name(*old_id)?;
+        }
+        for (old_id, _, alias) in &moved_aliases {
+            self.storage.remove_alias(*old_id, alias)?;
+        }
+        self.storage.links_mut().renumber(&mapping);
+        for (_, new_id, name) in &moved_names {
+            self.storage.set_name(*new_id, name)?;
+        }
+        for (_, new_id, alias) in &moved_aliases {
+            self.storage.add_alias(*new_id, alias)?;
+        }
+
+        Ok(CompactionReport {
+            pinned_types,
+            moved,
+        })
+    }
+}
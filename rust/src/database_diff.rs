@@ -0,0 +1,213 @@
+//! Differences between two links databases.
+//!
+//! `DatabaseDiff` reports every link that was created, deleted, changed or
+//! renamed between two databases as a `(before, after)` pair, the same shape
+//! as the changes returned by `process_query`. Links are matched by id, or,
+//! for databases built separately, by name: named links are paired by their
+//! names and unnamed links by the pairs of their source and target. External
+//! references to strings, which index each database's own names database,
+//! are paired by the strings they point to.
+
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::marker::PhantomData;
+
+use crate::hybrid_reference::external_reference_value;
+use crate::link::{Doublet, DoubletChange};
+use crate::link_address::LinkAddress;
+use crate::named_type_links::NamedTypeLinks;
+
+/// Compares a `before` database with an `after` database.
+pub struct DatabaseDiff<'a, B: NamedTypeLinks<T>, A: NamedTypeLinks<T>, T: LinkAddress = u32> {
+    before: &'a mut B,
+    after: &'a mut A,
+    match_by_name: bool,
+    address: PhantomData<T>,
+}
+
+impl<'a, B: NamedTypeLinks<T>, A: NamedTypeLinks<T>, T: LinkAddress> DatabaseDiff<'a, B, A, T> {
+    pub fn new(before: &'a mut B, after: &'a mut A) -> Self {
+        Self {
+            before,
+            after,
+            match_by_name: false,
+            address: PhantomData,
+        }
+    }
+
+    /// Matches links by name and structure instead of by id. Unnamed links
+    /// that refer to themselves, such as unnamed points, cannot be paired
+    /// this way and show up as deleted and created.
+    pub fn with_match_by_name(mut self, match_by_name: bool) -> Self {
+        self.match_by_name = match_by_name;
+        self
+    }
+
+    /// Returns the differing links. A matched link counts as changed when its
+    /// source, target or name differs.
+    pub fn changes(&mut self) -> Result<Vec<DoubletChange<T>>> {
+        if self.match_by_name {
+            self.changes_by_name()
+        } else {
+            self.changes_by_id()
+        }
+    }
+
+    /// Formats the changes like `--changes`, resolving names of `before`
+    /// links in the before database and of `after` links in the after
+    /// database.
+    pub fn lino_lines(&mut self) -> Result<Vec<String>> {
+        self.changes()?
+            .into_iter()
+            .map(|(before, after)| {
+                let before_text = before
+                    .map(|link| self.before.format_lino(&link))
+                    .transpose()?
+                    .unwrap_or_default();
+                let after_text = after
+                    .map(|link| self.after.format_lino(&link))
+                    .transpose()?
+                    .unwrap_or_default();
+                Ok(format!("({before_text}) ({after_text})"))
+            })
+            .collect()
+    }
+
+    /// Pairs links with the same id, ordered by id.
+    fn changes_by_id(&mut self) -> Result<Vec<DoubletChange<T>>> {
+        let ids = self
+            .before
+            .all_links()
+            .into_iter()
+            .chain(self.after.all_links())
+            .map(|link| link.index)
+            .collect::<BTreeSet<_>>();
+
+        let mut changes = Vec::new();
+        for id in ids {
+            let before = self.before.get_link(id);
+            let after = self.after.get_link(id);
+            let renamed = before.is_some()
+                && after.is_some()
+                && self.before.get_name(id)? != self.after.get_name(id)?;
+            if before != after || renamed {
+                changes.push((before, after));
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Maps external references of the before database to those of the
+    /// after database holding the same string. References that are not
+    /// strings stand for themselves; strings missing from the after database
+    /// stay unmapped.
+    fn match_external_references(
+        &mut self,
+        before_links: &[Doublet<T>],
+        after_links: &[Doublet<T>],
+        matches: &mut HashMap<T, T>,
+    ) -> Result<()> {
+        let mut after_strings = HashMap::new();
+        for reference in external_references(after_links) {
+            if let Some(content) = self.after.external_string(reference)? {
+                after_strings.insert(content, reference);
+            }
+        }
+        for reference in external_references(before_links) {
+            match self.before.external_string(reference)? {
+                Some(content) => {
+                    if let Some(&after) = after_strings.get(&content) {
+                        matches.insert(reference, after);
+                    }
+                }
+                None => {
+                    matches.insert(reference, reference);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pairs links by name and structure. Changed and deleted links come in
+    /// the order of the before database, followed by the created links.
+    fn changes_by_name(&mut self) -> Result<Vec<DoubletChange<T>>> {
+        let mut before_links = self.before.all_links();
+        before_links.sort_by_key(|link| link.index);
+        let mut after_links = self.after.all_links();
+        after_links.sort_by_key(|link| link.index);
+
+        let mut matches = HashMap::new();
+        for link in &before_links {
+            if let Some(name) = self.before.get_name(link.index)? {
+                if let Some(after) = self.after.get_by_name(&name)? {
+                    matches.insert(link.index, after);
+                }
+            }
+        }
+
+        // Unnamed links match once their source and target are matched,
+        // which may take several passes for nested structures.
+        let mut matched_after = matches.values().copied().collect::<HashSet<_>>();
+        self.match_external_references(&before_links, &after_links, &mut matches)?;
+        loop {
+            let mut progress = false;
+            for link in &before_links {
+                if matches.contains_key(&link.index) {
+                    continue;
+                }
+                let (Some(source), Some(target)) = (
+                    map_reference(&matches, link.source),
+                    map_reference(&matches, link.target),
+                ) else {
+                    continue;
+                };
+                if let Some(after) = self.after.search(source, target) {
+                    if matched_after.insert(after) {
+                        matches.insert(link.index, after);
+                        progress = true;
+                    }
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+
+        let mut changes = Vec::new();
+        for link in &before_links {
+            let Some(after) = matches
+                .get(&link.index)
+                .and_then(|&id| self.after.get_link(id))
+            else {
+                changes.push((Some(*link), None));
+                continue;
+            };
+            let same_structure = map_reference(&matches, link.source) == Some(after.source)
+                && map_reference(&matches, link.target) == Some(after.target);
+            if !same_structure {
+                changes.push((Some(*link), Some(after)));
+            }
+        }
+        changes.extend(
+            after_links
+                .into_iter()
+                .filter(|link| !matched_after.contains(&link.index))
+                .map(|link| (None, Some(link))),
+        );
+        Ok(changes)
+    }
+}
+
+/// Maps a before-database reference to the after database.
+fn map_reference<T: LinkAddress>(matches: &HashMap<T, T>, reference: T) -> Option<T> {
+    matches.get(&reference).copied()
+}
+
+/// Returns the external references used as a source or target.
+fn external_references<T: LinkAddress>(links: &[Doublet<T>]) -> BTreeSet<T> {
+    links
+        .iter()
+        .flat_map(|link| [link.source, link.target])
+        .filter(|&reference| external_reference_value(reference).is_some())
+        .collect()
+}
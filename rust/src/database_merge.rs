@@ -0,0 +1,260 @@
+//! Merging the links of one database into another.
+//!
+//! `DatabaseMerge` copies every link of a source database into a target
+//! database under new ids. Named links are unified with the target link of
+//! the same name, and an unnamed link whose `(source, target)` pair already
+//! exists in the target reuses that link, the way `get_or_create` does.
+//! Aliases are carried over unless the target uses the name already, and
+//! external references to strings are re-created in the target's names
+//! database, since their ids only have a meaning in the source's.
+
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::hybrid_reference::external_reference_value;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::named_type_links::NamedTypeLinks;
+
+/// What happened to a link of the merged database.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// The link was copied under a new id.
+    Created,
+    /// The target already has a link with the same name.
+    UnifiedByName,
+    /// The target already has a link with the same source and target.
+    Deduplicated,
+}
+
+impl fmt::Display for MergeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeOutcome::Created => write!(f, "created"),
+            MergeOutcome::UnifiedByName => write!(f, "unified by name"),
+            MergeOutcome::Deduplicated => write!(f, "deduplicated"),
+        }
+    }
+}
+
+/// The id a merged link got in the target database.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergedLink<T: LinkAddress = u32> {
+    pub source_id: T,
+    pub target_id: T,
+    pub outcome: MergeOutcome,
+}
+
+impl<T: LinkAddress> fmt::Display for MergedLink<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} -> {} {}",
+            self.source_id, self.target_id, self.outcome
+        )
+    }
+}
+
+/// The id mapping of a merge, ordered by the ids of the merged database.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeReport<T: LinkAddress = u32> {
+    pub links: Vec<MergedLink<T>>,
+}
+
+impl<T: LinkAddress> MergeReport<T> {
+    pub fn count(&self, outcome: MergeOutcome) -> usize {
+        self.links
+            .iter()
+            .filter(|link| link.outcome == outcome)
+            .count()
+    }
+
+    /// Returns the target id of a link of the merged database.
+    pub fn mapped_id(&self, source_id: T) -> Option<T> {
+        self.links
+            .iter()
+            .find(|link| link.source_id == source_id)
+            .map(|link| link.target_id)
+    }
+}
+
+/// Merges a source database `S` into a target database `D`.
+pub struct DatabaseMerge<'a, D: NamedTypeLinks<T>, S: NamedTypeLinks<T>, T: LinkAddress = u32> {
+    target: &'a mut D,
+    source: &'a mut S,
+    address: PhantomData<T>,
+}
+
+impl<'a, D: NamedTypeLinks<T>, S: NamedTypeLinks<T>, T: LinkAddress> DatabaseMerge<'a, D, S, T> {
+    pub fn new(target: &'a mut D, source: &'a mut S) -> Self {
+        Self {
+            target,
+            source,
+            address: PhantomData,
+        }
+    }
+
+    /// Copies the source links into the target and returns the id mapping.
+    ///
+    /// A named link keeps the structure it has in the target when the target
+    /// already has that name. A named link is only deduplicated against an
+    /// unnamed target link, so that distinct names stay on distinct links.
+    pub fn merge(&mut self) -> Result<MergeReport<T>> {
+        let mut links = self.source.all_links();
+        links.sort_by_key(|link| link.index);
+        for link in &links {
+            for reference in [link.source, link.target] {
+                if external_reference_value(reference).is_none() && !self.source.exists(reference) {
+                    bail!(
+                        "Link {} of the merged database refers to missing link {reference}.",
+                        link.index
+                    );
+                }
+            }
+        }
+
+        let external = self.map_external_strings(&links)?;
+
+        let mut names = HashMap::new();
+        for link in &links {
+            if let Some(name) = self.source.get_name(link.index)? {
+                names.insert(link.index, name);
+            }
+        }
+
+        let mut mapping = BTreeMap::new();
+        for link in &links {
+            if let Some(name) = names.get(&link.index) {
+                if let Some(existing) = self.target.get_by_name(name)? {
+                    mapping.insert(link.index, (existing, MergeOutcome::UnifiedByName));
+                }
+            }
+        }
+
+        // Links are copied once their references are mapped. A link in a
+        // reference cycle gets a placeholder id and its structure is set at
+        // the end, when every id is known.
+        let mut placeholders = Vec::new();
+        let mut pending = links
+            .iter()
+            .filter(|link| !mapping.contains_key(&link.index))
+            .copied()
+            .collect::<Vec<_>>();
+        while !pending.is_empty() {
+            let mut progress = false;
+            for link in &pending {
+                let resolvable = [link.source, link.target].into_iter().all(|reference| {
+                    reference == link.index
+                        || map_reference(&mapping, &external, reference).is_some()
+                });
+                if resolvable {
+                    let merged =
+                        self.copy_link(link, names.get(&link.index), &mapping, &external)?;
+                    mapping.insert(link.index, merged);
+                    progress = true;
+                }
+            }
+            pending.retain(|link| !mapping.contains_key(&link.index));
+
+            if !progress {
+                let link = pending.remove(0);
+                let id = self.target.create(T::ZERO, T::ZERO);
+                mapping.insert(link.index, (id, MergeOutcome::Created));
+                placeholders.push(link);
+            }
+        }
+
+        for link in placeholders {
+            let (id, _) = mapping[&link.index];
+            let source = map_reference(&mapping, &external, link.source).unwrap_or(id);
+            let target = map_reference(&mapping, &external, link.target).unwrap_or(id);
+            self.target.update(id, source, target)?;
+            if let Some(name) = names.get(&link.index) {
+                self.target.set_name(id, name)?;
+            }
+        }
+
+        for link in &links {
+            let (id, _) = mapping[&link.index];
+            for alias in self.source.aliases(link.index)? {
+                if self.target.get_by_name(&alias)?.is_none() {
+                    self.target.add_alias(id, &alias)?;
+                }
+            }
+        }
+
+        Ok(MergeReport {
+            links: mapping
+                .into_iter()
+                .map(|(source_id, (target_id, outcome))| MergedLink {
+                    source_id,
+                    target_id,
+                    outcome,
+                })
+                .collect(),
+        })
+    }
+
+    /// Stores the strings external references of the source point to in the
+    /// target and maps each reference to the target's one. References that
+    /// are not strings are kept as they are.
+    fn map_external_strings(&mut self, links: &[Doublet<T>]) -> Result<HashMap<T, T>> {
+        let mut external = HashMap::new();
+        for reference in links.iter().flat_map(|link| [link.source, link.target]) {
+            if external_reference_value(reference).is_none() || external.contains_key(&reference) {
+                continue;
+            }
+            let mapped = match self.source.external_string(reference)? {
+                Some(content) => self.target.create_external_string(&content)?,
+                None => reference,
+            };
+            external.insert(reference, mapped);
+        }
+        Ok(external)
+    }
+
+    fn copy_link(
+        &mut self,
+        link: &Doublet<T>,
+        name: Option<&String>,
+        mapping: &BTreeMap<T, (T, MergeOutcome)>,
+        external: &HashMap<T, T>,
+    ) -> Result<(T, MergeOutcome)> {
+        let self_referential = link.source == link.index || link.target == link.index;
+        let source = map_reference(mapping, external, link.source);
+        let target = map_reference(mapping, external, link.target);
+
+        if let (false, Some(source), Some(target)) = (self_referential, source, target) {
+            if let Some(existing) = self.target.search(source, target) {
+                if name.is_none() || self.target.get_name(existing)?.is_none() {
+                    if let Some(name) = name {
+                        self.target.set_name(existing, name)?;
+                    }
+                    return Ok((existing, MergeOutcome::Deduplicated));
+                }
+            }
+        }
+
+        let id = self.target.create(T::ZERO, T::ZERO);
+        self.target
+            .update(id, source.unwrap_or(id), target.unwrap_or(id))?;
+        if let Some(name) = name {
+            self.target.set_name(id, name)?;
+        }
+        Ok((id, MergeOutcome::Created))
+    }
+}
+
+/// Maps a reference of the merged database to the target database.
+fn map_reference<T: LinkAddress>(
+    mapping: &BTreeMap<T, (T, MergeOutcome)>,
+    external: &HashMap<T, T>,
+    reference: T,
+) -> Option<T> {
+    if let Some(&mapped) = external.get(&reference) {
+        return Some(mapped);
+    }
+    mapping.get(&reference).map(|&(id, _)| id)
+}
@@ -0,0 +1,53 @@
+//! Summary statistics for `clink stats`.
+
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::link_address::LinkAddress;
+use crate::named_type_links::NamedTypeLinks;
+use crate::named_types::{NamedTypesDecorator, NamesLayout};
+
+/// Link and name counts of a database and its names database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DatabaseStats<T: LinkAddress = u32> {
+    /// Number of links in the main database.
+    pub links: usize,
+    /// Number of self-referential point links `(i: i i)`.
+    pub points: usize,
+    /// Number of main-database links that have a name.
+    pub named_links: usize,
+    /// Highest link id in use, or 0 for an empty database.
+    pub max_id: T,
+    /// Number of links in the names database, or of the links that keep the
+    /// names in the single-file layout.
+    pub names_links: usize,
+}
+
+impl<T: LinkAddress> DatabaseStats<T> {
+    pub fn collect(storage: &mut NamedTypesDecorator<T>) -> Result<Self> {
+        let named_links = storage.all_names()?.len();
+        let names_links = match storage.names_layout() {
+            NamesLayout::SingleFile => storage.names_link_ids()?.len(),
+            NamesLayout::Separate => storage.names_links().all().len(),
+        };
+        let links = NamedTypeLinks::all_links(storage);
+        Ok(Self {
+            links: links.len(),
+            points: links.iter().filter(|link| link.is_full_point()).count(),
+            named_links,
+            max_id: links.iter().map(|link| link.index).max().unwrap_or(T::ZERO),
+            names_links,
+        })
+    }
+}
+
+impl<T: LinkAddress> fmt::Display for DatabaseStats<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "links: {}", self.links)?;
+        writeln!(f, "points: {}", self.points)?;
+        writeln!(f, "named links: {}", self.named_links)?;
+        writeln!(f, "max id: {}", self.max_id)?;
+        write!(f, "names database links: {}", self.names_links)
+    }
+}
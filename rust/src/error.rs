@@ -8,10 +8,14 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum LinkError {
     #[error("Link not found: {0}")]
-    NotFound(u32),
+    NotFound(u64),
 
-    #[error("Invalid link format: {0}")]
-    InvalidFormat(String),
+    #[error("Invalid link format in {path} at line {line}: {text}")]
+    InvalidFormat {
+        path: String,
+        line: usize,
+        text: String,
+    },
 
     #[error("Storage error: {0}")]
     StorageError(String),
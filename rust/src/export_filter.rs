@@ -0,0 +1,93 @@
+//! Link selection for exporting a subset of the database as LiNo.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+use crate::error::LinkError;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::named_type_links::NamedTypeLinks;
+use crate::query_processor::QueryProcessor;
+
+/// Selects the links written by a filtered export.
+///
+/// Links are selected by root ids and/or by a restriction pattern in query
+/// syntax. When dependencies are included, every link reachable through
+/// source and target references of the selected links is exported as well.
+/// An empty filter selects the whole database.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportFilter<T: LinkAddress = u32> {
+    pub roots: Vec<T>,
+    pub restriction: Option<String>,
+    pub include_dependencies: bool,
+}
+
+impl<T: LinkAddress> ExportFilter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_roots(mut self, roots: Vec<T>) -> Self {
+        self.roots = roots;
+        self
+    }
+
+    pub fn with_restriction(mut self, restriction: Option<String>) -> Self {
+        self.restriction = restriction;
+        self
+    }
+
+    pub fn with_dependencies(mut self, include_dependencies: bool) -> Self {
+        self.include_dependencies = include_dependencies;
+        self
+    }
+
+    /// Returns true if the filter does not restrict the exported links.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty() && self.restriction.is_none()
+    }
+
+    /// Returns the selected links sorted by index.
+    pub fn select(&self, storage: &mut impl NamedTypeLinks<T>) -> Result<Vec<Doublet<T>>> {
+        if self.is_empty() {
+            let mut links = storage.all_links();
+            links.sort_by_key(|link| link.index);
+            return Ok(links);
+        }
+
+        let mut selected = BTreeSet::new();
+        for &root in &self.roots {
+            if !storage.exists(root) {
+                return Err(LinkError::NotFound(root.into()).into());
+            }
+            selected.insert(root);
+        }
+        if let Some(restriction) = &self.restriction {
+            selected.extend(
+                QueryProcessor::new(false)
+                    .find_matching_links(storage, restriction)?
+                    .into_iter()
+                    .map(|link| link.index),
+            );
+        }
+
+        if self.include_dependencies {
+            let mut pending = selected.iter().copied().collect::<Vec<_>>();
+            while let Some(id) = pending.pop() {
+                let Some(link) = storage.get_link(id) else {
+                    continue;
+                };
+                for reference in [link.source, link.target] {
+                    if storage.exists(reference) && selected.insert(reference) {
+                        pending.push(reference);
+                    }
+                }
+            }
+        }
+
+        Ok(selected
+            .into_iter()
+            .filter_map(|id| storage.get_link(id))
+            .collect())
+    }
+}
@@ -1,36 +1,46 @@
-//! `Platform.Data.Hybrid<uint>`-compatible reference encoding.
+//! `Platform.Data.Hybrid<T>`-compatible reference encoding.
 
-const EXTERNAL_ZERO: u32 = (u32::MAX / 2) + 1;
+use crate::link_address::LinkAddress;
 
+/// A reference that is either a link address or an external value stored in
+/// the upper half of the address range.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct HybridReference {
-    encoded: u32,
+pub struct Hybrid<T: LinkAddress> {
+    encoded: T,
 }
 
-impl HybridReference {
-    pub fn external(value: u32) -> Self {
+/// Hybrid reference over `u32` addresses, matching `Platform.Data.Hybrid<uint>`.
+pub type HybridReference = Hybrid<u32>;
+
+impl<T: LinkAddress> Hybrid<T> {
+    /// The encoding of the external value zero.
+    fn external_zero() -> T {
+        T::MAX / (T::ONE + T::ONE) + T::ONE
+    }
+
+    pub fn external(value: T) -> Self {
         Self {
-            encoded: if value == 0 {
-                EXTERNAL_ZERO
+            encoded: if value == T::ZERO {
+                Self::external_zero()
             } else {
-                0u32.wrapping_sub(value)
+                T::ZERO.wrapping_sub(value)
             },
         }
     }
 
-    pub fn from_encoded(encoded: u32) -> Self {
+    pub fn from_encoded(encoded: T) -> Self {
         Self { encoded }
     }
 
-    pub fn encoded(self) -> u32 {
+    pub fn encoded(self) -> T {
         self.encoded
     }
 
-    pub fn absolute_value(self) -> Option<u32> {
-        if self.encoded == EXTERNAL_ZERO {
-            Some(0)
-        } else if self.encoded >= EXTERNAL_ZERO {
-            Some(0u32.wrapping_sub(self.encoded))
+    pub fn absolute_value(self) -> Option<T> {
+        if self.encoded == Self::external_zero() {
+            Some(T::ZERO)
+        } else if self.encoded >= Self::external_zero() {
+            Some(T::ZERO.wrapping_sub(self.encoded))
         } else {
             None
         }
@@ -41,12 +51,12 @@ impl HybridReference {
     }
 }
 
-/// Encodes an external reference the same way `Platform.Data.Hybrid<uint>` does.
-pub fn external_reference(value: u32) -> u32 {
-    HybridReference::external(value).encoded()
+/// Encodes an external reference the same way `Platform.Data.Hybrid<T>` does.
+pub fn external_reference<T: LinkAddress>(value: T) -> T {
+    Hybrid::external(value).encoded()
 }
 
-/// Decodes a `Platform.Data.Hybrid<uint>` external reference.
-pub fn external_reference_value(value: u32) -> Option<u32> {
-    HybridReference::from_encoded(value).absolute_value()
+/// Decodes a `Platform.Data.Hybrid<T>` external reference.
+pub fn external_reference_value<T: LinkAddress>(value: T) -> Option<T> {
+    Hybrid::from_encoded(value).absolute_value()
 }
@@ -0,0 +1,226 @@
+//! JSON document storage backed by deduplicated link trees.
+//!
+//! Values are stored in the main links database under named type markers:
+//! object keys are named point links, object members and array elements are
+//! joined into balanced sequences by `BalancedVariantConverter` in document
+//! order, and string and number contents are kept in the names database
+//! `UnicodeStringStorage` and referenced from the main database as external
+//! references.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Number, Value};
+
+use crate::error::LinkError;
+use crate::hybrid_reference::{external_reference, external_reference_value};
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
+use crate::named_type_links::NamedTypeLinks;
+use crate::named_types::{NamedTypes, NamedTypesDecorator};
+use crate::sequences::{BalancedVariantConverter, DefaultStack};
+
+const OBJECT_TYPE_NAME: &str = "JsonObject";
+const ARRAY_TYPE_NAME: &str = "JsonArray";
+const STRING_TYPE_NAME: &str = "JsonString";
+const NUMBER_TYPE_NAME: &str = "JsonNumber";
+const TRUE_VALUE_NAME: &str = "JsonTrue";
+const FALSE_VALUE_NAME: &str = "JsonFalse";
+const NULL_VALUE_NAME: &str = "JsonNull";
+
+/// Imports and exports JSON documents as link structures.
+pub struct JsonDocuments<'a, T: LinkAddress = u32> {
+    storage: &'a mut NamedTypesDecorator<T>,
+    balanced_variant_converter: BalancedVariantConverter,
+    object_type: T,
+    array_type: T,
+    string_type: T,
+    number_type: T,
+    true_value: T,
+    false_value: T,
+    null_value: T,
+}
+
+impl<'a, T: LinkAddress> JsonDocuments<'a, T> {
+    pub fn new(storage: &'a mut NamedTypesDecorator<T>) -> Result<Self> {
+        let object_type = storage.get_or_create_named(OBJECT_TYPE_NAME)?;
+        let array_type = storage.get_or_create_named(ARRAY_TYPE_NAME)?;
+        let string_type = storage.get_or_create_named(STRING_TYPE_NAME)?;
+        let number_type = storage.get_or_create_named(NUMBER_TYPE_NAME)?;
+        let true_value = storage.get_or_create_named(TRUE_VALUE_NAME)?;
+        let false_value = storage.get_or_create_named(FALSE_VALUE_NAME)?;
+        let null_value = storage.get_or_create_named(NULL_VALUE_NAME)?;
+
+        Ok(Self {
+            storage,
+            balanced_variant_converter: BalancedVariantConverter::new(),
+            object_type,
+            array_type,
+            string_type,
+            number_type,
+            true_value,
+            false_value,
+            null_value,
+        })
+    }
+
+    /// Stores a JSON value and returns the link that represents it.
+    pub fn import(&mut self, value: &Value) -> Result<T> {
+        match value {
+            Value::Null => Ok(self.null_value),
+            Value::Bool(true) => Ok(self.true_value),
+            Value::Bool(false) => Ok(self.false_value),
+            Value::Number(number) => self.create_text_value(self.number_type, &number.to_string()),
+            Value::String(text) => self.create_text_value(self.string_type, text),
+            Value::Array(items) => {
+                let elements = items
+                    .iter()
+                    .map(|item| self.import(item))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(self.create_container(self.array_type, &elements))
+            }
+            Value::Object(members) => {
+                let mut elements = Vec::with_capacity(members.len());
+                for (key, member) in members {
+                    let key_link = self.key_link(key)?;
+                    let value_link = self.import(member)?;
+                    elements.push(self.storage.get_or_create(key_link, value_link));
+                }
+                Ok(self.create_container(self.object_type, &elements))
+            }
+        }
+    }
+
+    /// Reconstructs the JSON value stored at the given link.
+    pub fn export(&mut self, link: T) -> Result<Value> {
+        if link == self.null_value {
+            return Ok(Value::Null);
+        }
+        if link == self.true_value {
+            return Ok(Value::Bool(true));
+        }
+        if link == self.false_value {
+            return Ok(Value::Bool(false));
+        }
+
+        let node = *self
+            .storage
+            .get(link)
+            .ok_or(LinkError::NotFound(link.into()))?;
+        if node.source == self.string_type {
+            return Ok(Value::String(self.read_text_value(&node)?));
+        }
+        if node.source == self.number_type {
+            let text = self.read_text_value(&node)?;
+            let number = text
+                .parse::<Number>()
+                .with_context(|| format!("Link {link} stores an invalid JSON number: {text}"))?;
+            return Ok(Value::Number(number));
+        }
+        if node.source == self.array_type {
+            let value_types = self.value_types();
+            let scalar_values = self.scalar_values();
+            let elements = self.container_elements(&node, |_, element| {
+                value_types.contains(&element.source) || scalar_values.contains(&element.index)
+            });
+            return elements
+                .into_iter()
+                .map(|element| self.export(element))
+                .collect::<Result<Vec<_>>>()
+                .map(Value::Array);
+        }
+        if node.source == self.object_type {
+            let members = self.container_elements(&node, |links, member| {
+                links.get(member.source).is_some_and(Doublet::is_full_point)
+            });
+            let mut object = Map::new();
+            for member in members {
+                let member = *self
+                    .storage
+                    .get(member)
+                    .ok_or(LinkError::NotFound(member.into()))?;
+                let key = NamedTypes::get_name(self.storage, member.source)?.ok_or_else(|| {
+                    anyhow::anyhow!("JSON object key link {} has no name.", member.source)
+                })?;
+                object.insert(key, self.export(member.target)?);
+            }
+            return Ok(Value::Object(object));
+        }
+
+        bail!("Link {link} is not a JSON value.")
+    }
+
+    fn value_types(&self) -> [T; 4] {
+        [
+            self.object_type,
+            self.array_type,
+            self.string_type,
+            self.number_type,
+        ]
+    }
+
+    fn scalar_values(&self) -> [T; 3] {
+        [self.true_value, self.false_value, self.null_value]
+    }
+
+    fn key_link(&mut self, key: &str) -> Result<T> {
+        let link = self.storage.get_or_create_named(key)?;
+        if !self.storage.get(link).is_some_and(Doublet::is_full_point) {
+            bail!("JSON key '{key}' is already the name of non-point link {link}.");
+        }
+        Ok(link)
+    }
+
+    fn create_container(&mut self, container_type: T, elements: &[T]) -> T {
+        if elements.is_empty() {
+            return self.storage.get_or_create(container_type, container_type);
+        }
+        let sequence = self
+            .balanced_variant_converter
+            .convert(self.storage.links_mut(), elements);
+        self.storage.get_or_create(container_type, sequence)
+    }
+
+    fn container_elements(
+        &self,
+        container: &Doublet<T>,
+        is_element: impl Fn(&DoubletStorage<T>, &Doublet<T>) -> bool,
+    ) -> Vec<T> {
+        if container.target == container.source {
+            return Vec::new();
+        }
+
+        let links = self.storage.links();
+        let mut output = Vec::new();
+        let mut stack = DefaultStack::new();
+        stack.push(container.target);
+
+        while let Some(element) = stack.pop() {
+            match links.get(element) {
+                Some(link) if !is_element(links, link) => {
+                    stack.push(link.target);
+                    stack.push(link.source);
+                }
+                _ => output.push(element),
+            }
+        }
+
+        output
+    }
+
+    fn create_text_value(&mut self, value_type: T, content: &str) -> Result<T> {
+        let string_link = self.storage.create_string(content)?;
+        Ok(self
+            .storage
+            .get_or_create(value_type, external_reference(string_link)))
+    }
+
+    fn read_text_value(&mut self, node: &Doublet<T>) -> Result<String> {
+        let Some(string_link) = external_reference_value(node.target) else {
+            bail!(
+                "Link {} does not reference a string in the names database.",
+                node.index
+            );
+        };
+        self.storage.get_string(string_link)
+    }
+}
@@ -7,44 +7,99 @@
 //! # Modules
 //!
 //! - `link` - The core Link data structure
+//! - `link_address` - Address types links can be stored with (`u32`, `u64`)
 //! - `error` - Error types for link operations
 //! - `lino_link` - LiNo link representation
 //! - `parser` - LiNo notation parser
 //! - `link_storage` - Persistent link storage
+//! - `name_resolver` - Cached name lookups for `NamedTypesDecorator`
+//! - `name_search` - Prefix and substring search over stored names
 //! - `changes_simplifier` - Changes simplification
 //! - `query_processor` - LiNo query processing
+//! - `structure_format` - Cycle-safe link structure rendering
+//! - `export_filter` - Link selection for partial LiNo exports
+//! - `json_documents` - JSON document import and export as link trees
+//! - `database_stats` - Link and name counts for `clink stats`
+//! - `database_checker` - Integrity checks and repairs for `clink check`
+//! - `database_compactor` - Dense id renumbering for `clink compact`
+//! - `names_collector` - Garbage collection of the names database for `clink gc`
+//! - `change_recorder` - Revertible recording of link and name changes
+//! - `change_history` - Persisted undo/redo history of applied queries
+//! - `snapshot_store` - Named snapshots sharing unchanged database chunks
+//! - `database_diff` - Link differences between two databases
+//! - `database_merge` - Merging databases with id remapping
+//! - `three_way_merge` - Three-way merge of database files for git
+//! - `repl` - Interactive session over a loaded database
 
+mod change_history;
+mod change_recorder;
 mod changes_simplifier;
 pub mod cli;
+mod database_checker;
+mod database_compactor;
+mod database_diff;
+mod database_merge;
+mod database_stats;
 mod error;
+mod export_filter;
 mod hybrid_reference;
+mod json_documents;
 mod link;
+mod link_address;
 mod link_reference_validator;
 mod link_storage;
 mod lino_link;
+mod name_resolver;
+mod name_search;
 mod named_links;
 mod named_type_links;
 mod named_types;
+mod names_collector;
 mod parser;
 mod pinned_types;
 mod query_options;
 mod query_processor;
 mod query_types;
+mod repl;
 pub mod sequences;
+mod snapshot_store;
+mod structure_format;
+mod three_way_merge;
 mod unicode_string_storage;
 
 // Re-export main types for easy access
+pub use change_history::{ChangeHistory, HistoryEntry};
+pub use change_recorder::{apply_changes, revert_changes, ChangeRecorder, StorageChange};
 pub use changes_simplifier::simplify_changes;
+pub use database_checker::{DatabaseChecker, IntegrityIssue, IntegrityReport, ReferenceRole};
+pub use database_compactor::{CompactionReport, DatabaseCompactor, MovedLink};
+pub use database_diff::DatabaseDiff;
+pub use database_merge::{DatabaseMerge, MergeOutcome, MergeReport, MergedLink};
+pub use database_stats::DatabaseStats;
 pub use error::LinkError;
-pub use hybrid_reference::{external_reference, external_reference_value, HybridReference};
-pub use link::{DoubletsLink, Link};
-pub use link_storage::LinkStorage;
+pub use export_filter::ExportFilter;
+pub use hybrid_reference::{external_reference, external_reference_value, Hybrid, HybridReference};
+pub use json_documents::JsonDocuments;
+pub use link::{Doublet, DoubletChange, DoubletsLink, Link};
+pub use link_address::LinkAddress;
+pub use link_storage::{
+    database_address_width, DoubletStorage, FreeIdRange, IdAllocation, InvalidationHook,
+    LinkStorage, LoadMode, LoadWarning,
+};
 pub use lino_link::LinoLink;
+pub use name_search::{NameMatch, NameSearch};
 pub use named_links::NamedLinks;
 pub use named_type_links::NamedTypeLinks;
-pub use named_types::{NamedTypes, NamedTypesDecorator};
+pub use named_types::{NamedTypes, NamedTypesDecorator, NamesLayout};
+pub use names_collector::{CollectionReport, NamesCollector};
 pub use parser::Parser;
 pub use pinned_types::{PinnedTypes, PinnedTypesAccess, PinnedTypesDecorator};
 pub use query_options::QueryOptions;
 pub use query_processor::QueryProcessor;
+pub use repl::{Repl, ReplFlow};
+pub use snapshot_store::{ListedSnapshot, SnapshotInfo, SnapshotStore};
+pub use structure_format::{StructureFormat, StructureLayout};
+pub use three_way_merge::{
+    merge_link_files, merge_separate_files, MergeConflict, ThreeWayMergeResult,
+};
 pub use unicode_string_storage::UnicodeStringStorage;
@@ -3,17 +3,26 @@
 //! This module provides the core Link data structure that represents
 //! a link in the doublet storage.
 
-/// Link represents a doublet (source, target) pair with an index
+use crate::link_address::LinkAddress;
+
+/// Doublet represents a (source, target) pair with an index of any address
+/// width
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
-pub struct Link {
-    pub index: u32,
-    pub source: u32,
-    pub target: u32,
+pub struct Doublet<T: LinkAddress> {
+    pub index: T,
+    pub source: T,
+    pub target: T,
 }
 
-impl Link {
+/// Link represents a doublet (source, target) pair with a `u32` index
+pub type Link = Doublet<u32>;
+
+/// A link before and after a change; `None` where the link does not exist
+pub type DoubletChange<T = u32> = (Option<Doublet<T>>, Option<Doublet<T>>);
+
+impl<T: LinkAddress> Doublet<T> {
     /// Creates a new link with the given index, source, and target
-    pub fn new(index: u32, source: u32, target: u32) -> Self {
+    pub fn new(index: T, source: T, target: T) -> Self {
         Self {
             index,
             source,
@@ -23,7 +32,7 @@ impl Link {
 
     /// Returns true if this link is null (all zeros)
     pub fn is_null(&self) -> bool {
-        self.index == 0 && self.source == 0 && self.target == 0
+        self.index == T::ZERO && self.source == T::ZERO && self.target == T::ZERO
     }
 
     /// Returns true if this is a full point (self-referential link)
@@ -0,0 +1,59 @@
+//! Address types that links can be stored with.
+//!
+//! `u32` addresses match the C# `uint` databases and are used throughout the
+//! CLI. `u64` addresses lift the limit of about two billion links, half of
+//! that once external references are used.
+
+use std::fmt;
+use std::hash::Hash;
+use std::ops::{Add, Div, Sub};
+use std::str::FromStr;
+
+/// An unsigned integer type used for link indices, sources and targets.
+pub trait LinkAddress:
+    Copy
+    + Eq
+    + Ord
+    + Hash
+    + Default
+    + fmt::Debug
+    + fmt::Display
+    + FromStr
+    + From<u16>
+    + From<u32>
+    + Into<u64>
+    + TryFrom<u64>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Div<Output = Self>
+    + Send
+    + Sync
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+    /// Width of the address in bits, recorded in database files.
+    const BITS: u32;
+
+    fn wrapping_sub(self, other: Self) -> Self;
+}
+
+macro_rules! impl_link_address {
+    ($($address:ty),*) => {
+        $(
+            impl LinkAddress for $address {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$address>::MAX;
+                const BITS: u32 = <$address>::BITS;
+
+                fn wrapping_sub(self, other: Self) -> Self {
+                    <$address>::wrapping_sub(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_link_address!(u32, u64);
@@ -2,25 +2,38 @@ use anyhow::Result;
 use std::collections::HashSet;
 
 use crate::error::LinkError;
-use crate::link::Link;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
 use crate::lino_link::LinoLink;
 use crate::named_type_links::NamedTypeLinks;
+use crate::query_types::SubsequencePattern;
 
 pub(crate) struct LinkReferenceValidator {
     trace: bool,
     auto_create_missing_references: bool,
 }
 
-#[derive(Debug, Default)]
-struct LinkReferencePlan {
-    numeric_ids_to_be_created: HashSet<u32>,
+#[derive(Debug)]
+struct LinkReferencePlan<T> {
+    numeric_ids_to_be_created: HashSet<T>,
     names_to_be_created: HashSet<String>,
-    missing_references: Vec<MissingLinkReference>,
+    missing_references: Vec<MissingLinkReference<T>>,
     missing_reference_keys: HashSet<String>,
 }
 
-impl LinkReferencePlan {
-    fn add_missing_reference(&mut self, reference: MissingLinkReference) {
+impl<T> Default for LinkReferencePlan<T> {
+    fn default() -> Self {
+        Self {
+            numeric_ids_to_be_created: HashSet::new(),
+            names_to_be_created: HashSet::new(),
+            missing_references: Vec::new(),
+            missing_reference_keys: HashSet::new(),
+        }
+    }
+}
+
+impl<T: LinkAddress> LinkReferencePlan<T> {
+    fn add_missing_reference(&mut self, reference: MissingLinkReference<T>) {
         let key = reference.key();
         if self.missing_reference_keys.insert(key) {
             self.missing_references.push(reference);
@@ -29,13 +42,13 @@ impl LinkReferencePlan {
 }
 
 #[derive(Debug, Clone)]
-struct MissingLinkReference {
+struct MissingLinkReference<T> {
     identifier: String,
     pattern_type: &'static str,
-    numeric_id: Option<u32>,
+    numeric_id: Option<T>,
 }
 
-impl MissingLinkReference {
+impl<T: LinkAddress> MissingLinkReference<T> {
     fn key(&self) -> String {
         self.numeric_id
             .map(|id| format!("id:{id}"))
@@ -51,12 +64,12 @@ impl LinkReferenceValidator {
         }
     }
 
-    pub(crate) fn validate_links_exist_or_will_be_created(
+    pub(crate) fn validate_links_exist_or_will_be_created<T: LinkAddress>(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         restriction_patterns: &[LinoLink],
         substitution_patterns: &[LinoLink],
-    ) -> Result<Vec<Link>> {
+    ) -> Result<Vec<Doublet<T>>> {
         self.trace_msg("[ValidateLinksExistOrWillBeCreated] Starting validation");
 
         let mut plan = self.build_link_reference_plan(storage, substitution_patterns);
@@ -103,11 +116,11 @@ impl LinkReferenceValidator {
         Ok(created)
     }
 
-    fn build_link_reference_plan(
+    fn build_link_reference_plan<T: LinkAddress>(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         substitution_patterns: &[LinoLink],
-    ) -> LinkReferencePlan {
+    ) -> LinkReferencePlan<T> {
         let mut plan = LinkReferencePlan::default();
         let mut reserved_numeric_ids = HashSet::new();
 
@@ -127,15 +140,15 @@ impl LinkReferenceValidator {
         plan
     }
 
-    fn collect_explicit_definitions(
+    fn collect_explicit_definitions<T: LinkAddress>(
         &self,
         pattern: &LinoLink,
-        plan: &mut LinkReferencePlan,
-        reserved_numeric_ids: &mut HashSet<u32>,
+        plan: &mut LinkReferencePlan<T>,
+        reserved_numeric_ids: &mut HashSet<T>,
     ) {
         if Self::is_composite_lino(pattern) {
             if let Some(identifier) = Self::concrete_identifier(pattern.id.as_deref()) {
-                if let Ok(link_id) = identifier.parse::<u32>() {
+                if let Ok(link_id) = identifier.parse::<T>() {
                     plan.numeric_ids_to_be_created.insert(link_id);
                     reserved_numeric_ids.insert(link_id);
                 } else {
@@ -151,12 +164,12 @@ impl LinkReferenceValidator {
         }
     }
 
-    fn collect_implicit_definitions(
+    fn collect_implicit_definitions<T: LinkAddress>(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         pattern: &LinoLink,
-        plan: &mut LinkReferencePlan,
-        reserved_numeric_ids: &mut HashSet<u32>,
+        plan: &mut LinkReferencePlan<T>,
+        reserved_numeric_ids: &mut HashSet<T>,
     ) {
         if let Some(values) = &pattern.values {
             for sub_pattern in values {
@@ -173,21 +186,21 @@ impl LinkReferenceValidator {
         }
     }
 
-    fn next_available_link_id(
-        storage: &mut impl NamedTypeLinks,
-        reserved_numeric_ids: &HashSet<u32>,
-    ) -> u32 {
-        let mut next_id = 1;
+    fn next_available_link_id<T: LinkAddress>(
+        storage: &mut impl NamedTypeLinks<T>,
+        reserved_numeric_ids: &HashSet<T>,
+    ) -> T {
+        let mut next_id = T::ONE;
         while storage.exists(next_id) || reserved_numeric_ids.contains(&next_id) {
-            next_id += 1;
+            next_id = next_id + T::ONE;
         }
         next_id
     }
 
-    fn collect_missing_references(
+    fn collect_missing_references<T: LinkAddress>(
         &self,
-        storage: &mut impl NamedTypeLinks,
-        plan: &mut LinkReferencePlan,
+        storage: &mut impl NamedTypeLinks<T>,
+        plan: &mut LinkReferencePlan<T>,
         patterns: &[LinoLink],
         is_substitution: bool,
         pattern_type: &'static str,
@@ -204,10 +217,10 @@ impl LinkReferenceValidator {
         Ok(())
     }
 
-    fn collect_missing_references_in_pattern(
+    fn collect_missing_references_in_pattern<T: LinkAddress>(
         &self,
-        storage: &mut impl NamedTypeLinks,
-        plan: &mut LinkReferencePlan,
+        storage: &mut impl NamedTypeLinks<T>,
+        plan: &mut LinkReferencePlan<T>,
         pattern: &LinoLink,
         is_substitution: bool,
         pattern_type: &'static str,
@@ -222,8 +235,10 @@ impl LinkReferenceValidator {
             }
         }
 
+        // The `contains` keyword of a subsequence restriction is not a link.
+        let skipped_values = usize::from(SubsequencePattern::from_lino(pattern).is_some());
         if let Some(values) = &pattern.values {
-            for sub_pattern in values {
+            for sub_pattern in &values[skipped_values..] {
                 self.collect_missing_references_in_pattern(
                     storage,
                     plan,
@@ -236,14 +251,14 @@ impl LinkReferenceValidator {
         Ok(())
     }
 
-    fn validate_reference_identifier(
+    fn validate_reference_identifier<T: LinkAddress>(
         &self,
-        storage: &mut impl NamedTypeLinks,
-        plan: &mut LinkReferencePlan,
+        storage: &mut impl NamedTypeLinks<T>,
+        plan: &mut LinkReferencePlan<T>,
         identifier: &str,
         pattern_type: &'static str,
     ) -> Result<()> {
-        if let Ok(link_id) = identifier.parse::<u32>() {
+        if let Ok(link_id) = identifier.parse::<T>() {
             if !storage.exists(link_id) && !plan.numeric_ids_to_be_created.contains(&link_id) {
                 plan.add_missing_reference(MissingLinkReference {
                     identifier: identifier.to_string(),
@@ -275,11 +290,11 @@ impl LinkReferenceValidator {
         Ok(())
     }
 
-    fn auto_create_missing_references(
+    fn auto_create_missing_references<T: LinkAddress>(
         &self,
-        storage: &mut impl NamedTypeLinks,
-        missing_references: &[MissingLinkReference],
-    ) -> Result<Vec<Link>> {
+        storage: &mut impl NamedTypeLinks<T>,
+        missing_references: &[MissingLinkReference<T>],
+    ) -> Result<Vec<Doublet<T>>> {
         let mut created = Vec::new();
         let mut numeric_references = missing_references
             .iter()
@@ -2,74 +2,250 @@
 //!
 //! This module provides the LinkStorage struct for managing link persistence.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
+use std::rc::Weak;
 
 use crate::error::LinkError;
-use crate::link::Link;
+use crate::hybrid_reference::{external_reference, external_reference_value};
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::structure_format::{render_structure, StructureFormat};
+
+mod free_ids;
+
+pub use free_ids::FreeIdRange;
+pub(crate) use free_ids::FreeIds;
+
+/// How `LinkStorage` treats lines of the database file that cannot be parsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoadMode {
+    /// Fail with `LinkError::InvalidFormat` on the first malformed line.
+    #[default]
+    Strict,
+    /// Skip malformed lines and keep them as `load_warnings`. Such a storage
+    /// refuses to `save`, because that would drop the skipped lines.
+    Lenient,
+}
+
+/// How `LinkStorage::create` picks the id of a new link.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdAllocation {
+    /// Every new link gets an id above all ids ever used.
+    #[default]
+    Monotonic,
+    /// New links take the most recently deleted id first, like the free
+    /// list of the C# doublets storage. Deleting the highest link also
+    /// releases the free ids directly below it. The free ids are saved in a
+    /// `#! free-ids` line, which switches the database to this mode on load.
+    ReuseDeleted,
+}
+
+/// Prefix of the line that stores the free ids, bottom of the list first.
+pub(crate) const FREE_IDS_DIRECTIVE: &str = "#! free-ids";
+
+/// Prefix of the line that records the address width of a database. Files
+/// without it store `u32` addresses.
+pub(crate) const ADDRESS_WIDTH_DIRECTIVE: &str = "#! address-width";
+
+pub(crate) const DEFAULT_ADDRESS_WIDTH: u32 = 32;
+
+/// Line that marks a single-file database, which keeps the links of its
+/// names database among its own links.
+pub(crate) const NAMES_DIRECTIVE: &str = "#! names";
+
+/// A database line skipped by a lenient load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadWarning {
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: malformed line: {}",
+            self.path, self.line, self.text
+        )
+    }
+}
+
+impl From<LoadWarning> for LinkError {
+    fn from(warning: LoadWarning) -> Self {
+        LinkError::InvalidFormat {
+            path: warning.path,
+            line: warning.line,
+            text: warning.text,
+        }
+    }
+}
 
-/// LinkStorage provides persistent storage for links
+/// Something that caches data derived from links, such as a converter
+/// cache, and has to forget it when one of those links changes.
+pub trait InvalidationHook<T: LinkAddress> {
+    /// Called after `link` was updated, deleted or moved to another id.
+    fn invalidate(&mut self, link: T);
+}
+
+/// DoubletStorage provides persistent storage for links of any address width
 /// Corresponds to the storage functionality in NamedLinksDecorator in C#
-pub struct LinkStorage {
-    links: HashMap<u32, Link>,
-    names: HashMap<u32, String>,
-    name_to_id: HashMap<String, u32>,
-    next_id: u32,
+pub struct DoubletStorage<T: LinkAddress> {
+    links: HashMap<T, Doublet<T>>,
+    names: HashMap<T, String>,
+    name_to_id: HashMap<String, T>,
+    next_id: T,
+    id_allocation: IdAllocation,
+    free_ids: FreeIds<T>,
     db_path: String,
+    holds_names: bool,
     trace: bool,
+    load_mode: LoadMode,
+    load_warnings: Vec<LoadWarning>,
+    invalidation_hooks: Vec<Weak<RefCell<dyn InvalidationHook<T>>>>,
 }
 
-impl LinkStorage {
-    /// Creates a new LinkStorage instance
+/// LinkStorage provides persistent storage for links with `u32` addresses
+pub type LinkStorage = DoubletStorage<u32>;
+
+impl<T: LinkAddress> DoubletStorage<T> {
+    /// Creates a new LinkStorage instance, loading the database strictly
     pub fn new(db_path: &str, trace: bool) -> Result<Self> {
-        let mut storage = Self {
-            links: HashMap::new(),
-            names: HashMap::new(),
-            name_to_id: HashMap::new(),
-            next_id: 1,
-            db_path: db_path.to_string(),
-            trace,
-        };
+        Self::with_load_mode(db_path, trace, LoadMode::Strict)
+    }
+
+    /// Creates a new LinkStorage instance with the given handling of
+    /// malformed lines. A database written with a different address width
+    /// fails to load, including a `u32` database opened with wider
+    /// addresses; see `widen`.
+    pub fn with_load_mode(db_path: &str, trace: bool, load_mode: LoadMode) -> Result<Self> {
+        Self::open(db_path, trace, load_mode, false)
+    }
+
+    /// Opens a database that may store `u32` addresses with the wider
+    /// addresses of `T`. Saving it records the new width, after which it no
+    /// longer opens as a `u32` database.
+    /// External references are re-encoded for the wider addresses.
+    pub fn widen(db_path: &str, trace: bool) -> Result<Self> {
+        Self::open(db_path, trace, LoadMode::Strict, true)
+    }
+
+    fn open(db_path: &str, trace: bool, load_mode: LoadMode, widen: bool) -> Result<Self> {
+        let mut storage = Self::empty(db_path, trace, load_mode);
 
         // Load existing database if it exists
         if Path::new(db_path).exists() {
-            storage.load()?;
+            let width_recorded = storage.load()?;
+            if !width_recorded && T::BITS != DEFAULT_ADDRESS_WIDTH && !storage.links.is_empty() {
+                if !widen {
+                    bail!(
+                        "{db_path} stores {DEFAULT_ADDRESS_WIDTH}-bit addresses, but was opened with {}-bit addresses. Open it with DoubletStorage::widen to convert it.",
+                        T::BITS
+                    );
+                }
+                storage.widen_external_references();
+            }
         }
 
         Ok(storage)
     }
 
-    /// Loads links from the database file
-    fn load(&mut self) -> Result<()> {
+    /// Reads database text that does not come from the file at the storage
+    /// path, such as a version handed to the merge driver. The storage is
+    /// strict and has no path.
+    pub(crate) fn from_text(text: &str) -> Result<Self> {
+        let mut storage = Self::empty("", false, LoadMode::Strict);
+        storage.read_lines(text.as_bytes())?;
+        Ok(storage)
+    }
+
+    fn empty(db_path: &str, trace: bool, load_mode: LoadMode) -> Self {
+        Self {
+            links: HashMap::new(),
+            names: HashMap::new(),
+            name_to_id: HashMap::new(),
+            next_id: T::ONE,
+            id_allocation: IdAllocation::Monotonic,
+            free_ids: FreeIds::default(),
+            db_path: db_path.to_string(),
+            holds_names: false,
+            trace,
+            load_mode,
+            load_warnings: Vec::new(),
+            invalidation_hooks: Vec::new(),
+        }
+    }
+
+    /// Loads links from the database file and tells whether it records its
+    /// address width
+    fn load(&mut self) -> Result<bool> {
         let file = File::open(&self.db_path)
             .with_context(|| format!("Failed to open database: {}", self.db_path))?;
+        self.read_lines(BufReader::new(file))
+    }
 
-        let reader = BufReader::new(file);
+    fn read_lines(&mut self, reader: impl BufRead) -> Result<bool> {
+        let mut width_recorded = false;
 
-        for line in reader.lines() {
+        for (line_index, line) in reader.lines().enumerate() {
             let line = line?;
             let line = line.trim();
 
-            if line.is_empty() || line.starts_with('#') {
+            if line == NAMES_DIRECTIVE {
+                self.holds_names = true;
                 continue;
             }
 
-            // Parse link format: (index source target) or (index source target "name")
-            if let Some((link, name)) = self.parse_link_line(line) {
-                self.links.insert(link.index, link);
-                if link.index >= self.next_id {
-                    self.next_id = link.index + 1;
+            if let Some(width) = line.strip_prefix(ADDRESS_WIDTH_DIRECTIVE) {
+                let width = width.trim();
+                if width != T::BITS.to_string() {
+                    bail!(
+                        "{} stores {width}-bit addresses, but was opened with {}-bit addresses.",
+                        self.db_path,
+                        T::BITS
+                    );
                 }
-                if let Some(name) = name {
-                    self.names.insert(link.index, name.clone());
-                    self.name_to_id.insert(name, link.index);
+                width_recorded = true;
+                continue;
+            }
+            if let Some(free_ids) = line.strip_prefix(FREE_IDS_DIRECTIVE) {
+                self.id_allocation = IdAllocation::ReuseDeleted;
+                match free_ids.split_whitespace().map(str::parse).collect() {
+                    Ok(ranges) => self.free_ids = FreeIds::from_ranges(ranges),
+                    Err(()) => self.reject_line(line_index, line)?,
                 }
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Parse link format: (index source target) or (index source target "name")
+            let Some((link, name)) = Self::parse_link_line(line) else {
+                self.reject_line(line_index, line)?;
+                continue;
+            };
+
+            self.links.insert(link.index, link);
+            if link.index >= self.next_id {
+                self.next_id = link.index + T::ONE;
+            }
+            if let Some(name) = name {
+                self.names.insert(link.index, name.clone());
+                self.name_to_id.insert(name, link.index);
             }
         }
 
+        if self.id_allocation == IdAllocation::ReuseDeleted {
+            self.track_free_ids();
+        }
+
         if self.trace {
             eprintln!(
                 "[TRACE] Loaded {} links from {}",
@@ -78,32 +254,73 @@ impl LinkStorage {
             );
         }
 
-        Ok(())
+        Ok(width_recorded)
     }
 
-    /// Parses a single link line from the database
-    fn parse_link_line(&self, line: &str) -> Option<(Link, Option<String>)> {
-        // Simple format: (index source target) or (index source target "name")
-        let line = line.trim_matches(|c| c == '(' || c == ')');
-        let parts: Vec<&str> = line.split_whitespace().collect();
+    /// Fails on a malformed line in strict mode, and keeps it as a warning
+    /// in lenient mode.
+    fn reject_line(&mut self, line_index: usize, line: &str) -> Result<()> {
+        let warning = LoadWarning {
+            path: self.db_path.clone(),
+            line: line_index + 1,
+            text: line.to_string(),
+        };
+        match self.load_mode {
+            LoadMode::Strict => Err(LinkError::from(warning).into()),
+            LoadMode::Lenient => {
+                self.load_warnings.push(warning);
+                Ok(())
+            }
+        }
+    }
 
-        if parts.len() >= 3 {
-            let index = parts[0].parse().ok()?;
-            let source = parts[1].parse().ok()?;
-            let target = parts[2].parse().ok()?;
-            let name = if parts.len() > 3 {
-                Some(parts[3].trim_matches('"').to_string())
-            } else {
-                None
-            };
-            return Some((Link::new(index, source, target), name));
+    /// Parses a single link line from the database: `(index source target)`
+    /// or `(index source target "name")`, with the name escaped as by
+    /// `escape_name`. Anything else, such as a missing parenthesis, extra
+    /// values or an unescaped quote in the name, is rejected.
+    pub(crate) fn parse_link_line(line: &str) -> Option<(Doublet<T>, Option<String>)> {
+        let inner = line.strip_prefix('(')?.strip_suffix(')')?;
+        let (numbers, name) = match inner.split_once('"') {
+            Some((numbers, quoted)) => {
+                let name = unescape_name(quoted.trim_end().strip_suffix('"')?)?;
+                if !numbers.ends_with(char::is_whitespace) {
+                    return None;
+                }
+                (numbers, Some(name))
+            }
+            None => (inner, None),
+        };
+        if numbers.contains(['(', ')']) {
+            return None;
         }
+        let parts: Vec<&str> = numbers.split_whitespace().collect();
+        let [index, source, target] = parts[..] else {
+            return None;
+        };
+        let link = Doublet::new(
+            index.parse().ok()?,
+            source.parse().ok()?,
+            target.parse().ok()?,
+        );
+        Some((link, name))
+    }
 
-        None
+    /// Returns the malformed lines skipped by a lenient load
+    pub fn load_warnings(&self) -> &[LoadWarning] {
+        &self.load_warnings
     }
 
     /// Saves all links to the database file
     pub fn save(&self) -> Result<()> {
+        if let Some(warning) = self.load_warnings.first() {
+            return Err(anyhow::anyhow!(
+                "Refusing to overwrite {} because {} malformed line(s) were skipped while loading it, first at line {}.",
+                self.db_path,
+                self.load_warnings.len(),
+                warning.line
+            ));
+        }
+
         let file = OpenOptions::new()
             .write(true)
             .create(true)
@@ -112,6 +329,57 @@ impl LinkStorage {
             .with_context(|| format!("Failed to create database: {}", self.db_path))?;
 
         let mut writer = BufWriter::new(file);
+        self.write_links(&mut writer)?;
+        writer.flush()?;
+
+        if self.trace {
+            eprintln!(
+                "[TRACE] Saved {} links to {}",
+                self.links.len(),
+                self.db_path
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Tells whether the links of the names database are kept among these
+    /// links, as in a single-file database
+    pub fn holds_names(&self) -> bool {
+        self.holds_names
+    }
+
+    /// Records whether these links hold the names database. Saving writes
+    /// a `#! names` line for it.
+    pub fn set_holds_names(&mut self, holds_names: bool) {
+        self.holds_names = holds_names;
+    }
+
+    /// Moves the storage to another file. Nothing is written until the next
+    /// save.
+    pub fn relocate(&mut self, db_path: &str) {
+        self.db_path = db_path.to_string();
+    }
+
+    /// Returns the database file path
+    pub fn path(&self) -> &str {
+        &self.db_path
+    }
+
+    pub(crate) fn write_links(&self, writer: &mut impl Write) -> Result<()> {
+        if T::BITS != DEFAULT_ADDRESS_WIDTH {
+            writeln!(writer, "{ADDRESS_WIDTH_DIRECTIVE} {}", T::BITS)?;
+        }
+        if self.id_allocation == IdAllocation::ReuseDeleted {
+            write!(writer, "{FREE_IDS_DIRECTIVE}")?;
+            for range in self.free_ids.ranges() {
+                write!(writer, " {range}")?;
+            }
+            writeln!(writer)?;
+        }
+        if self.holds_names {
+            writeln!(writer, "{NAMES_DIRECTIVE}")?;
+        }
 
         // Sort by index for consistent output
         let mut links: Vec<_> = self.links.values().collect();
@@ -122,32 +390,56 @@ impl LinkStorage {
                 writeln!(
                     writer,
                     "({} {} {} \"{}\")",
-                    link.index, link.source, link.target, name
+                    link.index,
+                    link.source,
+                    link.target,
+                    escape_name(name)
                 )?;
             } else {
                 writeln!(writer, "({} {} {})", link.index, link.source, link.target)?;
             }
         }
+        Ok(())
+    }
 
-        writer.flush()?;
+    /// Returns how new links get their ids
+    pub fn id_allocation(&self) -> IdAllocation {
+        self.id_allocation
+    }
 
-        if self.trace {
-            eprintln!(
-                "[TRACE] Saved {} links to {}",
-                self.links.len(),
-                self.db_path
-            );
+    /// Switches how new links get their ids. Switching to
+    /// `IdAllocation::ReuseDeleted` makes every unused id below the highest
+    /// link free, the lowest one on top.
+    pub fn set_id_allocation(&mut self, id_allocation: IdAllocation) {
+        if id_allocation == self.id_allocation {
+            return;
         }
+        self.id_allocation = id_allocation;
+        self.free_ids.clear();
+        if id_allocation == IdAllocation::ReuseDeleted {
+            self.next_id = self.links.keys().max().map_or(T::ONE, |&id| id + T::ONE);
+            self.track_free_ids();
+        }
+    }
 
-        Ok(())
+    /// Returns the free ids as ranges, the one reused next last
+    pub fn free_ids(&self) -> &[FreeIdRange<T>] {
+        self.free_ids.ranges()
     }
 
     /// Creates a new link and returns its ID
-    pub fn create(&mut self, source: u32, target: u32) -> u32 {
-        let id = self.next_id;
-        self.next_id += 1;
+    pub fn create(&mut self, source: T, target: T) -> T {
+        let id = match self.id_allocation {
+            IdAllocation::ReuseDeleted => self.free_ids.pop(),
+            IdAllocation::Monotonic => None,
+        }
+        .unwrap_or_else(|| {
+            let id = self.next_id;
+            self.next_id = id + T::ONE;
+            id
+        });
 
-        let link = Link::new(id, source, target);
+        let link = Doublet::new(id, source, target);
         self.links.insert(id, link);
 
         if self.trace {
@@ -158,49 +450,105 @@ impl LinkStorage {
     }
 
     /// Creates a link with a specific ID, ensuring all links up to that ID exist
-    pub fn ensure_created(&mut self, id: u32) -> u32 {
+    pub fn ensure_created(&mut self, id: T) -> T {
         if self.links.contains_key(&id) {
             return id;
         }
 
-        if self.next_id > id {
-            let link = Link::new(id, 0, 0);
-            self.links.insert(id, link);
-            if self.trace {
-                eprintln!("[TRACE] Ensured link: ({} 0 0)", id);
+        // Like the C# `EnsureCreated`, which creates links until the requested
+        // id comes up and deletes the others, freeing their ids again. The
+        // free list gets the same order without creating them one by one.
+        if self.id_allocation == IdAllocation::ReuseDeleted && id > T::ZERO {
+            if id < self.next_id {
+                self.free_ids.take(id);
+            } else {
+                self.free_ids.skip_to(self.next_id, id);
             }
-            return id;
         }
 
-        // Create placeholder links up to the requested ID
-        while self.next_id <= id {
-            let placeholder_id = self.next_id;
-            self.next_id += 1;
-            if placeholder_id == id {
-                let link = Link::new(id, 0, 0);
-                self.links.insert(id, link);
-                if self.trace {
-                    eprintln!("[TRACE] Ensured link: ({} 0 0)", id);
-                }
-                return id;
-            }
+        // In monotonic mode, ids skipped on the way to the requested one are
+        // left unused
+        if self.next_id <= id {
+            self.next_id = id + T::ONE;
+        }
+        let link = Doublet::new(id, T::ZERO, T::ZERO);
+        self.links.insert(id, link);
+        if self.trace {
+            eprintln!("[TRACE] Ensured link: ({} 0 0)", id);
         }
-
         id
     }
 
+    /// Moves links to the ids given by `mapping` and rewrites every source
+    /// and target that refers to a moved link. Ids missing from `mapping`
+    /// stay as they are, and the next created link follows the highest id.
+    pub fn renumber(&mut self, mapping: &HashMap<T, T>) {
+        let map = |id: T| mapping.get(&id).copied().unwrap_or(id);
+        self.links = self
+            .links
+            .values()
+            .map(|link| {
+                let link = Doublet::new(map(link.index), map(link.source), map(link.target));
+                (link.index, link)
+            })
+            .collect();
+        self.names = self
+            .names
+            .drain()
+            .map(|(id, name)| (map(id), name))
+            .collect();
+        self.name_to_id = self
+            .names
+            .iter()
+            .map(|(&id, name)| (name.clone(), id))
+            .collect();
+        self.next_id = self.links.keys().max().map_or(T::ONE, |&id| id + T::ONE);
+        if self.id_allocation == IdAllocation::ReuseDeleted {
+            self.free_ids.clear();
+            self.track_free_ids();
+        }
+
+        for (&from, &to) in mapping {
+            self.notify_invalidation(from);
+            self.notify_invalidation(to);
+        }
+
+        if self.trace {
+            eprintln!("[TRACE] Renumbered {} links", mapping.len());
+        }
+    }
+
+    /// Registers a hook called whenever a link is updated, deleted or
+    /// renumbered. The storage only keeps a weak reference, so a hook is
+    /// forgotten once its owner drops it.
+    pub fn add_invalidation_hook(&mut self, hook: Weak<RefCell<dyn InvalidationHook<T>>>) {
+        self.invalidation_hooks
+            .retain(|existing| existing.strong_count() > 0);
+        self.invalidation_hooks.push(hook);
+    }
+
+    fn notify_invalidation(&mut self, link: T) {
+        self.invalidation_hooks.retain(|hook| match hook.upgrade() {
+            Some(hook) => {
+                hook.borrow_mut().invalidate(link);
+                true
+            }
+            None => false,
+        });
+    }
+
     /// Gets a link by ID
-    pub fn get(&self, id: u32) -> Option<&Link> {
+    pub fn get(&self, id: T) -> Option<&Doublet<T>> {
         self.links.get(&id)
     }
 
     /// Checks if a link exists
-    pub fn exists(&self, id: u32) -> bool {
+    pub fn exists(&self, id: T) -> bool {
         self.links.contains_key(&id)
     }
 
     /// Updates a link's source and target
-    pub fn update(&mut self, id: u32, source: u32, target: u32) -> Result<Link> {
+    pub fn update(&mut self, id: T, source: T, target: T) -> Result<Doublet<T>> {
         if let Some(link) = self.links.get_mut(&id) {
             let before = *link;
             if self.trace {
@@ -211,14 +559,15 @@ impl LinkStorage {
             }
             link.source = source;
             link.target = target;
+            self.notify_invalidation(id);
             Ok(before)
         } else {
-            Err(LinkError::NotFound(id).into())
+            Err(LinkError::NotFound(id.into()).into())
         }
     }
 
     /// Deletes a link by ID
-    pub fn delete(&mut self, id: u32) -> Result<Link> {
+    pub fn delete(&mut self, id: T) -> Result<Doublet<T>> {
         // Also remove the name mapping
         if let Some(name) = self.names.remove(&id) {
             self.name_to_id.remove(&name);
@@ -231,24 +580,28 @@ impl LinkStorage {
                     link.index, link.source, link.target
                 );
             }
+            if self.id_allocation == IdAllocation::ReuseDeleted {
+                self.free_id(id);
+            }
+            self.notify_invalidation(id);
             Ok(link)
         } else {
-            Err(LinkError::NotFound(id).into())
+            Err(LinkError::NotFound(id.into()).into())
         }
     }
 
     /// Returns all links
-    pub fn all(&self) -> Vec<&Link> {
+    pub fn all(&self) -> Vec<&Doublet<T>> {
         self.links.values().collect()
     }
 
     /// Returns all links matching a query pattern
     pub fn query(
         &self,
-        index: Option<u32>,
-        source: Option<u32>,
-        target: Option<u32>,
-    ) -> Vec<&Link> {
+        index: Option<T>,
+        source: Option<T>,
+        target: Option<T>,
+    ) -> Vec<&Doublet<T>> {
         self.links
             .values()
             .filter(|link| {
@@ -260,7 +613,7 @@ impl LinkStorage {
     }
 
     /// Searches for a link with the given source and target
-    pub fn search(&self, source: u32, target: u32) -> Option<u32> {
+    pub fn search(&self, source: T, target: T) -> Option<T> {
         for link in self.links.values() {
             if link.source == source && link.target == target {
                 return Some(link.index);
@@ -270,7 +623,7 @@ impl LinkStorage {
     }
 
     /// Gets or creates a link with the given source and target
-    pub fn get_or_create(&mut self, source: u32, target: u32) -> u32 {
+    pub fn get_or_create(&mut self, source: T, target: T) -> T {
         if let Some(id) = self.search(source, target) {
             id
         } else {
@@ -279,7 +632,7 @@ impl LinkStorage {
     }
 
     /// Formats a link for display
-    pub fn format(&self, link: &Link) -> String {
+    pub fn format(&self, link: &Doublet<T>) -> String {
         // Use name if available
         let index_str = self
             .names
@@ -300,7 +653,7 @@ impl LinkStorage {
     }
 
     /// Formats a link as LiNo suitable for database export.
-    pub fn format_lino(&self, link: &Link) -> String {
+    pub fn format_lino(&self, link: &Doublet<T>) -> String {
         format!(
             "({}: {} {})",
             self.format_lino_reference(link.index),
@@ -338,48 +691,25 @@ impl LinkStorage {
     }
 
     /// Formats the structure of a link
-    pub fn format_structure(&self, id: u32) -> Result<String> {
-        if let Some(link) = self.get(id) {
-            Ok(self.format_structure_recursive(link, true))
-        } else {
-            Err(LinkError::NotFound(id).into())
-        }
+    pub fn format_structure(&self, id: T) -> Result<String> {
+        self.format_structure_with(id, &StructureFormat::default())
     }
 
-    /// Recursively formats a link structure
-    fn format_structure_recursive(&self, link: &Link, is_root: bool) -> String {
-        if link.is_full_point() && !is_root {
-            // Self-referential point - just show the name/id
-            return self
-                .names
-                .get(&link.index)
-                .cloned()
-                .unwrap_or_else(|| link.index.to_string());
+    /// Formats the structure of a link with a depth limit and layout
+    pub fn format_structure_with(&self, id: T, format: &StructureFormat) -> Result<String> {
+        if !self.exists(id) {
+            return Err(LinkError::NotFound(id.into()).into());
         }
-
-        let source_str = if link.source == link.index {
-            self.names
-                .get(&link.index)
-                .cloned()
-                .unwrap_or_else(|| link.index.to_string())
-        } else if let Some(source_link) = self.get(link.source) {
-            self.format_structure_recursive(source_link, false)
-        } else {
-            link.source.to_string()
-        };
-
-        let target_str = if link.target == link.index {
-            self.names
-                .get(&link.index)
-                .cloned()
-                .unwrap_or_else(|| link.index.to_string())
-        } else if let Some(target_link) = self.get(link.target) {
-            self.format_structure_recursive(target_link, false)
-        } else {
-            link.target.to_string()
-        };
-
-        format!("({} {})", source_str, target_str)
+        render_structure(id, format, |address| {
+            Ok(self.get(address).map(|link| {
+                let reference = self
+                    .names
+                    .get(&address)
+                    .cloned()
+                    .unwrap_or_else(|| address.to_string());
+                (*link, reference)
+            }))
+        })
     }
 
     /// Prints all links
@@ -392,7 +722,7 @@ impl LinkStorage {
     }
 
     /// Prints a change (before -> after)
-    pub fn print_change(&self, before: &Option<Link>, after: &Option<Link>) {
+    pub fn print_change(&self, before: &Option<Doublet<T>>, after: &Option<Doublet<T>>) {
         let before_text = before.map(|l| self.format(&l)).unwrap_or_default();
         let after_text = after.map(|l| self.format(&l)).unwrap_or_default();
         println!("({}) ({})", before_text, after_text);
@@ -401,12 +731,12 @@ impl LinkStorage {
     // Named links functionality (corresponds to NamedLinks.cs)
 
     /// Gets or creates a link with a name
-    pub fn get_or_create_named(&mut self, name: &str) -> u32 {
+    pub fn get_or_create_named(&mut self, name: &str) -> T {
         if let Some(&id) = self.name_to_id.get(name) {
             id
         } else {
             // Create a self-referential link for the name
-            let id = self.create(0, 0);
+            let id = self.create(T::ZERO, T::ZERO);
             self.update(id, id, id).ok();
             self.names.insert(id, name.to_string());
             self.name_to_id.insert(name.to_string(), id);
@@ -418,7 +748,7 @@ impl LinkStorage {
     }
 
     /// Sets the name for a link
-    pub fn set_name(&mut self, id: u32, name: &str) {
+    pub fn set_name(&mut self, id: T, name: &str) {
         // Remove old name mapping if exists
         if let Some(old_name) = self.names.remove(&id) {
             self.name_to_id.remove(&old_name);
@@ -431,17 +761,17 @@ impl LinkStorage {
     }
 
     /// Gets the name of a link
-    pub fn get_name(&self, id: u32) -> Option<&String> {
+    pub fn get_name(&self, id: T) -> Option<&String> {
         self.names.get(&id)
     }
 
     /// Gets a link ID by name
-    pub fn get_by_name(&self, name: &str) -> Option<u32> {
+    pub fn get_by_name(&self, name: &str) -> Option<T> {
         self.name_to_id.get(name).copied()
     }
 
     /// Removes the name for a link
-    pub fn remove_name(&mut self, id: u32) {
+    pub fn remove_name(&mut self, id: T) {
         if let Some(name) = self.names.remove(&id) {
             self.name_to_id.remove(&name);
             if self.trace {
@@ -455,7 +785,40 @@ impl LinkStorage {
         self.trace
     }
 
-    fn format_lino_reference(&self, id: u32) -> String {
+    /// Adds a deleted id to the free list. Deleting the highest link lowers
+    /// the next id instead, together with the free ids right below it.
+    fn free_id(&mut self, id: T) {
+        if id + T::ONE != self.next_id {
+            self.free_ids.push(id);
+            return;
+        }
+        self.next_id = self.free_ids.release_below(id);
+    }
+
+    /// Re-encodes the external references of links loaded from a `u32`
+    /// database, which sit in the upper half of the `u32` range, for the
+    /// range of `T`.
+    fn widen_external_references(&mut self) {
+        let widen = |reference: T| {
+            u32::try_from(reference.into())
+                .ok()
+                .and_then(external_reference_value)
+                .map_or(reference, |value| external_reference(T::from(value)))
+        };
+        for link in self.links.values_mut() {
+            link.source = widen(link.source);
+            link.target = widen(link.target);
+        }
+    }
+
+    /// Drops free ids that are in use or above the highest link, and adds
+    /// unused ids missing from the list at its bottom.
+    fn track_free_ids(&mut self) {
+        self.free_ids
+            .retrack(self.links.keys().copied(), self.next_id);
+    }
+
+    fn format_lino_reference(&self, id: T) -> String {
         self.names
             .get(&id)
             .map(|name| escape_lino_reference(name))
@@ -463,6 +826,75 @@ impl LinkStorage {
     }
 }
 
+/// Reads the address width a database file records: 32 bits if it records
+/// none, or if the file does not exist yet.
+pub fn database_address_width(db_path: &str) -> Result<u32> {
+    if !Path::new(db_path).exists() {
+        return Ok(DEFAULT_ADDRESS_WIDTH);
+    }
+    let text = std::fs::read_to_string(db_path)
+        .with_context(|| format!("Failed to open database: {db_path}"))?;
+    recorded_address_width(&text).with_context(|| format!("Failed to read {db_path}"))
+}
+
+/// Reads the address width recorded by the text of a database file.
+pub(crate) fn recorded_address_width(text: &str) -> Result<u32> {
+    let width = text
+        .lines()
+        .map(str::trim)
+        .find_map(|line| line.strip_prefix(ADDRESS_WIDTH_DIRECTIVE));
+    let Some(width) = width else {
+        return Ok(DEFAULT_ADDRESS_WIDTH);
+    };
+    width
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid address width: {}", width.trim()))
+}
+
+/// Escapes a name for the quotes of a database line: backslashes, quotes and
+/// line breaks get a backslash.
+pub(crate) fn escape_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for character in name.chars() {
+        match character {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Reverses `escape_name`, or returns `None` for an unescaped quote. Other
+/// backslashes are kept as they are, so names saved before escaping still
+/// load.
+fn unescape_name(escaped: &str) -> Option<String> {
+    let mut name = String::with_capacity(escaped.len());
+    let mut characters = escaped.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' => return None,
+            '\\' => match characters.peek() {
+                Some('\\' | '"') => name.extend(characters.next()),
+                Some('n') => {
+                    characters.next();
+                    name.push('\n');
+                }
+                Some('r') => {
+                    characters.next();
+                    name.push('\r');
+                }
+                _ => name.push('\\'),
+            },
+            _ => name.push(character),
+        }
+    }
+    Some(name)
+}
+
 fn escape_lino_reference(reference: &str) -> String {
     if reference.is_empty() || reference.trim().is_empty() {
         return String::new();
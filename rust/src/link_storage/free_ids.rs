@@ -0,0 +1,236 @@
+//! The free list of `IdAllocation::ReuseDeleted`, kept as runs of ids so that
+//! its size follows the number of deletions and gaps rather than the highest
+//! id.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::link_address::LinkAddress;
+
+/// Ids freed one after another, reused from `top` back to `bottom`. `bottom`
+/// is below `top` for ids freed in ascending order and above it for ids freed
+/// in descending order. In a `#! free-ids` line it reads `bottom-top`, or just
+/// the id when both are the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FreeIdRange<T> {
+    pub bottom: T,
+    pub top: T,
+}
+
+impl<T: LinkAddress> FreeIdRange<T> {
+    pub fn new(bottom: T, top: T) -> Self {
+        Self { bottom, top }
+    }
+
+    pub fn single(id: T) -> Self {
+        Self::new(id, id)
+    }
+
+    fn low(&self) -> T {
+        self.bottom.min(self.top)
+    }
+
+    fn high(&self) -> T {
+        self.bottom.max(self.top)
+    }
+
+    fn contains(&self, id: T) -> bool {
+        self.low() <= id && id <= self.high()
+    }
+
+    /// The same ids, reused in the opposite order.
+    fn reversed(self) -> Self {
+        Self::new(self.top, self.bottom)
+    }
+}
+
+/// Returns the id next to `from` in the direction of `towards`.
+fn step<T: LinkAddress>(from: T, towards: T) -> T {
+    if towards > from {
+        from + T::ONE
+    } else {
+        from - T::ONE
+    }
+}
+
+impl<T: LinkAddress> fmt::Display for FreeIdRange<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bottom == self.top {
+            write!(f, "{}", self.bottom)
+        } else {
+            write!(f, "{}-{}", self.bottom, self.top)
+        }
+    }
+}
+
+impl<T: LinkAddress> FromStr for FreeIdRange<T> {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let parse = |id: &str| id.parse::<T>().ok().filter(|&id| id > T::ZERO).ok_or(());
+        match text.split_once('-') {
+            Some((bottom, top)) => Ok(Self::new(parse(bottom)?, parse(top)?)),
+            None => parse(text).map(Self::single),
+        }
+    }
+}
+
+/// The free ids as a stack of ranges, the one reused next last.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FreeIds<T> {
+    ranges: Vec<FreeIdRange<T>>,
+}
+
+impl<T: LinkAddress> FreeIds<T> {
+    pub(crate) fn from_ranges(ranges: Vec<FreeIdRange<T>>) -> Self {
+        Self { ranges }
+    }
+
+    pub(crate) fn ranges(&self) -> &[FreeIdRange<T>] {
+        &self.ranges
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let range = self.ranges.last_mut()?;
+        let id = range.top;
+        if range.bottom == range.top {
+            self.ranges.pop();
+        } else {
+            range.top = step(range.top, range.bottom);
+        }
+        Some(id)
+    }
+
+    pub(crate) fn push(&mut self, id: T) {
+        if let Some(range) = self.ranges.last_mut() {
+            let extends_up = range.bottom <= range.top && range.top + T::ONE == id;
+            let extends_down = range.bottom >= range.top && id + T::ONE == range.top;
+            if extends_up || extends_down {
+                range.top = id;
+                return;
+            }
+        }
+        self.ranges.push(FreeIdRange::single(id));
+    }
+
+    /// Frees the ids from `next_id` up to `id` the way creating links until
+    /// `id` comes up and deleting the others again would: the free ids are
+    /// taken first, so they are freed again in reverse order, and the new
+    /// ids follow.
+    pub(crate) fn skip_to(&mut self, next_id: T, id: T) {
+        self.ranges.reverse();
+        for range in &mut self.ranges {
+            *range = range.reversed();
+        }
+        if next_id < id {
+            self.ranges.push(FreeIdRange::new(next_id, id - T::ONE));
+        }
+    }
+
+    /// Takes the free `id` off the list the way creating links until `id`
+    /// comes up and deleting the others again would: the ids reused before it
+    /// are freed again in the order they were taken. Returns false if `id`
+    /// is not free.
+    pub(crate) fn take(&mut self, id: T) -> bool {
+        let Some(position) = self.ranges.iter().rposition(|range| range.contains(id)) else {
+            return false;
+        };
+        let above = self.ranges.split_off(position + 1);
+        let range = self.ranges.pop().expect("the range holding the id");
+        if range.bottom != id {
+            self.ranges
+                .push(FreeIdRange::new(range.bottom, step(id, range.bottom)));
+        }
+        self.ranges
+            .extend(above.into_iter().rev().map(FreeIdRange::reversed));
+        if range.top != id {
+            self.ranges
+                .push(FreeIdRange::new(range.top, step(id, range.top)));
+        }
+        true
+    }
+
+    /// Drops the free ids right below `next_id`, which was lowered by
+    /// deleting the highest link, and returns the next id below them.
+    pub(crate) fn release_below(&mut self, mut next_id: T) -> T {
+        let by_high = self
+            .ranges
+            .iter()
+            .enumerate()
+            .map(|(position, range)| (range.high(), position))
+            .collect::<HashMap<_, _>>();
+        let mut released = HashSet::new();
+        while let Some(&position) = by_high.get(&(next_id - T::ONE)) {
+            released.insert(position);
+            next_id = self.ranges[position].low();
+        }
+        if !released.is_empty() {
+            let mut position = 0;
+            self.ranges.retain(|_| {
+                position += 1;
+                !released.contains(&(position - 1))
+            });
+        }
+        next_id
+    }
+
+    /// Drops free ids that are in use, listed twice or not below `next_id`,
+    /// and adds unused ids missing from the list at its bottom, the lowest
+    /// one on top.
+    pub(crate) fn retrack(&mut self, used: impl Iterator<Item = T>, next_id: T) {
+        let mut used = used
+            .filter(|&id| id > T::ZERO && id < next_id)
+            .collect::<Vec<_>>();
+        used.sort_unstable();
+        let mut unused = BTreeMap::new();
+        let mut low = T::ONE;
+        for id in used {
+            if id > low {
+                unused.insert(low, id - T::ONE);
+            }
+            low = id + T::ONE;
+        }
+        if next_id > low {
+            unused.insert(low, next_id - T::ONE);
+        }
+
+        let mut tracked = Vec::new();
+        for range in std::mem::take(&mut self.ranges) {
+            let (low, high) = (range.low(), range.high());
+            let overlapping = unused
+                .range(..=high)
+                .rev()
+                .take_while(|(_, &end)| end >= low)
+                .map(|(&start, &end)| (start, end))
+                .collect::<Vec<_>>();
+            let mut pieces = Vec::with_capacity(overlapping.len());
+            for (start, end) in overlapping {
+                unused.remove(&start);
+                if start < low {
+                    unused.insert(start, low - T::ONE);
+                }
+                if end > high {
+                    unused.insert(high + T::ONE, end);
+                }
+                pieces.push(FreeIdRange::new(start.max(low), end.min(high)));
+            }
+            if range.bottom <= range.top {
+                tracked.extend(pieces.into_iter().rev());
+            } else {
+                tracked.extend(pieces.into_iter().map(FreeIdRange::reversed));
+            }
+        }
+
+        self.ranges = unused
+            .into_iter()
+            .rev()
+            .map(|(start, end)| FreeIdRange::new(end, start))
+            .chain(tracked)
+            .collect();
+    }
+}
@@ -3,32 +3,138 @@
 //! This is the Rust implementation of the link-cli tool, providing
 //! similar functionality to the C# version.
 
-use anyhow::Result;
-use link_cli::cli::{Cli, CliCommand};
-use link_cli::{NamedTypeLinks, NamedTypesDecorator, QueryProcessor};
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+use link_cli::cli::{
+    AliasAction, AliasCli, CheckCli, Cli, CliCommand, CompactCli, DiffCli, EncodingCli, ExportCli,
+    GcCli, HistoryCli, ImportCli, LayoutCli, MergeCli, MergeDriverCli, NamesAction, NamesCli,
+    ReplCli, SnapshotAction, SnapshotCli, StatsCli,
+};
+use link_cli::{
+    database_address_width, merge_link_files, merge_separate_files, ChangeHistory, ChangeRecorder,
+    DatabaseChecker, DatabaseCompactor, DatabaseDiff, DatabaseMerge, DatabaseStats, ExportFilter,
+    HistoryEntry, IdAllocation, JsonDocuments, LinkAddress, LoadMode, NameSearch, NamedTypeLinks,
+    NamedTypesDecorator, NamesCollector, NamesLayout, QueryProcessor, Repl, SnapshotStore,
+    StorageChange, StructureFormat, StructureLayout,
+};
+use regex::Regex;
+
+/// Runs `$run::<T>(..)` with the address type `T` of the given width, or of
+/// the width the database at `db = ..` records.
+macro_rules! with_address_width {
+    (db = $db:expr, $($run:tt)*) => {
+        with_address_width!(width = database_address_width($db)?, $($run)*)
+    };
+    (width = $width:expr, $run:ident($($argument:expr),*)) => {
+        match $width {
+            32 => $run::<u32>($($argument),*),
+            64 => $run::<u64>($($argument),*),
+            width => bail!("Unsupported address width: {width}"),
+        }
+    };
+}
 
 fn main() -> Result<()> {
-    let cli = match Cli::parse()? {
-        CliCommand::Run(cli) => cli,
+    match Cli::parse()? {
+        CliCommand::Run(cli) => {
+            with_address_width!(width = query_address_width(&cli)?, run_query(&cli))
+        }
+        CliCommand::Import(import) => with_address_width!(db = &import.db, import_json(&import)),
+        CliCommand::Export(export) => with_address_width!(db = &export.db, export_links(&export)),
+        CliCommand::Stats(stats) => with_address_width!(db = &stats.db, print_stats(&stats)),
+        CliCommand::Check(check) => with_address_width!(db = &check.db, check_database(&check)),
+        CliCommand::Names(names) => with_address_width!(db = &names.db, run_names(&names)),
+        CliCommand::Alias(alias) => with_address_width!(db = &alias.db, run_alias(&alias)),
+        CliCommand::Repl(repl) => with_address_width!(db = &repl.db, run_repl(&repl)),
+        CliCommand::Undo(undo) => with_address_width!(db = &undo.db, undo_queries(&undo)),
+        CliCommand::Redo(redo) => with_address_width!(db = &redo.db, redo_queries(&redo)),
+        CliCommand::Snapshot(snapshot) => {
+            with_address_width!(db = &snapshot.db, run_snapshot(&snapshot))
+        }
+        CliCommand::Diff(diff) => with_address_width!(db = &diff.before, print_diff(&diff)),
+        CliCommand::Merge(merge) => with_address_width!(db = &merge.db, merge_database(&merge)),
+        CliCommand::MergeDriver(driver) => run_merge_driver(&driver),
+        CliCommand::Compact(compact) => {
+            with_address_width!(db = &compact.db, compact_database(&compact))
+        }
+        CliCommand::Gc(gc) => with_address_width!(db = &gc.db, collect_names(&gc)),
+        CliCommand::Layout(layout) => with_address_width!(db = &layout.db, change_layout(&layout)),
+        CliCommand::Encoding(encoding) => {
+            with_address_width!(db = &encoding.db, change_encoding(&encoding))
+        }
         CliCommand::Help => {
             Cli::print_help();
-            return Ok(());
+            Ok(())
+        }
+        CliCommand::CommandHelp(subcommand) => {
+            print!("{}", subcommand.help_text());
+            Ok(())
         }
         CliCommand::Version => {
             println!("{}", Cli::version_text());
-            return Ok(());
+            Ok(())
         }
+    }
+}
+
+/// Returns the address width a query runs with: the one the database
+/// records, unless `--address-width` asks for another. A new database is
+/// created with that width right away; an existing 32-bit database is widened
+/// to 64 bits together with its names database.
+fn query_address_width(cli: &Cli) -> Result<u32> {
+    let recorded = database_address_width(&cli.db)?;
+    let Some(requested) = cli.address_width.filter(|&requested| requested != recorded) else {
+        return Ok(recorded);
     };
+    if Path::new(&cli.db).exists() {
+        if requested < recorded {
+            bail!(
+                "{} stores {recorded}-bit addresses, which cannot be narrowed to {requested} bits.",
+                cli.db
+            );
+        }
+        NamedTypesDecorator::<u64>::widen(&cli.db, cli.trace)?.save()?;
+        // Recorded changes hold the narrower external references.
+        ChangeHistory::discard(&cli.db)?;
+    } else {
+        NamedTypesDecorator::<u64>::open(&cli.db, cli.trace, LoadMode::Strict)?.save()?;
+    }
+    Ok(requested)
+}
 
+fn run_query<T: LinkAddress>(cli: &Cli) -> Result<()> {
     // Create link storage with separate named-type aliases.
-    let mut storage = NamedTypesDecorator::new(&cli.db, cli.trace)?;
+    let mut storage = open_database::<T>(&cli.db, cli.trace)?;
+    if let Some(reuse_deleted_ids) = cli.reuse_deleted_ids {
+        let id_allocation = if reuse_deleted_ids {
+            IdAllocation::ReuseDeleted
+        } else {
+            IdAllocation::Monotonic
+        };
+        if storage.links().id_allocation() != id_allocation {
+            storage.links_mut().set_id_allocation(id_allocation);
+            storage.save()?;
+        }
+    }
 
     // If --structure is provided, handle it separately
     if let Some(link_id) = cli.structure {
-        let structure_formatted = storage.format_structure(link_id)?;
+        let layout = if cli.tree {
+            StructureLayout::Tree
+        } else {
+            StructureLayout::Inline
+        };
+        let format = StructureFormat::new()
+            .with_max_depth(cli.depth)
+            .with_layout(layout);
+        let link_id = T::try_from(link_id).map_err(|_| {
+            anyhow::anyhow!("Link {link_id} does not fit {}-bit addresses.", T::BITS)
+        })?;
+        let structure_formatted = storage.format_structure_with(link_id, &format)?;
         println!("{}", structure_formatted);
         if let Some(output_path) = &cli.lino_output {
-            storage.write_lino_output(output_path)?;
+            write_export(&mut storage, cli, output_path)?;
         }
         return Ok(());
     }
@@ -49,7 +155,10 @@ fn main() -> Result<()> {
         if !query.is_empty() {
             let processor = QueryProcessor::new(cli.trace)
                 .with_auto_create_missing_references(cli.auto_create_missing_references);
-            changes_list = processor.process_query(&mut storage, query)?;
+            // Record the applied changes so that `clink undo` can revert them.
+            let mut recorder = ChangeRecorder::new(&mut storage);
+            changes_list = processor.process_query(&mut recorder, query)?;
+            record_history(&cli.db, query, recorder.into_changes())?;
         }
     }
 
@@ -66,8 +175,507 @@ fn main() -> Result<()> {
     }
 
     if let Some(output_path) = &cli.lino_output {
-        storage.write_lino_output(output_path)?;
+        write_export(&mut storage, cli, output_path)?;
+    }
+
+    Ok(())
+}
+
+fn write_export<T: LinkAddress>(
+    storage: &mut NamedTypesDecorator<T>,
+    cli: &Cli,
+    output_path: &str,
+) -> Result<()> {
+    let filter = export_filter(
+        storage,
+        &cli.export_roots,
+        cli.export_filter.clone(),
+        cli.export_closure,
+    )?;
+    storage.write_filtered_lino_output(output_path, &filter)
+}
+
+fn export_filter<T: LinkAddress>(
+    storage: &mut NamedTypesDecorator<T>,
+    roots: &[String],
+    restriction: Option<String>,
+    include_dependencies: bool,
+) -> Result<ExportFilter<T>> {
+    let roots = roots
+        .iter()
+        .map(|reference| storage.resolve_reference(reference))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ExportFilter::new()
+        .with_roots(roots)
+        .with_restriction(restriction)
+        .with_dependencies(include_dependencies))
+}
+
+fn import_json<T: LinkAddress>(import: &ImportCli) -> Result<()> {
+    let content = std::fs::read_to_string(&import.json)
+        .with_context(|| format!("Failed to read JSON document: {}", import.json))?;
+    let document: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse JSON document: {}", import.json))?;
+
+    let mut storage = open_database::<T>(&import.db, import.trace)?;
+    let root = JsonDocuments::new(&mut storage)?.import(&document)?;
+    storage.save()?;
+
+    println!("{root}");
+    Ok(())
+}
+
+fn export_links<T: LinkAddress>(export: &ExportCli) -> Result<()> {
+    let mut storage = open_for_reading::<T>(&export.db, export.trace)?;
+
+    if let Some(reference) = &export.json {
+        let root = storage.resolve_reference(reference)?;
+        let document = JsonDocuments::new(&mut storage)?.export(root)?;
+        println!("{}", serde_json::to_string_pretty(&document)?);
+        return Ok(());
+    }
+
+    let filter = export_filter(
+        &mut storage,
+        &export.roots,
+        export.filter.clone(),
+        export.closure,
+    )?;
+    match &export.out {
+        Some(path) => storage.write_filtered_lino_output(path, &filter),
+        None => {
+            for line in storage.filtered_lino_lines(&filter)? {
+                println!("{line}");
+            }
+            Ok(())
+        }
+    }
+}
+
+fn print_stats<T: LinkAddress>(stats: &StatsCli) -> Result<()> {
+    let mut storage = open_for_reading::<T>(&stats.db, stats.trace)?;
+    println!("{}", DatabaseStats::collect(&mut storage)?);
+    Ok(())
+}
+
+fn check_database<T: LinkAddress>(check: &CheckCli) -> Result<()> {
+    let checker = DatabaseChecker::<T>::for_database(&check.db).with_trace(check.trace);
+    let report = if check.repair {
+        checker.repair()?
+    } else {
+        checker.check()?
+    };
+
+    for issue in &report.issues {
+        if report.repaired.contains(issue) {
+            println!("repaired: {issue}");
+        } else {
+            println!("{issue}");
+        }
     }
 
+    let remaining = report.remaining().len();
+    if remaining > 0 {
+        bail!("Found {remaining} unrepaired problem(s).");
+    }
+    if report.issues.is_empty() {
+        println!("No problems found.");
+    }
     Ok(())
 }
+
+fn run_names<T: LinkAddress>(names: &NamesCli) -> Result<()> {
+    match &names.action {
+        NamesAction::List(search) => list_names::<T>(names, search.as_ref()),
+        NamesAction::Find(pattern) => find_names::<T>(names, pattern),
+        NamesAction::Show(link) => show_name::<T>(names, link),
+        NamesAction::Rename(old, new) => {
+            let mut storage = open_database::<T>(&names.db, names.trace)?;
+            let mut recorder = ChangeRecorder::new(&mut storage);
+            recorder.rename(old, new)?;
+            let changes = recorder.into_changes();
+            storage.save()?;
+            record_history(&names.db, &format!("names rename {old} {new}"), changes)
+        }
+        NamesAction::Unset(link) => {
+            let mut storage = open_database::<T>(&names.db, names.trace)?;
+            let mut recorder = ChangeRecorder::new(&mut storage);
+            let id = recorder.resolve_reference(link)?;
+            if recorder.aliases(id)?.contains(link) {
+                recorder.remove_alias(id, link)?;
+            } else if recorder.get_name(id)?.is_some() {
+                recorder.remove_name(id)?;
+            } else {
+                bail!("Link {id} has no name.");
+            }
+            let changes = recorder.into_changes();
+            storage.save()?;
+            record_history(&names.db, &format!("names unset {link}"), changes)
+        }
+    }
+}
+
+/// Prints the named links, or those with a name or alias matching
+/// `search`, ordered by link.
+fn list_names<T: LinkAddress>(names: &NamesCli, search: Option<&NameSearch>) -> Result<()> {
+    let mut storage = open_for_reading::<T>(&names.db, names.trace)?;
+    let mut links = match search {
+        Some(search) => storage.search_names(search)?,
+        None => storage.all_names()?,
+    }
+    .into_iter()
+    .map(|(link, _)| link)
+    .collect::<Vec<_>>();
+    links.sort_unstable();
+    links.dedup();
+    for link in links {
+        println!("{link} {}", storage.format_reference(link)?);
+    }
+    Ok(())
+}
+
+/// Prints every primary name and alias matching `pattern`, ordered by link.
+fn find_names<T: LinkAddress>(names: &NamesCli, pattern: &str) -> Result<()> {
+    let pattern = Regex::new(pattern).with_context(|| format!("Invalid pattern '{pattern}'."))?;
+    let mut storage = open_for_reading::<T>(&names.db, names.trace)?;
+    let mut found = storage.all_names()?;
+    found.extend(storage.all_aliases()?);
+    found.retain(|(_, name)| pattern.is_match(name));
+    found.sort();
+    for (link, name) in found {
+        println!("{link} {name}");
+    }
+    Ok(())
+}
+
+fn show_name<T: LinkAddress>(names: &NamesCli, link: &str) -> Result<()> {
+    let mut storage = open_for_reading::<T>(&names.db, names.trace)?;
+    let id = storage.resolve_reference(link)?;
+    let doublet = NamedTypeLinks::get_link(&mut storage, id)
+        .ok_or_else(|| anyhow::anyhow!("Link {id} does not exist."))?;
+    println!("link: {id}");
+    if let Some(name) = NamedTypeLinks::get_name(&mut storage, id)? {
+        println!("name: {name}");
+    }
+    let aliases = NamedTypeLinks::aliases(&mut storage, id)?;
+    if !aliases.is_empty() {
+        println!("aliases: {}", aliases.join(", "));
+    }
+    println!("lino: {}", storage.format_lino(&doublet)?);
+    Ok(())
+}
+
+fn run_alias<T: LinkAddress>(alias: &AliasCli) -> Result<()> {
+    if let AliasAction::List(link) = &alias.action {
+        let mut storage = open_for_reading::<T>(&alias.db, alias.trace)?;
+        let aliases = match link {
+            Some(link) => {
+                let id = storage.resolve_reference(link)?;
+                NamedTypeLinks::aliases(&mut storage, id)?
+                    .into_iter()
+                    .map(|alias| (id, alias))
+                    .collect()
+            }
+            None => storage.all_aliases()?,
+        };
+        for (link, alias) in aliases {
+            println!("{link} {alias}");
+        }
+        return Ok(());
+    }
+
+    let mut storage = open_database::<T>(&alias.db, alias.trace)?;
+    let mut recorder = ChangeRecorder::new(&mut storage);
+    let (action, link, name) = match &alias.action {
+        AliasAction::Add(link, name) => ("add", link, name),
+        AliasAction::Remove(link, name) => ("remove", link, name),
+        AliasAction::Primary(link, name) => ("primary", link, name),
+        AliasAction::List(_) => unreachable!("listed above"),
+    };
+    let id = recorder.resolve_reference(link)?;
+    if !recorder.exists(id) {
+        bail!("Link {id} does not exist.");
+    }
+    match action {
+        "add" => recorder.add_alias(id, name)?,
+        "remove" => recorder.remove_alias(id, name)?,
+        _ => recorder.set_primary_name(id, name)?,
+    }
+    let changes = recorder.into_changes();
+    storage.save()?;
+    record_history(&alias.db, &format!("alias {action} {link} {name}"), changes)
+}
+
+fn run_repl<T: LinkAddress>(repl: &ReplCli) -> Result<()> {
+    let storage = open_database::<T>(&repl.db, repl.trace)?;
+    let history = repl
+        .history
+        .as_ref()
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| Repl::make_history_filename(&repl.db));
+
+    let change_history = ChangeHistory::load(ChangeHistory::make_history_filename(&repl.db))?;
+
+    Repl::new(storage)
+        .with_change_history(Some(change_history))
+        .with_trace(repl.trace)
+        .with_auto_create_missing_references(repl.auto_create_missing_references)
+        .with_history_file(Some(history))
+        .run(std::io::stdin().lock(), &mut std::io::stdout())
+}
+
+/// Adds applied changes to the history used by `clink undo`.
+fn record_history<T: LinkAddress>(
+    db: &str,
+    query: &str,
+    changes: Vec<StorageChange<T>>,
+) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let mut history = ChangeHistory::load(ChangeHistory::make_history_filename(db))?;
+    history.record(HistoryEntry {
+        query: query.to_string(),
+        changes,
+    });
+    history.save()
+}
+
+fn undo_queries<T: LinkAddress>(undo: &HistoryCli) -> Result<()> {
+    let mut storage = open_database::<T>(&undo.db, undo.trace)?;
+    let mut history = ChangeHistory::load(ChangeHistory::make_history_filename(&undo.db))?;
+    let reverted = history.undo(&mut storage, undo.count)?;
+    if reverted.is_empty() {
+        println!("Nothing to undo.");
+        return Ok(());
+    }
+
+    storage.save()?;
+    history.save()?;
+    for entry in reverted {
+        println!("undone: {}", entry.query);
+    }
+    Ok(())
+}
+
+fn redo_queries<T: LinkAddress>(redo: &HistoryCli) -> Result<()> {
+    let mut storage = open_database::<T>(&redo.db, redo.trace)?;
+    let mut history = ChangeHistory::load(ChangeHistory::make_history_filename(&redo.db))?;
+    let reapplied = history.redo(&mut storage, redo.count)?;
+    if reapplied.is_empty() {
+        println!("Nothing to redo.");
+        return Ok(());
+    }
+
+    storage.save()?;
+    history.save()?;
+    for entry in reapplied {
+        println!("redone: {}", entry.query);
+    }
+    Ok(())
+}
+
+fn run_snapshot<T: LinkAddress>(snapshot: &SnapshotCli) -> Result<()> {
+    let store = SnapshotStore::new(&snapshot.db).with_trace(snapshot.trace);
+    match &snapshot.action {
+        SnapshotAction::Create(name) => {
+            let info = store.create(name)?;
+            println!(
+                "Created snapshot '{}' with {} links.",
+                info.name, info.links
+            );
+        }
+        SnapshotAction::List => {
+            for info in store.list()? {
+                println!("{info}");
+            }
+        }
+        SnapshotAction::Restore(name) => {
+            let info = store.restore(name)?;
+            println!(
+                "Restored snapshot '{}' with {} links.",
+                info.name, info.links
+            );
+        }
+        SnapshotAction::Diff(name, other) => {
+            let mut before = store.open::<T>(name)?;
+            let mut after = match other {
+                Some(other) => store.open(other)?,
+                None => open_for_reading::<T>(&snapshot.db, snapshot.trace)?,
+            };
+            for line in DatabaseDiff::new(&mut before, &mut after).lino_lines()? {
+                println!("{line}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_diff<T: LinkAddress>(diff: &DiffCli) -> Result<()> {
+    let mut before = open_for_reading::<T>(&diff.before, diff.trace)?;
+    let mut after = open_for_reading::<T>(&diff.after, diff.trace)?;
+    let lines = DatabaseDiff::new(&mut before, &mut after)
+        .with_match_by_name(diff.by_name)
+        .lino_lines()?;
+    for line in lines {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn merge_database<T: LinkAddress>(merge: &MergeCli) -> Result<()> {
+    let mut storage = open_database::<T>(&merge.db, merge.trace)?;
+    let mut other = open_database::<T>(&merge.other, merge.trace)?;
+    let mut recorder = ChangeRecorder::new(&mut storage);
+    let report = DatabaseMerge::new(&mut recorder, &mut other).merge()?;
+    let changes = recorder.into_changes();
+    storage.save()?;
+    record_history(&merge.db, &format!("merge {}", merge.other), changes)?;
+
+    for link in &report.links {
+        println!("{link}");
+    }
+    Ok(())
+}
+
+fn run_merge_driver(driver: &MergeDriverCli) -> Result<()> {
+    let read = |path: &str| {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))
+    };
+    let write = |path: &str, text: &str| {
+        std::fs::write(path, text).with_context(|| format!("Failed to write {path}"))
+    };
+    let (base, ours, theirs) = (
+        read(&driver.base)?,
+        read(&driver.ours)?,
+        read(&driver.theirs)?,
+    );
+    let result = match &driver.names {
+        Some([base_names, ours_names, theirs_names]) => {
+            let (result, names) = merge_separate_files(
+                [&base, &read(base_names)?],
+                [&ours, &read(ours_names)?],
+                [&theirs, &read(theirs_names)?],
+            )?;
+            write(ours_names, &names)?;
+            result
+        }
+        None => merge_link_files(&base, &ours, &theirs)?,
+    };
+    write(&driver.ours, &result.text)?;
+
+    if driver.trace {
+        for (theirs, merged) in &result.renumbered {
+            eprintln!("[TRACE] Moved their link {theirs} to {merged}");
+        }
+    }
+    for conflict in &result.conflicts {
+        eprintln!("{conflict}");
+    }
+    if !result.is_clean() {
+        bail!(
+            "{} conflict(s) left in {}.",
+            result.conflicts.len(),
+            driver.ours
+        );
+    }
+    Ok(())
+}
+
+fn compact_database<T: LinkAddress>(compact: &CompactCli) -> Result<()> {
+    let mut storage = open_database::<T>(&compact.db, compact.trace)?;
+    let report = DatabaseCompactor::new(&mut storage).compact()?;
+    if report.moved.is_empty() {
+        println!("Nothing to compact.");
+        return Ok(());
+    }
+    storage.save()?;
+
+    ChangeHistory::discard(&compact.db)?;
+    for link in &report.moved {
+        println!("{link}");
+    }
+    Ok(())
+}
+
+fn collect_names<T: LinkAddress>(gc: &GcCli) -> Result<()> {
+    let mut storage = open_database::<T>(&gc.db, gc.trace)?;
+    let report = NamesCollector::new(&mut storage).collect()?;
+    if report.reclaimed.is_empty() {
+        println!("Nothing to collect.");
+        return Ok(());
+    }
+    storage.save()?;
+    if gc.trace {
+        for link in &report.reclaimed {
+            eprintln!(
+                "[TRACE] Reclaimed names link ({} {} {})",
+                link.index, link.source, link.target
+            );
+        }
+    }
+    println!("{report}");
+    Ok(())
+}
+
+fn change_layout<T: LinkAddress>(layout: &LayoutCli) -> Result<()> {
+    let mut storage = open_database::<T>(&layout.db, layout.trace)?;
+    let current = storage.names_layout();
+    let describe = |layout| match layout {
+        NamesLayout::SingleFile => "single-file",
+        NamesLayout::Separate => "separate",
+    };
+    let Some(target) = layout.layout.filter(|&target| target != current) else {
+        println!("{}", describe(current));
+        return Ok(());
+    };
+
+    let names_path = NamedTypesDecorator::make_names_database_filename(&layout.db);
+    if target == NamesLayout::Separate && names_path.exists() {
+        bail!(
+            "{} already exists; move it away before changing the layout.",
+            names_path.display()
+        );
+    }
+    let moved = storage.set_names_layout(target)?;
+    storage.save()?;
+    if target == NamesLayout::SingleFile && names_path.exists() {
+        std::fs::remove_file(&names_path)
+            .with_context(|| format!("Failed to remove {}", names_path.display()))?;
+    }
+    if !moved.is_empty() {
+        ChangeHistory::discard(&layout.db)?;
+    }
+    println!("{}", describe(target));
+    for link in &moved {
+        println!("{link}");
+    }
+    Ok(())
+}
+
+fn change_encoding<T: LinkAddress>(encoding: &EncodingCli) -> Result<()> {
+    let mut storage = open_database::<T>(&encoding.db, encoding.trace)?;
+    let current = storage.symbol_encoding()?;
+    let Some(target) = encoding.encoding.filter(|&target| target != current) else {
+        println!("{current}");
+        return Ok(());
+    };
+    storage.set_symbol_encoding(target)?;
+    storage.save()?;
+    println!("{target}");
+    Ok(())
+}
+
+fn open_database<T: LinkAddress>(db: &str, trace: bool) -> Result<NamedTypesDecorator<T>> {
+    NamedTypesDecorator::open(db, trace, LoadMode::Strict)
+}
+
+/// Opens a database for a read-only command, reporting malformed lines as
+/// warnings instead of failing.
+fn open_for_reading<T: LinkAddress>(db: &str, trace: bool) -> Result<NamedTypesDecorator<T>> {
+    let storage = NamedTypesDecorator::open(db, trace, LoadMode::Lenient)?;
+    for warning in storage.load_warnings() {
+        eprintln!("warning: {warning}");
+    }
+    Ok(storage)
+}
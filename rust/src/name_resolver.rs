@@ -0,0 +1,335 @@
+//! Long-lived name resolution for `NamedTypesDecorator`.
+//!
+//! Opening a `UnicodeStringStorage` validates the pinned types, builds the
+//! converters and names the built-in types. `NameResolver` does that once per
+//! decorator and keeps the converter caches warm. The names of the main
+//! database are loaded into maps on the first lookup, so later lookups do not
+//! walk the names database. Changes made through the resolver update the maps;
+//! `invalidate` drops everything when the names database changed behind its
+//! back.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+
+use crate::hybrid_reference::external_reference_value;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
+use crate::name_search::NameSearch;
+use crate::sequences::UnicodeSymbolEncoding;
+use crate::unicode_string_storage::{UnicodeStringState, UnicodeStringStorage};
+
+/// Names of the main database links, both ways. `by_name` resolves
+/// primary names and aliases.
+#[derive(Default)]
+struct NameMaps<T> {
+    by_link: HashMap<T, String>,
+    aliases: HashMap<T, Vec<String>>,
+    by_name: HashMap<String, T>,
+}
+
+impl<T: LinkAddress> NameMaps<T> {
+    fn insert(&mut self, link: T, name: &str) {
+        self.by_link.insert(link, name.to_string());
+        self.by_name.insert(name.to_string(), link);
+    }
+
+    fn insert_alias(&mut self, link: T, alias: &str) {
+        let aliases = self.aliases.entry(link).or_default();
+        if !aliases.iter().any(|existing| existing == alias) {
+            aliases.push(alias.to_string());
+        }
+        self.by_name.insert(alias.to_string(), link);
+    }
+
+    fn remove(&mut self, link: T) {
+        if let Some(name) = self.by_link.remove(&link) {
+            self.forget_name(link, &name);
+        }
+    }
+
+    fn remove_alias(&mut self, link: T, alias: &str) {
+        if let Some(aliases) = self.aliases.get_mut(&link) {
+            aliases.retain(|existing| existing != alias);
+            if aliases.is_empty() {
+                self.aliases.remove(&link);
+            }
+        }
+        self.forget_name(link, alias);
+    }
+
+    /// Drops `name` from `by_name` unless `link` still has it.
+    fn forget_name(&mut self, link: T, name: &str) {
+        let still_named = self
+            .by_link
+            .get(&link)
+            .is_some_and(|primary| primary == name)
+            || self
+                .aliases
+                .get(&link)
+                .is_some_and(|aliases| aliases.iter().any(|alias| alias == name));
+        if !still_named && self.by_name.get(name) == Some(&link) {
+            self.by_name.remove(name);
+        }
+    }
+}
+
+/// Resolves names stored as external references in a names database.
+#[derive(Default)]
+pub struct NameResolver<T: LinkAddress = u32> {
+    state: Option<UnicodeStringState<T>>,
+    names: Option<NameMaps<T>>,
+    trace: bool,
+}
+
+impl<T: LinkAddress> NameResolver<T> {
+    pub fn new(trace: bool) -> Self {
+        Self {
+            state: None,
+            names: None,
+            trace,
+        }
+    }
+
+    /// Drops the converter caches and the name maps. Call it after changing
+    /// the names database without the resolver.
+    pub fn invalidate(&mut self) {
+        if self.trace && (self.state.is_some() || self.names.is_some()) {
+            self.trace_cache_stats();
+            eprintln!("[TRACE] Name resolver caches invalidated");
+        }
+        self.state = None;
+        self.names = None;
+    }
+
+    pub fn get_name(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        link: T,
+    ) -> Result<Option<String>> {
+        Ok(self.maps(names_links)?.by_link.get(&link).cloned())
+    }
+
+    pub fn get_by_name(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        name: &str,
+    ) -> Result<Option<T>> {
+        let name = self.stored_form(names_links, name)?;
+        Ok(self.maps(names_links)?.by_name.get(name.as_ref()).copied())
+    }
+
+    /// Stores `name` for `link` without removing other names first.
+    pub fn set_name(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        link: T,
+        name: &str,
+    ) -> Result<T> {
+        let name = self.stored_form(names_links, name)?;
+        let name_link = open_strings(&mut self.state, names_links)?
+            .set_name_for_external_reference(link, &name)?;
+        if let Some(names) = &mut self.names {
+            names.insert(link, &name);
+        }
+        Ok(name_link)
+    }
+
+    pub fn remove_name(&mut self, names_links: &mut DoubletStorage<T>, link: T) -> Result<()> {
+        if self
+            .names
+            .as_ref()
+            .is_some_and(|names| !names.by_link.contains_key(&link))
+        {
+            return Ok(());
+        }
+        open_strings(&mut self.state, names_links)?.remove_name_by_external_reference(link)?;
+        if let Some(names) = &mut self.names {
+            names.remove(link);
+        }
+        Ok(())
+    }
+
+    /// Returns the aliases of a link in the order they were added.
+    pub fn aliases(&mut self, names_links: &mut DoubletStorage<T>, link: T) -> Result<Vec<String>> {
+        Ok(self
+            .maps(names_links)?
+            .aliases
+            .get(&link)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Stores an alias for `link` without checking other links for it.
+    pub fn add_alias(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        link: T,
+        alias: &str,
+    ) -> Result<()> {
+        let alias = self.stored_form(names_links, alias)?;
+        open_strings(&mut self.state, names_links)?
+            .add_alias_for_external_reference(link, &alias)?;
+        if let Some(names) = &mut self.names {
+            names.insert_alias(link, &alias);
+        }
+        Ok(())
+    }
+
+    pub fn remove_alias(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        link: T,
+        alias: &str,
+    ) -> Result<()> {
+        let alias = self.stored_form(names_links, alias)?;
+        open_strings(&mut self.state, names_links)?
+            .remove_alias_by_external_reference(link, &alias)?;
+        if let Some(names) = &mut self.names {
+            names.remove_alias(link, &alias);
+        }
+        Ok(())
+    }
+
+    /// Returns every alias with its link, ordered by link id.
+    pub fn all_aliases(&mut self, names_links: &mut DoubletStorage<T>) -> Result<Vec<(T, String)>> {
+        let mut aliases = self
+            .maps(names_links)?
+            .aliases
+            .iter()
+            .flat_map(|(&link, aliases)| aliases.iter().map(move |alias| (link, alias.clone())))
+            .collect::<Vec<_>>();
+        aliases.sort();
+        Ok(aliases)
+    }
+
+    /// Returns every named link with its name, ordered by link id.
+    pub fn all_names(&mut self, names_links: &mut DoubletStorage<T>) -> Result<Vec<(T, String)>> {
+        let mut names = self
+            .maps(names_links)?
+            .by_link
+            .iter()
+            .map(|(&link, name)| (link, name.clone()))
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Returns the main database links with a primary name or an alias
+    /// matching `search`, ordered by link.
+    pub fn search_names(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        search: &NameSearch,
+    ) -> Result<Vec<(T, String)>> {
+        Ok(open_strings(&mut self.state, names_links)?
+            .search_names(search)?
+            .into_iter()
+            .filter_map(|(link, name)| Some((external_reference_value(link)?, name)))
+            .collect())
+    }
+
+    /// Returns the ids of the links the names are stored with, see
+    /// `UnicodeStringStorage::storage_links`.
+    pub fn storage_links(&mut self, names_links: &mut DoubletStorage<T>) -> Result<HashSet<T>> {
+        open_strings(&mut self.state, names_links)?.storage_links()
+    }
+
+    pub fn symbol_encoding(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+    ) -> Result<UnicodeSymbolEncoding> {
+        Ok(open_strings(&mut self.state, names_links)?.symbol_encoding())
+    }
+
+    /// Stores a string in the names database and returns its link.
+    pub fn create_string(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        content: &str,
+    ) -> Result<T> {
+        open_strings(&mut self.state, names_links)?.create_string(content)
+    }
+
+    /// Reads a string stored in the names database.
+    pub fn get_string(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        string_link: T,
+    ) -> Result<String> {
+        open_strings(&mut self.state, names_links)?.get_string(string_link)
+    }
+
+    fn trace_cache_stats(&self) {
+        if let Some(state) = &self.state {
+            let (string_to_sequence, sequence_to_string) = state.cache_stats();
+            eprintln!("[TRACE] String to sequence cache: {string_to_sequence}");
+            eprintln!("[TRACE] Sequence to string cache: {sequence_to_string}");
+        }
+    }
+
+    /// Returns `name` the way the names database stores it, NFC normalized
+    /// if its encoding asks for that.
+    fn stored_form<'n>(
+        &mut self,
+        names_links: &mut DoubletStorage<T>,
+        name: &'n str,
+    ) -> Result<Cow<'n, str>> {
+        Ok(open_strings(&mut self.state, names_links)?
+            .symbol_encoding()
+            .normalize(name))
+    }
+
+    fn maps(&mut self, names_links: &mut DoubletStorage<T>) -> Result<&NameMaps<T>> {
+        let names = match self.names.take() {
+            Some(names) => names,
+            None => self.load_names(names_links)?,
+        };
+        Ok(self.names.insert(names))
+    }
+
+    fn load_names(&mut self, names_links: &mut DoubletStorage<T>) -> Result<NameMaps<T>> {
+        let strings = open_strings(&mut self.state, names_links)?;
+        let mut names = NameMaps::default();
+        for (link, name) in strings.names()? {
+            if let Some(link) = external_reference_value(link) {
+                if !names.by_link.contains_key(&link) {
+                    names.insert(link, &name);
+                }
+            }
+        }
+        // Primary names win over aliases of other links, and an alias two
+        // links share belongs to the one whose alias pair came first.
+        for (link, alias) in strings.aliases()? {
+            if let Some(link) = external_reference_value(link) {
+                if !names.by_name.contains_key(&alias) {
+                    names.insert_alias(link, &alias);
+                }
+            }
+        }
+        if self.trace {
+            eprintln!("[TRACE] Name resolver loaded {} names", names.by_link.len());
+        }
+        Ok(names)
+    }
+}
+
+impl<T: LinkAddress> Drop for NameResolver<T> {
+    fn drop(&mut self) {
+        if self.trace {
+            self.trace_cache_stats();
+        }
+    }
+}
+
+fn open_strings<'a, T: LinkAddress>(
+    state: &'a mut Option<UnicodeStringState<T>>,
+    names_links: &'a mut DoubletStorage<T>,
+) -> Result<UnicodeStringStorage<'a, T>> {
+    let state = match state {
+        Some(state) => state,
+        None => state.insert(UnicodeStringState::open(names_links)?),
+    };
+    Ok(UnicodeStringStorage::with_state(names_links, state))
+}
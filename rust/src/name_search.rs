@@ -0,0 +1,232 @@
+//! Prefix and substring search over the names stored in a names database.
+//!
+//! Names are not decoded one by one. The searched text is turned into the
+//! Unicode symbol links it would be stored with, and the search walks up from
+//! them to the Unicode sequences that contain them. `BalancedVariantConverter`
+//! pairs symbols level by level from the left, so every name starting with a
+//! prefix shares the block built for the first power-of-two symbols of that
+//! prefix. A prefix search starts from that block and only follows left
+//! children; a substring search starts from the least used symbol of the
+//! text. Only the names reached this way are decoded and compared with the
+//! whole text.
+
+use anyhow::Result;
+
+use crate::link_address::LinkAddress;
+use crate::sequences::LinkUsages;
+use crate::unicode_string_storage::UnicodeStringStorage;
+
+/// Where the searched text has to occur in a name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameMatch {
+    Prefix,
+    Substring,
+}
+
+/// A search over primary names and aliases.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameSearch {
+    pub text: String,
+    pub kind: NameMatch,
+    pub ignore_case: bool,
+}
+
+impl NameSearch {
+    /// Matches names starting with `text`.
+    pub fn prefix(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            kind: NameMatch::Prefix,
+            ignore_case: false,
+        }
+    }
+
+    /// Matches names containing `text`.
+    pub fn substring(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            kind: NameMatch::Substring,
+            ignore_case: false,
+        }
+    }
+
+    pub fn with_ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Returns true if `name` matches the search.
+    pub fn matches(&self, name: &str) -> bool {
+        let matches = |name: &str, text: &str| match self.kind {
+            NameMatch::Prefix => name.starts_with(text),
+            NameMatch::Substring => name.contains(text),
+        };
+        if self.ignore_case {
+            matches(&name.to_lowercase(), &self.text.to_lowercase())
+        } else {
+            matches(name, &self.text)
+        }
+    }
+}
+
+/// Returns the `(link, name)` pairs of primary names and aliases matching
+/// `search`, ordered by link.
+pub(crate) fn search_names<T: LinkAddress>(
+    strings: &UnicodeStringStorage<'_, T>,
+    search: &NameSearch,
+) -> Result<Vec<(T, String)>> {
+    if search.text.is_empty() {
+        let mut found = strings.names()?;
+        found.extend(strings.aliases()?);
+        found.sort();
+        found.dedup();
+        return Ok(found);
+    }
+
+    let search = &NameSearch {
+        text: strings
+            .symbol_encoding()
+            .normalize(&search.text)
+            .into_owned(),
+        ..search.clone()
+    };
+    let usages = LinkUsages::new(strings.links());
+    let Some(positions) = symbol_positions(strings, &usages, search) else {
+        return Ok(Vec::new());
+    };
+    let sequence_type = strings.unicode_sequence_type();
+    let sequences = match search.kind {
+        NameMatch::Prefix => {
+            usages.sequences_above(leftmost_blocks(&usages, positions), sequence_type, true)
+        }
+        NameMatch::Substring => {
+            let rarest = positions
+                .into_iter()
+                .min_by_key(|symbols| {
+                    symbols
+                        .iter()
+                        .map(|&symbol| usages.count(symbol))
+                        .sum::<usize>()
+                })
+                .unwrap_or_default();
+            usages.sequences_above(rarest, sequence_type, false)
+        }
+    };
+
+    let pair_types = [Some(strings.name_type()), strings.alias_type()?];
+    let mut found = Vec::new();
+    for sequence in sequences {
+        let Some(string) = usages.find(strings.string_type(), sequence) else {
+            continue;
+        };
+        let pair_links = pair_types
+            .iter()
+            .flatten()
+            .filter_map(|&pair_type| usages.find(pair_type, string))
+            .collect::<Vec<_>>();
+        if pair_links.is_empty() {
+            continue;
+        }
+        let content = strings.get_string(string)?;
+        if !search.matches(&content) {
+            continue;
+        }
+        for pair_link in pair_links {
+            for pair in usages.as_target(pair_link) {
+                if pair.index != pair.target {
+                    found.push((pair.source, content.clone()));
+                }
+            }
+        }
+    }
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+/// Returns the symbol links each symbol of the text can be stored as, or
+/// `None` if one of them was never stored.
+fn symbol_positions<T: LinkAddress>(
+    strings: &UnicodeStringStorage<'_, T>,
+    usages: &LinkUsages<T>,
+    search: &NameSearch,
+) -> Option<Vec<Vec<T>>> {
+    let converter = strings.address_to_number_converter();
+    let encoding = strings.symbol_encoding();
+    let mut positions = Vec::new();
+    for character in search.text.chars() {
+        let units = encoding.char_symbols(character).len();
+        let variants = case_variants(character, search.ignore_case)
+            .into_iter()
+            .map(|variant| encoding.char_symbols(variant))
+            .filter(|symbols| symbols.len() == units)
+            .collect::<Vec<_>>();
+        for unit in 0..units {
+            let mut symbols = variants
+                .iter()
+                .filter_map(|variant| {
+                    usages.find(
+                        converter.convert(T::from(variant[unit])),
+                        strings.unicode_symbol_type(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            symbols.sort_unstable();
+            symbols.dedup();
+            if symbols.is_empty() {
+                return None;
+            }
+            positions.push(symbols);
+        }
+    }
+    Some(positions)
+}
+
+/// Returns `character` and, when ignoring case, its single-character
+/// lowercase and uppercase forms.
+fn case_variants(character: char, ignore_case: bool) -> Vec<char> {
+    let mut variants = vec![character];
+    if ignore_case {
+        for mapped in [
+            character.to_lowercase().collect::<Vec<_>>(),
+            character.to_uppercase().collect::<Vec<_>>(),
+        ] {
+            if let [variant] = mapped[..] {
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+            }
+        }
+    }
+    variants
+}
+
+/// Builds the links `BalancedVariantConverter` creates for the first
+/// power-of-two symbols of a name, for every combination of `positions`
+/// that is stored.
+fn leftmost_blocks<T: LinkAddress>(usages: &LinkUsages<T>, mut positions: Vec<Vec<T>>) -> Vec<T> {
+    let mut size = 1;
+    while size * 2 <= positions.len() {
+        size *= 2;
+    }
+    positions.truncate(size);
+    while positions.len() > 1 {
+        positions = positions
+            .chunks(2)
+            .map(|pair| {
+                pair[0]
+                    .iter()
+                    .flat_map(|&left| {
+                        pair[1]
+                            .iter()
+                            .filter_map(move |&right| usages.find(left, right))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        if positions.iter().any(Vec::is_empty) {
+            return Vec::new();
+        }
+    }
+    positions.pop().unwrap_or_default()
+}
@@ -2,60 +2,74 @@
 
 use anyhow::Result;
 
-use crate::link_storage::LinkStorage;
+use crate::hybrid_reference::external_reference_value;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
 use crate::unicode_string_storage::UnicodeStringStorage;
 
-pub struct NamedLinks<'a> {
-    storage: UnicodeStringStorage<'a>,
+pub struct NamedLinks<'a, T: LinkAddress = u32> {
+    storage: UnicodeStringStorage<'a, T>,
 }
 
-impl<'a> NamedLinks<'a> {
-    pub fn new(links: &'a mut LinkStorage) -> Result<Self> {
+impl<'a, T: LinkAddress> NamedLinks<'a, T> {
+    pub fn new(links: &'a mut DoubletStorage<T>) -> Result<Self> {
         Ok(UnicodeStringStorage::new(links)?.into_named_links())
     }
 
-    pub(crate) fn from_storage(storage: UnicodeStringStorage<'a>) -> Self {
+    pub(crate) fn from_storage(storage: UnicodeStringStorage<'a, T>) -> Self {
         Self { storage }
     }
 
-    pub fn set_name_for_external_reference(&mut self, link: u32, name: &str) -> Result<u32> {
+    pub fn set_name_for_external_reference(&mut self, link: T, name: &str) -> Result<T> {
         self.storage.set_name_for_external_reference(link, name)
     }
 
-    pub fn set_name(&mut self, link: u32, name: &str) -> Result<u32> {
+    pub fn set_name(&mut self, link: T, name: &str) -> Result<T> {
         self.storage.set_name(link, name)
     }
 
-    pub fn get_name_by_external_reference(&self, link: u32) -> Result<Option<String>> {
+    pub fn get_name_by_external_reference(&self, link: T) -> Result<Option<String>> {
         self.storage.get_name_by_external_reference(link)
     }
 
-    pub fn get_name(&self, link: u32) -> Result<Option<String>> {
+    pub fn get_name(&self, link: T) -> Result<Option<String>> {
         self.storage.get_name(link)
     }
 
-    pub fn get_by_name(&mut self, name: &str) -> Result<Option<u32>> {
+    pub fn get_by_name(&mut self, name: &str) -> Result<Option<T>> {
         self.storage.get_by_name(name)
     }
 
-    pub fn get_external_reference_by_name(&mut self, name: &str) -> Result<Option<u32>> {
+    pub fn get_external_reference_by_name(&mut self, name: &str) -> Result<Option<T>> {
         self.storage.get_external_reference_by_name(name)
     }
 
-    pub fn remove_name(&mut self, link: u32) -> Result<()> {
+    /// Returns the names of all external references ordered by link id.
+    pub fn external_reference_names(&self) -> Result<Vec<(T, String)>> {
+        let mut names = self
+            .storage
+            .names()?
+            .into_iter()
+            .filter_map(|(link, name)| external_reference_value(link).map(|link| (link, name)))
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn remove_name(&mut self, link: T) -> Result<()> {
         self.storage.remove_name(link)
     }
 
-    pub fn remove_name_by_external_reference(&mut self, external_reference_id: u32) -> Result<()> {
+    pub fn remove_name_by_external_reference(&mut self, external_reference_id: T) -> Result<()> {
         self.storage
             .remove_name_by_external_reference(external_reference_id)
     }
 
-    pub fn unicode_storage(&self) -> &UnicodeStringStorage<'a> {
+    pub fn unicode_storage(&self) -> &UnicodeStringStorage<'a, T> {
         &self.storage
     }
 
-    pub fn unicode_storage_mut(&mut self) -> &mut UnicodeStringStorage<'a> {
+    pub fn unicode_storage_mut(&mut self) -> &mut UnicodeStringStorage<'a, T> {
         &mut self.storage
     }
 }
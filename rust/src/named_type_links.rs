@@ -1,48 +1,126 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::fs::OpenOptions;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
 use crate::error::LinkError;
-use crate::link::Link;
-use crate::link_storage::LinkStorage;
+use crate::export_filter::ExportFilter;
+use crate::hybrid_reference::{external_reference, external_reference_value};
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
 use crate::named_types::{NamedTypes, NamedTypesDecorator};
-
-pub trait NamedTypeLinks {
-    fn create(&mut self, source: u32, target: u32) -> u32;
-    fn ensure_created(&mut self, id: u32) -> u32;
-    fn get_link(&mut self, id: u32) -> Option<Link>;
-    fn exists(&mut self, id: u32) -> bool;
-    fn update(&mut self, id: u32, source: u32, target: u32) -> Result<Link>;
-    fn delete(&mut self, id: u32) -> Result<Link>;
-    fn all_links(&mut self) -> Vec<Link>;
-    fn search(&mut self, source: u32, target: u32) -> Option<u32>;
-    fn get_or_create(&mut self, source: u32, target: u32) -> u32;
-    fn get_name(&mut self, id: u32) -> Result<Option<String>>;
-    fn set_name(&mut self, id: u32, name: &str) -> Result<u32>;
-    fn get_by_name(&mut self, name: &str) -> Result<Option<u32>>;
-    fn remove_name(&mut self, id: u32) -> Result<()>;
+use crate::structure_format::{render_structure, StructureFormat};
+
+/// Link storage operations together with names, for any address width.
+pub trait NamedTypeLinks<T: LinkAddress = u32> {
+    fn create(&mut self, source: T, target: T) -> T;
+    fn ensure_created(&mut self, id: T) -> T;
+    fn get_link(&mut self, id: T) -> Option<Doublet<T>>;
+    fn exists(&mut self, id: T) -> bool;
+    fn update(&mut self, id: T, source: T, target: T) -> Result<Doublet<T>>;
+    fn delete(&mut self, id: T) -> Result<Doublet<T>>;
+    fn all_links(&mut self) -> Vec<Doublet<T>>;
+    fn search(&mut self, source: T, target: T) -> Option<T>;
+    fn get_or_create(&mut self, source: T, target: T) -> T;
+    fn get_name(&mut self, id: T) -> Result<Option<String>>;
+    fn set_name(&mut self, id: T, name: &str) -> Result<T>;
+    fn get_by_name(&mut self, name: &str) -> Result<Option<T>>;
+    fn remove_name(&mut self, id: T) -> Result<()>;
     fn save(&mut self) -> Result<()>;
 
-    fn get_or_create_named(&mut self, name: &str) -> Result<u32> {
+    /// Returns the aliases of a link. Storages that keep a single name per
+    /// link have none.
+    fn aliases(&mut self, _id: T) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn add_alias(&mut self, _id: T, _alias: &str) -> Result<()> {
+        bail!("This storage keeps a single name per link and has no aliases.")
+    }
+
+    fn remove_alias(&mut self, _id: T, _alias: &str) -> Result<()> {
+        bail!("This storage keeps a single name per link and has no aliases.")
+    }
+
+    /// Reads the string an external reference points to, such as a JSON
+    /// string value. Returns `None` for references that are not strings and
+    /// for storages without a names database.
+    fn external_string(&mut self, _reference: T) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Stores a string and returns the external reference pointing to it.
+    fn create_external_string(&mut self, _content: &str) -> Result<T> {
+        bail!("This storage has no names database to keep strings in.")
+    }
+
+    /// Makes an alias the primary name of a link. The previous primary name
+    /// becomes an alias.
+    fn set_primary_name(&mut self, id: T, alias: &str) -> Result<()> {
+        if !self.aliases(id)?.iter().any(|existing| existing == alias) {
+            bail!("Link {id} has no alias '{alias}'.");
+        }
+        let previous = self.get_name(id)?;
+        self.remove_alias(id, alias)?;
+        self.set_name(id, alias)?;
+        if let Some(previous) = previous {
+            self.add_alias(id, &previous)?;
+        }
+        Ok(())
+    }
+
+    /// Renames a primary name or an alias without touching the link it
+    /// belongs to. Unlike `set_name`, it refuses to take a name from another
+    /// link.
+    fn rename(&mut self, old: &str, new: &str) -> Result<T> {
+        let id = self
+            .get_by_name(old)?
+            .ok_or_else(|| anyhow::anyhow!("No link is named '{old}'."))?;
+        if old == new {
+            return Ok(id);
+        }
+        if let Some(holder) = self.get_by_name(new)? {
+            bail!("The name '{new}' already belongs to link {holder}.");
+        }
+        if self.get_name(id)?.as_deref() == Some(old) {
+            self.set_name(id, new)?;
+        } else {
+            self.remove_alias(id, old)?;
+            self.add_alias(id, new)?;
+        }
+        Ok(id)
+    }
+
+    fn get_or_create_named(&mut self, name: &str) -> Result<T> {
         if let Some(id) = self.get_by_name(name)? {
             return Ok(id);
         }
 
-        let id = self.create(0, 0);
+        let id = self.create(T::ZERO, T::ZERO);
         self.set_name(id, name)?;
         self.update(id, id, id)?;
         Ok(id)
     }
 
-    fn format_reference(&mut self, id: u32) -> Result<String> {
+    /// Resolves a numeric link id or a link name.
+    fn resolve_reference(&mut self, reference: &str) -> Result<T> {
+        match reference.parse::<T>() {
+            Ok(id) => Ok(id),
+            Err(_) => self
+                .get_by_name(reference)?
+                .ok_or_else(|| anyhow::anyhow!("No link is named '{reference}'.")),
+        }
+    }
+
+    fn format_reference(&mut self, id: T) -> Result<String> {
         Ok(self
             .get_name(id)?
             .map(|name| escape_lino_reference(&name))
             .unwrap_or_else(|| id.to_string()))
     }
 
-    fn format_lino(&mut self, link: &Link) -> Result<String> {
+    fn format_lino(&mut self, link: &Doublet<T>) -> Result<String> {
         Ok(format!(
             "({}: {} {})",
             self.format_reference(link.index)?,
@@ -61,21 +139,32 @@ pub trait NamedTypeLinks {
             .collect::<Result<Vec<_>>>()
     }
 
+    fn filtered_lino_lines(&mut self, filter: &ExportFilter<T>) -> Result<Vec<String>>
+    where
+        Self: Sized,
+    {
+        filter
+            .select(self)?
+            .iter()
+            .map(|link| self.format_lino(link))
+            .collect::<Result<Vec<_>>>()
+    }
+
     fn write_lino_output<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
-        let path = path.as_ref();
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(path)
-            .with_context(|| format!("Failed to create LiNo output: {}", path.display()))?;
-
-        let mut writer = BufWriter::new(file);
-        for line in self.lino_lines()? {
-            writeln!(writer, "{line}")?;
-        }
-        writer.flush()?;
-        Ok(())
+        let lines = self.lino_lines()?;
+        write_lino_lines(path.as_ref(), &lines)
+    }
+
+    fn write_filtered_lino_output<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        filter: &ExportFilter<T>,
+    ) -> Result<()>
+    where
+        Self: Sized,
+    {
+        let lines = self.filtered_lino_lines(filter)?;
+        write_lino_lines(path.as_ref(), &lines)
     }
 
     fn print_all_lino(&mut self) -> Result<()> {
@@ -85,7 +174,11 @@ pub trait NamedTypeLinks {
         Ok(())
     }
 
-    fn print_change_lino(&mut self, before: &Option<Link>, after: &Option<Link>) -> Result<()> {
+    fn format_change_lino(
+        &mut self,
+        before: &Option<Doublet<T>>,
+        after: &Option<Doublet<T>>,
+    ) -> Result<String> {
         let before_text = before
             .map(|link| self.format_lino(&link))
             .transpose()?
@@ -94,160 +187,200 @@ pub trait NamedTypeLinks {
             .map(|link| self.format_lino(&link))
             .transpose()?
             .unwrap_or_default();
-        println!("({before_text}) ({after_text})");
-        Ok(())
+        Ok(format!("({before_text}) ({after_text})"))
     }
 
-    fn format_structure(&mut self, id: u32) -> Result<String> {
-        let link = self.get_link(id).ok_or(LinkError::NotFound(id))?;
-        self.format_structure_recursive(&link, true)
+    fn print_change_lino(
+        &mut self,
+        before: &Option<Doublet<T>>,
+        after: &Option<Doublet<T>>,
+    ) -> Result<()> {
+        println!("{}", self.format_change_lino(before, after)?);
+        Ok(())
     }
 
-    fn format_structure_recursive(&mut self, link: &Link, is_root: bool) -> Result<String> {
-        if link.is_full_point() && !is_root {
-            return self.format_reference(link.index);
-        }
-
-        let source = if link.source == link.index {
-            self.format_reference(link.index)?
-        } else if let Some(source_link) = self.get_link(link.source) {
-            self.format_structure_recursive(&source_link, false)?
-        } else {
-            link.source.to_string()
-        };
-
-        let target = if link.target == link.index {
-            self.format_reference(link.index)?
-        } else if let Some(target_link) = self.get_link(link.target) {
-            self.format_structure_recursive(&target_link, false)?
-        } else {
-            link.target.to_string()
-        };
+    fn format_structure(&mut self, id: T) -> Result<String> {
+        self.format_structure_with(id, &StructureFormat::default())
+    }
 
-        Ok(format!("({source} {target})"))
+    fn format_structure_with(&mut self, id: T, format: &StructureFormat) -> Result<String> {
+        self.get_link(id).ok_or(LinkError::NotFound(id.into()))?;
+        render_structure(id, format, |address| match self.get_link(address) {
+            Some(link) => Ok(Some((link, self.format_reference(address)?))),
+            None => Ok(None),
+        })
     }
 }
 
-impl NamedTypeLinks for LinkStorage {
-    fn create(&mut self, source: u32, target: u32) -> u32 {
-        LinkStorage::create(self, source, target)
+impl<T: LinkAddress> NamedTypeLinks<T> for DoubletStorage<T> {
+    fn create(&mut self, source: T, target: T) -> T {
+        DoubletStorage::create(self, source, target)
     }
 
-    fn ensure_created(&mut self, id: u32) -> u32 {
-        LinkStorage::ensure_created(self, id)
+    fn ensure_created(&mut self, id: T) -> T {
+        DoubletStorage::ensure_created(self, id)
     }
 
-    fn get_link(&mut self, id: u32) -> Option<Link> {
+    fn get_link(&mut self, id: T) -> Option<Doublet<T>> {
         self.get(id).copied()
     }
 
-    fn exists(&mut self, id: u32) -> bool {
-        LinkStorage::exists(self, id)
+    fn exists(&mut self, id: T) -> bool {
+        DoubletStorage::exists(self, id)
     }
 
-    fn update(&mut self, id: u32, source: u32, target: u32) -> Result<Link> {
-        LinkStorage::update(self, id, source, target)
+    fn update(&mut self, id: T, source: T, target: T) -> Result<Doublet<T>> {
+        DoubletStorage::update(self, id, source, target)
     }
 
-    fn delete(&mut self, id: u32) -> Result<Link> {
-        LinkStorage::delete(self, id)
+    fn delete(&mut self, id: T) -> Result<Doublet<T>> {
+        DoubletStorage::delete(self, id)
     }
 
-    fn all_links(&mut self) -> Vec<Link> {
+    fn all_links(&mut self) -> Vec<Doublet<T>> {
         self.all().into_iter().copied().collect()
     }
 
-    fn search(&mut self, source: u32, target: u32) -> Option<u32> {
-        LinkStorage::search(self, source, target)
+    fn search(&mut self, source: T, target: T) -> Option<T> {
+        DoubletStorage::search(self, source, target)
     }
 
-    fn get_or_create(&mut self, source: u32, target: u32) -> u32 {
-        LinkStorage::get_or_create(self, source, target)
+    fn get_or_create(&mut self, source: T, target: T) -> T {
+        DoubletStorage::get_or_create(self, source, target)
     }
 
-    fn get_name(&mut self, id: u32) -> Result<Option<String>> {
-        Ok(LinkStorage::get_name(self, id).cloned())
+    fn get_name(&mut self, id: T) -> Result<Option<String>> {
+        Ok(DoubletStorage::get_name(self, id).cloned())
     }
 
-    fn set_name(&mut self, id: u32, name: &str) -> Result<u32> {
-        LinkStorage::set_name(self, id, name);
+    fn set_name(&mut self, id: T, name: &str) -> Result<T> {
+        DoubletStorage::set_name(self, id, name);
         Ok(id)
     }
 
-    fn get_by_name(&mut self, name: &str) -> Result<Option<u32>> {
-        Ok(LinkStorage::get_by_name(self, name))
+    fn get_by_name(&mut self, name: &str) -> Result<Option<T>> {
+        Ok(DoubletStorage::get_by_name(self, name))
     }
 
-    fn remove_name(&mut self, id: u32) -> Result<()> {
-        LinkStorage::remove_name(self, id);
+    fn remove_name(&mut self, id: T) -> Result<()> {
+        DoubletStorage::remove_name(self, id);
         Ok(())
     }
 
     fn save(&mut self) -> Result<()> {
-        LinkStorage::save(self)
+        DoubletStorage::save(self)
     }
 
-    fn get_or_create_named(&mut self, name: &str) -> Result<u32> {
-        Ok(LinkStorage::get_or_create_named(self, name))
+    fn get_or_create_named(&mut self, name: &str) -> Result<T> {
+        Ok(DoubletStorage::get_or_create_named(self, name))
     }
 }
 
-impl NamedTypeLinks for NamedTypesDecorator {
-    fn create(&mut self, source: u32, target: u32) -> u32 {
+impl<T: LinkAddress> NamedTypeLinks<T> for NamedTypesDecorator<T> {
+    fn create(&mut self, source: T, target: T) -> T {
         NamedTypesDecorator::create(self, source, target)
     }
 
-    fn ensure_created(&mut self, id: u32) -> u32 {
+    fn ensure_created(&mut self, id: T) -> T {
         NamedTypesDecorator::ensure_created(self, id)
     }
 
-    fn get_link(&mut self, id: u32) -> Option<Link> {
+    fn get_link(&mut self, id: T) -> Option<Doublet<T>> {
         self.get(id).copied()
     }
 
-    fn exists(&mut self, id: u32) -> bool {
+    fn exists(&mut self, id: T) -> bool {
         NamedTypesDecorator::exists(self, id)
     }
 
-    fn update(&mut self, id: u32, source: u32, target: u32) -> Result<Link> {
+    fn update(&mut self, id: T, source: T, target: T) -> Result<Doublet<T>> {
         NamedTypesDecorator::update(self, id, source, target)
     }
 
-    fn delete(&mut self, id: u32) -> Result<Link> {
+    fn delete(&mut self, id: T) -> Result<Doublet<T>> {
         NamedTypesDecorator::delete(self, id)
     }
 
-    fn all_links(&mut self) -> Vec<Link> {
-        self.all().into_iter().copied().collect()
+    /// Leaves out the links that keep the names in the single-file layout,
+    /// so queries, exports and merges only see the links of the database.
+    fn all_links(&mut self) -> Vec<Doublet<T>> {
+        let names_link_ids = self.names_link_ids().unwrap_or_default();
+        self.all()
+            .into_iter()
+            .filter(|link| !names_link_ids.contains(&link.index))
+            .copied()
+            .collect()
     }
 
-    fn search(&mut self, source: u32, target: u32) -> Option<u32> {
+    fn search(&mut self, source: T, target: T) -> Option<T> {
         NamedTypesDecorator::search(self, source, target)
     }
 
-    fn get_or_create(&mut self, source: u32, target: u32) -> u32 {
+    fn get_or_create(&mut self, source: T, target: T) -> T {
         NamedTypesDecorator::get_or_create(self, source, target)
     }
 
-    fn get_name(&mut self, id: u32) -> Result<Option<String>> {
+    fn get_name(&mut self, id: T) -> Result<Option<String>> {
         NamedTypes::get_name(self, id)
     }
 
-    fn set_name(&mut self, id: u32, name: &str) -> Result<u32> {
+    fn set_name(&mut self, id: T, name: &str) -> Result<T> {
         NamedTypes::set_name(self, id, name)
     }
 
-    fn get_by_name(&mut self, name: &str) -> Result<Option<u32>> {
+    fn get_by_name(&mut self, name: &str) -> Result<Option<T>> {
         NamedTypes::get_by_name(self, name)
     }
 
-    fn remove_name(&mut self, id: u32) -> Result<()> {
+    fn remove_name(&mut self, id: T) -> Result<()> {
         NamedTypes::remove_name(self, id)
     }
 
     fn save(&mut self) -> Result<()> {
         NamedTypesDecorator::save(self)
     }
+
+    fn aliases(&mut self, id: T) -> Result<Vec<String>> {
+        NamedTypes::aliases(self, id)
+    }
+
+    fn add_alias(&mut self, id: T, alias: &str) -> Result<()> {
+        NamedTypes::add_alias(self, id, alias)
+    }
+
+    fn remove_alias(&mut self, id: T, alias: &str) -> Result<()> {
+        NamedTypes::remove_alias(self, id, alias)
+    }
+
+    fn external_string(&mut self, reference: T) -> Result<Option<String>> {
+        let Some(string_link) = external_reference_value(reference) else {
+            return Ok(None);
+        };
+        if !self.names_links().exists(string_link) {
+            return Ok(None);
+        }
+        Ok(self.get_string(string_link).ok())
+    }
+
+    fn create_external_string(&mut self, content: &str) -> Result<T> {
+        Ok(external_reference(self.create_string(content)?))
+    }
+}
+
+fn write_lino_lines(path: &Path, lines: &[String]) -> Result<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .with_context(|| format!("Failed to create LiNo output: {}", path.display()))?;
+
+    let mut writer = BufWriter::new(file);
+    for line in lines {
+        writeln!(writer, "{line}")?;
+    }
+    writer.flush()?;
+    Ok(())
 }
 
 pub(crate) fn escape_lino_reference(reference: &str) -> String {
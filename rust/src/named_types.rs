@@ -2,27 +2,57 @@
 //!
 //! This mirrors the C# `NamedTypesDecorator<uint>` role: link operations and
 //! pinned type access are delegated through `PinnedTypesDecorator`, while names
-//! are stored as external references in a separate links database.
+//! are stored as external references in a separate links database. That
+//! database is a sibling `<stem>.names.links` file, or, in the single-file
+//! layout, the links of the database itself, which then start with the
+//! pinned types of the names database, like a C# `NamedLinksDecorator`
+//! given one storage for both.
 
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 
-use crate::link::Link;
-use crate::link_storage::LinkStorage;
-use crate::named_links::NamedLinks;
+use crate::database_compactor::MovedLink;
+use crate::hybrid_reference::external_reference_value;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::link_storage::{DoubletStorage, LoadMode, LoadWarning};
+use crate::name_resolver::NameResolver;
+use crate::name_search::NameSearch;
 use crate::pinned_types::{PinnedTypesAccess, PinnedTypesDecorator};
+use crate::sequences::UnicodeSymbolEncoding;
+use crate::unicode_string_storage::UnicodeStringStorage;
+
+pub trait NamedTypes<T: LinkAddress = u32> {
+    fn get_name(&mut self, link: T) -> Result<Option<String>>;
+    fn set_name(&mut self, link: T, name: &str) -> Result<T>;
+    fn get_by_name(&mut self, name: &str) -> Result<Option<T>>;
+    fn remove_name(&mut self, link: T) -> Result<()>;
+    /// Returns the aliases of a link: names besides its primary name that
+    /// resolve to it.
+    fn aliases(&mut self, link: T) -> Result<Vec<String>>;
+    /// Adds an alias to a link. The name must not belong to another link.
+    fn add_alias(&mut self, link: T, alias: &str) -> Result<()>;
+    fn remove_alias(&mut self, link: T, alias: &str) -> Result<()>;
+}
 
-pub trait NamedTypes {
-    fn get_name(&mut self, link: u32) -> Result<Option<String>>;
-    fn set_name(&mut self, link: u32, name: &str) -> Result<u32>;
-    fn get_by_name(&mut self, name: &str) -> Result<Option<u32>>;
-    fn remove_name(&mut self, link: u32) -> Result<()>;
+/// Where a database keeps its names database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NamesLayout {
+    /// A sibling `<stem>.names.links` file.
+    #[default]
+    Separate,
+    /// The links of the database file, under the pinned `Name` type. The
+    /// file starts with a `#! names` line.
+    SingleFile,
 }
 
-pub struct NamedTypesDecorator {
-    pinned_types_decorator: PinnedTypesDecorator,
-    names_links: LinkStorage,
+pub struct NamedTypesDecorator<T: LinkAddress = u32> {
+    pinned_types_decorator: PinnedTypesDecorator<T>,
+    /// The names database, or `None` in the single-file layout.
+    names_links: Option<DoubletStorage<T>>,
+    name_resolver: NameResolver<T>,
     trace: bool,
 }
 
@@ -31,9 +61,16 @@ impl NamedTypesDecorator {
     where
         P: AsRef<Path>,
     {
-        let names_database_filename =
-            Self::make_names_database_filename(database_filename.as_ref());
-        Self::with_names_database_path(database_filename, names_database_filename, trace)
+        Self::open(database_filename, trace, LoadMode::Strict)
+    }
+
+    /// Opens the database and its names database with the given handling of
+    /// malformed lines.
+    pub fn with_load_mode<P>(database_filename: P, trace: bool, load_mode: LoadMode) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open(database_filename, trace, load_mode)
     }
 
     pub fn with_names_database_path<P, N>(
@@ -45,26 +82,12 @@ impl NamedTypesDecorator {
         P: AsRef<Path>,
         N: AsRef<Path>,
     {
-        let database_path = path_to_string(database_filename.as_ref());
-        let names_database_path = path_to_string(names_database_filename.as_ref());
-        let links = LinkStorage::new(&database_path, trace)?;
-        let names_links = LinkStorage::new(&names_database_path, trace)?;
-        Ok(Self::from_link_storages_with_trace(
-            links,
-            names_links,
+        Self::open_with_names_database(
+            database_filename.as_ref(),
+            names_database_filename.as_ref(),
             trace,
-        ))
-    }
-
-    pub fn from_link_storages(links: LinkStorage, names_links: LinkStorage) -> Self {
-        Self::from_link_storages_with_trace(links, names_links, false)
-    }
-
-    pub fn from_pinned_types_decorator(
-        pinned_types_decorator: PinnedTypesDecorator,
-        names_links: LinkStorage,
-    ) -> Self {
-        Self::from_decorators_with_trace(pinned_types_decorator, names_links, false)
+            LoadMode::Strict,
+        )
     }
 
     pub fn make_names_database_filename<P>(database_filename: P) -> PathBuf
@@ -83,32 +106,206 @@ impl NamedTypesDecorator {
             .map(|parent| parent.join(&names_filename))
             .unwrap_or_else(|| PathBuf::from(names_filename))
     }
+}
 
-    pub fn links(&self) -> &LinkStorage {
+impl<T: LinkAddress> NamedTypesDecorator<T> {
+    /// Opens a database of any address width with its names database, such
+    /// as `NamedTypesDecorator::<u64>::open`. `NamedTypesDecorator::new`
+    /// opens `u32` databases.
+    pub fn open<P>(database_filename: P, trace: bool, load_mode: LoadMode) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let names_database_filename =
+            NamedTypesDecorator::make_names_database_filename(database_filename.as_ref());
+        Self::open_with_names_database(
+            database_filename.as_ref(),
+            &names_database_filename,
+            trace,
+            load_mode,
+        )
+    }
+
+    /// Opens a `u32` database and its names database with the wider
+    /// addresses of `T`, as `DoubletStorage::widen` does. Saving records the
+    /// new width in both.
+    pub fn widen<P>(database_filename: P, trace: bool) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let database_path = path_to_string(database_filename.as_ref());
+        let links = DoubletStorage::widen(&database_path, trace)?;
+        let names_links = if links.holds_names() {
+            None
+        } else {
+            let names_database_filename =
+                NamedTypesDecorator::make_names_database_filename(&database_path);
+            Some(DoubletStorage::widen(
+                &path_to_string(&names_database_filename),
+                trace,
+            )?)
+        };
+        Ok(Self::from_parts(
+            PinnedTypesDecorator::from_link_storage(links),
+            names_links,
+            trace,
+        ))
+    }
+
+    pub(crate) fn open_with_names_database(
+        database_filename: &Path,
+        names_database_filename: &Path,
+        trace: bool,
+        load_mode: LoadMode,
+    ) -> Result<Self> {
+        let database_path = path_to_string(database_filename);
+        let names_database_path = path_to_string(names_database_filename);
+        let links = DoubletStorage::with_load_mode(&database_path, trace, load_mode)?;
+        let names_links = if links.holds_names() {
+            None
+        } else {
+            Some(DoubletStorage::with_load_mode(
+                &names_database_path,
+                trace,
+                load_mode,
+            )?)
+        };
+        Ok(Self::from_parts(
+            PinnedTypesDecorator::from_link_storage(links),
+            names_links,
+            trace,
+        ))
+    }
+
+    pub fn from_link_storages(links: DoubletStorage<T>, names_links: DoubletStorage<T>) -> Self {
+        Self::from_link_storages_with_trace(links, names_links, false)
+    }
+
+    /// Builds a decorator in the single-file layout, with the names kept
+    /// among `links`.
+    pub fn from_single_file_storage(mut links: DoubletStorage<T>) -> Self {
+        links.set_holds_names(true);
+        Self::from_parts(PinnedTypesDecorator::from_link_storage(links), None, false)
+    }
+
+    pub fn from_pinned_types_decorator(
+        pinned_types_decorator: PinnedTypesDecorator<T>,
+        names_links: DoubletStorage<T>,
+    ) -> Self {
+        Self::from_decorators_with_trace(pinned_types_decorator, names_links, false)
+    }
+
+    pub fn links(&self) -> &DoubletStorage<T> {
         self.pinned_types_decorator.links()
     }
 
-    pub fn links_mut(&mut self) -> &mut LinkStorage {
+    pub fn names_layout(&self) -> NamesLayout {
+        match self.names_links {
+            Some(_) => NamesLayout::Separate,
+            None => NamesLayout::SingleFile,
+        }
+    }
+
+    /// Moves the links of the names database into the database or out of
+    /// it, to the default `<stem>.names.links` path, and returns the links
+    /// that moved to a new id. Nothing is written until `save`; removing a
+    /// names file that is no longer used is up to the caller.
+    ///
+    /// The names database keeps its ids, starting with its pinned types, so
+    /// the links of the database that use one of them move past the highest
+    /// id and take their names along. Moving the names out fails if a link
+    /// of the database refers to one of their links.
+    pub fn set_names_layout(&mut self, layout: NamesLayout) -> Result<Vec<MovedLink<T>>> {
+        if layout == self.names_layout() {
+            return Ok(Vec::new());
+        }
+        self.name_resolver.invalidate();
+        match layout {
+            NamesLayout::SingleFile => self.join_names(),
+            NamesLayout::Separate => {
+                self.split_names()?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Returns the ids of the links that keep the names in the single-file
+    /// layout, see `UnicodeStringStorage::storage_links`. In the separate
+    /// layout there are none.
+    pub fn names_link_ids(&mut self) -> Result<HashSet<T>> {
+        if self.names_links.is_some() {
+            return Ok(HashSet::new());
+        }
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.storage_links(names_links)
+    }
+
+    /// Returns how the names database splits strings into symbols.
+    pub fn symbol_encoding(&mut self) -> Result<UnicodeSymbolEncoding> {
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.symbol_encoding(names_links)
+    }
+
+    /// Switches the encoding strings are stored with. See
+    /// `UnicodeStringStorage::set_symbol_encoding` for when this fails.
+    pub fn set_symbol_encoding(&mut self, encoding: UnicodeSymbolEncoding) -> Result<()> {
+        UnicodeStringStorage::new(self.names_links_mut())?.set_symbol_encoding(encoding)
+    }
+
+    pub fn links_mut(&mut self) -> &mut DoubletStorage<T> {
         self.pinned_types_decorator.links_mut()
     }
 
-    pub fn pinned_types_decorator(&self) -> &PinnedTypesDecorator {
+    pub fn pinned_types_decorator(&self) -> &PinnedTypesDecorator<T> {
         &self.pinned_types_decorator
     }
 
-    pub fn pinned_types_decorator_mut(&mut self) -> &mut PinnedTypesDecorator {
+    pub fn pinned_types_decorator_mut(&mut self) -> &mut PinnedTypesDecorator<T> {
         &mut self.pinned_types_decorator
     }
 
-    pub fn names_links(&self) -> &LinkStorage {
-        &self.names_links
+    /// Returns the links of the names database, which are the links of the
+    /// database in the single-file layout.
+    pub fn names_links(&self) -> &DoubletStorage<T> {
+        self.names_links.as_ref().unwrap_or_else(|| self.links())
+    }
+
+    /// Gives direct access to the names database. The cached names are
+    /// dropped, since the caller may change them.
+    pub fn names_links_mut(&mut self) -> &mut DoubletStorage<T> {
+        self.name_resolver.invalidate();
+        self.name_resolver_with_links().1
+    }
+
+    /// Stores a string in the names database and returns its link.
+    pub fn create_string(&mut self, content: &str) -> Result<T> {
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.create_string(names_links, content)
+    }
+
+    /// Reads a string stored in the names database.
+    pub fn get_string(&mut self, string_link: T) -> Result<String> {
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.get_string(names_links, string_link)
     }
 
-    pub fn names_links_mut(&mut self) -> &mut LinkStorage {
-        &mut self.names_links
+    /// Returns the malformed lines skipped by a lenient load of either
+    /// database.
+    pub fn load_warnings(&self) -> Vec<&LoadWarning> {
+        self.links()
+            .load_warnings()
+            .iter()
+            .chain(
+                self.names_links
+                    .iter()
+                    .flat_map(|names_links| names_links.load_warnings()),
+            )
+            .collect()
     }
 
-    pub fn into_link_storages(self) -> (LinkStorage, LinkStorage) {
+    /// Returns the links and the names database, which is `None` in the
+    /// single-file layout.
+    pub fn into_link_storages(self) -> (DoubletStorage<T>, Option<DoubletStorage<T>>) {
         (
             self.pinned_types_decorator.into_link_storage(),
             self.names_links,
@@ -117,60 +314,65 @@ impl NamedTypesDecorator {
 
     pub fn save(&self) -> Result<()> {
         self.pinned_types_decorator.save()?;
-        self.names_links.save()?;
+        if let Some(names_links) = &self.names_links {
+            names_links.save()?;
+        }
         Ok(())
     }
 
-    pub fn create(&mut self, source: u32, target: u32) -> u32 {
+    pub fn create(&mut self, source: T, target: T) -> T {
         self.pinned_types_decorator.create(source, target)
     }
 
-    pub fn ensure_created(&mut self, id: u32) -> u32 {
+    pub fn ensure_created(&mut self, id: T) -> T {
         self.pinned_types_decorator.ensure_created(id)
     }
 
-    pub fn get(&self, id: u32) -> Option<&Link> {
+    pub fn get(&self, id: T) -> Option<&Doublet<T>> {
         self.pinned_types_decorator.get(id)
     }
 
-    pub fn exists(&self, id: u32) -> bool {
+    pub fn exists(&self, id: T) -> bool {
         self.pinned_types_decorator.exists(id)
     }
 
-    pub fn update(&mut self, id: u32, source: u32, target: u32) -> Result<Link> {
+    pub fn update(&mut self, id: T, source: T, target: T) -> Result<Doublet<T>> {
         self.pinned_types_decorator.update(id, source, target)
     }
 
-    pub fn delete(&mut self, id: u32) -> Result<Link> {
+    pub fn delete(&mut self, id: T) -> Result<Doublet<T>> {
         let deleted = self.pinned_types_decorator.delete(id)?;
         self.remove_name(id)?;
+        for alias in self.aliases(id)? {
+            self.remove_alias(id, &alias)?;
+        }
         Ok(deleted)
     }
 
-    pub fn all(&self) -> Vec<&Link> {
+    pub fn all(&self) -> Vec<&Doublet<T>> {
         self.pinned_types_decorator.all()
     }
 
     pub fn query(
         &self,
-        index: Option<u32>,
-        source: Option<u32>,
-        target: Option<u32>,
-    ) -> Vec<&Link> {
+        index: Option<T>,
+        source: Option<T>,
+        target: Option<T>,
+    ) -> Vec<&Doublet<T>> {
         self.pinned_types_decorator.query(index, source, target)
     }
 
-    pub fn search(&self, source: u32, target: u32) -> Option<u32> {
+    pub fn search(&self, source: T, target: T) -> Option<T> {
         self.pinned_types_decorator.search(source, target)
     }
 
-    pub fn get_or_create(&mut self, source: u32, target: u32) -> u32 {
+    pub fn get_or_create(&mut self, source: T, target: T) -> T {
         self.pinned_types_decorator.get_or_create(source, target)
     }
 
     fn from_link_storages_with_trace(
-        links: LinkStorage,
-        names_links: LinkStorage,
+        links: DoubletStorage<T>,
+        names_links: DoubletStorage<T>,
         trace: bool,
     ) -> Self {
         Self::from_decorators_with_trace(
@@ -181,64 +383,253 @@ impl NamedTypesDecorator {
     }
 
     fn from_decorators_with_trace(
-        pinned_types_decorator: PinnedTypesDecorator,
-        names_links: LinkStorage,
+        pinned_types_decorator: PinnedTypesDecorator<T>,
+        names_links: DoubletStorage<T>,
+        trace: bool,
+    ) -> Self {
+        Self::from_parts(pinned_types_decorator, Some(names_links), trace)
+    }
+
+    fn from_parts(
+        pinned_types_decorator: PinnedTypesDecorator<T>,
+        names_links: Option<DoubletStorage<T>>,
         trace: bool,
     ) -> Self {
         Self {
             pinned_types_decorator,
             names_links,
+            name_resolver: NameResolver::new(trace),
             trace,
         }
     }
 
-    fn with_named_links<T>(
-        &mut self,
-        action: impl FnOnce(&mut NamedLinks<'_>) -> Result<T>,
-    ) -> Result<T> {
-        let mut named_links = NamedLinks::new(&mut self.names_links)?;
-        action(&mut named_links)
+    /// Returns the name resolver together with the links it reads the names
+    /// from.
+    fn name_resolver_with_links(&mut self) -> (&mut NameResolver<T>, &mut DoubletStorage<T>) {
+        let names_links = match &mut self.names_links {
+            Some(names_links) => names_links,
+            None => self.pinned_types_decorator.links_mut(),
+        };
+        (&mut self.name_resolver, names_links)
+    }
+
+    /// Moves the links of the names database into the database, see
+    /// `set_names_layout`.
+    fn join_names(&mut self) -> Result<Vec<MovedLink<T>>> {
+        let Some(mut names_links) = self.names_links.take() else {
+            return Ok(Vec::new());
+        };
+        // Lays out the pinned types of a names database that has none yet.
+        let mut strings = UnicodeStringStorage::new(&mut names_links)?;
+        let names_ids = strings
+            .links()
+            .all()
+            .into_iter()
+            .map(|link| link.index)
+            .collect::<HashSet<_>>();
+        let links = self.pinned_types_decorator.links_mut();
+        let mut ids = links
+            .all()
+            .into_iter()
+            .map(|link| link.index)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        let mut next_id = ids
+            .iter()
+            .chain(&names_ids)
+            .max()
+            .map_or(T::ONE, |&id| id + T::ONE);
+
+        let mut mapping = HashMap::new();
+        let mut moved = Vec::new();
+        for old_id in ids.into_iter().filter(|id| names_ids.contains(id)) {
+            mapping.insert(old_id, next_id);
+            moved.push(MovedLink {
+                old_id,
+                new_id: next_id,
+            });
+            next_id = next_id + T::ONE;
+        }
+        // The names of moved links are set again once both databases share
+        // one storage, so the new name links cannot take a mapped id.
+        let moved_names = |names: Vec<(T, String)>| {
+            names
+                .into_iter()
+                .filter_map(|(reference, name)| {
+                    let new_id = mapping.get(&external_reference_value(reference)?)?;
+                    Some((reference, *new_id, name))
+                })
+                .collect::<Vec<_>>()
+        };
+        let names = moved_names(strings.names()?);
+        let aliases = moved_names(strings.aliases()?);
+        for (reference, _, _) in &names {
+            strings.remove_name(*reference)?;
+        }
+        for (reference, _, alias) in &aliases {
+            strings.remove_alias(*reference, alias)?;
+        }
+
+        links.renumber(&mapping);
+        let mut names_database = names_links.all().into_iter().copied().collect::<Vec<_>>();
+        names_database.sort_by_key(|link| link.index);
+        for link in names_database {
+            links.ensure_created(link.index);
+            links.update(link.index, link.source, link.target)?;
+        }
+        links.set_holds_names(true);
+
+        let mut strings = UnicodeStringStorage::new(links)?;
+        for (_, new_id, name) in &names {
+            strings.set_name_for_external_reference(*new_id, name)?;
+        }
+        for (_, new_id, alias) in &aliases {
+            strings.add_alias_for_external_reference(*new_id, alias)?;
+        }
+        Ok(moved)
+    }
+
+    /// Moves the links of the names database out of the database, see
+    /// `set_names_layout`.
+    fn split_names(&mut self) -> Result<()> {
+        let names_ids = self.names_link_ids()?;
+        let names_path = path_to_string(&NamedTypesDecorator::make_names_database_filename(
+            self.links().path(),
+        ));
+        let links = self.pinned_types_decorator.links_mut();
+        let mut ids = Vec::new();
+        for link in links.all() {
+            if names_ids.contains(&link.index) {
+                continue;
+            }
+            if let Some(reference) = [link.source, link.target]
+                .into_iter()
+                .find(|reference| names_ids.contains(reference))
+            {
+                bail!(
+                    "Link {} refers to link {reference} of the names database, which cannot be moved out of the database.",
+                    link.index
+                );
+            }
+            ids.push(link.index);
+        }
+
+        let mut text = Vec::new();
+        links.write_links(&mut text)?;
+        let mut names_links = DoubletStorage::from_text(&String::from_utf8(text)?)?;
+        for id in ids {
+            names_links.delete(id)?;
+        }
+        for &id in &names_ids {
+            links.delete(id)?;
+        }
+        links.set_holds_names(false);
+        names_links.set_holds_names(false);
+        names_links.relocate(&names_path);
+        self.names_links = Some(names_links);
+        Ok(())
+    }
+
+    /// Returns every named link of the main database with its name.
+    pub fn all_names(&mut self) -> Result<Vec<(T, String)>> {
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.all_names(names_links)
+    }
+
+    /// Returns every alias of a main database link with its link.
+    pub fn all_aliases(&mut self) -> Result<Vec<(T, String)>> {
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.all_aliases(names_links)
+    }
+
+    /// Returns the links with a primary name or an alias matching `search`,
+    /// ordered by link.
+    pub fn search_names(&mut self, search: &NameSearch) -> Result<Vec<(T, String)>> {
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.search_names(names_links, search)
     }
 }
 
-impl NamedTypes for NamedTypesDecorator {
-    fn get_name(&mut self, link: u32) -> Result<Option<String>> {
+impl<T: LinkAddress> NamedTypes<T> for NamedTypesDecorator<T> {
+    fn get_name(&mut self, link: T) -> Result<Option<String>> {
         if self.trace {
             eprintln!("[TRACE] NamedTypesDecorator get_name for link {link}");
         }
-        self.with_named_links(|named_links| named_links.get_name_by_external_reference(link))
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.get_name(names_links, link)
     }
 
-    fn set_name(&mut self, link: u32, name: &str) -> Result<u32> {
+    fn set_name(&mut self, link: T, name: &str) -> Result<T> {
         if self.trace {
             eprintln!("[TRACE] NamedTypesDecorator set_name for link {link}: {name}");
         }
         if let Some(existing_link_with_name) = self.get_by_name(name)? {
-            if existing_link_with_name != link {
+            if self
+                .aliases(existing_link_with_name)?
+                .iter()
+                .any(|alias| alias == name)
+            {
+                self.remove_alias(existing_link_with_name, name)?;
+            } else if existing_link_with_name != link {
                 self.remove_name(existing_link_with_name)?;
             }
         }
         self.remove_name(link)?;
-        self.with_named_links(|named_links| named_links.set_name_for_external_reference(link, name))
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.set_name(names_links, link, name)
     }
 
-    fn get_by_name(&mut self, name: &str) -> Result<Option<u32>> {
+    fn get_by_name(&mut self, name: &str) -> Result<Option<T>> {
         if self.trace {
             eprintln!("[TRACE] NamedTypesDecorator get_by_name for name {name}");
         }
-        self.with_named_links(|named_links| named_links.get_external_reference_by_name(name))
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.get_by_name(names_links, name)
     }
 
-    fn remove_name(&mut self, link: u32) -> Result<()> {
+    fn remove_name(&mut self, link: T) -> Result<()> {
         if self.trace {
             eprintln!("[TRACE] NamedTypesDecorator remove_name for link {link}");
         }
-        self.with_named_links(|named_links| named_links.remove_name_by_external_reference(link))
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.remove_name(names_links, link)
+    }
+
+    fn aliases(&mut self, link: T) -> Result<Vec<String>> {
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.aliases(names_links, link)
+    }
+
+    fn add_alias(&mut self, link: T, alias: &str) -> Result<()> {
+        if self.trace {
+            eprintln!("[TRACE] NamedTypesDecorator add_alias for link {link}: {alias}");
+        }
+        match self.get_by_name(alias)? {
+            Some(holder) if holder != link => {
+                bail!("The name '{alias}' already belongs to link {holder}.")
+            }
+            Some(_) => Ok(()),
+            None => {
+                let (name_resolver, names_links) = self.name_resolver_with_links();
+                name_resolver.add_alias(names_links, link, alias)
+            }
+        }
+    }
+
+    fn remove_alias(&mut self, link: T, alias: &str) -> Result<()> {
+        if self.trace {
+            eprintln!("[TRACE] NamedTypesDecorator remove_alias for link {link}: {alias}");
+        }
+        if !self.aliases(link)?.iter().any(|existing| existing == alias) {
+            bail!("Link {link} has no alias '{alias}'.");
+        }
+        let (name_resolver, names_links) = self.name_resolver_with_links();
+        name_resolver.remove_alias(names_links, link, alias)
     }
 }
 
-impl PinnedTypesAccess for NamedTypesDecorator {
-    fn pinned_types(&mut self, count: usize) -> Result<Vec<u32>> {
+impl<T: LinkAddress> PinnedTypesAccess<T> for NamedTypesDecorator<T> {
+    fn pinned_types(&mut self, count: usize) -> Result<Vec<T>> {
         self.pinned_types_decorator.pinned_types(count)
     }
 }
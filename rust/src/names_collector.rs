@@ -0,0 +1,131 @@
+//! Garbage collection of the names database for `clink gc`.
+//!
+//! Removing or replacing a name deletes its name pair, but the string, the
+//! Unicode sequence and the symbols it was stored with stay behind, so the
+//! names database only grows. `NamesCollector` keeps the types, the name and
+//! alias pairs and the strings the main database refers to, together with
+//! everything they are built from, and deletes the rest.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::hybrid_reference::external_reference_value;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::named_types::{NamedTypesDecorator, NamesLayout};
+use crate::unicode_string_storage::UnicodeStringStorage;
+
+/// The links a collection deleted from the names database, ordered by id.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CollectionReport<T: LinkAddress = u32> {
+    pub reclaimed: Vec<Doublet<T>>,
+    /// Number of reclaimed Unicode symbol links.
+    pub symbols: usize,
+    /// Number of reclaimed Unicode sequence links and the pairs they were
+    /// built from.
+    pub sequences: usize,
+    /// Number of reclaimed string links.
+    pub strings: usize,
+    /// Number of reclaimed name and alias links.
+    pub names: usize,
+}
+
+impl<T: LinkAddress> fmt::Display for CollectionReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "reclaimed links: {}", self.reclaimed.len())?;
+        writeln!(f, "symbols: {}", self.symbols)?;
+        writeln!(f, "sequences: {}", self.sequences)?;
+        writeln!(f, "strings: {}", self.strings)?;
+        write!(f, "names: {}", self.names)
+    }
+}
+
+/// Deletes the links of a names database that no name uses.
+pub struct NamesCollector<'a, T: LinkAddress = u32> {
+    storage: &'a mut NamedTypesDecorator<T>,
+}
+
+impl<'a, T: LinkAddress> NamesCollector<'a, T> {
+    pub fn new(storage: &'a mut NamedTypesDecorator<T>) -> Self {
+        Self { storage }
+    }
+
+    /// Deletes the unreachable links of the names database and returns them.
+    /// A link is reachable from a type, a name or alias pair, or an external
+    /// reference in the main database, such as a JSON string. In the
+    /// single-file layout only the links that keep the names are collected,
+    /// and those the database links refer to are kept too.
+    pub fn collect(&mut self) -> Result<CollectionReport<T>> {
+        let names_link_ids = self.storage.names_link_ids()?;
+        let single_file = self.storage.names_layout() == NamesLayout::SingleFile;
+        let mut roots = Vec::new();
+        for link in self.storage.all() {
+            if names_link_ids.contains(&link.index) {
+                continue;
+            }
+            for reference in [link.source, link.target] {
+                match external_reference_value(reference) {
+                    Some(value) => roots.push(value),
+                    None if single_file => roots.push(reference),
+                    None => {}
+                }
+            }
+        }
+
+        let mut strings = UnicodeStringStorage::new(self.storage.names_links_mut())?;
+        let pair_types = [Some(strings.name_type()), strings.alias_type()?];
+        let (type_type, symbol_type, string_type) = (
+            strings.type_type(),
+            strings.unicode_symbol_type(),
+            strings.string_type(),
+        );
+        let links = strings.links_mut();
+        for link in links.all() {
+            let is_type = link.source == type_type;
+            let is_pair = link.index != link.target
+                && links
+                    .get(link.target)
+                    .is_some_and(|target| pair_types.contains(&Some(target.source)));
+            if is_type || is_pair {
+                roots.push(link.index);
+            }
+        }
+
+        let mut reachable = HashSet::new();
+        while let Some(id) = roots.pop() {
+            let Some(link) = links.get(id) else {
+                continue;
+            };
+            if reachable.insert(id) {
+                roots.extend([link.source, link.target]);
+            }
+        }
+
+        let mut unreachable = links
+            .all()
+            .into_iter()
+            .filter(|link| !reachable.contains(&link.index))
+            .filter(|link| !single_file || names_link_ids.contains(&link.index))
+            .copied()
+            .collect::<Vec<_>>();
+        unreachable.sort_by_key(|link| link.index);
+
+        let mut report = CollectionReport::default();
+        for link in unreachable {
+            links.delete(link.index)?;
+            if link.target == symbol_type {
+                report.symbols += 1;
+            } else if link.source == string_type {
+                report.strings += 1;
+            } else if pair_types.contains(&Some(link.source)) {
+                report.names += 1;
+            } else {
+                report.sequences += 1;
+            }
+            report.reclaimed.push(link);
+        }
+        Ok(report)
+    }
+}
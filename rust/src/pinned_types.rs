@@ -3,35 +3,36 @@
 use anyhow::{bail, Result};
 use std::path::Path;
 
-use crate::link::Link;
-use crate::link_storage::LinkStorage;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
 
-pub trait PinnedTypesAccess {
-    fn pinned_types(&mut self, count: usize) -> Result<Vec<u32>>;
+pub trait PinnedTypesAccess<T: LinkAddress = u32> {
+    fn pinned_types(&mut self, count: usize) -> Result<Vec<T>>;
 
-    fn deconstruct_pinned_types(&mut self) -> Result<(u32, u32, u32)> {
+    fn deconstruct_pinned_types(&mut self) -> Result<(T, T, T)> {
         let pinned_types = self.pinned_types(3)?;
         Ok((pinned_types[0], pinned_types[1], pinned_types[2]))
     }
 }
 
 /// Creates or validates the reserved type links at deterministic addresses.
-pub struct PinnedTypes<'a> {
-    links: &'a mut LinkStorage,
-    current: u32,
-    initial_source: u32,
+pub struct PinnedTypes<'a, T: LinkAddress = u32> {
+    links: &'a mut DoubletStorage<T>,
+    current: T,
+    initial_source: T,
 }
 
-impl<'a> PinnedTypes<'a> {
-    pub fn new(links: &'a mut LinkStorage) -> Self {
+impl<'a, T: LinkAddress> PinnedTypes<'a, T> {
+    pub fn new(links: &'a mut DoubletStorage<T>) -> Self {
         Self {
             links,
-            current: 1,
-            initial_source: 1,
+            current: T::ONE,
+            initial_source: T::ONE,
         }
     }
 
-    pub fn next_type(&mut self) -> Result<u32> {
+    pub fn next_type(&mut self) -> Result<T> {
         let address = self.current;
         if let Some(link) = self.links.get(address) {
             if link.index != address || link.source != self.initial_source || link.target != address
@@ -53,48 +54,59 @@ impl<'a> PinnedTypes<'a> {
             }
         }
 
-        self.current += 1;
+        self.current = self.current + T::ONE;
         Ok(address)
     }
 
-    pub fn take_types(&mut self, count: usize) -> Result<Vec<u32>> {
+    pub fn take_types(&mut self, count: usize) -> Result<Vec<T>> {
         (0..count).map(|_| self.next_type()).collect()
     }
 
-    pub fn deconstruct(&mut self) -> Result<(u32, u32, u32)> {
+    pub fn deconstruct(&mut self) -> Result<(T, T, T)> {
         let pinned_types = self.take_types(3)?;
         Ok((pinned_types[0], pinned_types[1], pinned_types[2]))
     }
 }
 
 /// Link storage decorator that exposes pinned type allocation.
-pub struct PinnedTypesDecorator {
-    links: LinkStorage,
+pub struct PinnedTypesDecorator<T: LinkAddress = u32> {
+    links: DoubletStorage<T>,
 }
 
 impl PinnedTypesDecorator {
     pub fn new<P>(database_filename: P, trace: bool) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open(database_filename, trace)
+    }
+}
+
+impl<T: LinkAddress> PinnedTypesDecorator<T> {
+    /// Opens a database of any address width. `PinnedTypesDecorator::new`
+    /// opens `u32` databases.
+    pub fn open<P>(database_filename: P, trace: bool) -> Result<Self>
     where
         P: AsRef<Path>,
     {
         let database_path = database_filename.as_ref().to_string_lossy().into_owned();
-        let links = LinkStorage::new(&database_path, trace)?;
+        let links = DoubletStorage::new(&database_path, trace)?;
         Ok(Self::from_link_storage(links))
     }
 
-    pub fn from_link_storage(links: LinkStorage) -> Self {
+    pub fn from_link_storage(links: DoubletStorage<T>) -> Self {
         Self { links }
     }
 
-    pub fn links(&self) -> &LinkStorage {
+    pub fn links(&self) -> &DoubletStorage<T> {
         &self.links
     }
 
-    pub fn links_mut(&mut self) -> &mut LinkStorage {
+    pub fn links_mut(&mut self) -> &mut DoubletStorage<T> {
         &mut self.links
     }
 
-    pub fn into_link_storage(self) -> LinkStorage {
+    pub fn into_link_storage(self) -> DoubletStorage<T> {
         self.links
     }
 
@@ -102,54 +114,54 @@ impl PinnedTypesDecorator {
         self.links.save()
     }
 
-    pub fn create(&mut self, source: u32, target: u32) -> u32 {
+    pub fn create(&mut self, source: T, target: T) -> T {
         self.links.create(source, target)
     }
 
-    pub fn ensure_created(&mut self, id: u32) -> u32 {
+    pub fn ensure_created(&mut self, id: T) -> T {
         self.links.ensure_created(id)
     }
 
-    pub fn get(&self, id: u32) -> Option<&Link> {
+    pub fn get(&self, id: T) -> Option<&Doublet<T>> {
         self.links.get(id)
     }
 
-    pub fn exists(&self, id: u32) -> bool {
+    pub fn exists(&self, id: T) -> bool {
         self.links.exists(id)
     }
 
-    pub fn update(&mut self, id: u32, source: u32, target: u32) -> Result<Link> {
+    pub fn update(&mut self, id: T, source: T, target: T) -> Result<Doublet<T>> {
         self.links.update(id, source, target)
     }
 
-    pub fn delete(&mut self, id: u32) -> Result<Link> {
+    pub fn delete(&mut self, id: T) -> Result<Doublet<T>> {
         self.links.delete(id)
     }
 
-    pub fn all(&self) -> Vec<&Link> {
+    pub fn all(&self) -> Vec<&Doublet<T>> {
         self.links.all()
     }
 
     pub fn query(
         &self,
-        index: Option<u32>,
-        source: Option<u32>,
-        target: Option<u32>,
-    ) -> Vec<&Link> {
+        index: Option<T>,
+        source: Option<T>,
+        target: Option<T>,
+    ) -> Vec<&Doublet<T>> {
         self.links.query(index, source, target)
     }
 
-    pub fn search(&self, source: u32, target: u32) -> Option<u32> {
+    pub fn search(&self, source: T, target: T) -> Option<T> {
         self.links.search(source, target)
     }
 
-    pub fn get_or_create(&mut self, source: u32, target: u32) -> u32 {
+    pub fn get_or_create(&mut self, source: T, target: T) -> T {
         self.links.get_or_create(source, target)
     }
 }
 
-impl PinnedTypesAccess for PinnedTypesDecorator {
-    fn pinned_types(&mut self, count: usize) -> Result<Vec<u32>> {
+impl<T: LinkAddress> PinnedTypesAccess<T> for PinnedTypesDecorator<T> {
+    fn pinned_types(&mut self, count: usize) -> Result<Vec<T>> {
         PinnedTypes::new(&mut self.links).take_types(count)
     }
 }
@@ -4,30 +4,41 @@
 //! Corresponds to BasicQueryProcessor, MixedQueryProcessor, and AdvancedMixedQueryProcessor in C#
 
 use anyhow::Result;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 use crate::changes_simplifier::simplify_changes;
 use crate::error::LinkError;
-use crate::link::Link;
+use crate::link::{Doublet, DoubletChange};
+use crate::link_address::LinkAddress;
 use crate::link_reference_validator::LinkReferenceValidator;
 use crate::lino_link::LinoLink;
 use crate::named_type_links::NamedTypeLinks;
 use crate::parser::Parser;
-use crate::query_types::{Pattern, ResolvedLink};
+use crate::query_types::{Pattern, ResolvedLink, ResolvedOperation, SubsequencePattern};
+
+mod matching;
+mod subsequence;
+
+use subsequence::SubsequenceMatches;
 
 /// QueryProcessor handles LiNo query parsing and execution
 /// Corresponds to AdvancedMixedQueryProcessor in C#
-pub struct QueryProcessor {
+pub struct QueryProcessor<T: LinkAddress = u32> {
     trace: bool,
     auto_create_missing_references: bool,
+    auto_save: bool,
+    subsequence_matches: RefCell<SubsequenceMatches<T>>,
 }
 
-impl QueryProcessor {
+impl<T: LinkAddress> QueryProcessor<T> {
     /// Creates a new QueryProcessor
     pub fn new(trace: bool) -> Self {
         Self {
             trace,
             auto_create_missing_references: false,
+            auto_save: true,
+            subsequence_matches: RefCell::default(),
         }
     }
 
@@ -39,13 +50,21 @@ impl QueryProcessor {
         self
     }
 
+    /// Controls whether `process_query` saves the storage after applying a
+    /// query. Long-lived sessions disable it and save explicitly.
+    pub fn with_auto_save(mut self, auto_save: bool) -> Self {
+        self.auto_save = auto_save;
+        self
+    }
+
     /// Processes a LiNo query and returns the list of changes
     pub fn process_query(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         query: &str,
-    ) -> Result<Vec<(Option<Link>, Option<Link>)>> {
+    ) -> Result<Vec<DoubletChange<T>>> {
         self.trace_msg(&format!("[ProcessQuery] Query: \"{}\"", query));
+        self.subsequence_matches.take();
 
         let query = query.trim();
         if query.is_empty() {
@@ -121,7 +140,7 @@ impl QueryProcessor {
                     }
                 }
             }
-            storage.save()?;
+            self.save_storage(storage)?;
             return Ok(changes_list);
         }
 
@@ -137,14 +156,7 @@ impl QueryProcessor {
                     .map(|link| (None, Some(link))),
             );
 
-            let restriction_patterns = self.patterns_from_lino(restriction_link);
-            let mut links_to_delete = Vec::new();
-            for pattern in &restriction_patterns {
-                links_to_delete.extend(self.matched_links(storage, pattern, &HashMap::new())?);
-            }
-            links_to_delete.sort_by_key(|link| link.index);
-            links_to_delete.dedup_by_key(|link| link.index);
-
+            let links_to_delete = self.links_matching_restriction(storage, restriction_link)?;
             for link in links_to_delete {
                 if storage.exists(link.index) {
                     let before = storage.delete(link.index)?;
@@ -152,7 +164,7 @@ impl QueryProcessor {
                     self.trace_msg(&format!("[ProcessQuery] Deleted link ID #{}.", link.index));
                 }
             }
-            storage.save()?;
+            self.save_storage(storage)?;
             return Ok(changes_list);
         }
 
@@ -179,7 +191,7 @@ impl QueryProcessor {
         if solutions.is_empty() {
             self.trace_msg("[ProcessQuery] No solutions found => returning.");
             if !changes_list.is_empty() {
-                storage.save()?;
+                self.save_storage(storage)?;
             }
             return Ok(changes_list);
         }
@@ -221,7 +233,7 @@ impl QueryProcessor {
             }
         }
 
-        storage.save()?;
+        self.save_storage(storage)?;
 
         // Simplify changes
         let simplified = self.simplify_changes_list(&changes_list);
@@ -229,12 +241,43 @@ impl QueryProcessor {
         Ok(simplified)
     }
 
+    /// Returns the links matched by a restriction, such as `((* father))`,
+    /// without changing the storage.
+    pub fn find_matching_links(
+        &self,
+        storage: &mut impl NamedTypeLinks<T>,
+        restriction: &str,
+    ) -> Result<Vec<Doublet<T>>> {
+        self.subsequence_matches.take();
+        let mut links = Vec::new();
+        for restriction_link in Parser::new().parse(restriction.trim())? {
+            links.extend(self.links_matching_restriction(storage, &restriction_link)?);
+        }
+        links.sort_by_key(|link| link.index);
+        links.dedup_by_key(|link| link.index);
+        Ok(links)
+    }
+
+    fn links_matching_restriction(
+        &self,
+        storage: &mut impl NamedTypeLinks<T>,
+        restriction_link: &LinoLink,
+    ) -> Result<Vec<Doublet<T>>> {
+        let mut links = Vec::new();
+        for pattern in &self.patterns_from_lino(restriction_link) {
+            links.extend(self.matched_links(storage, pattern, &HashMap::new())?);
+        }
+        links.sort_by_key(|link| link.index);
+        links.dedup_by_key(|link| link.index);
+        Ok(links)
+    }
+
     fn validate_links_exist_or_will_be_created(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         restriction_patterns: &[LinoLink],
         substitution_patterns: &[LinoLink],
-    ) -> Result<Vec<Link>> {
+    ) -> Result<Vec<Doublet<T>>> {
         LinkReferenceValidator::new(self.trace, self.auto_create_missing_references)
             .validate_links_exist_or_will_be_created(
                 storage,
@@ -264,6 +307,9 @@ impl QueryProcessor {
 
     fn create_pattern_from_lino(lino_link: &LinoLink) -> Pattern {
         let index = lino_link.id.clone().unwrap_or_default();
+        if let Some(subsequence) = SubsequencePattern::from_lino(lino_link) {
+            return Pattern::with_subsequence(index, subsequence);
+        }
         match &lino_link.values {
             Some(values) if values.len() == 2 => Pattern::new(
                 index,
@@ -274,252 +320,13 @@ impl QueryProcessor {
         }
     }
 
-    fn find_all_solutions(
-        &self,
-        storage: &mut impl NamedTypeLinks,
-        patterns: &[Pattern],
-    ) -> Result<Vec<HashMap<String, u32>>> {
-        let mut partial_solutions = vec![HashMap::new()];
-
-        for pattern in patterns {
-            let mut new_solutions = Vec::new();
-            for solution in &partial_solutions {
-                for match_solution in self.match_pattern(storage, pattern, solution)? {
-                    if Self::solutions_are_compatible(solution, &match_solution) {
-                        let mut combined = solution.clone();
-                        combined.extend(match_solution);
-                        new_solutions.push(combined);
-                    }
-                }
-            }
-            partial_solutions = new_solutions;
-            if partial_solutions.is_empty() {
-                break;
-            }
-        }
-
-        Ok(partial_solutions)
-    }
-
-    fn solutions_are_compatible(
-        existing: &HashMap<String, u32>,
-        new_assignments: &HashMap<String, u32>,
-    ) -> bool {
-        new_assignments
-            .iter()
-            .all(|(key, value)| existing.get(key).is_none_or(|existing| existing == value))
-    }
-
-    fn match_pattern(
-        &self,
-        storage: &mut impl NamedTypeLinks,
-        pattern: &Pattern,
-        current_solution: &HashMap<String, u32>,
-    ) -> Result<Vec<HashMap<String, u32>>> {
-        if pattern.is_leaf() {
-            let resolved_index =
-                self.resolve_match_id(storage, &pattern.index, current_solution)?;
-            return Ok(storage
-                .all_links()
-                .into_iter()
-                .filter(|link| Self::is_any(resolved_index) || link.index == resolved_index)
-                .map(|link| {
-                    let mut assignments = HashMap::new();
-                    Self::assign_variable(&pattern.index, link.index, &mut assignments);
-                    assignments
-                })
-                .collect());
-        }
-
-        let resolved_index = self.resolve_match_id(storage, &pattern.index, current_solution)?;
-
-        if !Self::is_variable(&pattern.index)
-            && !Self::is_any(resolved_index)
-            && resolved_index != 0
-            && storage.exists(resolved_index)
-        {
-            let link = storage.get_link(resolved_index).unwrap();
-            return self.match_link_against_pattern(storage, pattern, link, current_solution);
-        }
-
-        let mut results = Vec::new();
-        for link in storage.all_links() {
-            results.extend(self.match_link_against_pattern(
-                storage,
-                pattern,
-                link,
-                current_solution,
-            )?);
-        }
-        Ok(results)
-    }
-
-    fn match_link_against_pattern(
-        &self,
-        storage: &mut impl NamedTypeLinks,
-        pattern: &Pattern,
-        link: Link,
-        current_solution: &HashMap<String, u32>,
-    ) -> Result<Vec<HashMap<String, u32>>> {
-        if !self.check_id_match(storage, &pattern.index, link.index, current_solution)? {
-            return Ok(Vec::new());
-        }
-
-        let mut results = Vec::new();
-        let source_matches = self.recursive_match_subpattern(
-            storage,
-            pattern.source.as_deref(),
-            link.source,
-            current_solution,
-        )?;
-
-        for source_solution in source_matches {
-            let target_matches = self.recursive_match_subpattern(
-                storage,
-                pattern.target.as_deref(),
-                link.target,
-                &source_solution,
-            )?;
-            for mut target_solution in target_matches {
-                Self::assign_variable(&pattern.index, link.index, &mut target_solution);
-                results.push(target_solution);
-            }
-        }
-
-        Ok(results)
-    }
-
-    fn recursive_match_subpattern(
-        &self,
-        storage: &mut impl NamedTypeLinks,
-        pattern: Option<&Pattern>,
-        link_id: u32,
-        current_solution: &HashMap<String, u32>,
-    ) -> Result<Vec<HashMap<String, u32>>> {
-        let Some(pattern) = pattern else {
-            return Ok(vec![current_solution.clone()]);
-        };
-
-        if pattern.is_leaf() {
-            if self.check_id_match(storage, &pattern.index, link_id, current_solution)? {
-                let mut solution = current_solution.clone();
-                Self::assign_variable(&pattern.index, link_id, &mut solution);
-                return Ok(vec![solution]);
-            }
-            return Ok(Vec::new());
-        }
-
-        let Some(link) = storage.get_link(link_id) else {
-            return Ok(Vec::new());
-        };
-
-        self.match_link_against_pattern(storage, pattern, link, current_solution)
-    }
-
-    fn check_id_match(
-        &self,
-        storage: &mut impl NamedTypeLinks,
-        pattern_id: &str,
-        candidate_id: u32,
-        current_solution: &HashMap<String, u32>,
-    ) -> Result<bool> {
-        if pattern_id.is_empty() || pattern_id == "*" {
-            return Ok(true);
-        }
-
-        if Self::is_variable(pattern_id) {
-            return Ok(current_solution
-                .get(pattern_id)
-                .is_none_or(|existing| *existing == candidate_id));
-        }
-
-        if let Ok(parsed) = pattern_id.parse::<u32>() {
-            return Ok(parsed == candidate_id);
-        }
-
-        Ok(storage
-            .get_by_name(pattern_id)?
-            .is_some_and(|named_id| named_id == candidate_id))
-    }
-
-    fn resolve_match_id(
-        &self,
-        storage: &mut impl NamedTypeLinks,
-        identifier: &str,
-        current_solution: &HashMap<String, u32>,
-    ) -> Result<u32> {
-        if identifier.is_empty() || identifier == "*" {
-            return Ok(u32::MAX);
-        }
-        if let Some(value) = current_solution.get(identifier) {
-            return Ok(*value);
-        }
-        if Self::is_variable(identifier) {
-            return Ok(u32::MAX);
-        }
-        if let Ok(parsed) = identifier.parse::<u32>() {
-            return Ok(parsed);
-        }
-        Ok(storage.get_by_name(identifier)?.unwrap_or(0))
-    }
-
-    fn matched_links(
-        &self,
-        storage: &mut impl NamedTypeLinks,
-        pattern: &Pattern,
-        solution: &HashMap<String, u32>,
-    ) -> Result<Vec<Link>> {
-        if pattern.is_leaf() {
-            let resolved_index = self.resolve_match_id(storage, &pattern.index, solution)?;
-            return Ok(storage
-                .all_links()
-                .into_iter()
-                .filter(|link| Self::is_any(resolved_index) || link.index == resolved_index)
-                .collect());
-        }
-
-        let mut links = Vec::new();
-        for matched_solution in self.match_pattern(storage, pattern, solution)? {
-            if let Some(definition) =
-                self.resolve_pattern_readonly(storage, pattern, &matched_solution, false)?
-            {
-                links.extend(self.links_matching_definition(storage, &definition)?);
-            }
-        }
-        Ok(links)
-    }
-
-    fn solution_is_no_operation(
-        &self,
-        storage: &mut impl NamedTypeLinks,
-        solution: &HashMap<String, u32>,
-        restrictions: &[Pattern],
-        substitutions: &[Pattern],
-    ) -> Result<bool> {
-        let mut restriction_links = self
-            .resolve_patterns_readonly(storage, restrictions, solution, false)?
-            .into_iter()
-            .map(|definition| definition.to_link())
-            .collect::<Vec<_>>();
-        let mut substitution_links = self
-            .resolve_patterns_readonly(storage, substitutions, solution, true)?
-            .into_iter()
-            .map(|definition| definition.to_link())
-            .collect::<Vec<_>>();
-
-        restriction_links.sort_by_key(|link| link.index);
-        substitution_links.sort_by_key(|link| link.index);
-
-        Ok(restriction_links == substitution_links)
-    }
-
     fn resolve_patterns_readonly(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         patterns: &[Pattern],
-        solution: &HashMap<String, u32>,
+        solution: &HashMap<String, T>,
         is_substitution: bool,
-    ) -> Result<Vec<ResolvedLink>> {
+    ) -> Result<Vec<ResolvedLink<T>>> {
         let mut resolved = Vec::new();
         for pattern in patterns {
             if let Some(link) =
@@ -533,39 +340,39 @@ impl QueryProcessor {
 
     fn resolve_pattern_readonly(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         pattern: &Pattern,
-        solution: &HashMap<String, u32>,
+        solution: &HashMap<String, T>,
         is_substitution: bool,
-    ) -> Result<Option<ResolvedLink>> {
+    ) -> Result<Option<ResolvedLink<T>>> {
         if pattern.is_leaf() {
             let index = self.resolve_identifier_readonly(
                 storage,
                 &pattern.index,
                 solution,
-                if is_substitution { 0 } else { u32::MAX },
+                if is_substitution { T::ZERO } else { T::MAX },
             )?;
-            return Ok(Some(ResolvedLink::new(index, u32::MAX, u32::MAX, None)));
+            return Ok(Some(ResolvedLink::new(index, T::MAX, T::MAX, None)));
         }
 
         let source_pattern = pattern
             .source
             .as_deref()
-            .ok_or_else(|| LinkError::InvalidFormat("Invalid source pattern".to_string()))?;
+            .ok_or_else(|| LinkError::QueryError("Invalid source pattern".to_string()))?;
         let target_pattern = pattern
             .target
             .as_deref()
-            .ok_or_else(|| LinkError::InvalidFormat("Invalid target pattern".to_string()))?;
+            .ok_or_else(|| LinkError::QueryError("Invalid target pattern".to_string()))?;
 
         let source = self
             .resolve_pattern_readonly(storage, source_pattern, solution, is_substitution)?
-            .ok_or_else(|| LinkError::InvalidFormat("Invalid source pattern".to_string()))?
+            .ok_or_else(|| LinkError::QueryError("Invalid source pattern".to_string()))?
             .index;
         let target = self
             .resolve_pattern_readonly(storage, target_pattern, solution, is_substitution)?
-            .ok_or_else(|| LinkError::InvalidFormat("Invalid target pattern".to_string()))?
+            .ok_or_else(|| LinkError::QueryError("Invalid target pattern".to_string()))?
             .index;
-        let default_index = if is_substitution { 0 } else { u32::MAX };
+        let default_index = if is_substitution { T::ZERO } else { T::MAX };
         let index =
             self.resolve_identifier_readonly(storage, &pattern.index, solution, default_index)?;
 
@@ -574,16 +381,16 @@ impl QueryProcessor {
 
     fn resolve_identifier_readonly(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         identifier: &str,
-        solution: &HashMap<String, u32>,
-        default_value: u32,
-    ) -> Result<u32> {
+        solution: &HashMap<String, T>,
+        default_value: T,
+    ) -> Result<T> {
         if identifier.is_empty() {
             return Ok(default_value);
         }
         if identifier == "*" {
-            return Ok(u32::MAX);
+            return Ok(T::MAX);
         }
         if let Some(value) = solution.get(identifier) {
             return Ok(*value);
@@ -591,7 +398,7 @@ impl QueryProcessor {
         if Self::is_variable(identifier) {
             return Ok(default_value);
         }
-        if let Ok(parsed) = identifier.parse::<u32>() {
+        if let Ok(parsed) = identifier.parse::<T>() {
             return Ok(parsed);
         }
         Ok(storage.get_by_name(identifier)?.unwrap_or(default_value))
@@ -599,11 +406,11 @@ impl QueryProcessor {
 
     fn resolve_patterns(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         patterns: &[Pattern],
-        solution: &HashMap<String, u32>,
+        solution: &HashMap<String, T>,
         is_substitution: bool,
-    ) -> Result<Vec<ResolvedLink>> {
+    ) -> Result<Vec<ResolvedLink<T>>> {
         patterns
             .iter()
             .map(|pattern| self.resolve_pattern(storage, pattern, solution, is_substitution))
@@ -612,20 +419,20 @@ impl QueryProcessor {
 
     fn resolve_pattern(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         pattern: &Pattern,
-        solution: &HashMap<String, u32>,
+        solution: &HashMap<String, T>,
         is_substitution: bool,
-    ) -> Result<ResolvedLink> {
+    ) -> Result<ResolvedLink<T>> {
         if pattern.is_leaf() {
             let index = self.resolve_identifier(
                 storage,
                 &pattern.index,
                 solution,
-                if is_substitution { 0 } else { u32::MAX },
+                if is_substitution { T::ZERO } else { T::MAX },
                 is_substitution,
             )?;
-            return Ok(ResolvedLink::new(index, u32::MAX, u32::MAX, None));
+            return Ok(ResolvedLink::new(index, T::MAX, T::MAX, None));
         }
 
         let source = self
@@ -644,7 +451,7 @@ impl QueryProcessor {
                 is_substitution,
             )?
             .index;
-        let default_index = if is_substitution { 0 } else { u32::MAX };
+        let default_index = if is_substitution { T::ZERO } else { T::MAX };
         let mut index =
             self.resolve_identifier(storage, &pattern.index, solution, default_index, false)?;
         let mut name = None;
@@ -654,7 +461,7 @@ impl QueryProcessor {
             && !Self::is_numeric_or_wildcard(&pattern.index)
         {
             name = Some(pattern.index.clone());
-            if index == 0 {
+            if index == T::ZERO {
                 if let Some(existing_id) = storage.search(source, target) {
                     index = existing_id;
                 }
@@ -666,17 +473,17 @@ impl QueryProcessor {
 
     fn resolve_identifier(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         identifier: &str,
-        solution: &HashMap<String, u32>,
-        default_value: u32,
+        solution: &HashMap<String, T>,
+        default_value: T,
         create_named_leaf: bool,
-    ) -> Result<u32> {
+    ) -> Result<T> {
         if identifier.is_empty() {
             return Ok(default_value);
         }
         if identifier == "*" {
-            return Ok(u32::MAX);
+            return Ok(T::MAX);
         }
         if let Some(value) = solution.get(identifier) {
             return Ok(*value);
@@ -684,7 +491,7 @@ impl QueryProcessor {
         if Self::is_variable(identifier) {
             return Ok(default_value);
         }
-        if let Ok(parsed) = identifier.parse::<u32>() {
+        if let Ok(parsed) = identifier.parse::<T>() {
             return Ok(parsed);
         }
         if let Some(named_id) = storage.get_by_name(identifier)? {
@@ -698,9 +505,9 @@ impl QueryProcessor {
 
     fn determine_operations(
         &self,
-        restrictions: &[ResolvedLink],
-        substitutions: &[ResolvedLink],
-    ) -> Vec<(Option<ResolvedLink>, Option<ResolvedLink>)> {
+        restrictions: &[ResolvedLink<T>],
+        substitutions: &[ResolvedLink<T>],
+    ) -> Vec<ResolvedOperation<T>> {
         let mut operations = Vec::new();
         let mut restriction_by_index = HashMap::new();
         let mut substitution_by_index = HashMap::new();
@@ -761,10 +568,10 @@ impl QueryProcessor {
 
     fn apply_operation(
         &self,
-        storage: &mut impl NamedTypeLinks,
-        before: Option<ResolvedLink>,
-        after: Option<ResolvedLink>,
-        changes: &mut Vec<(Option<Link>, Option<Link>)>,
+        storage: &mut impl NamedTypeLinks<T>,
+        before: Option<ResolvedLink<T>>,
+        after: Option<ResolvedLink<T>>,
+        changes: &mut Vec<DoubletChange<T>>,
     ) -> Result<()> {
         match (before, after) {
             (Some(before), None) => {
@@ -789,7 +596,7 @@ impl QueryProcessor {
                         storage.update(before.index, after.source, after.target)?;
                     }
                     if let Some(name) = &after.name {
-                        storage.set_name(before.index, name)?;
+                        Self::name_link(storage, before.index, name)?;
                     }
                     let after_link = storage.get_link(before.index).unwrap();
                     changes.push((Some(before_link), Some(after_link)));
@@ -806,9 +613,9 @@ impl QueryProcessor {
 
     fn create_or_update_resolved_link(
         &self,
-        storage: &mut impl NamedTypeLinks,
-        definition: &ResolvedLink,
-    ) -> Result<Link> {
+        storage: &mut impl NamedTypeLinks<T>,
+        definition: &ResolvedLink<T>,
+    ) -> Result<Doublet<T>> {
         let id = if Self::is_normal_index(definition.index) {
             storage.ensure_created(definition.index);
             storage.update(definition.index, definition.source, definition.target)?;
@@ -820,22 +627,31 @@ impl QueryProcessor {
         };
 
         if let Some(name) = &definition.name {
-            storage.set_name(id, name)?;
+            Self::name_link(storage, id, name)?;
         }
 
         Ok(storage.get_link(id).unwrap())
     }
 
+    /// Gives `id` the name, unless the name (or one of its aliases) already
+    /// resolves to it.
+    fn name_link(storage: &mut impl NamedTypeLinks<T>, id: T, name: &str) -> Result<()> {
+        if storage.get_by_name(name)? != Some(id) {
+            storage.set_name(id, name)?;
+        }
+        Ok(())
+    }
+
     fn links_matching_definition(
         &self,
-        storage: &mut impl NamedTypeLinks,
-        definition: &ResolvedLink,
-    ) -> Result<Vec<Link>> {
+        storage: &mut impl NamedTypeLinks<T>,
+        definition: &ResolvedLink<T>,
+    ) -> Result<Vec<Doublet<T>>> {
         Ok(storage
             .all_links()
             .into_iter()
             .filter(|link| {
-                (definition.index == 0
+                (definition.index == T::ZERO
                     || Self::is_any(definition.index)
                     || link.index == definition.index)
                     && (Self::is_any(definition.source) || link.source == definition.source)
@@ -844,8 +660,8 @@ impl QueryProcessor {
             .collect())
     }
 
-    fn assign_variable(id: &str, value: u32, assignments: &mut HashMap<String, u32>) {
-        if Self::is_variable(id) && value != 0 {
+    fn assign_variable(id: &str, value: T, assignments: &mut HashMap<String, T>) {
+        if Self::is_variable(id) && value != T::ZERO {
             assignments.insert(id.to_string(), value);
         }
     }
@@ -854,40 +670,40 @@ impl QueryProcessor {
         !identifier.is_empty() && identifier.starts_with('$')
     }
 
-    fn is_any(value: u32) -> bool {
-        value == u32::MAX
+    fn is_any(value: T) -> bool {
+        value == T::MAX
     }
 
-    fn is_normal_index(value: u32) -> bool {
-        value != 0 && !Self::is_any(value)
+    fn is_normal_index(value: T) -> bool {
+        value != T::ZERO && !Self::is_any(value)
     }
 
     fn is_numeric_or_wildcard(identifier: &str) -> bool {
-        identifier == "*" || identifier.parse::<u32>().is_ok()
+        identifier == "*" || identifier.parse::<T>().is_ok()
     }
 
     /// Ensures a link is created from a LinoLink pattern
     fn ensure_link_created(
         &self,
-        storage: &mut impl NamedTypeLinks,
+        storage: &mut impl NamedTypeLinks<T>,
         lino_link: &LinoLink,
-    ) -> Result<u32> {
+    ) -> Result<T> {
         // Handle leaf nodes (names or numbers)
         if !lino_link.has_values() {
             if let Some(ref id) = lino_link.id {
                 if id == "*" || Self::is_variable(id) {
-                    return Ok(u32::MAX);
+                    return Ok(T::MAX);
                 }
 
                 // Check if it's a number
-                if let Ok(num) = id.parse::<u32>() {
+                if let Ok(num) = id.parse::<T>() {
                     return Ok(num);
                 }
 
                 // It's a name - get or create
                 return storage.get_or_create_named(id);
             }
-            return Ok(0);
+            return Ok(T::ZERO);
         }
 
         // Handle composite links with 2 values
@@ -900,7 +716,7 @@ impl QueryProcessor {
 
             // Create or get the composite link
             let link_id = if let Some(ref id) = lino_link.id {
-                if let Ok(num) = id.parse::<u32>() {
+                if let Ok(num) = id.parse::<T>() {
                     // Specific ID requested
                     storage.ensure_created(num);
                     storage.update(num, source_id, target_id)?;
@@ -927,17 +743,14 @@ impl QueryProcessor {
             return Ok(link_id);
         }
 
-        Err(LinkError::InvalidFormat("Invalid link structure".to_string()).into())
+        Err(LinkError::QueryError("Invalid link structure".to_string()).into())
     }
 
     /// Simplifies the changes list
-    fn simplify_changes_list(
-        &self,
-        changes: &[(Option<Link>, Option<Link>)],
-    ) -> Vec<(Option<Link>, Option<Link>)> {
+    fn simplify_changes_list(&self, changes: &[DoubletChange<T>]) -> Vec<DoubletChange<T>> {
         // Convert to the format expected by simplify_changes
-        let mut to_simplify: Vec<(Link, Link)> = Vec::new();
-        let mut non_simplifiable: Vec<(Option<Link>, Option<Link>)> = Vec::new();
+        let mut to_simplify: Vec<(Doublet<T>, Doublet<T>)> = Vec::new();
+        let mut non_simplifiable: Vec<DoubletChange<T>> = Vec::new();
 
         for (before, after) in changes {
             match (before, after) {
@@ -952,7 +765,7 @@ impl QueryProcessor {
 
         let simplified = simplify_changes(to_simplify);
 
-        let mut result: Vec<(Option<Link>, Option<Link>)> = non_simplifiable;
+        let mut result: Vec<DoubletChange<T>> = non_simplifiable;
         for (b, a) in simplified {
             result.push((Some(b), Some(a)));
         }
@@ -960,6 +773,13 @@ impl QueryProcessor {
         result
     }
 
+    fn save_storage(&self, storage: &mut impl NamedTypeLinks<T>) -> Result<()> {
+        if self.auto_save {
+            storage.save()?;
+        }
+        Ok(())
+    }
+
     /// Logs a trace message if tracing is enabled
     fn trace_msg(&self, msg: &str) {
         if self.trace {
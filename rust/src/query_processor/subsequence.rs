@@ -0,0 +1,94 @@
+//! Matching of `contains` restrictions against stored sequences.
+
+use anyhow::Result;
+use std::collections::HashMap;
+
+use super::QueryProcessor;
+use crate::error::LinkError;
+use crate::link_address::LinkAddress;
+use crate::named_type_links::NamedTypeLinks;
+use crate::query_types::SubsequencePattern;
+use crate::sequences::{LinkUsages, Sequences};
+
+/// Sequences found for `contains` restrictions during one query, keyed by
+/// the resolved sequence type, element type and elements. The storage does
+/// not change while a query is matched, so the usage index is built once
+/// and a restriction nested under other patterns is not searched again for
+/// every candidate link.
+#[derive(Debug)]
+pub(super) struct SubsequenceMatches<T: LinkAddress> {
+    usages: Option<LinkUsages<T>>,
+    sequences: HashMap<Vec<T>, Vec<T>>,
+}
+
+impl<T: LinkAddress> Default for SubsequenceMatches<T> {
+    fn default() -> Self {
+        Self {
+            usages: None,
+            sequences: HashMap::new(),
+        }
+    }
+}
+
+impl<T: LinkAddress> QueryProcessor<T> {
+    /// Returns the sequences holding the elements of a `contains`
+    /// restriction that also match its index, such as `$s` or `*`.
+    pub(super) fn matched_sequences(
+        &self,
+        storage: &mut impl NamedTypeLinks<T>,
+        index: &str,
+        subsequence: &SubsequencePattern,
+        solution: &HashMap<String, T>,
+    ) -> Result<Vec<T>> {
+        let mut resolved = Vec::new();
+        for identifier in [&subsequence.sequence_type, &subsequence.element_type]
+            .into_iter()
+            .chain(&subsequence.elements)
+        {
+            let id = self.resolve_match_id(storage, identifier, solution)?;
+            if Self::is_any(id) {
+                return Err(LinkError::QueryError(format!(
+                    "'{identifier}' in a contains restriction must refer to a link."
+                ))
+                .into());
+            }
+            if id == T::ZERO {
+                return Ok(Vec::new());
+            }
+            resolved.push(id);
+        }
+
+        let sequences = self.sequences_containing(storage, resolved);
+        let mut matched = Vec::new();
+        for sequence in sequences {
+            if self.check_id_match(storage, index, sequence, solution)? {
+                matched.push(sequence);
+            }
+        }
+        Ok(matched)
+    }
+
+    /// Searches the sequences for `resolved` once per query.
+    fn sequences_containing(
+        &self,
+        storage: &mut impl NamedTypeLinks<T>,
+        resolved: Vec<T>,
+    ) -> Vec<T> {
+        let mut matches = self.subsequence_matches.borrow_mut();
+        if let Some(sequences) = matches.sequences.get(&resolved) {
+            return sequences.clone();
+        }
+        let usages = matches
+            .usages
+            .get_or_insert_with(|| LinkUsages::from_links(storage.all_links()));
+        let sequences =
+            Sequences::new(resolved[1], resolved[0]).containing_in(usages, &resolved[2..]);
+        self.trace_msg(&format!(
+            "[MatchedSequences] {} sequence(s) contain {:?}.",
+            sequences.len(),
+            &resolved[2..]
+        ));
+        matches.sequences.insert(resolved, sequences.clone());
+        sequences
+    }
+}
@@ -1,10 +1,19 @@
-use crate::link::Link;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::lino_link::LinoLink;
+
+/// First value of a `(contains <sequence type> <element type> (<elements>))`
+/// restriction.
+pub(crate) const CONTAINS_KEYWORD: &str = "contains";
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub(crate) struct Pattern {
     pub(crate) index: String,
     pub(crate) source: Option<Box<Pattern>>,
     pub(crate) target: Option<Box<Pattern>>,
+    /// Set for `contains` restrictions, which match the sequences holding
+    /// the elements instead of a doublet.
+    pub(crate) subsequence: Option<SubsequencePattern>,
 }
 
 impl Pattern {
@@ -13,6 +22,14 @@ impl Pattern {
             index,
             source: source.map(Box::new),
             target: target.map(Box::new),
+            subsequence: None,
+        }
+    }
+
+    pub(crate) fn with_subsequence(index: String, subsequence: SubsequencePattern) -> Self {
+        Self {
+            subsequence: Some(subsequence),
+            ..Self::new(index, None, None)
         }
     }
 
@@ -21,16 +38,59 @@ impl Pattern {
     }
 }
 
+/// Identifiers of a `contains` restriction, such as
+/// `($s: contains Sequence Element (a b))`: the sequences of the sequence
+/// type holding `a` directly followed by `b`, stored as in `Sequences`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct SubsequencePattern {
+    pub(crate) sequence_type: String,
+    pub(crate) element_type: String,
+    pub(crate) elements: Vec<String>,
+}
+
+impl SubsequencePattern {
+    /// Reads a `contains` restriction; a single element may be written
+    /// without parentheses.
+    pub(crate) fn from_lino(lino_link: &LinoLink) -> Option<Self> {
+        let [keyword, sequence_type, element_type, elements] = lino_link.values.as_deref()? else {
+            return None;
+        };
+        if keyword.has_values() || keyword.id.as_deref() != Some(CONTAINS_KEYWORD) {
+            return None;
+        }
+        let elements = match &elements.values {
+            Some(values) => values.iter().map(leaf_identifier).collect::<Option<_>>()?,
+            None => vec![leaf_identifier(elements)?],
+        };
+        Some(Self {
+            sequence_type: leaf_identifier(sequence_type)?,
+            element_type: leaf_identifier(element_type)?,
+            elements,
+        })
+    }
+}
+
+fn leaf_identifier(lino_link: &LinoLink) -> Option<String> {
+    if lino_link.has_values() {
+        return None;
+    }
+    lino_link.id.clone()
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub(crate) struct ResolvedLink {
-    pub(crate) index: u32,
-    pub(crate) source: u32,
-    pub(crate) target: u32,
+pub(crate) struct ResolvedLink<T: LinkAddress> {
+    pub(crate) index: T,
+    pub(crate) source: T,
+    pub(crate) target: T,
     pub(crate) name: Option<String>,
 }
 
-impl ResolvedLink {
-    pub(crate) fn new(index: u32, source: u32, target: u32, name: Option<String>) -> Self {
+/// A resolved restriction paired with its substitution; `None` where the
+/// query creates or deletes the link
+pub(crate) type ResolvedOperation<T> = (Option<ResolvedLink<T>>, Option<ResolvedLink<T>>);
+
+impl<T: LinkAddress> ResolvedLink<T> {
+    pub(crate) fn new(index: T, source: T, target: T, name: Option<String>) -> Self {
         Self {
             index,
             source,
@@ -39,7 +99,7 @@ impl ResolvedLink {
         }
     }
 
-    pub(crate) fn to_link(&self) -> Link {
-        Link::new(self.index, self.source, self.target)
+    pub(crate) fn to_link(&self) -> Doublet<T> {
+        Doublet::new(self.index, self.source, self.target)
     }
 }
@@ -0,0 +1,340 @@
+//! Interactive session over a database that stays loaded between queries.
+//!
+//! Queries are read line by line; an entry continues over several lines
+//! until its parentheses are balanced. Lines starting with `.` are
+//! meta-commands. Changes are kept in memory and written on `.save` and when
+//! the session ends, together with the undo history used by `clink undo`.
+
+use anyhow::{bail, Context, Result};
+use std::fs::OpenOptions;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::change_history::{ChangeHistory, HistoryEntry};
+use crate::change_recorder::{revert_changes, ChangeRecorder, StorageChange};
+use crate::link_address::LinkAddress;
+use crate::named_type_links::NamedTypeLinks;
+use crate::named_types::NamedTypesDecorator;
+use crate::query_processor::QueryProcessor;
+use crate::structure_format::StructureFormat;
+
+const PROMPT: &str = "clink> ";
+const CONTINUATION_PROMPT: &str = "  ...> ";
+
+const HELP_TEXT: &str = concat!(
+    "Enter a LiNo query, or one of:\n",
+    "  .links              Print all links\n",
+    "  .structure <LINK> [DEPTH]\n",
+    "                      Print the structure of a link\n",
+    "  .undo               Revert the last applied query\n",
+    "  .save               Write the database to disk\n",
+    "  .trace on|off       Toggle query tracing\n",
+    "  .history            Print the command history\n",
+    "  .help               Print this help\n",
+    "  .exit, .quit        Save and leave the session\n",
+);
+
+/// Whether the session continues after an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplFlow {
+    Continue,
+    Exit,
+}
+
+/// A read-eval-print loop over a `NamedTypesDecorator`.
+pub struct Repl<T: LinkAddress = u32> {
+    storage: NamedTypesDecorator<T>,
+    trace: bool,
+    auto_create_missing_references: bool,
+    history_path: Option<PathBuf>,
+    change_history: Option<ChangeHistory<T>>,
+    undo_stack: Vec<Vec<StorageChange<T>>>,
+    unsaved_changes: bool,
+    /// Set when changes could not be reverted completely, which leaves the
+    /// storage in a state no query produced, so it is not saved.
+    partly_reverted: bool,
+}
+
+impl Repl {
+    /// Returns `<stem>.repl_history` next to the database file.
+    pub fn make_history_filename<P: AsRef<Path>>(database_filename: P) -> PathBuf {
+        database_filename.as_ref().with_extension("repl_history")
+    }
+}
+
+impl<T: LinkAddress> Repl<T> {
+    pub fn new(storage: NamedTypesDecorator<T>) -> Self {
+        Self {
+            storage,
+            trace: false,
+            auto_create_missing_references: false,
+            history_path: None,
+            change_history: None,
+            undo_stack: Vec::new(),
+            unsaved_changes: false,
+            partly_reverted: false,
+        }
+    }
+
+    pub fn with_trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    pub fn with_auto_create_missing_references(
+        mut self,
+        auto_create_missing_references: bool,
+    ) -> Self {
+        self.auto_create_missing_references = auto_create_missing_references;
+        self
+    }
+
+    /// Appends every entry to the given file.
+    pub fn with_history_file(mut self, history_path: Option<PathBuf>) -> Self {
+        self.history_path = history_path;
+        self
+    }
+
+    /// Records applied queries in the undo history of the database, which is
+    /// saved along with it.
+    pub fn with_change_history(mut self, change_history: Option<ChangeHistory<T>>) -> Self {
+        self.change_history = change_history;
+        self
+    }
+
+    pub fn storage(&self) -> &NamedTypesDecorator<T> {
+        &self.storage
+    }
+
+    pub fn into_storage(self) -> NamedTypesDecorator<T> {
+        self.storage
+    }
+
+    /// Runs the session until `.exit` or the end of input, then saves.
+    pub fn run<R: BufRead, W: Write>(&mut self, mut input: R, output: &mut W) -> Result<()> {
+        let mut entry = String::new();
+        loop {
+            let prompt = if entry.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            write!(output, "{prompt}")?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                break;
+            }
+
+            entry.push_str(&line);
+            if !entry.trim_start().starts_with('.') && !is_complete_entry(&entry) {
+                continue;
+            }
+
+            let complete_entry = std::mem::take(&mut entry);
+            if self.execute(complete_entry.trim(), output)? == ReplFlow::Exit {
+                break;
+            }
+        }
+
+        if !entry.trim().is_empty() {
+            writeln!(output, "error: unbalanced parentheses in the last entry")?;
+        }
+        self.save_if_changed(output)
+    }
+
+    /// Executes one complete entry. Query and command errors are printed and
+    /// do not end the session.
+    pub fn execute<W: Write>(&mut self, entry: &str, output: &mut W) -> Result<ReplFlow> {
+        if entry.is_empty() {
+            return Ok(ReplFlow::Continue);
+        }
+        self.append_history(entry)?;
+
+        let result = match entry.strip_prefix('.') {
+            Some(command) => self.execute_command(command, output),
+            None => self
+                .execute_query(entry, output)
+                .map(|_| ReplFlow::Continue),
+        };
+        match result {
+            Ok(flow) => Ok(flow),
+            Err(error) => {
+                writeln!(output, "error: {error:#}")?;
+                Ok(ReplFlow::Continue)
+            }
+        }
+    }
+
+    fn execute_query<W: Write>(&mut self, query: &str, output: &mut W) -> Result<()> {
+        let processor = QueryProcessor::new(self.trace)
+            .with_auto_create_missing_references(self.auto_create_missing_references)
+            .with_auto_save(false);
+
+        let mut recorder = ChangeRecorder::new(&mut self.storage);
+        let result = processor.process_query(&mut recorder, query);
+        let recorded = recorder.into_changes();
+        // Like the CLI, which does not save after a failed query, the
+        // changes a failed query made before failing are dropped.
+        let changes = match result {
+            Ok(changes) => changes,
+            Err(error) => {
+                self.revert(&recorded)?;
+                return Err(error);
+            }
+        };
+        if !recorded.is_empty() {
+            if let Some(change_history) = &mut self.change_history {
+                change_history.record(HistoryEntry {
+                    query: single_line(query),
+                    changes: recorded.clone(),
+                });
+            }
+            self.undo_stack.push(recorded);
+            self.unsaved_changes = true;
+        }
+
+        for (before, after) in changes {
+            let line = self.storage.format_change_lino(&before, &after)?;
+            writeln!(output, "{line}")?;
+        }
+        Ok(())
+    }
+
+    fn execute_command<W: Write>(&mut self, command: &str, output: &mut W) -> Result<ReplFlow> {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let arguments = words.collect::<Vec<_>>();
+
+        match (name, arguments.as_slice()) {
+            ("links", []) => {
+                for line in self.storage.lino_lines()? {
+                    writeln!(output, "{line}")?;
+                }
+            }
+            ("structure", [reference]) | ("structure", [reference, _]) => {
+                let link = self.storage.resolve_reference(reference)?;
+                let max_depth = arguments
+                    .get(1)
+                    .map(|depth| {
+                        depth
+                            .parse()
+                            .with_context(|| format!("invalid depth '{depth}'"))
+                    })
+                    .transpose()?;
+                let format = StructureFormat::new().with_max_depth(max_depth);
+                let structure = self.storage.format_structure_with(link, &format)?;
+                writeln!(output, "{structure}")?;
+            }
+            ("undo", []) => {
+                let Some(changes) = self.undo_stack.last() else {
+                    writeln!(output, "Nothing to undo.")?;
+                    return Ok(ReplFlow::Continue);
+                };
+                let count = changes.len();
+                let reverted = match &mut self.change_history {
+                    Some(change_history) => change_history.undo(&mut self.storage, 1).map(|_| ()),
+                    None => revert_changes(&mut self.storage, changes),
+                };
+                if let Err(error) = reverted {
+                    self.partly_reverted = true;
+                    return Err(error);
+                }
+                self.undo_stack.pop();
+                self.unsaved_changes = true;
+                writeln!(output, "Reverted {count} change(s).")?;
+            }
+            ("save", []) => {
+                self.save()?;
+                writeln!(output, "Saved.")?;
+            }
+            ("trace", [value]) => {
+                self.trace = match *value {
+                    "on" => true,
+                    "off" => false,
+                    other => bail!("expected 'on' or 'off', got '{other}'"),
+                };
+            }
+            ("trace", []) => {
+                let state = if self.trace { "on" } else { "off" };
+                writeln!(output, "trace is {state}")?;
+            }
+            ("history", []) => {
+                if let Some(path) = self.history_path.as_ref().filter(|path| path.exists()) {
+                    let history = std::fs::read_to_string(path)?;
+                    write!(output, "{history}")?;
+                }
+            }
+            ("help", []) => write!(output, "{HELP_TEXT}")?,
+            ("exit" | "quit", []) => return Ok(ReplFlow::Exit),
+            _ => bail!("unknown command '.{command}', see .help"),
+        }
+        Ok(ReplFlow::Continue)
+    }
+
+    fn save_if_changed<W: Write>(&mut self, output: &mut W) -> Result<()> {
+        if self.unsaved_changes {
+            self.save()?;
+            writeln!(output, "Saved.")?;
+        }
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<()> {
+        if self.partly_reverted {
+            bail!("Changes could not be reverted completely; the database is not saved.");
+        }
+        self.storage.save()?;
+        if let Some(change_history) = &self.change_history {
+            change_history.save()?;
+        }
+        self.unsaved_changes = false;
+        Ok(())
+    }
+
+    /// Reverts the changes of a failed query.
+    fn revert(&mut self, changes: &[StorageChange<T>]) -> Result<()> {
+        if let Err(error) = revert_changes(&mut self.storage, changes) {
+            self.partly_reverted = true;
+            return Err(error.context("Failed to revert the changes of a failed query"));
+        }
+        Ok(())
+    }
+
+    fn append_history(&self, entry: &str) -> Result<()> {
+        let Some(path) = &self.history_path else {
+            return Ok(());
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open history file: {}", path.display()))?;
+        writeln!(file, "{}", single_line(entry))?;
+        Ok(())
+    }
+}
+
+/// Joins a multi-line entry into one line.
+fn single_line(entry: &str) -> String {
+    entry.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns true when every opened parenthesis outside quotes is closed.
+fn is_complete_entry(entry: &str) -> bool {
+    let mut depth = 0usize;
+    let mut quote = None;
+    for character in entry.chars() {
+        match (quote, character) {
+            (Some(open), _) if character == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(character),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, _) => {}
+        }
+    }
+    depth == 0 && quote.is_none()
+}
@@ -1,4 +1,5 @@
 use crate::hybrid_reference::external_reference;
+use crate::link_address::LinkAddress;
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct AddressToRawNumberConverter;
@@ -8,7 +9,7 @@ impl AddressToRawNumberConverter {
         Self
     }
 
-    pub fn convert(&self, address: u32) -> u32 {
+    pub fn convert<T: LinkAddress>(&self, address: T) -> T {
         external_reference(address)
     }
 }
@@ -1,4 +1,5 @@
-use crate::link_storage::LinkStorage;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct BalancedVariantConverter;
@@ -8,9 +9,9 @@ impl BalancedVariantConverter {
         Self
     }
 
-    pub fn convert(&self, links: &mut LinkStorage, elements: &[u32]) -> u32 {
+    pub fn convert<T: LinkAddress>(&self, links: &mut DoubletStorage<T>, elements: &[T]) -> T {
         match elements.len() {
-            0 => 0,
+            0 => T::ZERO,
             1 => elements[0],
             2 => links.get_or_create(elements[0], elements[1]),
             _ => {
@@ -30,4 +31,28 @@ impl BalancedVariantConverter {
             }
         }
     }
+
+    /// Returns the link `convert` would return for `elements` if all the
+    /// pairs it needs are stored, without creating any.
+    pub fn search<T: LinkAddress>(&self, links: &DoubletStorage<T>, elements: &[T]) -> Option<T> {
+        match elements.len() {
+            0 => Some(T::ZERO),
+            1 => Some(elements[0]),
+            _ => {
+                let mut layer = elements.to_vec();
+                while layer.len() > 1 {
+                    let mut next = Vec::with_capacity(layer.len().div_ceil(2));
+                    let mut chunks = layer.chunks_exact(2);
+                    for pair in &mut chunks {
+                        next.push(links.search(pair[0], pair[1])?);
+                    }
+                    if let Some(&remainder) = chunks.remainder().first() {
+                        next.push(remainder);
+                    }
+                    layer = next;
+                }
+                Some(layer[0])
+            }
+        }
+    }
 }
@@ -1,15 +1,58 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::hash::Hash;
 
+/// Number of entries a cache keeps unless `with_capacity` says otherwise.
+pub const DEFAULT_CACHE_CAPACITY: usize = 4096;
+
+/// Lookup counters of a `CachingConverterDecorator`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries dropped to stay within the capacity.
+    pub evictions: u64,
+    /// Entries dropped because a link they refer to changed.
+    pub invalidations: u64,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses, {} evictions, {} invalidations",
+            self.hits, self.misses, self.evictions, self.invalidations
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Entry<V> {
+    value: V,
+    last_used: u64,
+}
+
+/// A least recently used cache of converter results. Entries can be dropped
+/// by key or by value when the links they were computed from change.
 #[derive(Clone, Debug)]
 pub struct CachingConverterDecorator<K, V> {
-    cache: HashMap<K, V>,
+    cache: HashMap<K, Entry<V>>,
+    recency: BTreeMap<u64, K>,
+    keys_by_value: HashMap<V, Vec<K>>,
+    capacity: usize,
+    clock: u64,
+    stats: CacheStats,
 }
 
 impl<K, V> Default for CachingConverterDecorator<K, V> {
     fn default() -> Self {
         Self {
             cache: HashMap::new(),
+            recency: BTreeMap::new(),
+            keys_by_value: HashMap::new(),
+            capacity: DEFAULT_CACHE_CAPACITY,
+            clock: 0,
+            stats: CacheStats::default(),
         }
     }
 }
@@ -17,18 +60,56 @@ impl<K, V> Default for CachingConverterDecorator<K, V> {
 impl<K, V> CachingConverterDecorator<K, V>
 where
     K: Eq + Hash + Clone,
-    V: Clone,
+    V: Eq + Hash + Clone,
 {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn get(&self, input: &K) -> Option<V> {
-        self.cache.get(input).cloned()
+    /// Bounds the number of entries; the least recently used ones are
+    /// evicted first.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self.evict_to(capacity);
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn get(&mut self, input: &K) -> Option<V> {
+        let Some(entry) = self.cache.get_mut(input) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        self.stats.hits += 1;
+        self.clock += 1;
+        self.recency.remove(&entry.last_used);
+        entry.last_used = self.clock;
+        self.recency.insert(self.clock, input.clone());
+        Some(entry.value.clone())
     }
 
     pub fn insert(&mut self, input: K, output: V) -> V {
-        self.cache.insert(input, output.clone());
+        if self.capacity == 0 {
+            return output;
+        }
+        self.remove_entry(&input);
+        self.evict_to(self.capacity - 1);
+        self.clock += 1;
+        self.recency.insert(self.clock, input.clone());
+        self.keys_by_value
+            .entry(output.clone())
+            .or_default()
+            .push(input.clone());
+        self.cache.insert(
+            input,
+            Entry {
+                value: output.clone(),
+                last_used: self.clock,
+            },
+        );
         output
     }
 
@@ -43,6 +124,30 @@ where
         Ok(self.insert(input, output))
     }
 
+    /// Drops the entry for `input`, if any.
+    pub fn invalidate(&mut self, input: &K) {
+        if self.remove_entry(input) {
+            self.stats.invalidations += 1;
+        }
+    }
+
+    /// Drops every entry whose output is `output`.
+    pub fn invalidate_value(&mut self, output: &V) {
+        for input in self.keys_by_value.get(output).cloned().unwrap_or_default() {
+            self.invalidate(&input);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.recency.clear();
+        self.keys_by_value.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
     pub fn len(&self) -> usize {
         self.cache.len()
     }
@@ -50,4 +155,28 @@ where
     pub fn is_empty(&self) -> bool {
         self.cache.is_empty()
     }
+
+    fn evict_to(&mut self, size: usize) {
+        while self.cache.len() > size {
+            let Some((_, input)) = self.recency.pop_first() else {
+                break;
+            };
+            self.remove_entry(&input);
+            self.stats.evictions += 1;
+        }
+    }
+
+    fn remove_entry(&mut self, input: &K) -> bool {
+        let Some(entry) = self.cache.remove(input) else {
+            return false;
+        };
+        self.recency.remove(&entry.last_used);
+        if let Some(inputs) = self.keys_by_value.get_mut(&entry.value) {
+            inputs.retain(|existing| existing != input);
+            if inputs.is_empty() {
+                self.keys_by_value.remove(&entry.value);
+            }
+        }
+        true
+    }
 }
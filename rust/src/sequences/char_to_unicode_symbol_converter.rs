@@ -1,16 +1,17 @@
-use crate::link_storage::LinkStorage;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
 use crate::sequences::AddressToRawNumberConverter;
 
 #[derive(Clone, Copy, Debug)]
-pub struct CharToUnicodeSymbolConverter {
+pub struct CharToUnicodeSymbolConverter<T: LinkAddress = u32> {
     address_to_number_converter: AddressToRawNumberConverter,
-    unicode_symbol_type: u32,
+    unicode_symbol_type: T,
 }
 
-impl CharToUnicodeSymbolConverter {
+impl<T: LinkAddress> CharToUnicodeSymbolConverter<T> {
     pub fn new(
         address_to_number_converter: AddressToRawNumberConverter,
-        unicode_symbol_type: u32,
+        unicode_symbol_type: T,
     ) -> Self {
         Self {
             address_to_number_converter,
@@ -18,10 +19,16 @@ impl CharToUnicodeSymbolConverter {
         }
     }
 
-    pub fn convert(&self, links: &mut LinkStorage, code_unit: u16) -> u32 {
+    pub fn convert(&self, links: &mut DoubletStorage<T>, code_unit: u16) -> T {
+        self.convert_code_point(links, u32::from(code_unit))
+    }
+
+    /// Stores a symbol for any value of `UnicodeSymbolEncoding::symbols`,
+    /// a UTF-16 code unit or a Unicode scalar value.
+    pub fn convert_code_point(&self, links: &mut DoubletStorage<T>, code_point: u32) -> T {
         let raw_number = self
             .address_to_number_converter
-            .convert(u32::from(code_unit));
+            .convert(T::from(code_point));
         links.get_or_create(raw_number, self.unicode_symbol_type)
     }
 }
@@ -0,0 +1,25 @@
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
+use crate::sequences::TargetMatcher;
+
+/// Decides which links of a pair tree are sequence elements. Sequence
+/// walkers stop descending at elements and descend into everything else.
+pub trait ElementCriterion<T: LinkAddress> {
+    fn is_element(&self, links: &DoubletStorage<T>, link: T) -> bool;
+}
+
+impl<T: LinkAddress> ElementCriterion<T> for TargetMatcher<T> {
+    fn is_element(&self, links: &DoubletStorage<T>, link: T) -> bool {
+        self.is_matched(links, link)
+    }
+}
+
+impl<T, F> ElementCriterion<T> for F
+where
+    T: LinkAddress,
+    F: Fn(&DoubletStorage<T>, T) -> bool,
+{
+    fn is_element(&self, links: &DoubletStorage<T>, link: T) -> bool {
+        self(links, link)
+    }
+}
@@ -0,0 +1,26 @@
+use std::marker::PhantomData;
+
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
+use crate::sequences::right_sequence_walker::walk_pairs;
+use crate::sequences::{ElementCriterion, TargetMatcher};
+
+/// Returns the elements of a pair tree from right to left.
+#[derive(Clone, Copy, Debug)]
+pub struct LeftSequenceWalker<T: LinkAddress = u32, C = TargetMatcher<T>> {
+    element_criterion: C,
+    address: PhantomData<T>,
+}
+
+impl<T: LinkAddress, C: ElementCriterion<T>> LeftSequenceWalker<T, C> {
+    pub fn new(element_criterion: C) -> Self {
+        Self {
+            element_criterion,
+            address: PhantomData,
+        }
+    }
+
+    pub fn walk(&self, links: &DoubletStorage<T>, sequence: T) -> Vec<T> {
+        walk_pairs(links, sequence, &self.element_criterion, true)
+    }
+}
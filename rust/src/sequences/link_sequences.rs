@@ -0,0 +1,206 @@
+use anyhow::{bail, Result};
+
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
+use crate::sequences::{
+    BalancedVariantConverter, LeftSequenceWalker, LinkUsages, RightSequenceWalker, TargetMatcher,
+};
+
+/// Stores lists of links as balanced sequences.
+///
+/// Each element is wrapped in an `(element, element_type)` link, the wrapped
+/// elements are paired level by level with `BalancedVariantConverter`, and
+/// the root is marked with a `(root, sequence_type)` link, the same way
+/// strings are stored with Unicode symbol and sequence types. Every link is
+/// created with `get_or_create`, so equal lists, and equal leading blocks of
+/// different lists, share their links. The empty list is `sequence_type`
+/// itself.
+#[derive(Clone, Copy, Debug)]
+pub struct Sequences<T: LinkAddress = u32> {
+    element_type: T,
+    sequence_type: T,
+    balanced_variant_converter: BalancedVariantConverter,
+}
+
+impl<T: LinkAddress> Sequences<T> {
+    pub fn new(element_type: T, sequence_type: T) -> Self {
+        Self {
+            element_type,
+            sequence_type,
+            balanced_variant_converter: BalancedVariantConverter::new(),
+        }
+    }
+
+    pub fn element_type(&self) -> T {
+        self.element_type
+    }
+
+    pub fn sequence_type(&self) -> T {
+        self.sequence_type
+    }
+
+    /// Matches the links wrapping sequence elements.
+    pub fn element_criterion(&self) -> TargetMatcher<T> {
+        TargetMatcher::new(self.element_type)
+    }
+
+    pub fn right_walker(&self) -> RightSequenceWalker<T> {
+        RightSequenceWalker::new(self.element_criterion())
+    }
+
+    pub fn left_walker(&self) -> LeftSequenceWalker<T> {
+        LeftSequenceWalker::new(self.element_criterion())
+    }
+
+    /// Stores `elements` and returns the sequence link, reusing the links
+    /// of an equal list stored before.
+    pub fn create(&self, links: &mut DoubletStorage<T>, elements: &[T]) -> T {
+        if elements.is_empty() {
+            return self.sequence_type;
+        }
+        let wrapped = elements
+            .iter()
+            .map(|&element| links.get_or_create(element, self.element_type))
+            .collect::<Vec<_>>();
+        let root = self.balanced_variant_converter.convert(links, &wrapped);
+        links.get_or_create(root, self.sequence_type)
+    }
+
+    /// Returns the sequence link of `elements` if they were stored.
+    pub fn search(&self, links: &DoubletStorage<T>, elements: &[T]) -> Option<T> {
+        if elements.is_empty() {
+            return Some(self.sequence_type);
+        }
+        let wrapped = elements
+            .iter()
+            .map(|&element| links.search(element, self.element_type))
+            .collect::<Option<Vec<_>>>()?;
+        let root = self.balanced_variant_converter.search(links, &wrapped)?;
+        links.search(root, self.sequence_type)
+    }
+
+    pub fn is_sequence(&self, links: &DoubletStorage<T>, link: T) -> bool {
+        link == self.sequence_type || TargetMatcher::new(self.sequence_type).is_matched(links, link)
+    }
+
+    /// Returns the elements of a sequence in order.
+    pub fn read(&self, links: &DoubletStorage<T>, sequence: T) -> Result<Vec<T>> {
+        let Some(root) = self.root(links, sequence)? else {
+            return Ok(Vec::new());
+        };
+        Ok(unwrap_elements(
+            links,
+            self.right_walker().walk(links, root),
+        ))
+    }
+
+    /// Returns the elements of a sequence from the last to the first.
+    pub fn read_reversed(&self, links: &DoubletStorage<T>, sequence: T) -> Result<Vec<T>> {
+        let Some(root) = self.root(links, sequence)? else {
+            return Ok(Vec::new());
+        };
+        Ok(unwrap_elements(links, self.left_walker().walk(links, root)))
+    }
+
+    /// Returns true if `element` occurs in the sequence.
+    pub fn contains(&self, links: &DoubletStorage<T>, sequence: T, element: T) -> Result<bool> {
+        let Some(root) = self.root(links, sequence)? else {
+            return Ok(false);
+        };
+        let Some(wrapped) = links.search(element, self.element_type) else {
+            return Ok(false);
+        };
+        Ok(self.right_walker().walk(links, root).contains(&wrapped))
+    }
+
+    /// Returns the stored sequences in which `subsequence` occurs as a
+    /// contiguous run, in ascending order. An empty subsequence matches
+    /// every stored sequence.
+    pub fn containing(&self, links: &DoubletStorage<T>, subsequence: &[T]) -> Vec<T> {
+        self.containing_in(&LinkUsages::new(links), subsequence)
+    }
+
+    /// Walks up from the wrapped element used by the fewest links to the
+    /// sequences whose pair trees share it, and only reads those.
+    pub(crate) fn containing_in(&self, usages: &LinkUsages<T>, subsequence: &[T]) -> Vec<T> {
+        if subsequence.is_empty() {
+            let mut sequences = usages
+                .as_target(self.sequence_type)
+                .iter()
+                .filter(|link| link.index != self.sequence_type)
+                .map(|link| link.index)
+                .collect::<Vec<_>>();
+            sequences.sort_unstable();
+            return sequences;
+        }
+        let Some(wrapped) = subsequence
+            .iter()
+            .map(|&element| usages.find(element, self.element_type))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Vec::new();
+        };
+        let rarest = wrapped
+            .iter()
+            .copied()
+            .min_by_key(|&element| usages.count(element))
+            .unwrap_or_default();
+        usages
+            .sequences_above(vec![rarest], self.sequence_type, false)
+            .into_iter()
+            .filter(|&sequence| {
+                let Some(root) = usages.get(sequence) else {
+                    return false;
+                };
+                self.wrapped_elements(usages, root.source)
+                    .windows(wrapped.len())
+                    .any(|window| window == wrapped)
+            })
+            .collect()
+    }
+
+    /// Reads the wrapped elements of a pair tree from the usage index.
+    fn wrapped_elements(&self, usages: &LinkUsages<T>, root: T) -> Vec<T> {
+        let mut elements = Vec::new();
+        let mut pending = vec![root];
+        while let Some(node) = pending.pop() {
+            match usages.get(node) {
+                Some(link)
+                    if link.target != self.element_type
+                        && link.source != node
+                        && link.target != node =>
+                {
+                    pending.push(link.target);
+                    pending.push(link.source);
+                }
+                _ => elements.push(node),
+            }
+        }
+        elements
+    }
+
+    /// Returns the root of the pair tree of a sequence, or `None` for the
+    /// empty sequence.
+    fn root(&self, links: &DoubletStorage<T>, sequence: T) -> Result<Option<T>> {
+        if sequence == self.sequence_type {
+            return Ok(None);
+        }
+        match links.get(sequence) {
+            Some(link) if link.target == self.sequence_type => Ok(Some(link.source)),
+            Some(_) => bail!(
+                "Link {sequence} is not a sequence of type {}.",
+                self.sequence_type
+            ),
+            None => bail!("Sequence link {sequence} does not exist."),
+        }
+    }
+}
+
+/// Replaces the links wrapping elements with the elements themselves.
+fn unwrap_elements<T: LinkAddress>(links: &DoubletStorage<T>, wrapped: Vec<T>) -> Vec<T> {
+    wrapped
+        .into_iter()
+        .filter_map(|element| links.get(element))
+        .map(|link| link.source)
+        .collect()
+}
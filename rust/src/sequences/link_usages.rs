@@ -0,0 +1,89 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
+
+/// Links using each link as their source or target, collected in one pass
+/// so that walking up does not scan the storage at every step.
+#[derive(Clone, Debug)]
+pub(crate) struct LinkUsages<T: LinkAddress = u32> {
+    by_index: HashMap<T, Doublet<T>>,
+    by_source: HashMap<T, Vec<Doublet<T>>>,
+    by_target: HashMap<T, Vec<Doublet<T>>>,
+}
+
+impl<T: LinkAddress> LinkUsages<T> {
+    pub(crate) fn new(links: &DoubletStorage<T>) -> Self {
+        Self::from_links(links.all().into_iter().copied())
+    }
+
+    pub(crate) fn from_links(links: impl IntoIterator<Item = Doublet<T>>) -> Self {
+        let mut usages = Self {
+            by_index: HashMap::new(),
+            by_source: HashMap::new(),
+            by_target: HashMap::new(),
+        };
+        for link in links {
+            usages.by_index.insert(link.index, link);
+            usages.by_source.entry(link.source).or_default().push(link);
+            usages.by_target.entry(link.target).or_default().push(link);
+        }
+        usages
+    }
+
+    pub(crate) fn get(&self, link: T) -> Option<Doublet<T>> {
+        self.by_index.get(&link).copied()
+    }
+
+    pub(crate) fn as_source(&self, link: T) -> &[Doublet<T>] {
+        self.by_source.get(&link).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn as_target(&self, link: T) -> &[Doublet<T>] {
+        self.by_target.get(&link).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn count(&self, link: T) -> usize {
+        self.as_source(link).len() + self.as_target(link).len()
+    }
+
+    pub(crate) fn find(&self, source: T, target: T) -> Option<T> {
+        self.as_source(source)
+            .iter()
+            .find(|link| link.target == target)
+            .map(|link| link.index)
+    }
+
+    /// Returns the links marked with `sequence_type` whose pair trees contain
+    /// one of `starts`, or have it as their leftmost node when `left_only`
+    /// is set.
+    pub(crate) fn sequences_above(
+        &self,
+        starts: Vec<T>,
+        sequence_type: T,
+        left_only: bool,
+    ) -> BTreeSet<T> {
+        let mut sequences = BTreeSet::new();
+        let mut visited = HashSet::new();
+        let mut pending = starts;
+        while let Some(node) = pending.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            let right_parents = if left_only {
+                &[][..]
+            } else {
+                self.as_target(node)
+            };
+            for parent in self.as_source(node).iter().chain(right_parents) {
+                if parent.source == node && parent.target == sequence_type {
+                    sequences.insert(parent.index);
+                } else if parent.index != node {
+                    pending.push(parent.index);
+                }
+            }
+        }
+        sequences
+    }
+}
@@ -1,25 +1,42 @@
 //! Rust ports of the `Data.Doublets.Sequences` abstractions used by C#.
+//!
+//! The string pipelines are built from general parts: `Sequences` stores any
+//! list of links as a balanced pair tree, and the sequence walkers read such
+//! trees back with a pluggable `ElementCriterion`.
 
 mod address_to_raw_number_converter;
 mod balanced_variant_converter;
 mod caching_converter_decorator;
 mod char_to_unicode_symbol_converter;
 mod default_stack;
+mod element_criterion;
+mod left_sequence_walker;
+mod link_sequences;
+mod link_usages;
 mod raw_number_to_address_converter;
 mod right_sequence_walker;
 mod string_to_unicode_sequence_converter;
 mod target_matcher;
 mod unicode_sequence_to_string_converter;
+mod unicode_symbol_encoding;
 mod unicode_symbol_to_char_converter;
 
 pub use address_to_raw_number_converter::AddressToRawNumberConverter;
 pub use balanced_variant_converter::BalancedVariantConverter;
-pub use caching_converter_decorator::CachingConverterDecorator;
+pub use caching_converter_decorator::{
+    CacheStats, CachingConverterDecorator, DEFAULT_CACHE_CAPACITY,
+};
 pub use char_to_unicode_symbol_converter::CharToUnicodeSymbolConverter;
 pub use default_stack::DefaultStack;
+pub use element_criterion::ElementCriterion;
+pub use left_sequence_walker::LeftSequenceWalker;
+pub use link_sequences::Sequences;
+pub(crate) use link_usages::LinkUsages;
 pub use raw_number_to_address_converter::RawNumberToAddressConverter;
 pub use right_sequence_walker::RightSequenceWalker;
 pub use string_to_unicode_sequence_converter::StringToUnicodeSequenceConverter;
 pub use target_matcher::TargetMatcher;
+pub(crate) use unicode_sequence_to_string_converter::push_code_units;
 pub use unicode_sequence_to_string_converter::UnicodeSequenceToStringConverter;
+pub use unicode_symbol_encoding::UnicodeSymbolEncoding;
 pub use unicode_symbol_to_char_converter::UnicodeSymbolToCharConverter;
@@ -1,4 +1,5 @@
 use crate::hybrid_reference::external_reference_value;
+use crate::link_address::LinkAddress;
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct RawNumberToAddressConverter;
@@ -8,7 +9,7 @@ impl RawNumberToAddressConverter {
         Self
     }
 
-    pub fn convert(&self, raw_number: u32) -> u32 {
+    pub fn convert<T: LinkAddress>(&self, raw_number: T) -> T {
         external_reference_value(raw_number).unwrap_or(raw_number)
     }
 }
@@ -1,38 +1,57 @@
-use crate::link_storage::LinkStorage;
-use crate::sequences::{DefaultStack, TargetMatcher};
+use std::marker::PhantomData;
 
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
+use crate::sequences::{DefaultStack, ElementCriterion, TargetMatcher};
+
+/// Returns the elements of a pair tree from left to right.
 #[derive(Clone, Copy, Debug)]
-pub struct RightSequenceWalker {
-    unicode_symbol_criterion_matcher: TargetMatcher,
+pub struct RightSequenceWalker<T: LinkAddress = u32, C = TargetMatcher<T>> {
+    element_criterion: C,
+    address: PhantomData<T>,
 }
 
-impl RightSequenceWalker {
-    pub fn new(unicode_symbol_criterion_matcher: TargetMatcher) -> Self {
+impl<T: LinkAddress, C: ElementCriterion<T>> RightSequenceWalker<T, C> {
+    pub fn new(element_criterion: C) -> Self {
         Self {
-            unicode_symbol_criterion_matcher,
+            element_criterion,
+            address: PhantomData,
         }
     }
 
-    pub fn walk(&self, links: &LinkStorage, sequence: u32) -> Vec<u32> {
-        let mut output = Vec::new();
-        let mut stack = DefaultStack::new();
-        stack.push(sequence);
-
-        while let Some(element) = stack.pop() {
-            if self
-                .unicode_symbol_criterion_matcher
-                .is_matched(links, element)
-            {
-                output.push(element);
-                continue;
-            }
+    pub fn walk(&self, links: &DoubletStorage<T>, sequence: T) -> Vec<T> {
+        walk_pairs(links, sequence, &self.element_criterion, false)
+    }
+}
+
+/// Collects the elements below `sequence`, descending into the non-element
+/// links of the pair tree in order, or in reverse order when `from_right`.
+pub(super) fn walk_pairs<T: LinkAddress>(
+    links: &DoubletStorage<T>,
+    sequence: T,
+    element_criterion: &impl ElementCriterion<T>,
+    from_right: bool,
+) -> Vec<T> {
+    let mut output = Vec::new();
+    let mut stack = DefaultStack::new();
+    stack.push(sequence);
 
-            if let Some(link) = links.get(element) {
+    while let Some(element) = stack.pop() {
+        if element_criterion.is_element(links, element) {
+            output.push(element);
+            continue;
+        }
+
+        if let Some(link) = links.get(element) {
+            if from_right {
+                stack.push(link.source);
+                stack.push(link.target);
+            } else {
                 stack.push(link.target);
                 stack.push(link.source);
             }
         }
-
-        output
     }
+
+    output
 }
@@ -1,32 +1,48 @@
-use crate::link_storage::LinkStorage;
-use crate::sequences::{BalancedVariantConverter, CharToUnicodeSymbolConverter};
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
+use crate::sequences::{
+    BalancedVariantConverter, CharToUnicodeSymbolConverter, UnicodeSymbolEncoding,
+};
 
 #[derive(Clone, Copy, Debug)]
-pub struct StringToUnicodeSequenceConverter {
-    char_to_unicode_symbol_converter: CharToUnicodeSymbolConverter,
+pub struct StringToUnicodeSequenceConverter<T: LinkAddress = u32> {
+    char_to_unicode_symbol_converter: CharToUnicodeSymbolConverter<T>,
     balanced_variant_converter: BalancedVariantConverter,
-    unicode_sequence_type: u32,
+    unicode_sequence_type: T,
+    encoding: UnicodeSymbolEncoding,
 }
 
-impl StringToUnicodeSequenceConverter {
+impl<T: LinkAddress> StringToUnicodeSequenceConverter<T> {
     pub fn new(
-        char_to_unicode_symbol_converter: CharToUnicodeSymbolConverter,
+        char_to_unicode_symbol_converter: CharToUnicodeSymbolConverter<T>,
         balanced_variant_converter: BalancedVariantConverter,
-        unicode_sequence_type: u32,
+        unicode_sequence_type: T,
     ) -> Self {
         Self {
             char_to_unicode_symbol_converter,
             balanced_variant_converter,
             unicode_sequence_type,
+            encoding: UnicodeSymbolEncoding::default(),
         }
     }
 
-    pub fn convert(&self, links: &mut LinkStorage, content: &str) -> u32 {
-        let symbols = content
-            .encode_utf16()
-            .map(|code_unit| {
+    pub fn with_encoding(mut self, encoding: UnicodeSymbolEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn encoding(&self) -> UnicodeSymbolEncoding {
+        self.encoding
+    }
+
+    pub fn convert(&self, links: &mut DoubletStorage<T>, content: &str) -> T {
+        let symbols = self
+            .encoding
+            .symbols(content)
+            .into_iter()
+            .map(|code_point| {
                 self.char_to_unicode_symbol_converter
-                    .convert(links, code_unit)
+                    .convert_code_point(links, code_point)
             })
             .collect::<Vec<_>>();
 
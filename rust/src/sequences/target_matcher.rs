@@ -1,20 +1,21 @@
-use crate::link_storage::LinkStorage;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct TargetMatcher {
-    target: u32,
+pub struct TargetMatcher<T: LinkAddress = u32> {
+    target: T,
 }
 
-impl TargetMatcher {
-    pub fn new(target: u32) -> Self {
+impl<T: LinkAddress> TargetMatcher<T> {
+    pub fn new(target: T) -> Self {
         Self { target }
     }
 
-    pub fn target(&self) -> u32 {
+    pub fn target(&self) -> T {
         self.target
     }
 
-    pub fn is_matched(&self, links: &LinkStorage, link: u32) -> bool {
+    pub fn is_matched(&self, links: &DoubletStorage<T>, link: T) -> bool {
         links
             .get(link)
             .is_some_and(|candidate| candidate.target == self.target)
@@ -1,22 +1,23 @@
 use anyhow::{bail, Result};
 
-use crate::link_storage::LinkStorage;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
 use crate::sequences::{RightSequenceWalker, TargetMatcher, UnicodeSymbolToCharConverter};
 
 #[derive(Clone, Copy, Debug)]
-pub struct UnicodeSequenceToStringConverter {
-    unicode_sequence_criterion_matcher: TargetMatcher,
-    sequence_walker: RightSequenceWalker,
-    unicode_symbol_to_char_converter: UnicodeSymbolToCharConverter,
-    unicode_sequence_type: u32,
+pub struct UnicodeSequenceToStringConverter<T: LinkAddress = u32> {
+    unicode_sequence_criterion_matcher: TargetMatcher<T>,
+    sequence_walker: RightSequenceWalker<T>,
+    unicode_symbol_to_char_converter: UnicodeSymbolToCharConverter<T>,
+    unicode_sequence_type: T,
 }
 
-impl UnicodeSequenceToStringConverter {
+impl<T: LinkAddress> UnicodeSequenceToStringConverter<T> {
     pub fn new(
-        unicode_sequence_criterion_matcher: TargetMatcher,
-        sequence_walker: RightSequenceWalker,
-        unicode_symbol_to_char_converter: UnicodeSymbolToCharConverter,
-        unicode_sequence_type: u32,
+        unicode_sequence_criterion_matcher: TargetMatcher<T>,
+        sequence_walker: RightSequenceWalker<T>,
+        unicode_symbol_to_char_converter: UnicodeSymbolToCharConverter<T>,
+        unicode_sequence_type: T,
     ) -> Self {
         Self {
             unicode_sequence_criterion_matcher,
@@ -26,7 +27,7 @@ impl UnicodeSequenceToStringConverter {
         }
     }
 
-    pub fn convert(&self, links: &LinkStorage, sequence: u32) -> Result<String> {
+    pub fn convert(&self, links: &DoubletStorage<T>, sequence: T) -> Result<String> {
         if sequence == self.unicode_sequence_type {
             return Ok(String::new());
         }
@@ -40,13 +41,29 @@ impl UnicodeSequenceToStringConverter {
         let Some(sequence_link) = links.get(sequence) else {
             bail!("Unicode sequence link {sequence} does not exist.");
         };
-        let code_units = self
-            .sequence_walker
-            .walk(links, sequence_link.source)
-            .into_iter()
-            .map(|symbol| self.unicode_symbol_to_char_converter.convert(links, symbol))
-            .collect::<Result<Vec<_>>>()?;
+        let mut code_units = Vec::new();
+        for symbol in self.sequence_walker.walk(links, sequence_link.source) {
+            let code_point = self
+                .unicode_symbol_to_char_converter
+                .convert_code_point(links, symbol)?;
+            push_code_units(&mut code_units, code_point)?;
+        }
 
         Ok(String::from_utf16(&code_units)?)
     }
 }
+
+/// Appends a symbol value as UTF-16. Values up to U+FFFF are code units, so
+/// surrogate halves stored by `UnicodeSymbolEncoding::Utf16` pair up again;
+/// larger values are scalar values stored by the code-point encodings.
+pub(crate) fn push_code_units(code_units: &mut Vec<u16>, code_point: u32) -> Result<()> {
+    if let Ok(code_unit) = u16::try_from(code_point) {
+        code_units.push(code_unit);
+        return Ok(());
+    }
+    let Some(character) = char::from_u32(code_point) else {
+        bail!("Symbol value {code_point:#x} is not a Unicode scalar value.");
+    };
+    code_units.extend_from_slice(character.encode_utf16(&mut [0; 2]));
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// How strings are split into Unicode symbol links.
+///
+/// `Utf16` stores one symbol per UTF-16 code unit, like C#, so characters
+/// outside the Basic Multilingual Plane become two surrogate symbols.
+/// `CodePoints` stores one symbol per Unicode scalar value, and
+/// `NfcCodePoints` does so after NFC normalization. Symbols below U+10000
+/// are the same in every encoding, so strings of such characters are shared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnicodeSymbolEncoding {
+    #[default]
+    Utf16,
+    CodePoints,
+    NfcCodePoints,
+}
+
+impl UnicodeSymbolEncoding {
+    /// Returns `content` as it is stored: NFC normalized for `NfcCodePoints`
+    /// and unchanged otherwise.
+    pub fn normalize<'a>(self, content: &'a str) -> Cow<'a, str> {
+        if self == Self::NfcCodePoints && !is_nfc(content) {
+            Cow::Owned(content.nfc().collect())
+        } else {
+            Cow::Borrowed(content)
+        }
+    }
+
+    /// Returns the values of the symbols `content` is stored with.
+    pub fn symbols(self, content: &str) -> Vec<u32> {
+        self.normalize(content)
+            .chars()
+            .flat_map(|character| self.char_symbols(character))
+            .collect()
+    }
+
+    /// Returns the values of the symbols one character is stored with,
+    /// without normalizing it.
+    pub fn char_symbols(self, character: char) -> Vec<u32> {
+        match self {
+            Self::Utf16 => character
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|&code_unit| u32::from(code_unit))
+                .collect(),
+            Self::CodePoints | Self::NfcCodePoints => vec![u32::from(character)],
+        }
+    }
+}
+
+impl fmt::Display for UnicodeSymbolEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Utf16 => "utf-16",
+            Self::CodePoints => "code-points",
+            Self::NfcCodePoints => "code-points-nfc",
+        })
+    }
+}
@@ -1,18 +1,19 @@
 use anyhow::{bail, Result};
 
-use crate::link_storage::LinkStorage;
+use crate::link_address::LinkAddress;
+use crate::link_storage::DoubletStorage;
 use crate::sequences::{RawNumberToAddressConverter, TargetMatcher};
 
 #[derive(Clone, Copy, Debug)]
-pub struct UnicodeSymbolToCharConverter {
+pub struct UnicodeSymbolToCharConverter<T: LinkAddress = u32> {
     number_to_address_converter: RawNumberToAddressConverter,
-    unicode_symbol_criterion_matcher: TargetMatcher,
+    unicode_symbol_criterion_matcher: TargetMatcher<T>,
 }
 
-impl UnicodeSymbolToCharConverter {
+impl<T: LinkAddress> UnicodeSymbolToCharConverter<T> {
     pub fn new(
         number_to_address_converter: RawNumberToAddressConverter,
-        unicode_symbol_criterion_matcher: TargetMatcher,
+        unicode_symbol_criterion_matcher: TargetMatcher<T>,
     ) -> Self {
         Self {
             number_to_address_converter,
@@ -20,7 +21,12 @@ impl UnicodeSymbolToCharConverter {
         }
     }
 
-    pub fn convert(&self, links: &LinkStorage, symbol: u32) -> Result<u16> {
+    pub fn convert(&self, links: &DoubletStorage<T>, symbol: T) -> Result<u16> {
+        Ok(u16::try_from(self.convert_code_point(links, symbol)?)?)
+    }
+
+    /// Returns the value of a symbol stored in any `UnicodeSymbolEncoding`.
+    pub fn convert_code_point(&self, links: &DoubletStorage<T>, symbol: T) -> Result<u32> {
         if !self
             .unicode_symbol_criterion_matcher
             .is_matched(links, symbol)
@@ -31,7 +37,7 @@ impl UnicodeSymbolToCharConverter {
         let Some(link) = links.get(symbol) else {
             bail!("Unicode symbol link {symbol} does not exist.");
         };
-        let code_unit = self.number_to_address_converter.convert(link.source);
-        Ok(u16::try_from(code_unit)?)
+        let code_point: u64 = self.number_to_address_converter.convert(link.source).into();
+        Ok(u32::try_from(code_point)?)
     }
 }
@@ -0,0 +1,452 @@
+//! Named point-in-time snapshots of a database and its names database.
+//!
+//! Snapshots live in `<stem>.snapshots/` next to the database. Both files are
+//! split into chunks at content-defined line boundaries and every chunk is
+//! stored once under `chunks/`, named by its hash, so snapshots share the
+//! parts of the databases that did not change between them. Each snapshot is
+//! a small JSON manifest listing the chunks of both files.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::change_history::ChangeHistory;
+use crate::link_address::LinkAddress;
+use crate::link_storage::{recorded_address_width, DoubletStorage, LoadMode, NAMES_DIRECTIVE};
+use crate::named_type_links::NamedTypeLinks;
+use crate::named_types::NamedTypesDecorator;
+
+const MANIFEST_FORMAT_VERSION: u64 = 1;
+const MANIFEST_EXTENSION: &str = "snapshot.json";
+const CHUNKS_DIRECTORY: &str = "chunks";
+
+/// A line whose hash has these bits clear ends a chunk, which gives chunks of
+/// about 32 lines whose boundaries do not move when lines are inserted or
+/// removed elsewhere in the file.
+const CHUNK_BOUNDARY_MASK: u64 = 0x1f;
+const MAX_CHUNK_LINES: usize = 1024;
+
+/// A snapshot as listed by `SnapshotStore::list`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub name: String,
+    /// Creation time in seconds since the Unix epoch.
+    pub created: u64,
+    /// Number of links in the snapshot of the main database.
+    pub links: usize,
+}
+
+impl fmt::Display for SnapshotInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {}  {} links",
+            self.name,
+            format_utc_timestamp(self.created),
+            self.links
+        )
+    }
+}
+
+/// An entry of `SnapshotStore::list`. A snapshot whose manifest cannot be
+/// read is listed with the reason instead of hiding the others.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListedSnapshot {
+    Readable(SnapshotInfo),
+    Unreadable { name: String, error: String },
+}
+
+impl ListedSnapshot {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Readable(info) => &info.name,
+            Self::Unreadable { name, .. } => name,
+        }
+    }
+}
+
+impl fmt::Display for ListedSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Readable(info) => info.fmt(f),
+            Self::Unreadable { name, error } => write!(f, "{name}  unreadable: {error}"),
+        }
+    }
+}
+
+/// Creates, lists, opens and restores snapshots of a database.
+pub struct SnapshotStore {
+    database_path: PathBuf,
+    names_database_path: PathBuf,
+    directory: PathBuf,
+    trace: bool,
+}
+
+impl SnapshotStore {
+    pub fn new<P: AsRef<Path>>(database_path: P) -> Self {
+        let database_path = database_path.as_ref().to_path_buf();
+        Self {
+            names_database_path: NamedTypesDecorator::make_names_database_filename(&database_path),
+            directory: Self::make_snapshots_directory(&database_path),
+            database_path,
+            trace: false,
+        }
+    }
+
+    pub fn with_names_database_path<P: AsRef<Path>>(mut self, names_database_path: P) -> Self {
+        self.names_database_path = names_database_path.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn with_trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Returns `<stem>.snapshots` next to the database file.
+    pub fn make_snapshots_directory<P: AsRef<Path>>(database_filename: P) -> PathBuf {
+        let path = database_filename.as_ref();
+        let filename_without_extension = path
+            .file_stem()
+            .and_then(|value| value.to_str())
+            .unwrap_or_default();
+        path.with_file_name(format!("{filename_without_extension}.snapshots"))
+    }
+
+    /// Stores the current database and names database under `name`.
+    pub fn create(&self, name: &str) -> Result<SnapshotInfo> {
+        validate_snapshot_name(name)?;
+        let manifest_path = self.manifest_path(name);
+        if manifest_path.exists() {
+            bail!("Snapshot '{name}' already exists.");
+        }
+        fs::create_dir_all(self.directory.join(CHUNKS_DIRECTORY)).with_context(|| {
+            format!(
+                "Failed to create snapshots directory: {}",
+                self.directory.display()
+            )
+        })?;
+
+        let links = self.store_file(&self.database_path)?;
+        let names = self.store_file(&self.names_database_path)?;
+        let info = SnapshotInfo {
+            name: name.to_string(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            links: links.as_ref().map_or(0, |file| file.links),
+        };
+        let manifest = json!({
+            "version": MANIFEST_FORMAT_VERSION,
+            "name": info.name,
+            "created": info.created,
+            "links": links.map(|file| file.to_json()),
+            "names": names.map(|file| file.to_json()),
+        });
+        fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+            .with_context(|| format!("Failed to write snapshot: {}", manifest_path.display()))?;
+
+        if self.trace {
+            eprintln!("[TRACE] Created snapshot {}", manifest_path.display());
+        }
+        Ok(info)
+    }
+
+    /// Returns all snapshots, oldest first, followed by those whose manifest
+    /// cannot be read.
+    pub fn list(&self) -> Result<Vec<ListedSnapshot>> {
+        if !self.directory.exists() {
+            return Ok(Vec::new());
+        }
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let file_name = entry?.file_name();
+            let Some(name) = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_suffix(&format!(".{MANIFEST_EXTENSION}")))
+            else {
+                continue;
+            };
+            snapshots.push(match self.read_manifest(name) {
+                Ok(manifest) => ListedSnapshot::Readable(manifest.info),
+                Err(error) => ListedSnapshot::Unreadable {
+                    name: name.to_string(),
+                    error: format!("{error:#}"),
+                },
+            });
+        }
+        snapshots.sort_by(|a, b| match (a, b) {
+            (ListedSnapshot::Readable(a), ListedSnapshot::Readable(b)) => {
+                (a.created, &a.name).cmp(&(b.created, &b.name))
+            }
+            (ListedSnapshot::Readable(_), ListedSnapshot::Unreadable { .. }) => Ordering::Less,
+            (ListedSnapshot::Unreadable { .. }, ListedSnapshot::Readable(_)) => Ordering::Greater,
+            (
+                ListedSnapshot::Unreadable { name: a, .. },
+                ListedSnapshot::Unreadable { name: b, .. },
+            ) => a.cmp(b),
+        });
+        Ok(snapshots)
+    }
+
+    /// Overwrites the database and names database with a snapshot. A file
+    /// that did not exist when the snapshot was taken is removed, and so is
+    /// the undo history, which no longer matches the restored links.
+    pub fn restore(&self, name: &str) -> Result<SnapshotInfo> {
+        let manifest = self.read_manifest(name)?;
+        let links = self.read_file(&manifest.links)?;
+        let names = self.read_file(&manifest.names)?;
+        write_or_remove(&self.database_path, links)?;
+        write_or_remove(&self.names_database_path, names)?;
+        ChangeHistory::discard(&self.database_path)?;
+        Ok(manifest.info)
+    }
+
+    /// Loads a snapshot into memory without touching the database files. The
+    /// returned storage is meant for reading; it must not be saved.
+    pub fn open<T: LinkAddress>(&self, name: &str) -> Result<NamedTypesDecorator<T>> {
+        let manifest = self.read_manifest(name)?;
+        let checkout = self
+            .directory
+            .join(format!(".checkout-{}-{name}", std::process::id()));
+        fs::create_dir_all(&checkout)?;
+
+        let database_path = checkout.join("snapshot.links");
+        let names_database_path = checkout.join("snapshot.names.links");
+        let opened = (|| {
+            write_or_remove(&database_path, self.read_file(&manifest.links)?)?;
+            write_or_remove(&names_database_path, self.read_file(&manifest.names)?)?;
+            NamedTypesDecorator::open_with_names_database(
+                &database_path,
+                &names_database_path,
+                self.trace,
+                LoadMode::Strict,
+            )
+        })();
+        fs::remove_dir_all(&checkout)?;
+        opened
+    }
+
+    fn manifest_path(&self, name: &str) -> PathBuf {
+        self.directory.join(format!("{name}.{MANIFEST_EXTENSION}"))
+    }
+
+    fn chunk_path(&self, hash: &str) -> PathBuf {
+        self.directory.join(CHUNKS_DIRECTORY).join(hash)
+    }
+
+    fn store_file(&self, path: &Path) -> Result<Option<StoredFile>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read database: {}", path.display()))?;
+
+        let mut chunks = Vec::new();
+        for chunk in split_into_chunks(&content) {
+            let hash = format!("{:016x}", fnv1a_hash(chunk.as_bytes()));
+            let chunk_path = self.chunk_path(&hash);
+            if chunk_path.exists() {
+                if fs::read_to_string(&chunk_path)? != chunk {
+                    bail!("Snapshot chunk {hash} collides with different content.");
+                }
+            } else {
+                fs::write(&chunk_path, chunk)?;
+            }
+            chunks.push(hash);
+        }
+
+        // A single-file database also holds the links of its names.
+        let links = if content.lines().any(|line| line.trim() == NAMES_DIRECTIVE) {
+            match recorded_address_width(&content)? {
+                64 => count_single_file_links::<u64>(&content)?,
+                _ => count_single_file_links::<u32>(&content)?,
+            }
+        } else {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .count()
+        };
+        Ok(Some(StoredFile { chunks, links }))
+    }
+
+    fn read_file(&self, chunks: &Option<Vec<String>>) -> Result<Option<String>> {
+        let Some(chunks) = chunks else {
+            return Ok(None);
+        };
+        let mut content = String::new();
+        for hash in chunks {
+            let chunk_path = self.chunk_path(hash);
+            content
+                .push_str(&fs::read_to_string(&chunk_path).with_context(|| {
+                    format!("Missing snapshot chunk: {}", chunk_path.display())
+                })?);
+        }
+        Ok(Some(content))
+    }
+
+    fn read_manifest(&self, name: &str) -> Result<Manifest> {
+        validate_snapshot_name(name)?;
+        let manifest_path = self.manifest_path(name);
+        if !manifest_path.exists() {
+            bail!("No snapshot is named '{name}'.");
+        }
+        let document: Value = serde_json::from_str(&fs::read_to_string(&manifest_path)?)
+            .with_context(|| format!("Failed to parse snapshot: {}", manifest_path.display()))?;
+        if document["version"].as_u64() != Some(MANIFEST_FORMAT_VERSION) {
+            bail!(
+                "Unsupported snapshot version in {}.",
+                manifest_path.display()
+            );
+        }
+
+        let links = chunks_from_json(&document["links"], &manifest_path)?;
+        let names = chunks_from_json(&document["names"], &manifest_path)?;
+        Ok(Manifest {
+            info: SnapshotInfo {
+                name: name.to_string(),
+                created: document["created"].as_u64().unwrap_or_default(),
+                links: document["links"]["links"].as_u64().unwrap_or_default() as usize,
+            },
+            links,
+            names,
+        })
+    }
+}
+
+/// The chunks of a database file stored by a snapshot.
+struct StoredFile {
+    chunks: Vec<String>,
+    links: usize,
+}
+
+impl StoredFile {
+    fn to_json(&self) -> Value {
+        json!({ "links": self.links, "chunks": self.chunks })
+    }
+}
+
+/// A parsed snapshot manifest. `None` marks a file that did not exist.
+struct Manifest {
+    info: SnapshotInfo,
+    links: Option<Vec<String>>,
+    names: Option<Vec<String>>,
+}
+
+/// Reads the chunk hashes of a stored file. Hashes become file names under
+/// `chunks/`, so anything but the 16 lowercase hex digits `store_file`
+/// writes is refused rather than joined to the chunks directory.
+fn chunks_from_json(value: &Value, manifest_path: &Path) -> Result<Option<Vec<String>>> {
+    let Some(chunks) = value["chunks"].as_array() else {
+        return Ok(None);
+    };
+    chunks
+        .iter()
+        .map(|hash| match hash.as_str() {
+            Some(hash) if is_chunk_hash(hash) => Ok(hash.to_string()),
+            _ => bail!("Invalid chunk hash {hash} in {}.", manifest_path.display()),
+        })
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+fn is_chunk_hash(hash: &str) -> bool {
+    hash.len() == 16
+        && hash
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Snapshot names become file names, so they are limited to a safe set of
+/// characters.
+fn validate_snapshot_name(name: &str) -> Result<()> {
+    let valid_characters = name
+        .chars()
+        .all(|character| character.is_ascii_alphanumeric() || "._-".contains(character));
+    if name.is_empty() || name.starts_with('.') || !valid_characters {
+        bail!("Invalid snapshot name '{name}': use letters, digits, '.', '_' and '-'.");
+    }
+    Ok(())
+}
+
+/// Counts the links of a single-file database, leaving out those that keep
+/// its names.
+fn count_single_file_links<T: LinkAddress>(content: &str) -> Result<usize> {
+    let links = DoubletStorage::<T>::from_text(content)?;
+    let mut storage = NamedTypesDecorator::from_single_file_storage(links);
+    Ok(NamedTypeLinks::all_links(&mut storage).len())
+}
+
+fn write_or_remove(path: &Path, content: Option<String>) -> Result<()> {
+    match content {
+        Some(content) => fs::write(path, content)
+            .with_context(|| format!("Failed to write database: {}", path.display())),
+        None if path.exists() => fs::remove_file(path)
+            .with_context(|| format!("Failed to remove database: {}", path.display())),
+        None => Ok(()),
+    }
+}
+
+fn split_into_chunks(content: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut chunk_start = 0;
+    let mut line_start = 0;
+    let mut lines = 0;
+    for (offset, _) in content.match_indices('\n') {
+        let line_end = offset + 1;
+        lines += 1;
+        let line_hash = fnv1a_hash(&content.as_bytes()[line_start..line_end]);
+        if line_hash & CHUNK_BOUNDARY_MASK == 0 || lines == MAX_CHUNK_LINES {
+            chunks.push(&content[chunk_start..line_end]);
+            chunk_start = line_end;
+            lines = 0;
+        }
+        line_start = line_end;
+    }
+    if chunk_start < content.len() {
+        chunks.push(&content[chunk_start..]);
+    }
+    chunks
+}
+
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS UTC`.
+fn format_utc_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
@@ -0,0 +1,179 @@
+//! Structure rendering for `--structure`.
+//!
+//! A link is expanded into its source and target, and those into theirs.
+//! Points and links beyond the depth limit are printed as plain references
+//! instead of being expanded. Every link is expanded only once: later visits,
+//! whether through a cycle or a shared subtree, are printed as back-references
+//! marked with `^`, such as `^2`.
+
+use anyhow::Result;
+use std::collections::HashSet;
+
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+
+/// Layout used to print a link structure.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StructureLayout {
+    /// One line of nested parentheses: `((1 1) (2 2))`.
+    #[default]
+    Inline,
+    /// Indented LiNo tree with one reference per line.
+    Tree,
+}
+
+/// Options for rendering a link structure.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StructureFormat {
+    pub max_depth: Option<usize>,
+    pub layout: StructureLayout,
+}
+
+impl StructureFormat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: StructureLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+}
+
+/// Marks a reference to a link that is expanded elsewhere in the output.
+const BACK_REFERENCE_MARKER: char = '^';
+
+/// One entry of the structure in pre-order, with its depth below the root.
+enum StructureItem {
+    /// A link printed as a reference without expanding it.
+    Reference(String, usize),
+    /// A link expanded into its source and target, which follow as the next
+    /// two subtrees.
+    Pair(String, usize),
+}
+
+/// An address still to be visited, or a reference already decided on.
+enum Pending<T> {
+    Visit(T, usize),
+    Reference(String, usize),
+}
+
+/// Renders the structure of `root`.
+///
+/// `lookup` returns the link stored at an address together with its printed
+/// reference, or `None` when the address does not exist.
+pub(crate) fn render_structure<T, F>(root: T, format: &StructureFormat, lookup: F) -> Result<String>
+where
+    T: LinkAddress,
+    F: FnMut(T) -> Result<Option<(Doublet<T>, String)>>,
+{
+    let items = collect_items(root, format, lookup)?;
+    Ok(match format.layout {
+        StructureLayout::Inline => render_inline(&items),
+        StructureLayout::Tree => render_tree(&items),
+    })
+}
+
+/// Walks the structure with an explicit stack, so deep chains do not
+/// overflow the call stack. Each link is expanded at most once; later visits
+/// print a back-reference, which keeps shared subtrees and cycles linear in
+/// the number of links.
+fn collect_items<T, F>(
+    root: T,
+    format: &StructureFormat,
+    mut lookup: F,
+) -> Result<Vec<StructureItem>>
+where
+    T: LinkAddress,
+    F: FnMut(T) -> Result<Option<(Doublet<T>, String)>>,
+{
+    let mut items = Vec::new();
+    let mut expanded = HashSet::new();
+    let mut pending = vec![Pending::Visit(root, 0)];
+    while let Some(next) = pending.pop() {
+        let (id, depth) = match next {
+            Pending::Visit(id, depth) => (id, depth),
+            Pending::Reference(reference, depth) => {
+                items.push(StructureItem::Reference(reference, depth));
+                continue;
+            }
+        };
+        let Some((link, reference)) = lookup(id)? else {
+            items.push(StructureItem::Reference(id.to_string(), depth));
+            continue;
+        };
+
+        let is_root = depth == 0;
+        let beyond_depth = format.max_depth.is_some_and(|max| depth > max);
+        if !is_root && expanded.contains(&id) {
+            items.push(StructureItem::Reference(
+                format!("{BACK_REFERENCE_MARKER}{reference}"),
+                depth,
+            ));
+            continue;
+        }
+        if !is_root && (link.is_full_point() || beyond_depth) {
+            items.push(StructureItem::Reference(reference, depth));
+            continue;
+        }
+
+        expanded.insert(id);
+        for child in [link.target, link.source] {
+            pending.push(if child == link.index {
+                Pending::Reference(reference.clone(), depth + 1)
+            } else {
+                Pending::Visit(child, depth + 1)
+            });
+        }
+        items.push(StructureItem::Pair(reference, depth));
+    }
+    Ok(items)
+}
+
+fn render_inline(items: &[StructureItem]) -> String {
+    let mut output = String::new();
+    // The number of subtrees already printed for each open pair.
+    let mut open = Vec::new();
+    for item in items {
+        if let Some(printed) = open.last_mut() {
+            if *printed == 1 {
+                output.push(' ');
+            }
+            *printed += 1;
+        }
+        match item {
+            StructureItem::Pair(..) => {
+                output.push('(');
+                open.push(0);
+            }
+            StructureItem::Reference(reference, _) => {
+                output.push_str(reference);
+                while open.last() == Some(&2) {
+                    open.pop();
+                    output.push(')');
+                }
+            }
+        }
+    }
+    output
+}
+
+fn render_tree(items: &[StructureItem]) -> String {
+    items
+        .iter()
+        .map(|item| match item {
+            StructureItem::Reference(reference, depth) => {
+                format!("{}{reference}", "  ".repeat(*depth))
+            }
+            StructureItem::Pair(reference, depth) => {
+                format!("{}{reference}:", "  ".repeat(*depth))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
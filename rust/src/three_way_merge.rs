@@ -0,0 +1,877 @@
+//! Structural three-way merge of database files for `clink merge-driver`.
+//!
+//! The files are read as the `(index source target)` and
+//! `(index source target "name")` lines written by `LinkStorage::save` and
+//! merged link by link. A link changed on one side only takes that change.
+//! Links added on both sides under the same id are kept apart by giving the
+//! link added by `theirs` a new id, unless both sides added the same named
+//! link. Conflicting edits keep the `ours` version and are reported as `#`
+//! comment lines, which the loader skips, so the merged file stays loadable.
+//! The `#! address-width` and `#! free-ids` lines are carried over: all three
+//! versions must store the same address width, and the free ids of both sides
+//! are joined and cleared of ids the merged links use.
+//!
+//! The links that keep the names of a single-file database, or a separate
+//! names database, are not merged by their own ids. The names and aliases
+//! they give to links are read as (link, name) pairs, follow the links
+//! `theirs` moved to new ids, are merged per link and are written back into
+//! the names of `ours`. Exported LiNo files are merged by link identifier in
+//! the `lino` module.
+
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use crate::hybrid_reference::external_reference_value;
+use crate::link::Doublet;
+use crate::link_address::LinkAddress;
+use crate::link_storage::{
+    escape_name, recorded_address_width, DoubletStorage, FreeIdRange, FreeIds,
+    ADDRESS_WIDTH_DIRECTIVE, DEFAULT_ADDRESS_WIDTH, FREE_IDS_DIRECTIVE, NAMES_DIRECTIVE,
+};
+use crate::named_types::{NamedTypes, NamedTypesDecorator};
+
+mod lino;
+
+/// A link line: the link and its name, if the line carries one. Conflicts
+/// report ids as `u64`, whatever the address width of the files.
+type LinkRecord<T = u64> = (Doublet<T>, Option<String>);
+
+/// Edits of both sides that could not be merged automatically.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MergeConflict {
+    /// Both sides changed a link in different ways.
+    Link {
+        index: u64,
+        base: Option<LinkRecord>,
+        ours: Option<LinkRecord>,
+        theirs: Option<LinkRecord>,
+    },
+    /// Both sides gave the same name to different links.
+    Name {
+        name: String,
+        ours: u64,
+        theirs: u64,
+    },
+    /// Both sides gave a link different names in the names database.
+    LinkName {
+        index: u64,
+        base: Option<String>,
+        ours: Option<String>,
+        theirs: Option<String>,
+    },
+    /// Both sides changed a link of an exported LiNo file in different
+    /// ways. The versions are the LiNo lines of the link.
+    LinoLink {
+        id: String,
+        base: Option<String>,
+        ours: Option<String>,
+        theirs: Option<String>,
+    },
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeConflict::Link {
+                index,
+                base,
+                ours,
+                theirs,
+            } => write!(
+                f,
+                "conflict: link {index} base {} ours {} theirs {}",
+                describe_record(base),
+                describe_record(ours),
+                describe_record(theirs)
+            ),
+            MergeConflict::Name { name, ours, theirs } => write!(
+                f,
+                "conflict: name \"{name}\" ours on link {ours} theirs on link {theirs}"
+            ),
+            MergeConflict::LinkName {
+                index,
+                base,
+                ours,
+                theirs,
+            } => {
+                let describe = |name: &Option<String>| {
+                    name.as_ref()
+                        .map_or("none".to_string(), |name| format!("\"{name}\""))
+                };
+                write!(
+                    f,
+                    "conflict: name of link {index} base {} ours {} theirs {}",
+                    describe(base),
+                    describe(ours),
+                    describe(theirs)
+                )
+            }
+            MergeConflict::LinoLink {
+                id,
+                base,
+                ours,
+                theirs,
+            } => write!(
+                f,
+                "conflict: link {id} base {} ours {} theirs {}",
+                base.as_deref().unwrap_or("none"),
+                ours.as_deref().unwrap_or("none"),
+                theirs.as_deref().unwrap_or("none")
+            ),
+        }
+    }
+}
+
+/// The merged file and what happened while merging it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ThreeWayMergeResult {
+    /// The merged file, with conflicts as `#` comment lines, or as
+    /// `(conflict: ...)` links in a LiNo file.
+    pub text: String,
+    pub conflicts: Vec<MergeConflict>,
+    /// Links added by `theirs` that were moved to a new id, as
+    /// `(theirs id, merged id)`.
+    pub renumbered: Vec<(u64, u64)>,
+}
+
+impl ThreeWayMergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Merges the `ours` and `theirs` versions of a database file, or of an
+/// exported LiNo file, that both derive from `base`.
+pub fn merge_link_files(base: &str, ours: &str, theirs: &str) -> Result<ThreeWayMergeResult> {
+    if [base, ours, theirs].into_iter().any(lino::is_lino) {
+        return lino::merge_lino_files(base, ours, theirs);
+    }
+
+    match address_width(base, ours, theirs)? {
+        32 => merge_database_files::<u32>(base, ours, theirs),
+        64 => merge_database_files::<u64>(base, ours, theirs),
+        width => bail!("Unsupported address width: {width}"),
+    }
+}
+
+/// Merges a database kept in the separate layout together with its names
+/// database, given as `[links, names]` file contents per version, and
+/// returns the merge result with the merged names database.
+pub fn merge_separate_files(
+    base: [&str; 2],
+    ours: [&str; 2],
+    theirs: [&str; 2],
+) -> Result<(ThreeWayMergeResult, String)> {
+    match address_width(base[0], ours[0], theirs[0])? {
+        32 => merge_separate_versions::<u32>(base, ours, theirs),
+        64 => merge_separate_versions::<u64>(base, ours, theirs),
+        width => bail!("Unsupported address width: {width}"),
+    }
+}
+
+/// Returns the address width all three versions record.
+fn address_width(base: &str, ours: &str, theirs: &str) -> Result<u32> {
+    let [base_width, ours_width, theirs_width] = [("base", base), ("our", ours), ("their", theirs)]
+        .map(|(side, text)| {
+            recorded_address_width(text)
+                .with_context(|| format!("Failed to read the {side} version"))
+        });
+    let (base_width, ours_width, theirs_width) = (base_width?, ours_width?, theirs_width?);
+    if base_width != ours_width || ours_width != theirs_width {
+        bail!(
+            "The versions store different address widths: base {base_width}, ours {ours_width}, theirs {theirs_width}. Widen all of them before merging."
+        );
+    }
+    Ok(ours_width)
+}
+
+fn merge_database_files<T: LinkAddress>(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+) -> Result<ThreeWayMergeResult> {
+    let base = Version::<T>::parse(base).context("Failed to read the base version")?;
+    let ours = Version::<T>::parse(ours).context("Failed to read our version")?;
+    let theirs = Version::<T>::parse(theirs).context("Failed to read their version")?;
+    let (result, _) = merge_versions(base, ours, theirs)?;
+    Ok(result)
+}
+
+fn merge_separate_versions<T: LinkAddress>(
+    base: [&str; 2],
+    ours: [&str; 2],
+    theirs: [&str; 2],
+) -> Result<(ThreeWayMergeResult, String)> {
+    let base =
+        Version::<T>::with_names_database(base).context("Failed to read the base version")?;
+    let ours = Version::<T>::with_names_database(ours).context("Failed to read our version")?;
+    let theirs =
+        Version::<T>::with_names_database(theirs).context("Failed to read their version")?;
+    let (result, names) = merge_versions(base, ours, theirs)?;
+    Ok((result, names.unwrap_or_default()))
+}
+
+/// Merges three versions and returns the merged file, and the merged names
+/// database when the versions keep one apart.
+fn merge_versions<T: LinkAddress>(
+    base: Version<T>,
+    mut ours: Version<T>,
+    mut theirs: Version<T>,
+) -> Result<(ThreeWayMergeResult, Option<String>)> {
+    if ours.holds_names != theirs.holds_names {
+        bail!(
+            "Only one side keeps its names in the database file. Change the layout of the other side with clink layout before merging."
+        );
+    }
+
+    let (remap, renumbered) = separate_added_links(&base, &ours, &theirs);
+    theirs.remap(&remap);
+
+    let mut merged = BTreeMap::new();
+    let mut conflicts = Vec::new();
+    let indices = base
+        .links
+        .keys()
+        .chain(ours.links.keys())
+        .chain(theirs.links.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for index in indices {
+        let (base, ours, theirs) = (
+            base.links.get(&index),
+            ours.links.get(&index),
+            theirs.links.get(&index),
+        );
+        let result = merge_item(base, ours, theirs).unwrap_or_else(|| {
+            conflicts.push(MergeConflict::Link {
+                index: index.into(),
+                base: base.map(widen_record),
+                ours: ours.map(widen_record),
+                theirs: theirs.map(widen_record),
+            });
+            ours
+        });
+        if let Some(record) = result {
+            merged.insert(index, record.clone());
+        }
+    }
+    conflicts.extend(separate_duplicate_names(&ours.links, &mut merged));
+
+    let has_names = [&base, &ours, &theirs]
+        .iter()
+        .any(|version| version.names_links.is_some());
+    let mut names_database = None;
+    if has_names {
+        let (names, names_conflicts) = merge_names(&base.names, &ours.names, &theirs.names);
+        conflicts.extend(names_conflicts);
+        if ours.holds_names {
+            join_names(ours.names_links.take(), &ours.names, &names, &mut merged)
+                .context("Failed to write the merged names")?;
+        } else {
+            names_database = Some(
+                write_names(ours.names_links.take(), &ours.names, &names)
+                    .context("Failed to write the merged names database")?,
+            );
+        }
+    }
+    let free_ids = merge_free_ids(&base, &ours, &theirs, &merged);
+
+    let text = format_links(&merged, free_ids.as_deref(), ours.holds_names, &conflicts);
+    let result = ThreeWayMergeResult {
+        text,
+        conflicts,
+        renumbered,
+    };
+    Ok((result, names_database))
+}
+
+/// One version of a database file.
+struct Version<T: LinkAddress> {
+    /// The links, without those that keep the names.
+    links: BTreeMap<T, LinkRecord<T>>,
+    /// The free ids of a database that reuses deleted ids.
+    free_ids: Option<Vec<FreeIdRange<T>>>,
+    /// Whether the file keeps its names among its links.
+    holds_names: bool,
+    /// The links that keep the names, if the version has any.
+    names_links: Option<DoubletStorage<T>>,
+    names: LinkNames<T>,
+}
+
+impl<T: LinkAddress> Version<T> {
+    fn parse(text: &str) -> Result<Self> {
+        let (links, free_ids) = parse_records(text)?;
+        let mut version = Self {
+            links,
+            free_ids,
+            holds_names: false,
+            names_links: None,
+            names: LinkNames::default(),
+        };
+        if text.lines().any(|line| line.trim() == NAMES_DIRECTIVE) {
+            let links = DoubletStorage::from_text(text).context("Failed to read the names")?;
+            let mut storage = NamedTypesDecorator::from_single_file_storage(links);
+            version.read_names(&mut storage)?;
+            let names_link_ids = storage.names_link_ids()?;
+            version
+                .links
+                .retain(|index, _| !names_link_ids.contains(index));
+            let (mut names_links, _) = storage.into_link_storages();
+            for &index in version.links.keys() {
+                names_links.delete(index)?;
+            }
+            version.holds_names = true;
+            version.names_links = Some(names_links);
+        }
+        Ok(version)
+    }
+
+    /// Reads a version kept in the separate layout from the contents of the
+    /// database file and of its names database.
+    fn with_names_database([links, names]: [&str; 2]) -> Result<Self> {
+        let (links, free_ids) = parse_records(links)?;
+        let names_links =
+            DoubletStorage::from_text(names).context("Failed to read the names database")?;
+        let mut storage = open_names(names_links)?;
+        let mut version = Self {
+            links,
+            free_ids,
+            holds_names: false,
+            names_links: None,
+            names: LinkNames::default(),
+        };
+        version.read_names(&mut storage)?;
+        version.names_links = storage.into_link_storages().1;
+        Ok(version)
+    }
+
+    fn read_names(&mut self, storage: &mut NamedTypesDecorator<T>) -> Result<()> {
+        self.names.names = storage.all_names()?.into_iter().collect();
+        for (link, alias) in storage.all_aliases()? {
+            self.names.aliases.entry(link).or_default().insert(alias);
+        }
+        Ok(())
+    }
+
+    /// Returns the ids the links that keep the names use in a single-file
+    /// version.
+    fn names_link_ids(&self) -> impl Iterator<Item = T> + '_ {
+        self.names_links
+            .iter()
+            .filter(|_| self.holds_names)
+            .flat_map(|names_links| names_links.all())
+            .map(|link| link.index)
+    }
+
+    /// The name a link goes by: its name in the names database, or else the
+    /// name on its line.
+    fn name(&self, index: T) -> Option<&str> {
+        self.names
+            .names
+            .get(&index)
+            .or_else(|| self.links.get(&index)?.1.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Moves links, and the names given to them, to the ids in `remap`.
+    fn remap(&mut self, remap: &HashMap<T, T>) {
+        let map = |reference: T| {
+            if external_reference_value(reference).is_some() {
+                reference
+            } else {
+                remap.get(&reference).copied().unwrap_or(reference)
+            }
+        };
+        self.links = std::mem::take(&mut self.links)
+            .into_values()
+            .map(|(link, name)| {
+                let link = Doublet::new(map(link.index), map(link.source), map(link.target));
+                (link.index, (link, name))
+            })
+            .collect();
+        self.names.names = std::mem::take(&mut self.names.names)
+            .into_iter()
+            .map(|(index, name)| (map(index), name))
+            .collect();
+        self.names.aliases = std::mem::take(&mut self.names.aliases)
+            .into_iter()
+            .map(|(index, aliases)| (map(index), aliases))
+            .collect();
+    }
+}
+
+/// The primary names and aliases a names database gives to links.
+struct LinkNames<T> {
+    names: BTreeMap<T, String>,
+    aliases: BTreeMap<T, BTreeSet<String>>,
+}
+
+impl<T> Default for LinkNames<T> {
+    fn default() -> Self {
+        Self {
+            names: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: LinkAddress> LinkNames<T> {
+    fn has_alias(&self, index: T, alias: &str) -> bool {
+        self.aliases
+            .get(&index)
+            .is_some_and(|aliases| aliases.contains(alias))
+    }
+
+    fn holds(&self, index: T, name: &str) -> bool {
+        self.names
+            .get(&index)
+            .is_some_and(|primary| primary == name)
+            || self.has_alias(index, name)
+    }
+
+    fn remove(&mut self, index: T, name: &str) {
+        if self
+            .names
+            .get(&index)
+            .is_some_and(|primary| primary == name)
+        {
+            self.names.remove(&index);
+        }
+        if let Some(aliases) = self.aliases.get_mut(&index) {
+            aliases.remove(name);
+            if aliases.is_empty() {
+                self.aliases.remove(&index);
+            }
+        }
+    }
+
+    /// Keeps a name or alias on the link `ours` gave it when the merge left
+    /// it on two links, and reports the clash.
+    fn separate_duplicates(&mut self, ours: &LinkNames<T>) -> Vec<MergeConflict> {
+        let mut holders: BTreeMap<String, Vec<T>> = BTreeMap::new();
+        let aliases = self
+            .aliases
+            .iter()
+            .flat_map(|(&index, aliases)| aliases.iter().map(move |alias| (index, alias)));
+        for (index, name) in self
+            .names
+            .iter()
+            .map(|(&index, name)| (index, name))
+            .chain(aliases)
+        {
+            holders.entry(name.clone()).or_default().push(index);
+        }
+
+        let mut conflicts = Vec::new();
+        for (name, mut indices) in holders {
+            indices.sort_unstable();
+            indices.dedup();
+            if indices.len() < 2 {
+                continue;
+            }
+            let keep = indices
+                .iter()
+                .copied()
+                .find(|&index| ours.holds(index, &name))
+                .unwrap_or(indices[0]);
+            for index in indices.into_iter().filter(|&index| index != keep) {
+                self.remove(index, &name);
+                conflicts.push(MergeConflict::Name {
+                    name: name.clone(),
+                    ours: keep.into(),
+                    theirs: index.into(),
+                });
+            }
+        }
+        conflicts
+    }
+}
+
+/// Merges the names of each link, and its aliases as a set: an alias stays
+/// if both sides have it or one side added it.
+fn merge_names<T: LinkAddress>(
+    base: &LinkNames<T>,
+    ours: &LinkNames<T>,
+    theirs: &LinkNames<T>,
+) -> (LinkNames<T>, Vec<MergeConflict>) {
+    let mut merged = LinkNames::default();
+    let mut conflicts = Vec::new();
+    let indices = [base, ours, theirs]
+        .iter()
+        .flat_map(|names| names.names.keys().chain(names.aliases.keys()))
+        .copied()
+        .collect::<BTreeSet<_>>();
+    for index in indices {
+        let [base_name, ours_name, theirs_name] =
+            [base, ours, theirs].map(|names| names.names.get(&index));
+        let name = merge_item(base_name, ours_name, theirs_name).unwrap_or_else(|| {
+            conflicts.push(MergeConflict::LinkName {
+                index: index.into(),
+                base: base_name.cloned(),
+                ours: ours_name.cloned(),
+                theirs: theirs_name.cloned(),
+            });
+            ours_name
+        });
+        if let Some(name) = name {
+            merged.names.insert(index, name.clone());
+        }
+
+        let aliases = [ours, theirs]
+            .iter()
+            .filter_map(|names| names.aliases.get(&index))
+            .flatten()
+            .filter(|alias| {
+                (ours.has_alias(index, alias) && theirs.has_alias(index, alias))
+                    || !base.has_alias(index, alias)
+            })
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        if !aliases.is_empty() {
+            merged.aliases.insert(index, aliases);
+        }
+    }
+    conflicts.extend(merged.separate_duplicates(ours));
+    (merged, conflicts)
+}
+
+/// Applies the merged names to the names database of `ours`, or to a new
+/// one, and returns its lines.
+fn write_names<T: LinkAddress>(
+    names_links: Option<DoubletStorage<T>>,
+    ours: &LinkNames<T>,
+    merged: &LinkNames<T>,
+) -> Result<String> {
+    let names_links = match names_links {
+        Some(names_links) => names_links,
+        None => DoubletStorage::from_text("")?,
+    };
+    let mut storage = open_names(names_links)?;
+    apply_names(&mut storage, ours, merged)?;
+
+    let mut text = Vec::new();
+    storage.names_links().write_links(&mut text)?;
+    Ok(String::from_utf8(text)?)
+}
+
+/// Lays the merged links out together with the links that keep the names
+/// of `ours`, applies the merged names and adds the links that keep them to
+/// `merged`.
+fn join_names<T: LinkAddress>(
+    names_links: Option<DoubletStorage<T>>,
+    ours: &LinkNames<T>,
+    merged_names: &LinkNames<T>,
+    merged: &mut BTreeMap<T, LinkRecord<T>>,
+) -> Result<()> {
+    let mut links = DoubletStorage::from_text("")?;
+    let names_links = names_links
+        .iter()
+        .flat_map(|names_links| names_links.all())
+        .copied()
+        .collect::<Vec<_>>();
+    let all_links = merged
+        .values()
+        .map(|(link, _)| *link)
+        .chain(names_links.iter().copied())
+        .collect::<Vec<_>>();
+    for link in &names_links {
+        if merged.contains_key(&link.index) {
+            bail!(
+                "Link {} of the merged links has the id of a link our names are kept with.",
+                link.index
+            );
+        }
+    }
+    for link in &all_links {
+        links.ensure_created(link.index);
+    }
+    for link in &all_links {
+        links.update(link.index, link.source, link.target)?;
+    }
+
+    let mut storage = NamedTypesDecorator::from_single_file_storage(links);
+    apply_names(&mut storage, ours, merged_names)?;
+    for link in storage.all() {
+        merged.entry(link.index).or_insert((*link, None));
+    }
+    Ok(())
+}
+
+/// Turns the names of `ours` into the merged names.
+fn apply_names<T: LinkAddress>(
+    storage: &mut NamedTypesDecorator<T>,
+    ours: &LinkNames<T>,
+    merged: &LinkNames<T>,
+) -> Result<()> {
+    for (&index, aliases) in &ours.aliases {
+        for alias in aliases
+            .iter()
+            .filter(|alias| !merged.has_alias(index, alias))
+        {
+            storage.remove_alias(index, alias)?;
+        }
+    }
+    for (&index, name) in &ours.names {
+        if merged.names.get(&index) != Some(name) {
+            storage.remove_name(index)?;
+        }
+    }
+    for (&index, name) in &merged.names {
+        if ours.names.get(&index) != Some(name) {
+            storage.set_name(index, name)?;
+        }
+    }
+    for (&index, aliases) in &merged.aliases {
+        for alias in aliases.iter().filter(|alias| !ours.has_alias(index, alias)) {
+            storage.add_alias(index, alias)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads names from a names database without a main database.
+fn open_names<T: LinkAddress>(names_links: DoubletStorage<T>) -> Result<NamedTypesDecorator<T>> {
+    Ok(NamedTypesDecorator::from_link_storages(
+        DoubletStorage::from_text("")?,
+        names_links,
+    ))
+}
+
+/// Picks the merged version of an item, or `None` when both sides changed it
+/// in different ways.
+fn merge_item<'a, T: PartialEq>(
+    base: Option<&'a T>,
+    ours: Option<&'a T>,
+    theirs: Option<&'a T>,
+) -> Option<Option<&'a T>> {
+    if ours == theirs || theirs == base {
+        Some(ours)
+    } else if ours == base {
+        Some(theirs)
+    } else {
+        None
+    }
+}
+
+/// Reads the link lines of a database file with the address width it
+/// records, and the free ids of its `#! free-ids` line, if it has one.
+#[allow(clippy::type_complexity)]
+fn parse_records<T: LinkAddress>(
+    text: &str,
+) -> Result<(BTreeMap<T, LinkRecord<T>>, Option<Vec<FreeIdRange<T>>>)> {
+    let mut records = BTreeMap::new();
+    let mut free_ids = None;
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(ranges) = line.strip_prefix(FREE_IDS_DIRECTIVE) {
+            let ranges = ranges.split_whitespace().map(str::parse).collect();
+            let Ok(ranges) = ranges else {
+                bail!("Invalid free ids at line {}: {line}", line_index + 1);
+            };
+            free_ids = Some(ranges);
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (link, name) = DoubletStorage::<T>::parse_link_line(line)
+            .with_context(|| format!("Invalid link format at line {}: {line}", line_index + 1))?;
+        records.insert(link.index, (link, name));
+    }
+    Ok((records, free_ids))
+}
+
+/// Finds the new ids of links that `theirs` added under an id that `ours`
+/// also added for a different link or keeps its names with, and the ids of
+/// links both sides added under the same name.
+fn separate_added_links<T: LinkAddress>(
+    base: &Version<T>,
+    ours: &Version<T>,
+    theirs: &Version<T>,
+) -> (HashMap<T, T>, Vec<(u64, u64)>) {
+    let ours_names = ours
+        .links
+        .keys()
+        .filter_map(|&index| Some((ours.name(index)?, index)))
+        .collect::<HashMap<_, _>>();
+    let ours_names_link_ids = ours.names_link_ids().collect::<BTreeSet<_>>();
+    let mut next_index = [base, ours, theirs]
+        .iter()
+        .flat_map(|version| {
+            version
+                .links
+                .keys()
+                .copied()
+                .chain(version.names_link_ids())
+        })
+        .max()
+        .map_or(T::ONE, |index| index + T::ONE);
+
+    let mut remap = HashMap::new();
+    let mut renumbered = Vec::new();
+    for &index in theirs
+        .links
+        .keys()
+        .filter(|index| !base.links.contains_key(index))
+    {
+        let same_name = theirs
+            .name(index)
+            .and_then(|name| ours_names.get(name))
+            .filter(|&&ours_index| !base.links.contains_key(&ours_index));
+        if let Some(&ours_index) = same_name {
+            if ours_index != index {
+                remap.insert(index, ours_index);
+            }
+        } else if ours_names_link_ids.contains(&index)
+            || (ours.links.contains_key(&index)
+                && (ours.links.get(&index) != theirs.links.get(&index)
+                    || ours.name(index) != theirs.name(index)))
+        {
+            remap.insert(index, next_index);
+            renumbered.push((index.into(), next_index.into()));
+            next_index = next_index + T::ONE;
+        }
+    }
+    (remap, renumbered)
+}
+
+/// Keeps a name on the link `ours` gave it when the merge left it on two
+/// links, and reports the clash.
+fn separate_duplicate_names<T: LinkAddress>(
+    ours: &BTreeMap<T, LinkRecord<T>>,
+    merged: &mut BTreeMap<T, LinkRecord<T>>,
+) -> Vec<MergeConflict> {
+    let mut holders: BTreeMap<String, Vec<T>> = BTreeMap::new();
+    for (&index, (_, name)) in merged.iter() {
+        if let Some(name) = name {
+            holders.entry(name.clone()).or_default().push(index);
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    for (name, indices) in holders.into_iter().filter(|(_, indices)| indices.len() > 1) {
+        let keep = indices
+            .iter()
+            .copied()
+            .find(|index| ours.get(index).and_then(|(_, name)| name.as_ref()) == Some(&name))
+            .unwrap_or(indices[0]);
+        for index in indices.into_iter().filter(|&index| index != keep) {
+            if let Some((_, record_name)) = merged.get_mut(&index) {
+                *record_name = None;
+            }
+            conflicts.push(MergeConflict::Name {
+                name: name.clone(),
+                ours: keep.into(),
+                theirs: index.into(),
+            });
+        }
+    }
+    conflicts
+}
+
+/// Joins the free ids of both sides when the merged database reuses deleted
+/// ids, and drops those the merged links use, as loading the file would.
+fn merge_free_ids<T: LinkAddress>(
+    base: &Version<T>,
+    ours: &Version<T>,
+    theirs: &Version<T>,
+    merged: &BTreeMap<T, LinkRecord<T>>,
+) -> Option<Vec<FreeIdRange<T>>> {
+    let [base_reuses, ours_reuses, theirs_reuses] =
+        [base, ours, theirs].map(|version| version.free_ids.is_some());
+    let reuses = merge_item(Some(&base_reuses), Some(&ours_reuses), Some(&theirs_reuses));
+    if reuses != Some(Some(&true)) {
+        return None;
+    }
+
+    let ranges = [ours, theirs]
+        .iter()
+        .flat_map(|version| version.free_ids.iter().flatten().copied())
+        .collect();
+    let mut free_ids = FreeIds::from_ranges(ranges);
+    let next_id = merged
+        .keys()
+        .next_back()
+        .map_or(T::ONE, |&index| index + T::ONE);
+    free_ids.retrack(merged.keys().copied(), next_id);
+    Some(free_ids.ranges().to_vec())
+}
+
+/// Writes the directives and the merged links, with each conflict as a
+/// comment line after the link it is about.
+fn format_links<T: LinkAddress>(
+    merged: &BTreeMap<T, LinkRecord<T>>,
+    free_ids: Option<&[FreeIdRange<T>]>,
+    holds_names: bool,
+    conflicts: &[MergeConflict],
+) -> String {
+    let mut text = String::new();
+    if T::BITS != DEFAULT_ADDRESS_WIDTH {
+        text.push_str(&format!("{ADDRESS_WIDTH_DIRECTIVE} {}\n", T::BITS));
+    }
+    if let Some(free_ids) = free_ids {
+        text.push_str(FREE_IDS_DIRECTIVE);
+        for range in free_ids {
+            text.push_str(&format!(" {range}"));
+        }
+        text.push('\n');
+    }
+    if holds_names {
+        text.push_str(NAMES_DIRECTIVE);
+        text.push('\n');
+    }
+    for (&index, (link, name)) in merged {
+        text.push_str(&format_record(link, name.as_deref()));
+        text.push('\n');
+        for conflict in conflicts
+            .iter()
+            .filter(|conflict| conflict_index(conflict) == Some(index.into()))
+        {
+            text.push_str(&format!("# {conflict}\n"));
+        }
+    }
+    for conflict in conflicts.iter().filter(|conflict| {
+        conflict_index(conflict).is_none_or(|index| {
+            T::try_from(index).map_or(true, |index| !merged.contains_key(&index))
+        })
+    }) {
+        text.push_str(&format!("# {conflict}\n"));
+    }
+    text
+}
+
+fn conflict_index(conflict: &MergeConflict) -> Option<u64> {
+    match conflict {
+        MergeConflict::Link { index, .. } | MergeConflict::LinkName { index, .. } => Some(*index),
+        MergeConflict::Name { theirs, .. } => Some(*theirs),
+        MergeConflict::LinoLink { .. } => None,
+    }
+}
+
+fn format_record<T: LinkAddress>(link: &Doublet<T>, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!(
+            "({} {} {} \"{}\")",
+            link.index,
+            link.source,
+            link.target,
+            escape_name(name)
+        ),
+        None => format!("({} {} {})", link.index, link.source, link.target),
+    }
+}
+
+fn widen_record<T: LinkAddress>((link, name): &LinkRecord<T>) -> LinkRecord {
+    (
+        Doublet::new(link.index.into(), link.source.into(), link.target.into()),
+        name.clone(),
+    )
+}
+
+fn describe_record(record: &Option<LinkRecord>) -> String {
+    match record {
+        Some((link, name)) => format_record(link, name.as_deref()),
+        None => "none".to_string(),
+    }
+}
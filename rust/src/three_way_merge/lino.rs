@@ -0,0 +1,217 @@
+//! Merge of exported LiNo files, whose lines read `(id: source target)`.
+//!
+//! Links are matched by their identifier: the name of a named link or the
+//! number of an unnamed one. Numbered links that `theirs` added under a
+//! number `ours` also added are moved to a new number, and the references to
+//! them move along. A conflict keeps the line of `ours` and adds a
+//! `(conflict: ...)` link with the three versions, so the file still reads
+//! as LiNo.
+
+use anyhow::{bail, Context, Result};
+use links_notation::{parse_lino_to_links, LiNo};
+use std::collections::{HashMap, HashSet};
+
+use super::{merge_item, MergeConflict, ThreeWayMergeResult};
+use crate::link_storage::DoubletStorage;
+use crate::named_type_links::escape_lino_reference;
+
+/// A LiNo line with its references unescaped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct LinoRecord {
+    id: String,
+    source: String,
+    target: String,
+}
+
+impl LinoRecord {
+    fn format(&self) -> String {
+        format!(
+            "({}: {} {})",
+            escape_lino_reference(&self.id),
+            escape_lino_reference(&self.source),
+            escape_lino_reference(&self.target)
+        )
+    }
+}
+
+/// Tells an exported LiNo file from a database file by its first line that
+/// is not a comment.
+pub(super) fn is_lino(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .is_some_and(|line| DoubletStorage::<u64>::parse_link_line(line).is_none())
+}
+
+pub(super) fn merge_lino_files(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+) -> Result<ThreeWayMergeResult> {
+    let base = parse_records(base).context("Failed to read the base version")?;
+    let ours = parse_records(ours).context("Failed to read our version")?;
+    let theirs = parse_records(theirs).context("Failed to read their version")?;
+
+    let (theirs, renumbered) = separate_added_links(&base, &ours, theirs);
+
+    let [base_by_id, ours_by_id, theirs_by_id] = [&base, &ours, &theirs].map(|records| {
+        records
+            .iter()
+            .map(|record| (record.id.as_str(), record))
+            .collect::<HashMap<_, _>>()
+    });
+    let mut seen = HashSet::new();
+    let ids = ours
+        .iter()
+        .chain(&theirs)
+        .chain(&base)
+        .map(|record| record.id.as_str())
+        .filter(|id| seen.insert(*id));
+
+    let mut text = String::new();
+    let mut conflicts = Vec::new();
+    for id in ids {
+        let (base, ours, theirs) = (
+            base_by_id.get(id).copied(),
+            ours_by_id.get(id).copied(),
+            theirs_by_id.get(id).copied(),
+        );
+        let merged = merge_item(base, ours, theirs);
+        if let Some(record) = merged.unwrap_or(ours) {
+            text.push_str(&record.format());
+            text.push('\n');
+        }
+        if merged.is_none() {
+            let [base, ours, theirs] =
+                [base, ours, theirs].map(|record| record.map(LinoRecord::format));
+            text.push_str(&format_conflict(&base, &ours, &theirs));
+            text.push('\n');
+            conflicts.push(MergeConflict::LinoLink {
+                id: id.to_string(),
+                base,
+                ours,
+                theirs,
+            });
+        }
+    }
+
+    Ok(ThreeWayMergeResult {
+        text,
+        conflicts,
+        renumbered,
+    })
+}
+
+fn parse_records(text: &str) -> Result<Vec<LinoRecord>> {
+    let mut records = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(record) = parse_record(line) else {
+            bail!("Invalid LiNo link at line {}: {line}", line_index + 1);
+        };
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn parse_record(line: &str) -> Option<LinoRecord> {
+    let links = parse_lino_to_links(line).ok()?;
+    let [LiNo::Link {
+        id: Some(id),
+        values,
+    }] = links.as_slice()
+    else {
+        return None;
+    };
+    let [source, target] = values.as_slice() else {
+        return None;
+    };
+    Some(LinoRecord {
+        id: id.clone(),
+        source: reference(source)?.clone(),
+        target: reference(target)?.clone(),
+    })
+}
+
+fn reference(value: &LiNo<String>) -> Option<&String> {
+    match value {
+        LiNo::Ref(reference) => Some(reference),
+        LiNo::Link {
+            id: Some(reference),
+            values,
+        } if values.is_empty() => Some(reference),
+        LiNo::Link { .. } => None,
+    }
+}
+
+/// Moves numbered links that `theirs` added under a number `ours` used for a
+/// different link to new numbers, along with the references to them.
+fn separate_added_links(
+    base: &[LinoRecord],
+    ours: &[LinoRecord],
+    mut theirs: Vec<LinoRecord>,
+) -> (Vec<LinoRecord>, Vec<(u64, u64)>) {
+    let base_ids = base
+        .iter()
+        .map(|record| record.id.as_str())
+        .collect::<HashSet<_>>();
+    let ours_by_id = ours
+        .iter()
+        .map(|record| (record.id.as_str(), record))
+        .collect::<HashMap<_, _>>();
+    let mut next_number = base
+        .iter()
+        .chain(ours)
+        .chain(&theirs)
+        .filter_map(|record| record.id.parse::<u64>().ok())
+        .max()
+        .map_or(1, |number| number + 1);
+
+    let mut remap = HashMap::new();
+    let mut renumbered = Vec::new();
+    for record in theirs
+        .iter()
+        .filter(|record| !base_ids.contains(record.id.as_str()))
+    {
+        let Ok(number) = record.id.parse::<u64>() else {
+            continue;
+        };
+        if ours_by_id
+            .get(record.id.as_str())
+            .is_some_and(|ours| *ours != record)
+        {
+            remap.insert(record.id.clone(), next_number.to_string());
+            renumbered.push((number, next_number));
+            next_number += 1;
+        }
+    }
+
+    for record in &mut theirs {
+        for reference in [&mut record.id, &mut record.source, &mut record.target] {
+            if let Some(moved) = remap.get(reference.as_str()) {
+                *reference = moved.clone();
+            }
+        }
+    }
+    (theirs, renumbered)
+}
+
+/// Writes a conflict as a LiNo link holding the three versions, with an
+/// empty `(side)` link for a version that does not have the link.
+fn format_conflict(
+    base: &Option<String>,
+    ours: &Option<String>,
+    theirs: &Option<String>,
+) -> String {
+    let sides = [("base", base), ("ours", ours), ("theirs", theirs)]
+        .into_iter()
+        .map(|(side, line)| match line {
+            Some(line) => format!("({side}: {line})"),
+            None => format!("({side})"),
+        })
+        .collect::<Vec<_>>();
+    format!("(conflict: {})", sides.join(" "))
+}
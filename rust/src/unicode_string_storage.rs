@@ -6,45 +6,93 @@
 //! `NamedLinks`.
 
 use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ops::{Deref, DerefMut};
+use std::rc::{Rc, Weak};
 
 use anyhow::{bail, Result};
 
 use crate::hybrid_reference::{external_reference, external_reference_value};
-use crate::link_storage::LinkStorage;
+use crate::link_address::LinkAddress;
+use crate::link_storage::{DoubletStorage, InvalidationHook};
+use crate::name_search::{self, NameSearch};
 use crate::named_links::NamedLinks;
 use crate::pinned_types::PinnedTypes;
 use crate::sequences::{
-    AddressToRawNumberConverter, BalancedVariantConverter, CachingConverterDecorator,
-    CharToUnicodeSymbolConverter, RawNumberToAddressConverter, RightSequenceWalker,
-    StringToUnicodeSequenceConverter, TargetMatcher, UnicodeSequenceToStringConverter,
+    push_code_units, AddressToRawNumberConverter, BalancedVariantConverter, CacheStats,
+    CachingConverterDecorator, CharToUnicodeSymbolConverter, LinkUsages,
+    RawNumberToAddressConverter, RightSequenceWalker, Sequences, StringToUnicodeSequenceConverter,
+    TargetMatcher, UnicodeSequenceToStringConverter, UnicodeSymbolEncoding,
     UnicodeSymbolToCharConverter,
 };
 
+/// Name of the type of alias links, created with the first alias.
+const ALIAS_TYPE_NAME: &str = "Alias";
+
+/// Names of the types whose presence records a code-point encoding. A
+/// database without them uses the C# compatible UTF-16 encoding.
+const ENCODING_TYPE_NAMES: [(UnicodeSymbolEncoding, &str); 2] = [
+    (UnicodeSymbolEncoding::CodePoints, "UnicodeCodePoints"),
+    (UnicodeSymbolEncoding::NfcCodePoints, "NfcUnicodeCodePoints"),
+];
+
 /// Link-backed Unicode string storage with C# pinned type layout.
-pub struct UnicodeStringStorage<'a> {
-    links: &'a mut LinkStorage,
-    type_type: u32,
-    unicode_symbol_type: u32,
-    unicode_sequence_type: u32,
-    string_type: u32,
-    empty_string_type: u32,
-    name_type: u32,
+pub struct UnicodeStringStorage<'a, T: LinkAddress = u32> {
+    links: &'a mut DoubletStorage<T>,
+    state: StateSlot<'a, T>,
+}
+
+/// The pinned types, converters and caches of a `UnicodeStringStorage`,
+/// kept apart from the links so that they can outlive a single borrow of
+/// them.
+pub(crate) struct UnicodeStringState<T: LinkAddress = u32> {
+    type_type: T,
+    unicode_symbol_type: T,
+    unicode_sequence_type: T,
+    string_type: T,
+    empty_string_type: T,
+    name_type: T,
     address_to_number_converter: AddressToRawNumberConverter,
     number_to_address_converter: RawNumberToAddressConverter,
     balanced_variant_converter: BalancedVariantConverter,
-    unicode_symbol_criterion_matcher: TargetMatcher,
-    unicode_sequence_criterion_matcher: TargetMatcher,
-    char_to_unicode_symbol_converter: CharToUnicodeSymbolConverter,
-    unicode_symbol_to_char_converter: UnicodeSymbolToCharConverter,
-    string_to_unicode_sequence_converter: StringToUnicodeSequenceConverter,
-    sequence_walker: RightSequenceWalker,
-    unicode_sequence_to_string_converter: UnicodeSequenceToStringConverter,
-    string_to_unicode_sequence_cache: CachingConverterDecorator<String, u32>,
-    unicode_sequence_to_string_cache: RefCell<CachingConverterDecorator<u32, String>>,
+    unicode_symbol_criterion_matcher: TargetMatcher<T>,
+    unicode_sequence_criterion_matcher: TargetMatcher<T>,
+    char_to_unicode_symbol_converter: CharToUnicodeSymbolConverter<T>,
+    unicode_symbol_to_char_converter: UnicodeSymbolToCharConverter<T>,
+    string_to_unicode_sequence_converter: StringToUnicodeSequenceConverter<T>,
+    sequence_walker: RightSequenceWalker<T>,
+    unicode_sequence_to_string_converter: UnicodeSequenceToStringConverter<T>,
+    caches: Rc<RefCell<StringCaches<T>>>,
 }
 
-impl<'a> UnicodeStringStorage<'a> {
-    pub fn new(links: &'a mut LinkStorage) -> Result<Self> {
+/// Converter caches of a `UnicodeStringState`. They are registered with the
+/// names database, so changed or deleted sequence links are dropped from them
+/// instead of being returned for a string.
+struct StringCaches<T> {
+    string_to_unicode_sequence: CachingConverterDecorator<String, T>,
+    unicode_sequence_to_string: CachingConverterDecorator<T, String>,
+}
+
+impl<T> Default for StringCaches<T> {
+    fn default() -> Self {
+        Self {
+            string_to_unicode_sequence: CachingConverterDecorator::default(),
+            unicode_sequence_to_string: CachingConverterDecorator::default(),
+        }
+    }
+}
+
+impl<T: LinkAddress> InvalidationHook<T> for StringCaches<T> {
+    fn invalidate(&mut self, link: T) {
+        self.string_to_unicode_sequence.invalidate_value(&link);
+        self.unicode_sequence_to_string.invalidate(&link);
+    }
+}
+
+impl<T: LinkAddress> UnicodeStringState<T> {
+    /// Validates the pinned types of `links`, builds the converters and
+    /// names the built-in types.
+    pub(crate) fn open(links: &mut DoubletStorage<T>) -> Result<Self> {
         let (
             type_type,
             unicode_symbol_type,
@@ -88,8 +136,7 @@ impl<'a> UnicodeStringStorage<'a> {
             unicode_sequence_type,
         );
 
-        let mut storage = Self {
-            links,
+        let mut state = Self {
             type_type,
             unicode_symbol_type,
             unicode_sequence_type,
@@ -106,105 +153,177 @@ impl<'a> UnicodeStringStorage<'a> {
             string_to_unicode_sequence_converter,
             sequence_walker,
             unicode_sequence_to_string_converter,
-            string_to_unicode_sequence_cache: CachingConverterDecorator::new(),
-            unicode_sequence_to_string_cache: RefCell::new(CachingConverterDecorator::new()),
+            caches: Rc::new(RefCell::new(StringCaches::default())),
         };
+        let caches: Weak<RefCell<StringCaches<T>>> = Rc::downgrade(&state.caches);
+        links.add_invalidation_hook(caches);
 
+        let mut storage = UnicodeStringStorage::with_state(links, &mut state);
         storage.set_name(type_type, "Type")?;
         storage.set_name(unicode_symbol_type, "UnicodeSymbol")?;
         storage.set_name(unicode_sequence_type, "UnicodeSequence")?;
         storage.set_name(string_type, "String")?;
         storage.set_name(empty_string_type, "EmptyString")?;
         storage.set_name(name_type, "Name")?;
+        let encoding = storage
+            .encoding_types()?
+            .first()
+            .map_or(UnicodeSymbolEncoding::Utf16, |&(encoding, _)| encoding);
+        state.string_to_unicode_sequence_converter = state
+            .string_to_unicode_sequence_converter
+            .with_encoding(encoding);
+
+        Ok(state)
+    }
+
+    /// Returns the lookup counters of the string to sequence and the
+    /// sequence to string caches.
+    pub(crate) fn cache_stats(&self) -> (CacheStats, CacheStats) {
+        let caches = self.caches.borrow();
+        (
+            caches.string_to_unicode_sequence.stats(),
+            caches.unicode_sequence_to_string.stats(),
+        )
+    }
+}
 
-        Ok(storage)
+enum StateSlot<'a, T: LinkAddress> {
+    Owned(Box<UnicodeStringState<T>>),
+    Borrowed(&'a mut UnicodeStringState<T>),
+}
+
+impl<T: LinkAddress> Deref for StateSlot<'_, T> {
+    type Target = UnicodeStringState<T>;
+
+    fn deref(&self) -> &UnicodeStringState<T> {
+        match self {
+            StateSlot::Owned(state) => state,
+            StateSlot::Borrowed(state) => state,
+        }
+    }
+}
+
+impl<T: LinkAddress> DerefMut for StateSlot<'_, T> {
+    fn deref_mut(&mut self) -> &mut UnicodeStringState<T> {
+        match self {
+            StateSlot::Owned(state) => state,
+            StateSlot::Borrowed(state) => state,
+        }
+    }
+}
+
+impl<'a, T: LinkAddress> UnicodeStringStorage<'a, T> {
+    pub fn new(links: &'a mut DoubletStorage<T>) -> Result<Self> {
+        let state = UnicodeStringState::open(links)?;
+        Ok(Self {
+            links,
+            state: StateSlot::Owned(Box::new(state)),
+        })
+    }
+
+    /// Reuses the types, converters and caches of an open state instead of
+    /// validating and naming the pinned types again.
+    pub(crate) fn with_state(
+        links: &'a mut DoubletStorage<T>,
+        state: &'a mut UnicodeStringState<T>,
+    ) -> Self {
+        Self {
+            links,
+            state: StateSlot::Borrowed(state),
+        }
     }
 
-    pub fn links_mut(&mut self) -> &mut LinkStorage {
+    pub fn links(&self) -> &DoubletStorage<T> {
         self.links
     }
 
-    pub fn into_named_links(self) -> NamedLinks<'a> {
+    pub fn links_mut(&mut self) -> &mut DoubletStorage<T> {
+        self.links
+    }
+
+    pub fn into_named_links(self) -> NamedLinks<'a, T> {
         NamedLinks::from_storage(self)
     }
 
-    pub fn type_type(&self) -> u32 {
-        self.type_type
+    pub fn type_type(&self) -> T {
+        self.state.type_type
     }
 
-    pub fn unicode_symbol_type(&self) -> u32 {
-        self.unicode_symbol_type
+    pub fn unicode_symbol_type(&self) -> T {
+        self.state.unicode_symbol_type
     }
 
-    pub fn unicode_sequence_type(&self) -> u32 {
-        self.unicode_sequence_type
+    pub fn unicode_sequence_type(&self) -> T {
+        self.state.unicode_sequence_type
     }
 
-    pub fn string_type(&self) -> u32 {
-        self.string_type
+    pub fn string_type(&self) -> T {
+        self.state.string_type
     }
 
-    pub fn empty_string_type(&self) -> u32 {
-        self.empty_string_type
+    pub fn empty_string_type(&self) -> T {
+        self.state.empty_string_type
     }
 
-    pub fn name_type(&self) -> u32 {
-        self.name_type
+    pub fn name_type(&self) -> T {
+        self.state.name_type
     }
 
     pub fn address_to_number_converter(&self) -> AddressToRawNumberConverter {
-        self.address_to_number_converter
+        self.state.address_to_number_converter
     }
 
     pub fn number_to_address_converter(&self) -> RawNumberToAddressConverter {
-        self.number_to_address_converter
+        self.state.number_to_address_converter
     }
 
     pub fn balanced_variant_converter(&self) -> BalancedVariantConverter {
-        self.balanced_variant_converter
+        self.state.balanced_variant_converter
     }
 
-    pub fn unicode_symbol_criterion_matcher(&self) -> TargetMatcher {
-        self.unicode_symbol_criterion_matcher
+    pub fn unicode_symbol_criterion_matcher(&self) -> TargetMatcher<T> {
+        self.state.unicode_symbol_criterion_matcher
     }
 
-    pub fn unicode_sequence_criterion_matcher(&self) -> TargetMatcher {
-        self.unicode_sequence_criterion_matcher
+    pub fn unicode_sequence_criterion_matcher(&self) -> TargetMatcher<T> {
+        self.state.unicode_sequence_criterion_matcher
     }
 
-    pub fn char_to_unicode_symbol_converter(&self) -> CharToUnicodeSymbolConverter {
-        self.char_to_unicode_symbol_converter
+    pub fn char_to_unicode_symbol_converter(&self) -> CharToUnicodeSymbolConverter<T> {
+        self.state.char_to_unicode_symbol_converter
     }
 
-    pub fn unicode_symbol_to_char_converter(&self) -> UnicodeSymbolToCharConverter {
-        self.unicode_symbol_to_char_converter
+    pub fn unicode_symbol_to_char_converter(&self) -> UnicodeSymbolToCharConverter<T> {
+        self.state.unicode_symbol_to_char_converter
     }
 
-    pub fn string_to_unicode_sequence_converter(&self) -> StringToUnicodeSequenceConverter {
-        self.string_to_unicode_sequence_converter
+    pub fn string_to_unicode_sequence_converter(&self) -> StringToUnicodeSequenceConverter<T> {
+        self.state.string_to_unicode_sequence_converter
     }
 
-    pub fn sequence_walker(&self) -> RightSequenceWalker {
-        self.sequence_walker
+    pub fn sequence_walker(&self) -> RightSequenceWalker<T> {
+        self.state.sequence_walker
     }
 
-    pub fn unicode_sequence_to_string_converter(&self) -> UnicodeSequenceToStringConverter {
-        self.unicode_sequence_to_string_converter
+    pub fn unicode_sequence_to_string_converter(&self) -> UnicodeSequenceToStringConverter<T> {
+        self.state.unicode_sequence_to_string_converter
     }
 
-    pub fn create_string(&mut self, content: &str) -> Result<u32> {
+    pub fn create_string(&mut self, content: &str) -> Result<T> {
         let string_sequence = self.get_string_sequence(content);
-        Ok(self.links.get_or_create(self.string_type, string_sequence))
+        Ok(self
+            .links
+            .get_or_create(self.state.string_type, string_sequence))
     }
 
-    pub fn get_string(&self, string_value: u32) -> Result<String> {
+    pub fn get_string(&self, string_value: T) -> Result<String> {
         let mut current = string_value;
         for _ in 0..3 {
             let Some(link) = self.links.get(current) else {
                 break;
             };
-            if link.source == self.string_type {
-                return if link.target == self.empty_string_type {
+            if link.source == self.state.string_type {
+                return if link.target == self.state.empty_string_type {
                     Ok(String::new())
                 } else {
                     self.unicode_sequence_to_string(link.target)
@@ -215,12 +334,24 @@ impl<'a> UnicodeStringStorage<'a> {
         bail!("The passed link does not contain a string.")
     }
 
-    pub fn unicode_sequence_code_units(&self, string_value: u32) -> Result<Vec<u16>> {
+    pub fn unicode_sequence_code_units(&self, string_value: T) -> Result<Vec<u16>> {
+        let mut code_units = Vec::new();
+        for code_point in self.unicode_sequence_code_points(string_value)? {
+            push_code_units(&mut code_units, code_point)?;
+        }
+        Ok(code_units)
+    }
+
+    /// Returns the values of the symbols a string is stored with: UTF-16 code
+    /// units, Unicode scalar values, or both in a database that switched
+    /// encodings.
+    pub fn unicode_sequence_code_points(&self, string_value: T) -> Result<Vec<u32>> {
         let sequence = self.unwrap_string_sequence(string_value)?;
-        if sequence == self.empty_string_type {
+        if sequence == self.state.empty_string_type {
             return Ok(Vec::new());
         }
         if !self
+            .state
             .unicode_sequence_criterion_matcher
             .is_matched(self.links, sequence)
         {
@@ -231,79 +362,261 @@ impl<'a> UnicodeStringStorage<'a> {
             .get(sequence)
             .ok_or_else(|| anyhow::anyhow!("Unicode sequence link {sequence} does not exist."))?;
 
-        self.sequence_walker
+        self.state
+            .sequence_walker
             .walk(self.links, unicode_sequence.source)
             .into_iter()
             .map(|symbol| {
-                self.unicode_symbol_to_char_converter
-                    .convert(self.links, symbol)
+                self.state
+                    .unicode_symbol_to_char_converter
+                    .convert_code_point(self.links, symbol)
             })
             .collect()
     }
 
-    pub fn get_types(&self) -> Vec<u32> {
+    /// Returns how strings are split into symbols when they are stored.
+    pub fn symbol_encoding(&self) -> UnicodeSymbolEncoding {
+        self.state.string_to_unicode_sequence_converter.encoding()
+    }
+
+    /// Records the encoding strings are stored with from now on. Fails if a
+    /// stored string would be split differently, since looking it up by its
+    /// content would no longer find it.
+    pub fn set_symbol_encoding(&mut self, encoding: UnicodeSymbolEncoding) -> Result<()> {
+        if encoding == self.symbol_encoding() {
+            return Ok(());
+        }
+        let strings = self
+            .links
+            .all()
+            .into_iter()
+            .filter(|link| {
+                link.source == self.state.string_type
+                    && self
+                        .state
+                        .unicode_sequence_criterion_matcher
+                        .is_matched(self.links, link.target)
+            })
+            .map(|link| link.index)
+            .collect::<Vec<_>>();
+        for string in strings {
+            let content = self.get_string(string)?;
+            if encoding.symbols(&content) != self.unicode_sequence_code_points(string)? {
+                bail!("String {string} ({content:?}) would be stored differently as {encoding}.");
+            }
+        }
+
+        for (_, type_link) in self.encoding_types()? {
+            self.remove_name(type_link)?;
+            self.links.delete(type_link)?;
+        }
+        if let Some(&(_, type_name)) = ENCODING_TYPE_NAMES
+            .iter()
+            .find(|(type_encoding, _)| *type_encoding == encoding)
+        {
+            self.create_type(type_name)?;
+        }
+        self.state.string_to_unicode_sequence_converter = self
+            .state
+            .string_to_unicode_sequence_converter
+            .with_encoding(encoding);
+        self.state
+            .caches
+            .borrow_mut()
+            .string_to_unicode_sequence
+            .clear();
+        Ok(())
+    }
+
+    pub fn get_types(&self) -> Vec<T> {
         self.links
-            .query(None, Some(self.type_type), None)
+            .query(None, Some(self.state.type_type), None)
             .into_iter()
             .map(|link| link.index)
             .collect()
     }
 
-    pub fn is_type(&self, address: u32) -> bool {
+    pub fn is_type(&self, address: T) -> bool {
         self.links
             .get(address)
-            .is_some_and(|link| link.source == self.type_type)
+            .is_some_and(|link| link.source == self.state.type_type)
     }
 
-    pub fn get_or_create_type(&mut self, name: &str) -> Result<u32> {
+    pub fn get_or_create_type(&mut self, name: &str) -> Result<T> {
         if let Some(existing) = self.get_by_name(name)? {
             return Ok(existing);
         }
 
-        let type_link = self.links.create(0, 0);
-        self.links.update(type_link, self.type_type, type_link)?;
-        self.set_name(type_link, name)?;
-        Ok(type_link)
+        self.create_type(name)
+    }
+
+    /// Returns the type of alias links, or `None` while no alias was stored.
+    pub fn alias_type(&self) -> Result<Option<T>> {
+        for type_link in self.get_types() {
+            if type_link != self.state.type_type
+                && self.get_name(type_link)?.as_deref() == Some(ALIAS_TYPE_NAME)
+            {
+                return Ok(Some(type_link));
+            }
+        }
+        Ok(None)
     }
 
-    pub fn set_name_for_external_reference(&mut self, link: u32, name: &str) -> Result<u32> {
+    /// Adds an alias for a link. Aliases are stored like names, but pair the
+    /// link with an `(Alias, string)` link, so C# only sees the primary name.
+    pub fn add_alias(&mut self, link: T, alias: &str) -> Result<T> {
+        let alias_type = match self.alias_type()? {
+            Some(alias_type) => alias_type,
+            None => self.create_type(ALIAS_TYPE_NAME)?,
+        };
+        let alias_sequence = self.create_string(alias)?;
+        let alias_link = self.links.get_or_create(alias_type, alias_sequence);
+        Ok(self.links.get_or_create(link, alias_link))
+    }
+
+    pub fn add_alias_for_external_reference(&mut self, link: T, alias: &str) -> Result<T> {
+        self.add_alias(external_reference(link), alias)
+    }
+
+    /// Returns every `(link, alias)` pair stored in this database, in the
+    /// order they were stored.
+    pub fn aliases(&self) -> Result<Vec<(T, String)>> {
+        match self.alias_type()? {
+            Some(alias_type) => self.pairs_of_type(alias_type),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn remove_alias(&mut self, link: T, alias: &str) -> Result<()> {
+        match self.alias_type()? {
+            Some(alias_type) => self.remove_pairs(link, alias_type, Some(alias)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn remove_alias_by_external_reference(&mut self, link: T, alias: &str) -> Result<()> {
+        self.remove_alias(external_reference(link), alias)
+    }
+
+    pub fn set_name_for_external_reference(&mut self, link: T, name: &str) -> Result<T> {
         self.set_name(external_reference(link), name)
     }
 
-    pub fn get_name_by_external_reference(&self, link: u32) -> Result<Option<String>> {
+    pub fn get_name_by_external_reference(&self, link: T) -> Result<Option<String>> {
         self.get_name(external_reference(link))
     }
 
-    pub fn get_external_reference_by_name(&mut self, name: &str) -> Result<Option<u32>> {
+    pub fn get_external_reference_by_name(&mut self, name: &str) -> Result<Option<T>> {
         Ok(self.get_by_name(name)?.and_then(external_reference_value))
     }
 
-    pub fn remove_name_by_external_reference(&mut self, external_reference_id: u32) -> Result<()> {
+    pub fn remove_name_by_external_reference(&mut self, external_reference_id: T) -> Result<()> {
         self.remove_name(external_reference(external_reference_id))
     }
 
-    pub fn set_name(&mut self, link: u32, name: &str) -> Result<u32> {
+    pub fn set_name(&mut self, link: T, name: &str) -> Result<T> {
         let name_sequence = self.create_string(name)?;
-        let name_link = self.links.get_or_create(self.name_type, name_sequence);
+        let name_link = self
+            .links
+            .get_or_create(self.state.name_type, name_sequence);
         Ok(self.links.get_or_create(link, name_link))
     }
 
-    pub fn get_name(&self, link: u32) -> Result<Option<String>> {
+    pub fn get_name(&self, link: T) -> Result<Option<String>> {
         for name_pair in self.links.query(None, Some(link), None) {
             let name_candidate = name_pair.target;
             let Some(candidate) = self.links.get(name_candidate) else {
                 continue;
             };
-            if candidate.source == self.name_type {
+            if candidate.source == self.state.name_type {
                 return self.get_string(candidate.target).map(Some);
             }
         }
         Ok(None)
     }
 
-    pub fn get_by_name(&mut self, name: &str) -> Result<Option<u32>> {
+    /// Returns every `(link, name)` pair stored in this database, in the
+    /// order they were stored.
+    pub fn names(&self) -> Result<Vec<(T, String)>> {
+        self.pairs_of_type(self.state.name_type)
+    }
+
+    /// Returns the `(link, name)` pairs of primary names and aliases matching
+    /// `search`, ordered by link. See `name_search` for how the stored
+    /// sequences are walked instead of decoding every name.
+    pub fn search_names(&self, search: &NameSearch) -> Result<Vec<(T, String)>> {
+        name_search::search_names(self, search)
+    }
+
+    /// Returns the string links whose content contains `text`, ordered by
+    /// link. Only the sequences sharing the symbol links of `text` are
+    /// visited, see `Sequences::containing`.
+    pub fn strings_containing(&self, text: &str) -> Vec<T> {
+        let usages = LinkUsages::new(self.links);
+        let encoding = self.symbol_encoding();
+        let converter = self.state.address_to_number_converter;
+        let symbols = encoding
+            .symbols(&encoding.normalize(text))
+            .into_iter()
+            .map(|symbol| converter.convert(T::from(symbol)))
+            .collect::<Vec<_>>();
+        let mut sequences = Sequences::new(
+            self.state.unicode_symbol_type,
+            self.state.unicode_sequence_type,
+        )
+        .containing_in(&usages, &symbols);
+        if symbols.is_empty() {
+            sequences.push(self.state.empty_string_type);
+        }
+        let mut strings = sequences
+            .into_iter()
+            .filter_map(|sequence| usages.find(self.state.string_type, sequence))
+            .collect::<Vec<_>>();
+        strings.sort_unstable();
+        strings
+    }
+
+    /// Returns the ids of the links strings and names are stored with: the
+    /// types, the strings, the Unicode symbols and sequences, the name and
+    /// alias pairs, and the links they are built from. A single-file
+    /// database keeps these among its own links.
+    pub fn storage_links(&self) -> Result<HashSet<T>> {
+        let pair_types = [Some(self.state.name_type), self.alias_type()?];
+        let mut pending = self
+            .links
+            .all()
+            .into_iter()
+            .filter(|link| {
+                let is_type = link.source == self.state.type_type && link.target == link.index;
+                let is_pair = link.index != link.target
+                    && self
+                        .links
+                        .get(link.target)
+                        .is_some_and(|target| pair_types.contains(&Some(target.source)));
+                is_type
+                    || is_pair
+                    || link.source == self.state.string_type
+                    || link.target == self.state.unicode_symbol_type
+                    || link.target == self.state.unicode_sequence_type
+            })
+            .map(|link| link.index)
+            .collect::<Vec<_>>();
+
+        let mut found = HashSet::new();
+        while let Some(id) = pending.pop() {
+            let Some(link) = self.links.get(id) else {
+                continue;
+            };
+            if found.insert(id) {
+                pending.extend([link.source, link.target]);
+            }
+        }
+        Ok(found)
+    }
+
+    pub fn get_by_name(&mut self, name: &str) -> Result<Option<T>> {
         let name_sequence = self.create_string(name)?;
-        let Some(name_link) = self.links.search(self.name_type, name_sequence) else {
+        let Some(name_link) = self.links.search(self.state.name_type, name_sequence) else {
             return Ok(None);
         };
         Ok(self
@@ -314,7 +627,59 @@ impl<'a> UnicodeStringStorage<'a> {
             .next())
     }
 
-    pub fn remove_name(&mut self, link: u32) -> Result<()> {
+    pub fn remove_name(&mut self, link: T) -> Result<()> {
+        self.remove_pairs(link, self.state.name_type, None)
+    }
+
+    /// Returns the encoding types stored in this database with their links.
+    fn encoding_types(&self) -> Result<Vec<(UnicodeSymbolEncoding, T)>> {
+        let mut found = Vec::new();
+        for type_link in self.get_types() {
+            if type_link == self.state.type_type {
+                continue;
+            }
+            let Some(name) = self.get_name(type_link)? else {
+                continue;
+            };
+            if let Some(&(encoding, _)) = ENCODING_TYPE_NAMES
+                .iter()
+                .find(|(_, type_name)| *type_name == name)
+            {
+                found.push((encoding, type_link));
+            }
+        }
+        Ok(found)
+    }
+
+    fn create_type(&mut self, name: &str) -> Result<T> {
+        let type_link = self.links.create(T::ZERO, T::ZERO);
+        self.links
+            .update(type_link, self.state.type_type, type_link)?;
+        self.set_name(type_link, name)?;
+        Ok(type_link)
+    }
+
+    /// Returns the `(link, string)` pairs whose target is a `pair_type` link.
+    /// The pairs come in the order of their ids, so the ones stored first
+    /// win when two links share a name.
+    fn pairs_of_type(&self, pair_type: T) -> Result<Vec<(T, String)>> {
+        let mut links = self.links.all();
+        links.sort_by_key(|link| link.index);
+        let mut pairs = Vec::new();
+        for pair in links {
+            let Some(candidate) = self.links.get(pair.target) else {
+                continue;
+            };
+            if pair.index != pair.target && candidate.source == pair_type {
+                pairs.push((pair.source, self.get_string(candidate.target)?));
+            }
+        }
+        Ok(pairs)
+    }
+
+    /// Deletes the pairs of `link` with `pair_type` links, only the one for
+    /// `content` if given, and the `pair_type` links nothing else uses.
+    fn remove_pairs(&mut self, link: T, pair_type: T, content: Option<&str>) -> Result<()> {
         let name_pairs = self
             .links
             .query(None, Some(link), None)
@@ -326,9 +691,14 @@ impl<'a> UnicodeStringStorage<'a> {
             let Some(candidate) = self.links.get(name_candidate).copied() else {
                 continue;
             };
-            if candidate.source != self.name_type {
+            if candidate.source != pair_type {
                 continue;
             }
+            if let Some(content) = content {
+                if self.get_string(candidate.target)? != self.symbol_encoding().normalize(content) {
+                    continue;
+                }
+            }
 
             if self.links.exists(name_pair) {
                 self.links.delete(name_pair)?;
@@ -347,51 +717,65 @@ impl<'a> UnicodeStringStorage<'a> {
         Ok(())
     }
 
-    fn get_string_sequence(&mut self, content: &str) -> u32 {
+    fn get_string_sequence(&mut self, content: &str) -> T {
         if content.is_empty() {
-            self.empty_string_type
+            self.state.empty_string_type
         } else {
             self.string_to_unicode_sequence(content)
         }
     }
 
-    fn string_to_unicode_sequence(&mut self, content: &str) -> u32 {
+    fn string_to_unicode_sequence(&mut self, content: &str) -> T {
         let input = content.to_string();
-        if let Some(cached) = self.string_to_unicode_sequence_cache.get(&input) {
+        if let Some(cached) = self
+            .state
+            .caches
+            .borrow_mut()
+            .string_to_unicode_sequence
+            .get(&input)
+        {
             return cached;
         }
 
-        let converter = self.string_to_unicode_sequence_converter;
+        let converter = self.state.string_to_unicode_sequence_converter;
         let sequence = converter.convert(self.links, content);
-        self.string_to_unicode_sequence_cache
+        self.state
+            .caches
+            .borrow_mut()
+            .string_to_unicode_sequence
             .insert(input, sequence)
     }
 
-    fn unicode_sequence_to_string(&self, sequence: u32) -> Result<String> {
+    fn unicode_sequence_to_string(&self, sequence: T) -> Result<String> {
         if let Some(cached) = self
-            .unicode_sequence_to_string_cache
-            .borrow()
+            .state
+            .caches
+            .borrow_mut()
+            .unicode_sequence_to_string
             .get(&sequence)
         {
             return Ok(cached);
         }
 
         let output = self
+            .state
             .unicode_sequence_to_string_converter
             .convert(self.links, sequence)?;
-        self.unicode_sequence_to_string_cache
+        self.state
+            .caches
             .borrow_mut()
+            .unicode_sequence_to_string
             .insert(sequence, output.clone());
         Ok(output)
     }
 
-    fn unwrap_string_sequence(&self, string_value: u32) -> Result<u32> {
+    fn unwrap_string_sequence(&self, string_value: T) -> Result<T> {
         let mut current = string_value;
         for _ in 0..3 {
             let Some(link) = self.links.get(current) else {
                 break;
             };
-            if link.source == self.string_type {
+            if link.source == self.state.string_type {
                 return Ok(link.target);
             }
             current = link.target;
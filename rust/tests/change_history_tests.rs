@@ -0,0 +1,83 @@
+use anyhow::Result;
+use link_cli::{ChangeHistory, NamedTypeLinks, NamedTypesDecorator};
+use std::path::Path;
+use tempfile::tempdir;
+
+mod common;
+
+use common::record;
+
+#[test]
+fn history_file_is_named_after_the_database() {
+    assert_eq!(
+        ChangeHistory::make_history_filename("data/db.links"),
+        Path::new("data/db.history.json")
+    );
+}
+
+#[test]
+fn undo_and_redo_survive_saving_and_loading_the_history() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let history_path = temp_dir.path().join("db.history.json");
+    let mut storage = NamedTypesDecorator::new(temp_dir.path().join("db.links"), false)?;
+    let mut history = ChangeHistory::load(&history_path)?;
+
+    record(&mut storage, &mut history, "() ((child: father mother))")?;
+    let created = storage.lino_lines()?;
+    record(&mut storage, &mut history, "((* *)) ()")?;
+    assert!(storage.lino_lines()?.is_empty());
+    history.save()?;
+
+    let mut history = ChangeHistory::load(&history_path)?;
+    assert_eq!(history.applied().len(), 2);
+    let reverted = history.undo(&mut storage, 1)?;
+    assert_eq!(reverted[0].query, "((* *)) ()");
+    assert_eq!(storage.lino_lines()?, created);
+    assert_eq!(storage.get_by_name("child")?, Some(3));
+    history.save()?;
+
+    let mut history = ChangeHistory::load(&history_path)?;
+    assert_eq!(history.undone().len(), 1);
+    history.redo(&mut storage, 1)?;
+    assert!(storage.lino_lines()?.is_empty());
+    assert_eq!(storage.get_by_name("child")?, None);
+
+    assert_eq!(history.undo(&mut storage, 5)?.len(), 2);
+    assert!(storage.all_links().is_empty());
+    assert!(history.undo(&mut storage, 1)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn undo_refuses_to_overwrite_later_changes() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut storage = NamedTypesDecorator::new(temp_dir.path().join("db.links"), false)?;
+    let mut history = ChangeHistory::load(temp_dir.path().join("db.history.json"))?;
+
+    record(&mut storage, &mut history, "() ((1 1))")?;
+    // Changed without being recorded in the history.
+    storage.update(1, 1, 2)?;
+
+    assert!(history.undo(&mut storage, 1).is_err());
+    assert_eq!(history.applied().len(), 1);
+    assert_eq!(storage.get_link(1).map(|link| link.target), Some(2));
+    Ok(())
+}
+
+#[test]
+fn recording_a_query_discards_redo_and_respects_the_limit() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut storage = NamedTypesDecorator::new(temp_dir.path().join("db.links"), false)?;
+    let mut history = ChangeHistory::load(temp_dir.path().join("db.history.json"))?.with_limit(2);
+
+    record(&mut storage, &mut history, "() ((1 1))")?;
+    record(&mut storage, &mut history, "() ((2 2))")?;
+    record(&mut storage, &mut history, "() ((3 3))")?;
+    assert_eq!(history.applied().len(), 2);
+
+    history.undo(&mut storage, 1)?;
+    assert_eq!(history.undone().len(), 1);
+    record(&mut storage, &mut history, "() ((4 4))")?;
+    assert!(history.undone().is_empty());
+    Ok(())
+}
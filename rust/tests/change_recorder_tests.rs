@@ -0,0 +1,68 @@
+use anyhow::Result;
+use link_cli::{
+    apply_changes, revert_changes, ChangeRecorder, NamedTypeLinks, NamedTypesDecorator,
+    QueryProcessor, StorageChange,
+};
+use tempfile::tempdir;
+
+#[test]
+fn reverting_a_query_restores_links_and_names() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut storage = NamedTypesDecorator::new(temp_dir.path().join("undo.links"), false)?;
+    let processor = QueryProcessor::new(false)
+        .with_auto_create_missing_references(true)
+        .with_auto_save(false);
+    processor.process_query(&mut storage, "() ((child: father mother))")?;
+    let initial = storage.lino_lines()?;
+
+    let mut recorder = ChangeRecorder::new(&mut storage);
+    processor.process_query(
+        &mut recorder,
+        "((child: father mother)) ((kid: mother father))",
+    )?;
+    let changes = recorder.into_changes();
+    let changed = storage.lino_lines()?;
+    assert_ne!(initial, changed);
+
+    revert_changes(&mut storage, &changes)?;
+    assert_eq!(initial, storage.lino_lines()?);
+    assert_eq!(storage.get_by_name("child")?, Some(3));
+    assert_eq!(storage.get_by_name("kid")?, None);
+
+    apply_changes(&mut storage, &changes)?;
+    assert_eq!(changed, storage.lino_lines()?);
+    Ok(())
+}
+
+#[test]
+fn moving_a_name_records_the_previous_holder() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut storage = NamedTypesDecorator::new(temp_dir.path().join("names.links"), false)?;
+    let first = storage.get_or_create_named("first")?;
+    let second = storage.get_or_create_named("second")?;
+
+    let mut recorder = ChangeRecorder::new(&mut storage);
+    recorder.set_name(second, "first")?;
+    let changes = recorder.into_changes();
+
+    assert_eq!(
+        changes,
+        vec![
+            StorageChange::Name {
+                link: first,
+                before: Some("first".to_string()),
+                after: None,
+            },
+            StorageChange::Name {
+                link: second,
+                before: Some("second".to_string()),
+                after: Some("first".to_string()),
+            },
+        ]
+    );
+
+    revert_changes(&mut storage, &changes)?;
+    assert_eq!(storage.get_name(first)?.as_deref(), Some("first"));
+    assert_eq!(storage.get_name(second)?.as_deref(), Some("second"));
+    Ok(())
+}
@@ -1,6 +1,12 @@
 //! Tests for Rust CLI argument parity with the C# command surface.
 
-use link_cli::cli::{Cli, CliCommand};
+use link_cli::cli::{
+    AliasAction, AliasCli, CheckCli, Cli, CliCommand, CompactCli, DiffCli, EncodingCli, ExportCli,
+    HistoryCli, ImportCli, LayoutCli, MergeCli, MergeDriverCli, NamesAction, NamesCli, ReplCli,
+    SnapshotAction, SnapshotCli, StatsCli, Subcommand,
+};
+use link_cli::sequences::UnicodeSymbolEncoding;
+use link_cli::{NameSearch, NamesLayout};
 
 fn parse_run(args: &[&str]) -> Cli {
     match Cli::parse_from(args).expect("CLI arguments should parse") {
@@ -103,3 +109,435 @@ fn rejects_extra_positional_queries() {
 
     assert!(error.to_string().contains("unexpected extra positional"));
 }
+
+#[test]
+fn parses_json_import_and_export_commands() {
+    assert_eq!(
+        Cli::parse_from([
+            "clink",
+            "--db",
+            "docs.links",
+            "import",
+            "--json",
+            "doc.json"
+        ])
+        .expect("import should parse"),
+        CliCommand::Import(ImportCli {
+            db: "docs.links".to_string(),
+            trace: false,
+            json: "doc.json".to_string(),
+        })
+    );
+    assert_eq!(
+        Cli::parse_from(["clink", "export", "--json=42", "--trace"]).expect("export should parse"),
+        CliCommand::Export(ExportCli {
+            db: "db.links".to_string(),
+            trace: true,
+            json: Some("42".to_string()),
+            ..ExportCli::default()
+        })
+    );
+}
+
+#[test]
+fn parses_structure_depth_and_tree_layout() {
+    let cli = parse_run(&["clink", "-s", "7", "--depth", "2", "--tree"]);
+
+    assert_eq!(cli.structure, Some(7));
+    assert_eq!(cli.depth, Some(2));
+    assert!(cli.tree);
+}
+
+#[test]
+fn parses_address_width() {
+    assert_eq!(parse_run(&["clink", "(1 2)"]).address_width, None);
+    let cli = parse_run(&["clink", "--address-width", "64", "(1 2)"]);
+    assert_eq!(cli.address_width, Some(64));
+
+    assert!(Cli::parse_from(["clink", "--address-width=16", "(1 2)"]).is_err());
+}
+
+#[test]
+fn parses_export_filter_options() {
+    let cli = parse_run(&[
+        "clink",
+        "--export",
+        "subset.lino",
+        "--export-roots",
+        "1,child",
+        "--export-roots=3",
+        "--export-filter",
+        "((* father))",
+        "--export-closure",
+    ]);
+
+    assert_eq!(cli.lino_output.as_deref(), Some("subset.lino"));
+    assert_eq!(cli.export_roots, vec!["1", "child", "3"]);
+    assert_eq!(cli.export_filter.as_deref(), Some("((* father))"));
+    assert!(cli.export_closure);
+}
+
+#[test]
+fn query_subcommand_accepts_the_bare_query_options() {
+    let cli = match Cli::parse_from(["clink", "-d", "q.links", "query", "(1 2)", "--changes"])
+        .expect("query should parse")
+    {
+        CliCommand::Run(cli) => cli,
+        other => panic!("expected run command, got {other:?}"),
+    };
+
+    assert_eq!(cli.db, "q.links");
+    assert_eq!(cli.query_arg.as_deref(), Some("(1 2)"));
+    assert!(cli.changes);
+}
+
+#[test]
+fn subcommand_names_after_a_query_are_not_commands() {
+    let cli = parse_run(&["clink", "--", "stats"]);
+    assert_eq!(cli.query_arg.as_deref(), Some("stats"));
+
+    let error = Cli::parse_from(["clink", "(1 2)", "names"]).expect_err("extra query should fail");
+    assert!(error.to_string().contains("unexpected extra positional"));
+}
+
+#[test]
+fn parses_database_subcommands_with_global_options() {
+    assert_eq!(
+        Cli::parse_from(["clink", "--db", "a.links", "stats", "--trace"]).unwrap(),
+        CliCommand::Stats(StatsCli {
+            db: "a.links".to_string(),
+            trace: true,
+        })
+    );
+    assert_eq!(
+        Cli::parse_from(["clink", "check", "--db=b.links"]).unwrap(),
+        CliCommand::Check(CheckCli {
+            db: "b.links".to_string(),
+            trace: false,
+            repair: false,
+        })
+    );
+    assert_eq!(
+        Cli::parse_from(["clink", "names"]).unwrap(),
+        CliCommand::Names(NamesCli {
+            db: "db.links".to_string(),
+            trace: false,
+            action: NamesAction::List(None),
+        })
+    );
+}
+
+#[test]
+fn parses_layout_changes() {
+    assert_eq!(
+        Cli::parse_from(["clink", "layout"]).unwrap(),
+        CliCommand::Layout(LayoutCli {
+            db: "db.links".to_string(),
+            trace: false,
+            layout: None,
+        })
+    );
+    assert_eq!(
+        Cli::parse_from(["clink", "layout", "single-file"]).unwrap(),
+        CliCommand::Layout(LayoutCli {
+            db: "db.links".to_string(),
+            trace: false,
+            layout: Some(NamesLayout::SingleFile),
+        })
+    );
+    assert!(Cli::parse_from(["clink", "layout", "merged"]).is_err());
+}
+
+#[test]
+fn parses_encoding_changes() {
+    assert_eq!(
+        Cli::parse_from(["clink", "encoding", "code-points-nfc", "--db", "e.links"]).unwrap(),
+        CliCommand::Encoding(EncodingCli {
+            db: "e.links".to_string(),
+            trace: false,
+            encoding: Some(UnicodeSymbolEncoding::NfcCodePoints),
+        })
+    );
+    assert!(Cli::parse_from(["clink", "encoding", "utf-8"]).is_err());
+}
+
+#[test]
+fn parses_names_actions() {
+    let action = |args: &[&str]| match Cli::parse_from(args).unwrap() {
+        CliCommand::Names(NamesCli { action, .. }) => action,
+        other => panic!("expected names command, got {other:?}"),
+    };
+    assert_eq!(
+        action(&["clink", "names", "list", "--prefix", "fa"]),
+        NamesAction::List(Some(NameSearch::prefix("fa")))
+    );
+    assert_eq!(
+        action(&["clink", "names", "--contains", "th", "--ignore-case"]),
+        NamesAction::List(Some(NameSearch::substring("th").with_ignore_case(true)))
+    );
+    assert!(Cli::parse_from(["clink", "names", "--prefix", "a", "--contains", "b"]).is_err());
+    assert!(Cli::parse_from(["clink", "names", "--ignore-case"]).is_err());
+    assert_eq!(
+        action(&["clink", "names", "find", "^m.*r$"]),
+        NamesAction::Find("^m.*r$".to_string())
+    );
+    assert_eq!(
+        action(&["clink", "names", "rename", "child", "kid"]),
+        NamesAction::Rename("child".to_string(), "kid".to_string())
+    );
+    assert_eq!(
+        action(&["clink", "names", "show", "3"]),
+        NamesAction::Show("3".to_string())
+    );
+    assert!(Cli::parse_from(["clink", "names", "rename", "child"])
+        .unwrap_err()
+        .to_string()
+        .contains("names rename expects the old and the new name"));
+    assert!(Cli::parse_from(["clink", "names", "unset", "3", "--prefix", "a"]).is_err());
+}
+
+#[test]
+fn parses_lino_export_subcommand_options() {
+    assert_eq!(
+        Cli::parse_from([
+            "clink",
+            "export",
+            "--roots",
+            "1,child",
+            "--filter",
+            "((* father))",
+            "--closure",
+            "--out",
+            "subset.lino",
+        ])
+        .unwrap(),
+        CliCommand::Export(ExportCli {
+            db: "db.links".to_string(),
+            trace: false,
+            json: None,
+            out: Some("subset.lino".to_string()),
+            roots: vec!["1".to_string(), "child".to_string()],
+            filter: Some("((* father))".to_string()),
+            closure: true,
+        })
+    );
+}
+
+#[test]
+fn returns_per_subcommand_help() {
+    assert_eq!(
+        Cli::parse_from(["clink", "export", "--help"]).unwrap(),
+        CliCommand::CommandHelp(Subcommand::Export)
+    );
+    assert_eq!(
+        Cli::parse_from(["clink", "query", "-h"]).unwrap(),
+        CliCommand::CommandHelp(Subcommand::Query)
+    );
+    assert_eq!(
+        Cli::parse_from(["clink", "help", "stats"]).unwrap(),
+        CliCommand::CommandHelp(Subcommand::Stats)
+    );
+    assert!(Subcommand::Check.help_text().contains("Usage: clink check"));
+}
+
+#[test]
+fn rejects_options_of_other_subcommands() {
+    let error = Cli::parse_from(["clink", "stats", "--closure"]).expect_err("option should fail");
+
+    assert!(error
+        .to_string()
+        .contains("unknown option '--closure' for stats"));
+}
+
+#[test]
+fn parses_repl_subcommand() {
+    assert_eq!(
+        Cli::parse_from([
+            "clink",
+            "repl",
+            "--db",
+            "r.links",
+            "--auto-create-missing-references",
+            "--history=r.history",
+        ])
+        .unwrap(),
+        CliCommand::Repl(ReplCli {
+            db: "r.links".to_string(),
+            trace: false,
+            auto_create_missing_references: true,
+            history: Some("r.history".to_string()),
+        })
+    );
+}
+
+#[test]
+fn parses_check_repair_flag() {
+    assert_eq!(
+        Cli::parse_from(["clink", "check", "--repair"]).unwrap(),
+        CliCommand::Check(CheckCli {
+            db: "db.links".to_string(),
+            trace: false,
+            repair: true,
+        })
+    );
+}
+
+#[test]
+fn parses_undo_and_redo_counts() {
+    assert_eq!(
+        Cli::parse_from(["clink", "-d", "u.links", "undo", "3"]).unwrap(),
+        CliCommand::Undo(HistoryCli {
+            db: "u.links".to_string(),
+            trace: false,
+            count: 3,
+        })
+    );
+    assert_eq!(
+        Cli::parse_from(["clink", "redo"]).unwrap(),
+        CliCommand::Redo(HistoryCli {
+            db: "db.links".to_string(),
+            trace: false,
+            count: 1,
+        })
+    );
+    assert!(Cli::parse_from(["clink", "undo", "many"])
+        .unwrap_err()
+        .to_string()
+        .contains("invalid count 'many' for undo"));
+}
+
+#[test]
+fn parses_snapshot_actions() {
+    let action = |args: &[&str]| match Cli::parse_from(args).unwrap() {
+        CliCommand::Snapshot(SnapshotCli { action, .. }) => action,
+        other => panic!("expected snapshot command, got {other:?}"),
+    };
+    assert_eq!(
+        action(&["clink", "snapshot", "create", "pre-migration"]),
+        SnapshotAction::Create("pre-migration".to_string())
+    );
+    assert_eq!(action(&["clink", "snapshot", "list"]), SnapshotAction::List);
+    assert_eq!(
+        action(&["clink", "snapshot", "diff", "a", "b"]),
+        SnapshotAction::Diff("a".to_string(), Some("b".to_string()))
+    );
+    assert!(Cli::parse_from(["clink", "snapshot", "restore"])
+        .unwrap_err()
+        .to_string()
+        .contains("snapshot restore expects a snapshot name"));
+    assert!(Cli::parse_from(["clink", "snapshot", "list", "extra"]).is_err());
+}
+
+#[test]
+fn parses_alias_actions() {
+    assert_eq!(
+        Cli::parse_from(["clink", "alias", "add", "3", "kid", "--db", "family.links"]).unwrap(),
+        CliCommand::Alias(AliasCli {
+            db: "family.links".to_string(),
+            trace: false,
+            action: AliasAction::Add("3".to_string(), "kid".to_string()),
+        })
+    );
+    let action = |args: &[&str]| match Cli::parse_from(args).unwrap() {
+        CliCommand::Alias(AliasCli { action, .. }) => action,
+        other => panic!("expected alias command, got {other:?}"),
+    };
+    assert_eq!(action(&["clink", "alias", "list"]), AliasAction::List(None));
+    assert_eq!(
+        action(&["clink", "alias", "primary", "child", "kid"]),
+        AliasAction::Primary("child".to_string(), "kid".to_string())
+    );
+    assert!(Cli::parse_from(["clink", "alias", "remove", "3"])
+        .unwrap_err()
+        .to_string()
+        .contains("alias remove expects a link and an alias"));
+    assert!(Cli::parse_from(["clink", "alias", "rename", "3", "kid"]).is_err());
+}
+
+#[test]
+fn parses_diff_databases() {
+    assert_eq!(
+        Cli::parse_from(["clink", "diff", "a.links", "b.links", "--by-name"]).unwrap(),
+        CliCommand::Diff(DiffCli {
+            before: "a.links".to_string(),
+            after: "b.links".to_string(),
+            trace: false,
+            by_name: true,
+        })
+    );
+    assert!(Cli::parse_from(["clink", "diff", "a.links"]).is_err());
+    assert!(Cli::parse_from(["clink", "diff", "a", "b", "c"]).is_err());
+}
+
+#[test]
+fn parses_merge_database() {
+    assert_eq!(
+        Cli::parse_from(["clink", "merge", "other.links", "--db", "main.links"]).unwrap(),
+        CliCommand::Merge(MergeCli {
+            db: "main.links".to_string(),
+            trace: false,
+            other: "other.links".to_string(),
+        })
+    );
+    assert!(Cli::parse_from(["clink", "merge"]).is_err());
+}
+
+#[test]
+fn parses_merge_driver_files() {
+    assert_eq!(
+        Cli::parse_from(["clink", "merge-driver", "base", "ours", "theirs"]).unwrap(),
+        CliCommand::MergeDriver(MergeDriverCli {
+            base: "base".to_string(),
+            ours: "ours".to_string(),
+            theirs: "theirs".to_string(),
+            names: None,
+            trace: false,
+        })
+    );
+    let files = [
+        "base",
+        "ours",
+        "theirs",
+        "base.names",
+        "ours.names",
+        "theirs.names",
+    ];
+    assert_eq!(
+        Cli::parse_from(["clink", "merge-driver"].into_iter().chain(files)).unwrap(),
+        CliCommand::MergeDriver(MergeDriverCli {
+            base: "base".to_string(),
+            ours: "ours".to_string(),
+            theirs: "theirs".to_string(),
+            names: Some(["base.names", "ours.names", "theirs.names"].map(str::to_string)),
+            trace: false,
+        })
+    );
+    assert!(Cli::parse_from(["clink", "merge-driver", "base", "ours"]).is_err());
+    assert!(Cli::parse_from(["clink", "merge-driver", "a", "b", "c", "d"]).is_err());
+    assert!(Cli::parse_from(["clink", "merge-driver", "a", "b", "c", "--db", "x"]).is_err());
+}
+
+#[test]
+fn parses_compact() {
+    assert_eq!(
+        Cli::parse_from(["clink", "--db", "main.links", "compact", "--trace"]).unwrap(),
+        CliCommand::Compact(CompactCli {
+            db: "main.links".to_string(),
+            trace: true,
+        })
+    );
+    assert!(Cli::parse_from(["clink", "compact", "extra"]).is_err());
+}
+
+#[test]
+fn parses_reuse_deleted_ids() {
+    assert_eq!(parse_run(&["clink"]).reuse_deleted_ids, None);
+    assert_eq!(
+        parse_run(&["clink", "--reuse-deleted-ids"]).reuse_deleted_ids,
+        Some(true)
+    );
+    assert_eq!(
+        parse_run(&["clink", "--reuse-deleted-ids=false"]).reuse_deleted_ids,
+        Some(false)
+    );
+}
@@ -37,6 +37,36 @@ fn export_alias_writes_named_references() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn export_roots_with_closure_writes_named_subgraph() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("subset.links");
+    let output_path = temp_dir.path().join("subset.lino");
+
+    let output = run_clink(
+        &db_path,
+        "() ((child: father mother) (other: x y))",
+        true,
+        &output_path,
+    )?;
+    ensure_success(&output)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_clink"))
+        .arg("--db")
+        .arg(&db_path)
+        .args(["--export-roots", "child", "--export-closure", "--export"])
+        .arg(&output_path)
+        .output()?;
+
+    ensure_success(&output)?;
+    assert_eq!(
+        std::fs::read_to_string(&output_path)?,
+        "(father: father father)\n(mother: mother mother)\n(child: father mother)\n"
+    );
+
+    Ok(())
+}
+
 fn run_clink(
     db_path: &Path,
     query: &str,
@@ -40,3 +40,36 @@ fn cli_stores_string_aliases_in_separate_names_database() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn trace_reports_name_cache_statistics() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("cache-stats.links");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_clink"))
+        .args([
+            "--db",
+            db_path.to_str().unwrap(),
+            "--trace",
+            "--auto-create-missing-references",
+            "() ((pair: left right))",
+        ])
+        .output()?;
+
+    ensure!(
+        output.status.success(),
+        "clink failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(
+        stderr.contains("[TRACE] String to sequence cache: "),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("[TRACE] Sequence to string cache: "),
+        "{stderr}"
+    );
+    assert!(stderr.contains(" hits, "), "{stderr}");
+    Ok(())
+}
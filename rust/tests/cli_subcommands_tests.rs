@@ -0,0 +1,573 @@
+//! End-to-end tests for the `clink` subcommands.
+
+use anyhow::{ensure, Result};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::tempdir;
+
+#[test]
+fn bare_query_and_query_subcommand_are_equivalent() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("query.links");
+
+    ensure_success(&clink(&db_path, &["() ((1 1))"])?)?;
+    ensure_success(&clink(&db_path, &["query", "() ((2 2))"])?)?;
+
+    let output = clink(&db_path, &["export"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "(1: 1 1)\n(2: 2 2)\n");
+    Ok(())
+}
+
+#[test]
+fn stats_and_names_describe_the_database() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("stats.links");
+    ensure_success(&clink(
+        &db_path,
+        &[
+            "--auto-create-missing-references",
+            "() ((child: father mother))",
+        ],
+    )?)?;
+
+    let output = clink(&db_path, &["stats"])?;
+    ensure_success(&output)?;
+    let stats = String::from_utf8(output.stdout)?;
+    assert!(stats.contains("links: 3\n"), "{stats}");
+    assert!(stats.contains("points: 2\n"), "{stats}");
+    assert!(stats.contains("named links: 3\n"), "{stats}");
+    assert!(stats.contains("max id: 3\n"), "{stats}");
+
+    let output = clink(&db_path, &["names"])?;
+    ensure_success(&output)?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "1 father\n2 mother\n3 child\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn check_reports_and_repairs_problems() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("check.links");
+    std::fs::write(&db_path, "(1 1 1)\n(2 1 5)\n")?;
+
+    let output = clink(&db_path, &["check"])?;
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "link 2: target 5 does not exist\n"
+    );
+
+    std::fs::write(&db_path, "(1 1 1)\n(2 1 1)\n")?;
+    let output = clink(&db_path, &["check", "--repair"])?;
+    ensure_success(&output)?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "repaired: link 2 duplicates link 1\n"
+    );
+
+    let output = clink(&db_path, &["check"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "No problems found.\n");
+    Ok(())
+}
+
+#[test]
+fn writes_fail_on_malformed_lines_while_reads_warn() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("malformed.links");
+    let content = "(1 1 1)\n(2 oops)\n";
+    std::fs::write(&db_path, content)?;
+
+    let output = clink(&db_path, &["() ((3 3))"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("at line 2: (2 oops)"));
+    assert_eq!(std::fs::read_to_string(&db_path)?, content);
+
+    let output = clink(&db_path, &["export"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "(1: 1 1)\n");
+    assert!(String::from_utf8(output.stderr)?.contains(":2: malformed line: (2 oops)"));
+    Ok(())
+}
+
+#[test]
+fn undo_recovers_from_an_accidental_delete_all() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("undo.links");
+    ensure_success(&clink(
+        &db_path,
+        &[
+            "--auto-create-missing-references",
+            "() ((child: father mother))",
+        ],
+    )?)?;
+    let content = std::fs::read_to_string(&db_path)?;
+    let names = clink(&db_path, &["names"])?.stdout;
+
+    ensure_success(&clink(&db_path, &["((* *)) ()"])?)?;
+    assert_eq!(std::fs::read_to_string(&db_path)?, "");
+
+    let output = clink(&db_path, &["undo"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "undone: ((* *)) ()\n");
+    assert_eq!(std::fs::read_to_string(&db_path)?, content);
+    assert_eq!(clink(&db_path, &["names"])?.stdout, names);
+
+    let output = clink(&db_path, &["redo"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "redone: ((* *)) ()\n");
+    assert_eq!(std::fs::read_to_string(&db_path)?, "");
+
+    let output = clink(&db_path, &["undo", "2"])?;
+    ensure_success(&output)?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "undone: ((* *)) ()\nundone: () ((child: father mother))\n"
+    );
+    assert_eq!(std::fs::read_to_string(&db_path)?, "");
+
+    let output = clink(&db_path, &["undo"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "Nothing to undo.\n");
+    Ok(())
+}
+
+#[test]
+fn alias_is_resolved_by_queries_and_can_be_undone() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("alias.links");
+    ensure_success(&clink(
+        &db_path,
+        &[
+            "--auto-create-missing-references",
+            "() ((child: father mother))",
+        ],
+    )?)?;
+
+    ensure_success(&clink(&db_path, &["alias", "add", "father", "dad"])?)?;
+    ensure_success(&clink(&db_path, &["alias", "add", "3", "kid"])?)?;
+    let output = clink(&db_path, &["alias", "list"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "1 dad\n3 kid\n");
+
+    ensure_success(&clink(
+        &db_path,
+        &["((kid: dad mother)) ((kid: mother dad))"],
+    )?)?;
+    assert!(std::fs::read_to_string(&db_path)?.contains("(3 2 1)"));
+
+    ensure_success(&clink(&db_path, &["alias", "primary", "child", "kid"])?)?;
+    let output = clink(&db_path, &["alias", "list", "kid"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "3 child\n");
+
+    let output = clink(&db_path, &["undo"])?;
+    ensure_success(&output)?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "undone: alias primary child kid\n"
+    );
+    let output = clink(&db_path, &["names"])?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "1 father\n2 mother\n3 child\n"
+    );
+
+    let output = clink(&db_path, &["alias", "add", "2", "dad"])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("The name 'dad' already belongs to link 1."));
+    Ok(())
+}
+
+#[test]
+fn names_can_be_found_renamed_and_unset() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("names.links");
+    ensure_success(&clink(
+        &db_path,
+        &[
+            "--auto-create-missing-references",
+            "() ((child: father mother))",
+        ],
+    )?)?;
+    let content = std::fs::read_to_string(&db_path)?;
+    let stdout = |args: &[&str]| -> Result<String> {
+        let output = clink(&db_path, args)?;
+        ensure_success(&output)?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    assert_eq!(stdout(&["names", "list", "--prefix", "m"])?, "2 mother\n");
+    assert_eq!(
+        stdout(&["names", "list", "--contains", "OTHER", "--ignore-case"])?,
+        "2 mother\n"
+    );
+    assert_eq!(
+        stdout(&["names", "find", "^.*h.r$"])?,
+        "1 father\n2 mother\n"
+    );
+
+    stdout(&["names", "rename", "child", "kid"])?;
+    assert_eq!(std::fs::read_to_string(&db_path)?, content);
+    assert_eq!(
+        stdout(&["names", "show", "kid"])?,
+        "link: 3\nname: kid\nlino: (kid: father mother)\n"
+    );
+    let output = clink(&db_path, &["names", "rename", "kid", "father"])?;
+    assert!(
+        String::from_utf8(output.stderr)?.contains("The name 'father' already belongs to link 1.")
+    );
+
+    stdout(&["names", "unset", "father"])?;
+    assert_eq!(stdout(&["names"])?, "2 mother\n3 kid\n");
+    assert_eq!(
+        stdout(&["undo", "2"])?,
+        "undone: names unset father\nundone: names rename child kid\n"
+    );
+    assert_eq!(stdout(&["names"])?, "1 father\n2 mother\n3 child\n");
+    Ok(())
+}
+
+#[test]
+fn snapshot_create_diff_and_restore() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("snap.links");
+    ensure_success(&clink(&db_path, &["() ((1 1) (2 2))"])?)?;
+
+    let output = clink(&db_path, &["snapshot", "create", "base"])?;
+    ensure_success(&output)?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "Created snapshot 'base' with 2 links.\n"
+    );
+
+    ensure_success(&clink(&db_path, &["((1: 1 1)) ((1: 1 2))"])?)?;
+    let output = clink(&db_path, &["snapshot", "diff", "base"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "((1: 1 1)) ((1: 1 2))\n");
+
+    let output = clink(&db_path, &["snapshot", "list"])?;
+    ensure_success(&output)?;
+    assert!(String::from_utf8(output.stdout)?.starts_with("base  "));
+
+    ensure_success(&clink(&db_path, &["snapshot", "restore", "base"])?)?;
+    assert_eq!(std::fs::read_to_string(&db_path)?, "(1 1 1)\n(2 2 2)\n");
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["undo"])?.stdout)?,
+        "Nothing to undo.\n"
+    );
+    assert_eq!(std::fs::read_to_string(&db_path)?, "(1 1 1)\n(2 2 2)\n");
+    Ok(())
+}
+
+#[test]
+fn diff_compares_two_databases() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let before = temp_dir.path().join("before.links");
+    let after = temp_dir.path().join("after.links");
+    for (db_path, query) in [
+        (&before, "() ((x: x x) (y: y y))"),
+        (&after, "() ((y: y y) (x: x x) (x y))"),
+    ] {
+        ensure_success(&clink(
+            db_path,
+            &["--auto-create-missing-references", query],
+        )?)?;
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_clink"))
+        .arg("diff")
+        .args([&before, &after])
+        .arg("--by-name")
+        .output()?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "() ((3: x y))\n");
+    Ok(())
+}
+
+#[test]
+fn merge_prints_the_id_mapping_and_can_be_undone() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("main.links");
+    let other_path = temp_dir.path().join("other.links");
+    ensure_success(&clink(
+        &db_path,
+        &["--auto-create-missing-references", "() ((a: a a))"],
+    )?)?;
+    ensure_success(&clink(
+        &other_path,
+        &[
+            "--auto-create-missing-references",
+            "() ((b: b b) (a: a a) (b a))",
+        ],
+    )?)?;
+    let before = std::fs::read_to_string(&db_path)?;
+
+    let output = clink(&db_path, &["merge", other_path.to_str().unwrap()])?;
+    ensure_success(&output)?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "1 -> 2 created\n2 -> 1 unified by name\n3 -> 3 created\n"
+    );
+
+    let output = clink(&db_path, &["export"])?;
+    ensure_success(&output)?;
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        "(a: a a)\n(b: b b)\n(3: b a)\n"
+    );
+
+    ensure_success(&clink(&db_path, &["undo"])?)?;
+    assert_eq!(std::fs::read_to_string(&db_path)?, before);
+    Ok(())
+}
+
+#[test]
+fn merge_driver_writes_the_result_to_ours() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("unused.links");
+    let [base, ours, theirs] = ["base", "ours", "theirs"].map(|name| temp_dir.path().join(name));
+    std::fs::write(&base, "(1 1 1 \"a\")\n(2 1 1)\n")?;
+    std::fs::write(&ours, "(1 1 1 \"a\")\n(2 1 1)\n(3 2 2)\n")?;
+    std::fs::write(&theirs, "(1 1 1 \"a\")\n(2 1 1)\n(3 1 2)\n")?;
+    let files = [&base, &ours, &theirs].map(|path| path.to_str().unwrap());
+
+    let output = clink(&db_path, &["merge-driver", files[0], files[1], files[2]])?;
+    ensure_success(&output)?;
+    assert_eq!(
+        std::fs::read_to_string(&ours)?,
+        "(1 1 1 \"a\")\n(2 1 1)\n(3 2 2)\n(4 1 2)\n"
+    );
+
+    std::fs::write(&ours, "(1 1 1 \"a\")\n(2 2 2)\n")?;
+    std::fs::write(&theirs, "(1 1 1 \"a\")\n(2 1 2)\n")?;
+    let output = clink(&db_path, &["merge-driver", files[0], files[1], files[2]])?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)?.contains("1 conflict(s) left in"));
+    assert_eq!(
+        std::fs::read_to_string(&ours)?,
+        "(1 1 1 \"a\")\n(2 2 2)\n# conflict: link 2 base (2 1 1) ours (2 2 2) theirs (2 1 2)\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn compact_prints_moved_ids_and_clears_the_history() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("compact.links");
+    for query in [
+        "() ((a: a a))",
+        "() ((1 2))",
+        "() ((b: a a))",
+        "((2: 1 2)) ()",
+    ] {
+        ensure_success(&clink(&db_path, &[query])?)?;
+    }
+
+    let output = clink(&db_path, &["compact"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "3 -> 2\n");
+    assert!(!temp_dir.path().join("compact.history.json").exists());
+
+    let output = clink(&db_path, &["export"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "(a: a a)\n(b: a a)\n");
+
+    let output = clink(&db_path, &["compact"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "Nothing to compact.\n");
+    Ok(())
+}
+
+#[test]
+fn gc_reclaims_strings_of_renamed_links() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("gc.links");
+    ensure_success(&clink(
+        &db_path,
+        &["--auto-create-missing-references", "() ((zz: zz zz))"],
+    )?)?;
+    ensure_success(&clink(&db_path, &["names", "rename", "zz", "point"])?)?;
+
+    let output = clink(&db_path, &["gc"])?;
+    ensure_success(&output)?;
+    let report = String::from_utf8(output.stdout)?;
+    assert!(report.starts_with("reclaimed links: "), "{report}");
+    assert!(report.contains("strings: 1\n"), "{report}");
+
+    let output = clink(&db_path, &["gc"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "Nothing to collect.\n");
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["names"])?.stdout)?,
+        "1 point\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn layout_moves_names_into_the_database_file_and_back() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("layout.links");
+    let names_path = temp_dir.path().join("layout.names.links");
+    ensure_success(&clink(
+        &db_path,
+        &[
+            "--auto-create-missing-references",
+            "() ((point: point point))",
+        ],
+    )?)?;
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["layout"])?.stdout)?,
+        "separate\n"
+    );
+
+    // The pinned types of the names database take the first ids, so the
+    // point moves past them.
+    let output = clink(&db_path, &["layout", "single-file"])?;
+    ensure_success(&output)?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let moved = stdout
+        .strip_prefix("single-file\n1 -> ")
+        .and_then(|rest| rest.trim_end().parse::<u32>().ok())
+        .unwrap_or_else(|| panic!("{stdout}"));
+    assert!(!names_path.exists());
+    assert!(fs::read_to_string(&db_path)?.starts_with("#! names\n(1 1 1)\n"));
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["names"])?.stdout)?,
+        format!("{moved} point\n")
+    );
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["export"])?.stdout)?,
+        "(point: point point)\n"
+    );
+    ensure_success(&clink(&db_path, &["() ((line: point point))"])?)?;
+    assert!(!names_path.exists());
+    ensure_success(&clink(&db_path, &["--reuse-deleted-ids", "() ()"])?)?;
+    assert!(fs::read_to_string(&db_path)?.contains("#! names\n"));
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["snapshot", "create", "single"])?.stdout)?,
+        "Created snapshot 'single' with 2 links.\n"
+    );
+    let names = String::from_utf8(clink(&db_path, &["names"])?.stdout)?;
+    assert!(names.starts_with(&format!("{moved} point\n")), "{names}");
+
+    ensure_success(&clink(&db_path, &["layout", "separate"])?)?;
+    assert!(names_path.exists());
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["names"])?.stdout)?,
+        names
+    );
+    Ok(())
+}
+
+#[test]
+fn encoding_stores_names_as_code_points() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("encoding.links");
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["encoding"])?.stdout)?,
+        "utf-16\n"
+    );
+    ensure_success(&clink(&db_path, &["encoding", "code-points"])?)?;
+    ensure_success(&clink(
+        &db_path,
+        &["--auto-create-missing-references", "() ((🚀: 🚀 🚀))"],
+    )?)?;
+
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["names", "--prefix", "🚀"])?.stdout)?,
+        "1 🚀\n"
+    );
+    assert_eq!(
+        String::from_utf8(clink(&db_path, &["encoding"])?.stdout)?,
+        "code-points\n"
+    );
+    assert!(!clink(&db_path, &["encoding", "utf-16"])?.status.success());
+    Ok(())
+}
+
+#[test]
+fn address_width_creates_and_widens_64_bit_databases() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("wide.links");
+    ensure_success(&clink(
+        &db_path,
+        &[
+            "--address-width",
+            "64",
+            "--auto-create-missing-references",
+            "() ((child: father mother))",
+        ],
+    )?)?;
+    assert!(fs::read_to_string(&db_path)?.starts_with("#! address-width 64\n"));
+
+    ensure_success(&clink(&db_path, &["() ((pair: father child))"])?)?;
+    let output = clink(&db_path, &["export"])?;
+    ensure_success(&output)?;
+    assert!(String::from_utf8(output.stdout)?.contains("(pair: father child)\n"));
+    let output = clink(&db_path, &["names"])?;
+    ensure_success(&output)?;
+    assert!(String::from_utf8(output.stdout)?.contains("4 pair\n"));
+
+    let narrow_path = temp_dir.path().join("narrow.links");
+    ensure_success(&clink(
+        &narrow_path,
+        &["--auto-create-missing-references", "() ((a: b b))"],
+    )?)?;
+    assert!(!fs::read_to_string(&narrow_path)?.starts_with("#! address-width"));
+    ensure_success(&clink(
+        &narrow_path,
+        &["--address-width", "64", "() ((c: a a))"],
+    )?)?;
+    assert!(fs::read_to_string(&narrow_path)?.starts_with("#! address-width 64\n"));
+    let output = clink(&narrow_path, &["names"])?;
+    ensure_success(&output)?;
+    assert_eq!(String::from_utf8(output.stdout)?, "1 b\n2 a\n3 c\n");
+
+    assert!(
+        !clink(&narrow_path, &["--address-width", "32", "() ((d: a a))"])?
+            .status
+            .success()
+    );
+    assert!(
+        !clink(&db_path, &["--address-width", "16", "() ((d: a a))"])?
+            .status
+            .success()
+    );
+    Ok(())
+}
+
+#[test]
+fn subcommand_help_is_printed() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("help.links");
+
+    let output = clink(&db_path, &["help", "export"])?;
+    ensure_success(&output)?;
+    assert!(String::from_utf8(output.stdout)?.starts_with("Print the database as LiNo"));
+    Ok(())
+}
+
+fn clink(db_path: &Path, args: &[&str]) -> Result<Output> {
+    Ok(Command::new(env!("CARGO_BIN_EXE_clink"))
+        .arg("--db")
+        .arg(db_path)
+        .args(args)
+        .output()?)
+}
+
+fn ensure_success(output: &Output) -> Result<()> {
+    ensure!(
+        output.status.success(),
+        "clink failed\nstdout:\n{}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! Helpers shared by the integration tests.
+
+// Each test crate uses only some of the helpers.
+#![allow(dead_code)]
+
+use anyhow::Result;
+use link_cli::{ChangeHistory, ChangeRecorder, HistoryEntry, NamedTypesDecorator, QueryProcessor};
+use std::path::Path;
+
+fn processor() -> QueryProcessor {
+    QueryProcessor::new(false)
+        .with_auto_create_missing_references(true)
+        .with_auto_save(false)
+}
+
+/// Opens the database at `path` and runs `queries` on it without saving.
+pub fn database(path: &Path, queries: &[&str]) -> Result<NamedTypesDecorator> {
+    let mut storage = NamedTypesDecorator::new(path, false)?;
+    let processor = processor();
+    for query in queries {
+        processor.process_query(&mut storage, query)?;
+    }
+    Ok(storage)
+}
+
+/// Runs `query` on the database at `path` and saves it.
+pub fn apply(path: &Path, query: &str) -> Result<()> {
+    database(path, &[query])?.save()
+}
+
+/// Runs `query` on `storage` and records its changes in `history`.
+pub fn record(
+    storage: &mut NamedTypesDecorator,
+    history: &mut ChangeHistory,
+    query: &str,
+) -> Result<()> {
+    let mut recorder = ChangeRecorder::new(storage);
+    processor().process_query(&mut recorder, query)?;
+    history.record(HistoryEntry {
+        query: query.to_string(),
+        changes: recorder.into_changes(),
+    });
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use anyhow::Result;
+use link_cli::{DatabaseChecker, IntegrityIssue, NamedTypes, NamedTypesDecorator, ReferenceRole};
+use std::path::Path;
+use tempfile::tempdir;
+
+fn write_names(db_path: &Path, names: &[(u32, &str)]) -> Result<()> {
+    let mut storage = NamedTypesDecorator::new(db_path, false)?;
+    for (link, name) in names {
+        storage.set_name(*link, name)?;
+    }
+    storage.names_links().save()
+}
+
+#[test]
+fn reports_every_kind_of_issue() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("broken.links");
+    write_names(&db_path, &[(1, "one"), (7, "ghost")])?;
+    std::fs::write(&db_path, "(1 1 1)\n(2 1 9)\nnot a link\n(3 1 1)\n")?;
+
+    let report = DatabaseChecker::new(&db_path).check()?;
+
+    assert_eq!(
+        report.issues,
+        vec![
+            IntegrityIssue::MalformedLine {
+                path: db_path.clone(),
+                line: 3,
+                text: "not a link".to_string(),
+            },
+            IntegrityIssue::DanglingReference {
+                link: 2,
+                role: ReferenceRole::Target,
+                reference: 9,
+            },
+            IntegrityIssue::DuplicatePair {
+                link: 3,
+                original: 1,
+            },
+            IntegrityIssue::OrphanName {
+                link: 7,
+                name: "ghost".to_string(),
+            },
+        ]
+    );
+    assert!(!report.is_clean());
+    Ok(())
+}
+
+#[test]
+fn reports_broken_pinned_types_of_the_names_database() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("pinned.links");
+    let names_path = NamedTypesDecorator::make_names_database_filename(&db_path);
+    std::fs::write(&db_path, "(1 1 1)\n")?;
+    std::fs::write(&names_path, "(1 1 1)\n(2 1 2)\n(3 3 3)\n")?;
+
+    let report = DatabaseChecker::new(&db_path).check()?;
+
+    assert_eq!(report.issues.len(), 4);
+    assert_eq!(
+        report.issues[0].to_string(),
+        "names database: pinned type 3 should be (3: 1 3), found (3: 3 3)"
+    );
+    assert!(report.issues[1].to_string().ends_with("found nothing"));
+    Ok(())
+}
+
+#[test]
+fn repair_merges_duplicates_and_removes_orphan_names() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("repair.links");
+    std::fs::write(&db_path, "(1 1 1)\n(2 2 2)\n(3 1 2)\n(4 1 2)\n(5 4 4)\n")?;
+    write_names(&db_path, &[(4, "copy"), (8, "ghost")])?;
+
+    let report = DatabaseChecker::new(&db_path).repair()?;
+    assert_eq!(report.repaired.len(), 2);
+    assert!(report.is_clean());
+
+    assert_eq!(
+        std::fs::read_to_string(&db_path)?,
+        "(1 1 1)\n(2 2 2)\n(3 1 2)\n(5 3 3)\n"
+    );
+    let mut storage = NamedTypesDecorator::new(&db_path, false)?;
+    assert_eq!(storage.get_by_name("copy")?, Some(3));
+    assert_eq!(storage.get_by_name("ghost")?, None);
+    assert!(DatabaseChecker::new(&db_path).check()?.issues.is_empty());
+    Ok(())
+}
+
+#[test]
+fn repair_merges_links_that_become_duplicates() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("cascade.links");
+    std::fs::write(
+        &db_path,
+        "(1 1 1)\n(2 2 2)\n(3 1 2)\n(4 1 2)\n(5 3 3)\n(6 4 4)\n(7 5 6)\n",
+    )?;
+    write_names(&db_path, &[(7, "top")])?;
+
+    let report = DatabaseChecker::new(&db_path).repair()?;
+
+    assert_eq!(
+        report.repaired,
+        vec![
+            IntegrityIssue::DuplicatePair {
+                link: 4,
+                original: 3,
+            },
+            IntegrityIssue::DuplicatePair {
+                link: 6,
+                original: 5,
+            },
+        ]
+    );
+    assert!(report.is_clean());
+    assert_eq!(
+        std::fs::read_to_string(&db_path)?,
+        "(1 1 1)\n(2 2 2)\n(3 1 2)\n(5 3 3)\n(7 5 5)\n"
+    );
+    assert!(DatabaseChecker::new(&db_path).check()?.issues.is_empty());
+    Ok(())
+}
+
+#[test]
+fn repair_leaves_duplicates_alone_while_names_cannot_be_read() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("unreadable.links");
+    let names_path = NamedTypesDecorator::make_names_database_filename(&db_path);
+    let content = "(1 1 1)\n(2 2 2)\n(3 1 2)\n(4 1 2)\n";
+    std::fs::write(&db_path, content)?;
+    std::fs::write(&names_path, "(1 1 1)\n(2 1 2)\n(3 3 3)\n")?;
+
+    let report = DatabaseChecker::new(&db_path).repair()?;
+
+    assert!(report.repaired.is_empty());
+    assert!(report.issues.contains(&IntegrityIssue::DuplicatePair {
+        link: 4,
+        original: 3,
+    }));
+    assert_eq!(std::fs::read_to_string(&db_path)?, content);
+    Ok(())
+}
+
+#[test]
+fn repair_keeps_duplicates_with_different_names() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("named.links");
+    let content = "(1 1 1)\n(2 2 2)\n(3 1 2)\n(4 1 2)\n(5 3 4)\n";
+    std::fs::write(&db_path, content)?;
+    write_names(&db_path, &[(3, "a"), (4, "b"), (5, "c")])?;
+
+    let report = DatabaseChecker::new(&db_path).repair()?;
+
+    assert!(report.repaired.is_empty());
+    assert_eq!(
+        report.issues[0].to_string(),
+        "link 4 ('b') duplicates link 3 ('a'); rename or delete one of them"
+    );
+    assert!(!report.issues[0].is_repairable());
+    assert_eq!(std::fs::read_to_string(&db_path)?, content);
+    let mut storage = NamedTypesDecorator::new(&db_path, false)?;
+    assert_eq!(storage.get_by_name("b")?, Some(4));
+    Ok(())
+}
+
+#[test]
+fn repair_leaves_files_with_malformed_lines_untouched() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("malformed.links");
+    let content = "(1 1 1)\n(2 1 1)\n(3 oops)\n";
+    std::fs::write(&db_path, content)?;
+
+    let report = DatabaseChecker::new(&db_path).repair()?;
+
+    assert!(report.repaired.is_empty());
+    assert_eq!(report.remaining().len(), 2);
+    assert_eq!(std::fs::read_to_string(&db_path)?, content);
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use link_cli::{DatabaseCompactor, Link, MovedLink, NamedTypeLinks};
+use tempfile::tempdir;
+
+mod common;
+
+use common::database;
+
+#[test]
+fn compaction_closes_gaps_and_moves_names_and_references() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut storage = database(
+        &temp_dir.path().join("gaps.links"),
+        &[
+            "() ((a: a a))",
+            "() ((b: b b))",
+            "() ((1 2))",
+            "() ((2 1))",
+            "((3: 1 2)) ()",
+            "((4: 2 1)) ()",
+            "() ((c: a b))",
+            "() ((c b))",
+        ],
+    )?;
+
+    let report = DatabaseCompactor::new(&mut storage).compact()?;
+
+    assert_eq!(report.pinned_types, 1);
+    assert_eq!(
+        report.moved,
+        vec![
+            MovedLink {
+                old_id: 5,
+                new_id: 3
+            },
+            MovedLink {
+                old_id: 6,
+                new_id: 4
+            },
+        ]
+    );
+    let mut links = storage.all_links();
+    links.sort_by_key(|link| link.index);
+    assert_eq!(
+        links,
+        vec![
+            Link::new(1, 1, 1),
+            Link::new(2, 2, 2),
+            Link::new(3, 1, 2),
+            Link::new(4, 3, 2),
+        ]
+    );
+    assert_eq!(NamedTypeLinks::get_by_name(&mut storage, "c")?, Some(3));
+    assert_eq!(NamedTypeLinks::get_name(&mut storage, 5)?, None);
+    assert_eq!(storage.create(0, 0), 5);
+    Ok(())
+}
+
+#[test]
+fn compaction_refuses_dangling_references() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut storage = database(
+        &temp_dir.path().join("dangling.links"),
+        &["() ((1 1))", "() ((2 2))", "() ((3 2))", "((2: 2 2)) ()"],
+    )?;
+
+    let error = DatabaseCompactor::new(&mut storage).compact().unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Link 3 refers to missing link 2; it cannot be compacted."
+    );
+    assert_eq!(storage.get_link(3), Some(Link::new(3, 3, 2)));
+    Ok(())
+}
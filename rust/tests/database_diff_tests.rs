@@ -0,0 +1,96 @@
+use anyhow::Result;
+use link_cli::{DatabaseDiff, NamedTypeLinks};
+use tempfile::tempdir;
+
+mod common;
+
+use common::database;
+
+#[test]
+fn id_matching_reports_renames_and_new_links() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut before = database(&temp_dir.path().join("a.links"), &["() ((a: a a))"])?;
+    let mut after = database(
+        &temp_dir.path().join("b.links"),
+        &["() ((b: b b))", "() ((2 2))"],
+    )?;
+
+    let lines = DatabaseDiff::new(&mut before, &mut after).lino_lines()?;
+    assert_eq!(lines, vec!["((a: a a)) ((b: b b))", "() ((2: 2 2))"]);
+    Ok(())
+}
+
+#[test]
+fn name_matching_ignores_different_ids() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let queries = [
+        "() ((father: father father))",
+        "() ((mother: mother mother))",
+        "() ((father mother))",
+    ];
+    let mut before = database(&temp_dir.path().join("a.links"), &queries)?;
+    let mut same = database(&temp_dir.path().join("same.links"), &queries)?;
+    let mut after = database(
+        &temp_dir.path().join("b.links"),
+        &[
+            "() ((child: child child))",
+            "() ((mother: mother mother))",
+            "() ((father: father father))",
+            "() ((father mother))",
+            "() ((mother father))",
+        ],
+    )?;
+
+    let identical = DatabaseDiff::new(&mut before, &mut same)
+        .with_match_by_name(true)
+        .changes()?;
+    assert!(identical.is_empty());
+
+    let lines = DatabaseDiff::new(&mut before, &mut after)
+        .with_match_by_name(true)
+        .lino_lines()?;
+    assert_eq!(
+        lines,
+        vec!["() ((child: child child))", "() ((5: mother father))",]
+    );
+
+    // Matching by id sees every link except `mother` as changed.
+    let by_id = DatabaseDiff::new(&mut before, &mut after).changes()?;
+    assert_eq!(by_id.len(), 4);
+    Ok(())
+}
+
+#[test]
+fn name_matching_compares_external_strings_by_content() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut before = database(&temp_dir.path().join("a.links"), &[])?;
+    let (hello, world) = (
+        before.create_external_string("hello")?,
+        before.create_external_string("world")?,
+    );
+    before.create(hello, world);
+    let farewell = before.create(world, world);
+
+    let mut after = database(&temp_dir.path().join("b.links"), &[])?;
+    after.create_external_string("padding")?;
+    let (world, hello, moon) = (
+        after.create_external_string("world")?,
+        after.create_external_string("hello")?,
+        after.create_external_string("moon")?,
+    );
+    assert_ne!(hello, before.create_external_string("hello")?);
+    after.create(hello, world);
+    let changed = after.create(moon, moon);
+
+    let changes = DatabaseDiff::new(&mut before, &mut after)
+        .with_match_by_name(true)
+        .changes()?;
+    assert_eq!(
+        changes,
+        vec![
+            (before.get_link(farewell), None),
+            (None, after.get_link(changed)),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use link_cli::{DatabaseMerge, JsonDocuments, Link, MergeOutcome, NamedTypeLinks};
+use serde_json::json;
+use tempfile::tempdir;
+
+mod common;
+
+use common::database;
+
+#[test]
+fn merge_unifies_names_and_deduplicates_pairs() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut target = database(
+        &temp_dir.path().join("target.links"),
+        &["() ((child: father mother))", "() ((9 9))"],
+    )?;
+    let mut source = database(
+        &temp_dir.path().join("source.links"),
+        &[
+            "() ((mother: mother mother))",
+            "() ((father: father father))",
+            "() ((father mother))",
+            "() ((sibling: mother father))",
+        ],
+    )?;
+    let target_father = target.get_by_name("father")?.unwrap();
+    let target_mother = target.get_by_name("mother")?.unwrap();
+
+    let report = DatabaseMerge::new(&mut target, &mut source).merge()?;
+
+    assert_eq!(report.mapped_id(1), Some(target_mother));
+    assert_eq!(report.mapped_id(2), Some(target_father));
+    assert_eq!(report.mapped_id(3), target.get_by_name("child")?);
+    assert_eq!(report.count(MergeOutcome::UnifiedByName), 2);
+    assert_eq!(report.count(MergeOutcome::Deduplicated), 1);
+    assert_eq!(report.count(MergeOutcome::Created), 1);
+
+    let sibling = target.get_by_name("sibling")?.unwrap();
+    assert_eq!(report.mapped_id(4), Some(sibling));
+    assert_eq!(
+        target.get_link(sibling),
+        Some(Link::new(sibling, target_mother, target_father))
+    );
+    Ok(())
+}
+
+#[test]
+fn merge_remaps_points_and_cycles() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut target = database(&temp_dir.path().join("target.links"), &["() ((1 1) (2 2))"])?;
+    let mut source = database(&temp_dir.path().join("source.links"), &["() ((1 1) (2 2))"])?;
+    // Links 1 and 2 refer to each other.
+    source.update(1, 1, 2)?;
+    source.update(2, 2, 1)?;
+
+    let report = DatabaseMerge::new(&mut target, &mut source).merge()?;
+
+    assert_eq!(report.count(MergeOutcome::Created), 2);
+    let first = report.mapped_id(1).unwrap();
+    let second = report.mapped_id(2).unwrap();
+    assert!(first > 2 && second > 2);
+    assert_eq!(
+        target.get_link(first),
+        Some(Link::new(first, first, second))
+    );
+    assert_eq!(
+        target.get_link(second),
+        Some(Link::new(second, second, first))
+    );
+    Ok(())
+}
+
+#[test]
+fn merge_rejects_dangling_references() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut target = database(&temp_dir.path().join("target.links"), &[])?;
+    let mut source = database(&temp_dir.path().join("source.links"), &["() ((1 1))"])?;
+    source.update(1, 1, 7)?;
+
+    let error = DatabaseMerge::new(&mut target, &mut source)
+        .merge()
+        .unwrap_err();
+    assert!(error.to_string().contains("refers to missing link 7"));
+    Ok(())
+}
+
+#[test]
+fn merge_recreates_strings_and_carries_aliases() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut target = database(&temp_dir.path().join("target.links"), &[])?;
+    JsonDocuments::new(&mut target)?.import(&json!(["unrelated", "strings"]))?;
+    let mut source = database(
+        &temp_dir.path().join("source.links"),
+        &["() ((point: point point))"],
+    )?;
+    let document = json!({"greeting": "hello", "count": 3});
+    let root = JsonDocuments::new(&mut source)?.import(&document)?;
+    let point = source.get_by_name("point")?.unwrap();
+    source.add_alias(point, "dot")?;
+
+    let report = DatabaseMerge::new(&mut target, &mut source).merge()?;
+
+    let merged_root = report.mapped_id(root).unwrap();
+    assert_eq!(
+        JsonDocuments::new(&mut target)?.export(merged_root)?,
+        document
+    );
+    let merged_point = report.mapped_id(point).unwrap();
+    assert_eq!(target.aliases(merged_point)?, vec!["dot".to_string()]);
+    assert_eq!(target.get_by_name("dot")?, Some(merged_point));
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Tests for filtered LiNo export of a database subset.
+
+use anyhow::Result;
+use link_cli::{ExportFilter, NamedTypeLinks, NamedTypesDecorator, QueryProcessor};
+use tempfile::NamedTempFile;
+
+fn with_family(test: impl FnOnce(&mut NamedTypesDecorator) -> Result<()>) -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let db_path = db_file.path().to_str().unwrap();
+    let names_path = names_file.path().to_str().unwrap();
+    let mut storage = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    QueryProcessor::new(false)
+        .with_auto_create_missing_references(true)
+        .process_query(
+            &mut storage,
+            "() ((child: father mother) (other: x y) (grand: child other))",
+        )?;
+    test(&mut storage)
+}
+
+#[test]
+fn empty_filter_exports_every_link() -> Result<()> {
+    with_family(|storage| {
+        assert_eq!(
+            storage.lino_lines()?,
+            storage.filtered_lino_lines(&ExportFilter::new())?
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn restriction_pattern_exports_only_matching_links() -> Result<()> {
+    with_family(|storage| {
+        let filter = ExportFilter::new().with_restriction(Some("((child *))".to_string()));
+
+        assert_eq!(
+            vec!["(grand: child other)".to_string()],
+            storage.filtered_lino_lines(&filter)?
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn roots_with_dependencies_export_the_transitive_closure() -> Result<()> {
+    with_family(|storage| {
+        let child = storage.get_by_name("child")?.unwrap();
+
+        let roots_only = ExportFilter::new().with_roots(vec![child]);
+        let closure = roots_only.clone().with_dependencies(true);
+
+        assert_eq!(
+            vec!["(child: father mother)".to_string()],
+            storage.filtered_lino_lines(&roots_only)?
+        );
+        assert_eq!(
+            vec![
+                "(father: father father)".to_string(),
+                "(mother: mother mother)".to_string(),
+                "(child: father mother)".to_string(),
+            ],
+            storage.filtered_lino_lines(&closure)?
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn missing_root_is_reported() -> Result<()> {
+    with_family(|storage| {
+        let error = ExportFilter::new()
+            .with_roots(vec![999])
+            .select(storage)
+            .expect_err("missing root should fail");
+
+        assert!(error.to_string().contains("999"));
+        Ok(())
+    })
+}
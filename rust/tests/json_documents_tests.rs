@@ -0,0 +1,136 @@
+//! Tests for JSON document import and export.
+
+use anyhow::{ensure, Result};
+use link_cli::{JsonDocuments, NamedTypes, NamedTypesDecorator};
+use serde_json::json;
+use std::process::Command;
+use tempfile::{tempdir, NamedTempFile};
+
+fn with_documents(test: impl FnOnce(&mut JsonDocuments<'_>) -> Result<()>) -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let db_path = db_file.path().to_str().unwrap();
+    let names_path = names_file.path().to_str().unwrap();
+    let mut storage = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    let mut documents = JsonDocuments::new(&mut storage)?;
+    test(&mut documents)
+}
+
+#[test]
+fn import_and_export_round_trips_all_value_kinds() -> Result<()> {
+    with_documents(|documents| {
+        let document = json!({
+            "name": "clink",
+            "version": 2.5,
+            "count": -3,
+            "enabled": true,
+            "disabled": false,
+            "missing": null,
+            "empty": "",
+            "tags": ["links", "doublets", "links"],
+            "nested": { "list": [], "object": {} }
+        });
+
+        let root = documents.import(&document)?;
+
+        assert_eq!(document, documents.export(root)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn identical_documents_share_the_same_root_link() -> Result<()> {
+    with_documents(|documents| {
+        let first = documents.import(&json!({ "a": [1, 2, 3], "b": "text" }))?;
+        let second = documents.import(&json!({ "a": [1, 2, 3], "b": "text" }))?;
+        let different = documents.import(&json!({ "a": [1, 2, 3], "b": "other" }))?;
+
+        assert_eq!(first, second);
+        assert_ne!(first, different);
+        Ok(())
+    })
+}
+
+#[test]
+fn keys_keep_their_order_and_are_named_point_links() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let mut storage =
+        NamedTypesDecorator::with_names_database_path(db_file.path(), names_file.path(), false)?;
+    let text = r#"{"zebra":1,"123":{"b":true,"a":null},"apple":"zebra"}"#;
+    let document: serde_json::Value = serde_json::from_str(text)?;
+
+    let mut documents = JsonDocuments::new(&mut storage)?;
+    let root = documents.import(&document)?;
+    let exported = documents.export(root)?;
+
+    assert_eq!(serde_json::to_string(&exported)?, text);
+    for key in ["zebra", "123", "apple", "a"] {
+        let link = storage.get_by_name(key)?.expect("keys are named");
+        assert!(storage.get(link).is_some_and(|link| link.is_full_point()));
+    }
+    Ok(())
+}
+
+#[test]
+fn single_element_and_nested_arrays_round_trip() -> Result<()> {
+    with_documents(|documents| {
+        let document = json!([[1], [[true]], [null, "x"], [{ "k": [] }]]);
+
+        let root = documents.import(&document)?;
+
+        assert_eq!(document, documents.export(root)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn export_rejects_links_that_are_not_json_values() -> Result<()> {
+    with_documents(|documents| {
+        let root = documents.import(&json!({ "key": 1 }))?;
+
+        let error = documents
+            .export(root + 100)
+            .expect_err("missing link should fail");
+
+        assert!(error.to_string().contains("not found"));
+        Ok(())
+    })
+}
+
+#[test]
+fn cli_imports_and_exports_json_documents() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("documents.links");
+    let json_path = temp_dir.path().join("document.json");
+    std::fs::write(&json_path, r#"{"title": "Links", "pages": [1, 2]}"#)?;
+
+    let import = Command::new(env!("CARGO_BIN_EXE_clink"))
+        .arg("--db")
+        .arg(&db_path)
+        .arg("import")
+        .arg("--json")
+        .arg(&json_path)
+        .output()?;
+    ensure!(
+        import.status.success(),
+        "import failed: {}",
+        String::from_utf8_lossy(&import.stderr)
+    );
+    let root = String::from_utf8(import.stdout)?.trim().to_string();
+
+    let export = Command::new(env!("CARGO_BIN_EXE_clink"))
+        .arg("--db")
+        .arg(&db_path)
+        .args(["export", "--json", &root])
+        .output()?;
+    ensure!(
+        export.status.success(),
+        "export failed: {}",
+        String::from_utf8_lossy(&export.stderr)
+    );
+    let exported: serde_json::Value = serde_json::from_slice(&export.stdout)?;
+
+    assert_eq!(json!({ "title": "Links", "pages": [1, 2] }), exported);
+    Ok(())
+}
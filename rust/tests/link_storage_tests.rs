@@ -1,7 +1,13 @@
 //! Tests for the LinkStorage module
 
 use anyhow::Result;
-use link_cli::LinkStorage;
+use link_cli::{
+    external_reference, external_reference_value, DoubletStorage, FreeIdRange, IdAllocation,
+    InvalidationHook, LinkError, LinkStorage, LoadMode, LoadWarning,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
 use tempfile::NamedTempFile;
 
 #[test]
@@ -225,3 +231,309 @@ fn test_write_lino_output_writes_complete_database() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_strict_load_reports_malformed_line() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    std::fs::write(db_path, "(1 1 1)\n# comment\n(2 1\n(3 3 3)\n")?;
+
+    let error = match LinkStorage::new(db_path, false) {
+        Ok(_) => panic!("strict load should fail on a malformed line"),
+        Err(error) => error,
+    };
+
+    match error.downcast_ref::<LinkError>() {
+        Some(LinkError::InvalidFormat { path, line, text }) => {
+            assert_eq!(path, db_path);
+            assert_eq!(*line, 3);
+            assert_eq!(text, "(2 1");
+        }
+        other => panic!("expected InvalidFormat, got {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_strict_load_rejects_unbalanced_lines_and_extra_values() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    for malformed in [
+        "(4 1 2",
+        "4 1 2)",
+        "((4 1 2)",
+        "(3 1 2 junk more)",
+        "(3 1 2 \"name\" more)",
+        "(3 1 2 \"unterminated)",
+    ] {
+        std::fs::write(db_path, format!("(1 1 1)\n{malformed}\n"))?;
+        match LinkStorage::new(db_path, false) {
+            Ok(_) => panic!("strict load should reject {malformed:?}"),
+            Err(error) => assert!(
+                matches!(
+                    error.downcast_ref::<LinkError>(),
+                    Some(LinkError::InvalidFormat { line: 2, .. })
+                ),
+                "unexpected error for {malformed:?}: {error}"
+            ),
+        }
+    }
+
+    std::fs::write(db_path, "(1 1 1)\n( 2  1 1 \"two words\" )\n")?;
+    let storage = LinkStorage::new(db_path, false)?;
+    assert_eq!(storage.get_name(2).map(String::as_str), Some("two words"));
+
+    Ok(())
+}
+
+#[test]
+fn test_lenient_load_collects_warnings_and_refuses_to_save() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    let content = "(1 1 1)\n(2 1\n(3 3 3)\n";
+    std::fs::write(db_path, content)?;
+
+    let storage = LinkStorage::with_load_mode(db_path, false, LoadMode::Lenient)?;
+
+    assert_eq!(storage.all().len(), 2);
+    assert_eq!(
+        storage.load_warnings(),
+        [LoadWarning {
+            path: db_path.to_string(),
+            line: 2,
+            text: "(2 1".to_string(),
+        }]
+    );
+    assert!(storage.save().is_err());
+    assert_eq!(std::fs::read_to_string(db_path)?, content);
+
+    Ok(())
+}
+
+#[test]
+fn test_names_with_quotes_and_line_breaks_survive_a_reload() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    let names = ["say \"hi\"", "C:\\links\\", "two\nlines\r", "(a) \\n"];
+
+    let mut storage = LinkStorage::new(db_path, false)?;
+    for name in names {
+        let id = storage.create(1, 1);
+        storage.set_name(id, name);
+    }
+    storage.save()?;
+    assert!(std::fs::read_to_string(db_path)?.contains("(1 1 1 \"say \\\"hi\\\"\")\n"));
+
+    let storage = LinkStorage::new(db_path, false)?;
+    for (id, name) in (1..).zip(names) {
+        assert_eq!(storage.get_name(id).map(String::as_str), Some(name));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_storage_reuses_deleted_ids_most_recent_first() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+
+    let mut storage = LinkStorage::new(db_path, false)?;
+    storage.set_id_allocation(IdAllocation::ReuseDeleted);
+    for _ in 0..4 {
+        storage.create(0, 0);
+    }
+    storage.delete(1)?;
+    storage.delete(2)?;
+    assert_eq!(storage.create(0, 0), 2);
+
+    // Deleting the highest link also releases the free ids below it.
+    storage.delete(3)?;
+    storage.delete(4)?;
+    assert_eq!(storage.free_ids(), &[FreeIdRange::single(1)]);
+    assert_eq!(storage.create(0, 0), 1);
+    assert_eq!(storage.create(0, 0), 3);
+
+    Ok(())
+}
+
+#[test]
+fn test_storage_persists_free_ids() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+
+    let mut storage = LinkStorage::new(db_path, false)?;
+    storage.set_id_allocation(IdAllocation::ReuseDeleted);
+    storage.ensure_created(4);
+    storage.create(4, 4);
+    storage.save()?;
+    assert_eq!(
+        std::fs::read_to_string(db_path)?,
+        "#! free-ids 1-2\n(3 4 4)\n(4 0 0)\n"
+    );
+
+    let mut storage = LinkStorage::new(db_path, false)?;
+    assert_eq!(storage.id_allocation(), IdAllocation::ReuseDeleted);
+    assert_eq!(storage.create(0, 0), 2);
+
+    storage.set_id_allocation(IdAllocation::Monotonic);
+    assert_eq!(storage.create(0, 0), 5);
+    storage.save()?;
+    assert!(!std::fs::read_to_string(db_path)?.contains("free-ids"));
+
+    Ok(())
+}
+
+#[test]
+fn test_free_ids_are_kept_as_ranges() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    std::fs::write(db_path, "(1 1 1)\n(2000000 1 1)\n")?;
+
+    let mut storage = LinkStorage::new(db_path, false)?;
+    storage.set_id_allocation(IdAllocation::ReuseDeleted);
+    storage.save()?;
+    assert_eq!(
+        std::fs::read_to_string(db_path)?,
+        "#! free-ids 1999999-2\n(1 1 1)\n(2000000 1 1)\n"
+    );
+
+    let mut storage = LinkStorage::new(db_path, false)?;
+    assert_eq!(storage.create(0, 0), 2);
+    storage.ensure_created(3_000_000);
+    assert_eq!(
+        storage.free_ids(),
+        &[
+            FreeIdRange::new(3, 1999999),
+            FreeIdRange::new(2000001, 2999999)
+        ]
+    );
+
+    // Taking an id from the middle frees the ids reused before it again in
+    // the order they were taken.
+    storage.ensure_created(2999990);
+    assert_eq!(
+        storage.free_ids(),
+        &[
+            FreeIdRange::new(3, 1999999),
+            FreeIdRange::new(2000001, 2999989),
+            FreeIdRange::new(2999999, 2999991)
+        ]
+    );
+    assert_eq!(storage.create(0, 0), 2999991);
+
+    Ok(())
+}
+
+#[test]
+fn test_malformed_free_ids_are_rejected() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    std::fs::write(db_path, "#! free-ids 1 x\n(3 3 3)\n")?;
+
+    let error = LinkStorage::new(db_path, false).err().unwrap();
+    assert!(matches!(
+        error.downcast_ref::<LinkError>(),
+        Some(LinkError::InvalidFormat { line: 1, .. })
+    ));
+
+    let storage = LinkStorage::with_load_mode(db_path, false, LoadMode::Lenient)?;
+    assert_eq!(storage.load_warnings().len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_storage_with_u64_addresses_records_the_width() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    let beyond_u32 = u64::from(u32::MAX) + 1;
+
+    let mut storage = DoubletStorage::<u64>::new(db_path, false)?;
+    storage.ensure_created(beyond_u32);
+    let id = storage.create(beyond_u32, beyond_u32);
+    assert_eq!(id, beyond_u32 + 1);
+    storage.save()?;
+    assert_eq!(
+        std::fs::read_to_string(db_path)?,
+        "#! address-width 64\n(4294967296 0 0)\n(4294967297 4294967296 4294967296)\n"
+    );
+
+    let storage = DoubletStorage::<u64>::new(db_path, false)?;
+    assert_eq!(storage.get(id).unwrap().source, beyond_u32);
+
+    let error = LinkStorage::new(db_path, false).err().unwrap();
+    assert!(error
+        .to_string()
+        .contains("stores 64-bit addresses, but was opened with 32-bit addresses"));
+
+    Ok(())
+}
+
+#[test]
+fn test_u32_database_is_widened_only_on_request() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    std::fs::write(db_path, "(1 1 1)\n")?;
+
+    let error = DoubletStorage::<u64>::new(db_path, false).err().unwrap();
+    assert!(error
+        .to_string()
+        .contains("stores 32-bit addresses, but was opened with 64-bit addresses"));
+    assert_eq!(std::fs::read_to_string(db_path)?, "(1 1 1)\n");
+
+    let storage = DoubletStorage::<u64>::widen(db_path, false)?;
+    assert_eq!(storage.get(1).unwrap().target, 1);
+    storage.save()?;
+    assert_eq!(
+        std::fs::read_to_string(db_path)?,
+        "#! address-width 64\n(1 1 1)\n"
+    );
+    assert!(DoubletStorage::<u64>::new(db_path, false).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_widening_re_encodes_external_references() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    let reference = external_reference(7u32);
+    std::fs::write(db_path, format!("(1 1 {reference})\n"))?;
+
+    let storage = DoubletStorage::<u64>::widen(db_path, false)?;
+    let target = storage.get(1).unwrap().target;
+    assert_ne!(target, u64::from(reference));
+    assert_eq!(external_reference_value(target), Some(7));
+    assert_eq!(storage.get(1).unwrap().source, 1);
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct ChangedLinks(Vec<u32>);
+
+impl InvalidationHook<u32> for ChangedLinks {
+    fn invalidate(&mut self, link: u32) {
+        self.0.push(link);
+    }
+}
+
+#[test]
+fn test_invalidation_hooks_see_updates_deletes_and_moves() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    let mut storage = LinkStorage::new(db_path, false)?;
+    let changed = Rc::new(RefCell::new(ChangedLinks::default()));
+    let hook: Weak<RefCell<ChangedLinks>> = Rc::downgrade(&changed);
+    storage.add_invalidation_hook(hook);
+
+    let first = storage.create(1, 1);
+    let second = storage.create(2, 2);
+    storage.update(first, 2, 2)?;
+    storage.delete(first)?;
+    storage.renumber(&HashMap::from([(second, first)]));
+    assert_eq!(vec![first, first, second, first], changed.borrow().0);
+
+    drop(changed);
+    storage.delete(first)?;
+    Ok(())
+}
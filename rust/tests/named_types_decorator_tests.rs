@@ -1,8 +1,10 @@
 use anyhow::Result;
+use link_cli::sequences::UnicodeSymbolEncoding;
 use link_cli::{
-    LinkStorage, NamedTypes, NamedTypesDecorator, PinnedTypesAccess, PinnedTypesDecorator,
+    LinkStorage, LoadMode, NameSearch, NamedTypes, NamedTypesDecorator, NamesLayout,
+    PinnedTypesAccess, PinnedTypesDecorator, QueryProcessor, UnicodeStringStorage,
 };
-use tempfile::NamedTempFile;
+use tempfile::{tempdir, NamedTempFile};
 
 #[test]
 fn decorator_exposes_link_storage_operations_and_named_types() -> Result<()> {
@@ -117,6 +119,96 @@ fn reassigning_existing_name_moves_name_to_new_link() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn aliases_resolve_to_their_link_and_keep_the_primary_name() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let db_path = db_file.path().to_str().unwrap();
+    let names_path = names_file.path().to_str().unwrap();
+
+    let mut decorator = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    let link = decorator.create(1, 1);
+    let other = decorator.create(2, 2);
+    decorator.set_name(link, "cat")?;
+    decorator.add_alias(link, "kitty")?;
+    decorator.add_alias(link, "felis")?;
+    decorator.set_name(other, "dog")?;
+
+    assert_eq!(Some("cat".to_string()), decorator.get_name(link)?);
+    assert_eq!(Some(link), decorator.get_by_name("kitty")?);
+    assert_eq!(vec!["kitty", "felis"], decorator.aliases(link)?);
+    assert!(decorator.add_alias(other, "kitty").is_err());
+    assert!(decorator.add_alias(other, "cat").is_err());
+
+    decorator.set_name(other, "felis")?;
+    assert_eq!(vec!["kitty"], decorator.aliases(link)?);
+    assert_eq!(Some("cat".to_string()), decorator.get_name(link)?);
+    assert_eq!(Some(other), decorator.get_by_name("felis")?);
+    decorator.save()?;
+
+    let mut reopened = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    assert_eq!(Some(link), reopened.get_by_name("kitty")?);
+    reopened.delete(link)?;
+    assert_eq!(None, reopened.get_by_name("kitty")?);
+    assert_eq!(vec![(other, "felis".to_string())], reopened.all_names()?);
+    assert!(reopened.all_aliases()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn names_of_links_are_searched_without_case() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let db_path = db_file.path().to_str().unwrap();
+    let names_path = names_file.path().to_str().unwrap();
+
+    let mut decorator = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    let string = decorator.create(1, 1);
+    let symbol = decorator.create(2, 2);
+    decorator.set_name(string, "String")?;
+    decorator.set_name(symbol, "unicode symbol")?;
+
+    assert_eq!(
+        vec![(string, "String".to_string())],
+        decorator.search_names(&NameSearch::prefix("str").with_ignore_case(true))?
+    );
+    assert_eq!(
+        vec![(symbol, "unicode symbol".to_string())],
+        decorator.search_names(&NameSearch::substring("Symbol").with_ignore_case(true))?
+    );
+    assert!(decorator
+        .search_names(&NameSearch::substring("Symbol"))?
+        .is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn cached_names_follow_changes_made_directly_to_the_names_database() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let db_path = db_file.path().to_str().unwrap();
+    let names_path = names_file.path().to_str().unwrap();
+
+    let mut decorator = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    let first = decorator.get_or_create(1, 1);
+    let second = decorator.get_or_create(2, 2);
+    decorator.set_name(first, "first")?;
+    assert_eq!(Some(first), decorator.get_by_name("first")?);
+    assert_eq!(None, decorator.get_name(second)?);
+
+    let mut strings = UnicodeStringStorage::new(decorator.names_links_mut())?;
+    strings.remove_name_by_external_reference(first)?;
+    strings.set_name_for_external_reference(second, "second")?;
+
+    assert_eq!(None, decorator.get_by_name("first")?);
+    assert_eq!(Some("second".to_string()), decorator.get_name(second)?);
+    assert_eq!(vec![(second, "second".to_string())], decorator.all_names()?);
+
+    Ok(())
+}
+
 #[test]
 fn default_names_database_path_matches_csharp_convention() {
     assert_eq!(
@@ -155,3 +247,205 @@ fn decorator_can_be_built_from_existing_link_storages() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn names_can_be_kept_in_the_database_file() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("single.links");
+    let db_path = db_path.to_str().unwrap();
+    let names_path = NamedTypesDecorator::make_names_database_filename(db_path);
+
+    let mut decorator = NamedTypesDecorator::new(db_path, false)?;
+    let link = decorator.create(1, 1);
+    let other = decorator.create(link, link);
+    decorator.set_name(link, "kept")?;
+    decorator.add_alias(other, "other")?;
+    let moved = decorator.set_names_layout(NamesLayout::SingleFile)?;
+    decorator.save()?;
+    assert!(!names_path.exists());
+
+    // The pinned types of the names database take the first ids.
+    assert_eq!(
+        vec![link, other],
+        moved.iter().map(|link| link.old_id).collect::<Vec<_>>()
+    );
+    let (link, other) = (moved[0].new_id, moved[1].new_id);
+    let text = std::fs::read_to_string(db_path)?;
+    assert!(text.starts_with("#! names\n(1 1 1)\n"), "{text}");
+    let links = LinkStorage::new(db_path, false)?;
+    assert_eq!(
+        links.get(other).map(|link| (link.source, link.target)),
+        Some((link, link))
+    );
+    links.save()?;
+    assert_eq!(text, std::fs::read_to_string(db_path)?);
+
+    let mut decorator = NamedTypesDecorator::new(db_path, false)?;
+    assert_eq!(NamesLayout::SingleFile, decorator.names_layout());
+    assert_eq!(Some(link), decorator.get_by_name("kept")?);
+    assert_eq!(Some(other), decorator.get_by_name("other")?);
+    assert_eq!(vec![(link, "kept".to_string())], decorator.all_names()?);
+    let mut ids = link_cli::NamedTypeLinks::all_links(&mut decorator)
+        .into_iter()
+        .map(|link| link.index)
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    assert_eq!(vec![link, other], ids);
+
+    assert!(decorator
+        .set_names_layout(NamesLayout::Separate)?
+        .is_empty());
+    decorator.save()?;
+    assert!(names_path.exists());
+    assert!(!std::fs::read_to_string(db_path)?.contains("#! names"));
+    let mut decorator = NamedTypesDecorator::new(db_path, false)?;
+    assert_eq!(NamesLayout::Separate, decorator.names_layout());
+    assert_eq!(2, decorator.all().len());
+    assert_eq!(Some(link), decorator.get_by_name("kept")?);
+    assert_eq!(Some(other), decorator.get_by_name("other")?);
+
+    Ok(())
+}
+
+#[test]
+fn queries_on_a_single_file_database_leave_the_names_alone() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("single.links");
+    let db_path = db_path.to_str().unwrap();
+
+    let mut decorator = NamedTypesDecorator::new(db_path, false)?;
+    decorator.set_names_layout(NamesLayout::SingleFile)?;
+    let link = decorator.create(0, 0);
+    decorator.set_name(link, "kept")?;
+    let names_links = decorator.names_link_ids()?;
+    assert!(!names_links.contains(&link));
+
+    QueryProcessor::new(false).process_query(&mut decorator, "((($i: $s $t)) ())")?;
+    assert!(!decorator.exists(link));
+    // Only the name pair of the deleted link goes.
+    let remaining = decorator.names_link_ids()?;
+    assert!(remaining.is_subset(&names_links));
+    assert!((1..=6).all(|pinned_type| remaining.contains(&pinned_type)));
+    let link = decorator.create(0, 0);
+    decorator.set_name(link, "kept")?;
+    assert_eq!(Some(link), decorator.get_by_name("kept")?);
+    assert!(decorator
+        .set_names_layout(NamesLayout::Separate)?
+        .is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn aliases_reload_in_the_order_they_were_stored() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let db_path = db_file.path().to_str().unwrap();
+    let names_path = names_file.path().to_str().unwrap();
+
+    let mut names_links = LinkStorage::new(names_path, false)?;
+    let mut strings = UnicodeStringStorage::new(&mut names_links)?;
+    for alias in ["b", "a", "c"] {
+        strings.add_alias_for_external_reference(1, alias)?;
+    }
+    // A names database written elsewhere may give one alias to two links.
+    strings.add_alias_for_external_reference(2, "a")?;
+    names_links.save()?;
+
+    for _ in 0..8 {
+        let mut decorator =
+            NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+        assert_eq!(decorator.aliases(1)?, ["b", "a", "c"]);
+        assert_eq!(decorator.get_by_name("a")?, Some(1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn normalized_names_resolve_from_any_spelling() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let db_path = db_file.path().to_str().unwrap();
+    let names_path = names_file.path().to_str().unwrap();
+
+    let mut decorator = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    decorator.set_symbol_encoding(UnicodeSymbolEncoding::NfcCodePoints)?;
+    let link = decorator.get_or_create(1, 2);
+    decorator.set_name(link, "re\u{301}sume\u{301} 📄")?;
+
+    assert_eq!(Some(link), decorator.get_by_name("r\u{e9}sum\u{e9} 📄")?);
+    assert_eq!(
+        Some("r\u{e9}sum\u{e9} 📄".to_string()),
+        decorator.get_name(link)?
+    );
+    assert_eq!(
+        vec![(link, "r\u{e9}sum\u{e9} 📄".to_string())],
+        decorator.search_names(&NameSearch::prefix("re\u{301}s"))?
+    );
+    assert_eq!(
+        vec![(link, "r\u{e9}sum\u{e9} 📄".to_string())],
+        decorator.search_names(&NameSearch::substring("📄"))?
+    );
+
+    decorator.save()?;
+    let mut decorator = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    assert_eq!(
+        UnicodeSymbolEncoding::NfcCodePoints,
+        decorator.symbol_encoding()?
+    );
+    assert_eq!(
+        Some(link),
+        decorator.get_by_name("re\u{301}sume\u{301} 📄")?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn wide_databases_keep_names_and_run_queries() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("wide.links");
+    let beyond_u32 = u64::from(u32::MAX) + 1;
+
+    let mut decorator = NamedTypesDecorator::<u64>::open(&db_path, false, LoadMode::Strict)?;
+    decorator.links_mut().ensure_created(beyond_u32);
+    decorator.set_name(beyond_u32, "far")?;
+    QueryProcessor::<u64>::new(false)
+        .with_auto_create_missing_references(true)
+        .process_query(&mut decorator, "() ((near: far far))")?;
+    decorator.save()?;
+
+    let mut decorator = NamedTypesDecorator::<u64>::open(&db_path, false, LoadMode::Strict)?;
+    let near = decorator.get_by_name("near")?.unwrap();
+    assert_eq!(near, beyond_u32 + 1);
+    assert_eq!(decorator.get_name(beyond_u32)?.as_deref(), Some("far"));
+    assert_eq!(
+        link_cli::NamedTypeLinks::lino_lines(&mut decorator)?,
+        ["(far: 0 0)", "(near: far far)"]
+    );
+    assert!(NamedTypesDecorator::new(&db_path, false).is_err());
+    Ok(())
+}
+
+#[test]
+fn widening_keeps_names_and_external_references() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("widened.links");
+    let mut decorator = NamedTypesDecorator::new(&db_path, false)?;
+    let point = link_cli::NamedTypeLinks::get_or_create_named(&mut decorator, "point")?;
+    let text = link_cli::NamedTypeLinks::create_external_string(&mut decorator, "text")?;
+    let link = decorator.get_or_create(point, text);
+    decorator.save()?;
+
+    let widened = NamedTypesDecorator::<u64>::widen(&db_path, false)?;
+    widened.save()?;
+    let mut decorator = NamedTypesDecorator::<u64>::open(&db_path, false, LoadMode::Strict)?;
+    assert_eq!(decorator.get_by_name("point")?, Some(u64::from(point)));
+    let target = decorator.links().get(u64::from(link)).unwrap().target;
+    assert_eq!(
+        link_cli::NamedTypeLinks::external_string(&mut decorator, target)?.as_deref(),
+        Some("text")
+    );
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use link_cli::{JsonDocuments, NamedTypes, NamedTypesDecorator, NamesCollector};
+use serde_json::json;
+use tempfile::tempdir;
+
+#[test]
+fn replaced_and_removed_names_are_reclaimed() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("gc.links");
+    let mut storage = NamedTypesDecorator::new(&db_path, false)?;
+    let first = storage.create(1, 1);
+    let second = storage.create(2, 2);
+    storage.set_name(first, "zz-9")?;
+    storage.set_name(first, "final")?;
+    storage.set_name(second, "jj_8")?;
+    storage.add_alias(second, "kept")?;
+    storage.remove_name(second)?;
+    let names_links = storage.names_links().all().len();
+
+    let report = NamesCollector::new(&mut storage).collect()?;
+
+    assert_eq!(report.strings, 2);
+    assert_eq!(report.symbols, 6);
+    assert!(report.sequences > 0);
+    assert_eq!(
+        report.reclaimed.len(),
+        report.symbols + report.sequences + report.strings + report.names
+    );
+    assert_eq!(
+        storage.names_links().all().len(),
+        names_links - report.reclaimed.len()
+    );
+    assert_eq!(Some("final".to_string()), storage.get_name(first)?);
+    assert_eq!(Some(second), storage.get_by_name("kept")?);
+    assert_eq!(None, storage.get_by_name("zz-9")?);
+    assert!(NamesCollector::new(&mut storage)
+        .collect()?
+        .reclaimed
+        .is_empty());
+
+    storage.save()?;
+    let mut reopened = NamedTypesDecorator::new(&db_path, false)?;
+    assert_eq!(vec![(first, "final".to_string())], reopened.all_names()?);
+    Ok(())
+}
+
+#[test]
+fn strings_of_json_documents_are_kept() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let mut storage = NamedTypesDecorator::new(temp_dir.path().join("json.links"), false)?;
+    let document = json!({ "title": "Quartz", "size": 12 });
+    let root = JsonDocuments::new(&mut storage)?.import(&document)?;
+
+    NamesCollector::new(&mut storage).collect()?;
+
+    assert_eq!(document, JsonDocuments::new(&mut storage)?.export(root)?);
+    Ok(())
+}
@@ -1,7 +1,7 @@
 //! C# AdvancedMixedQueryProcessor parity tests.
 
 use anyhow::Result;
-use link_cli::{Link, NamedTypes, NamedTypesDecorator, QueryProcessor};
+use link_cli::{FreeIdRange, IdAllocation, Link, NamedTypes, NamedTypesDecorator, QueryProcessor};
 use tempfile::NamedTempFile;
 
 fn with_storage(
@@ -16,6 +16,19 @@ fn with_storage(
     test(&mut storage, &processor)
 }
 
+/// Runs a test against a storage that reuses deleted ids like the C#
+/// `UnitedMemoryLinks` free list.
+fn with_reusing_storage(
+    test: impl FnOnce(&mut NamedTypesDecorator, &QueryProcessor) -> Result<()>,
+) -> Result<()> {
+    with_storage(|storage, processor| {
+        storage
+            .links_mut()
+            .set_id_allocation(IdAllocation::ReuseDeleted);
+        test(storage, processor)
+    })
+}
+
 fn sorted_links(storage: &NamedTypesDecorator) -> Vec<Link> {
     let mut links: Vec<Link> = storage.all().into_iter().copied().collect();
     links.sort_by_key(|link| link.index);
@@ -242,3 +255,53 @@ fn test_string_aliases_in_variable_restriction_constrain_matches_to_named_links_
         Ok(())
     })
 }
+
+#[test]
+fn test_create_after_delete_reuses_the_deleted_id_matches_csharp() -> Result<()> {
+    with_reusing_storage(|storage, processor| {
+        processor.process_query(storage, "(() ((1 1) (2 2) (3 3)))")?;
+        processor.process_query(storage, "(((2: 2 2)) ())")?;
+        processor.process_query(storage, "(() ((1 3)))")?;
+
+        assert_eq!(
+            sorted_links(storage),
+            vec![Link::new(1, 1, 1), Link::new(2, 1, 3), Link::new(3, 3, 3)]
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn test_delete_last_links_shrinks_allocation_matches_csharp() -> Result<()> {
+    with_reusing_storage(|storage, processor| {
+        processor.process_query(storage, "(() ((1 1) (2 2) (3 3)))")?;
+        processor.process_query(storage, "(((2: 2 2)) ())")?;
+        processor.process_query(storage, "(((3: 3 3)) ())")?;
+        processor.process_query(storage, "(() ((1 2)))")?;
+
+        assert!(storage.links().free_ids().is_empty());
+        assert_eq!(
+            sorted_links(storage),
+            vec![Link::new(1, 1, 1), Link::new(2, 1, 2)]
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn test_create_after_explicit_index_gap_reuses_gap_ids_matches_csharp() -> Result<()> {
+    with_reusing_storage(|storage, processor| {
+        processor.process_query(storage, "(() ((4: 4 4)))")?;
+        processor.process_query(storage, "(() ((1: 1 1)))")?;
+        processor.process_query(storage, "(() ((1 4)))")?;
+
+        // `EnsureCreated` frees 1, 2 and 3 in that order and then takes 3, 2
+        // and 1 back to create link 1, freeing 3 and 2 again.
+        assert_eq!(storage.links().free_ids(), &[FreeIdRange::single(3)]);
+        assert_eq!(
+            sorted_links(storage),
+            vec![Link::new(1, 1, 1), Link::new(2, 1, 4), Link::new(4, 4, 4)]
+        );
+        Ok(())
+    })
+}
@@ -1,6 +1,7 @@
 //! Tests for the QueryProcessor module
 
 use anyhow::Result;
+use link_cli::sequences::Sequences;
 use link_cli::{LinkStorage, QueryProcessor};
 use tempfile::NamedTempFile;
 
@@ -459,3 +460,79 @@ fn test_deduplicate_mixed_named_and_numeric() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_contains_restriction_matches_sequences_with_subsequence() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+
+    let mut storage = LinkStorage::new(db_path, false)?;
+    let (element_type, sequence_type) = (storage.create(0, 0), storage.create(0, 0));
+    let items = (0..3).map(|_| storage.create(0, 0)).collect::<Vec<_>>();
+    let sequences = Sequences::new(element_type, sequence_type);
+    let abc = sequences.create(&mut storage, &items);
+    let cb = sequences.create(&mut storage, &[items[2], items[1]]);
+    let processor = QueryProcessor::new(false);
+
+    let read = format!(
+        "(($s: contains {sequence_type} {element_type} ({} {}))) (($s: contains {sequence_type} {element_type} ({} {})))",
+        items[1], items[2], items[1], items[2]
+    );
+    let changes = processor.process_query(&mut storage, &read)?;
+    assert_eq!(1, changes.len());
+    assert_eq!(Some(abc), changes[0].0.map(|link| link.index));
+    assert_eq!(changes[0].0, changes[0].1);
+
+    let matched = processor.find_matching_links(
+        &mut storage,
+        &format!(
+            "((*: contains {sequence_type} {element_type} {}))",
+            items[1]
+        ),
+    )?;
+    assert_eq!(
+        vec![abc, cb],
+        matched.iter().map(|link| link.index).collect::<Vec<_>>()
+    );
+
+    let delete = format!(
+        "((*: contains {sequence_type} {element_type} ({} {}))) ()",
+        items[2], items[1]
+    );
+    let changes = processor.process_query(&mut storage, &delete)?;
+    assert_eq!(1, changes.len());
+    assert!(!storage.exists(cb));
+    assert!(storage.exists(abc));
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_contains_restriction_matches_links_to_sequences() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+
+    let mut storage = LinkStorage::new(db_path, false)?;
+    let (element_type, sequence_type) = (storage.create(0, 0), storage.create(0, 0));
+    let items = (0..3).map(|_| storage.create(0, 0)).collect::<Vec<_>>();
+    let sequences = Sequences::new(element_type, sequence_type);
+    let abc = sequences.create(&mut storage, &items);
+    let cb = sequences.create(&mut storage, &[items[2], items[1]]);
+    let (abc_first, cb_first) = (storage.create(abc, items[0]), storage.create(cb, items[0]));
+    storage.create(items[0], abc);
+    let processor = QueryProcessor::new(false);
+
+    let matched = processor.find_matching_links(
+        &mut storage,
+        &format!(
+            "(($l: ($s: contains {sequence_type} {element_type} {}) *))",
+            items[1]
+        ),
+    )?;
+    assert_eq!(
+        vec![abc_first, cb_first],
+        matched.iter().map(|link| link.index).collect::<Vec<_>>()
+    );
+
+    Ok(())
+}
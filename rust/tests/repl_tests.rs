@@ -0,0 +1,124 @@
+use anyhow::Result;
+use link_cli::{ChangeHistory, NamedTypeLinks, NamedTypesDecorator, Repl};
+use std::io::Cursor;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn run_session(db_path: &Path, input: &str) -> Result<String> {
+    let storage = NamedTypesDecorator::new(db_path, false)?;
+    let mut output = Vec::new();
+    Repl::new(storage)
+        .with_auto_create_missing_references(true)
+        .with_history_file(Some(Repl::make_history_filename(db_path)))
+        .run(Cursor::new(input), &mut output)?;
+    Ok(String::from_utf8(output)?)
+}
+
+#[test]
+fn multi_line_queries_are_applied_and_printed() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("repl.links");
+
+    let output = run_session(&db_path, "() (\n  (child: father mother)\n)\n.links\n")?;
+
+    assert!(
+        output.contains("clink>   ...>   ...> () ((father: father father))"),
+        "{output}"
+    );
+    assert!(output.contains("() ((child: father mother))\n"), "{output}");
+    assert!(output
+        .contains("(father: father father)\n(mother: mother mother)\n(child: father mother)\n"));
+    Ok(())
+}
+
+#[test]
+fn undo_reverts_the_last_query_before_saving() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("undo.links");
+
+    let output = run_session(
+        &db_path,
+        "() ((a: a a))\n() ((b: b b))\n.undo\n.undo\n.undo\n",
+    )?;
+    assert!(output.contains("Reverted 3 change(s)."), "{output}");
+    assert!(output.contains("Nothing to undo."), "{output}");
+
+    let mut storage = NamedTypesDecorator::new(&db_path, false)?;
+    assert!(storage.lino_lines()?.is_empty());
+    assert_eq!(storage.get_by_name("a")?, None);
+    Ok(())
+}
+
+#[test]
+fn failed_queries_leave_no_changes_behind() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("failed.links");
+
+    // The second query creates b before it fails on the malformed link.
+    let output = run_session(
+        &db_path,
+        "() ((a: a a))\n() ((b: b b) (a: a a a))\n.links\n.undo\n",
+    )?;
+    assert!(output.contains("error: "), "{output}");
+    assert!(output.contains("clink> (a: a a)\nclink> "), "{output}");
+    assert!(output.contains("Reverted 3 change(s)."), "{output}");
+
+    let mut storage = NamedTypesDecorator::new(&db_path, false)?;
+    assert!(storage.lino_lines()?.is_empty());
+    assert_eq!(storage.get_by_name("b")?, None);
+    Ok(())
+}
+
+#[test]
+fn session_saves_once_and_keeps_history() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("save.links");
+
+    let output = run_session(
+        &db_path,
+        "() ((1 1))\n.structure 1\n.bogus\n.exit\n() ((2 2))\n",
+    )?;
+
+    assert!(output.contains("(1 1)\n"), "{output}");
+    assert!(
+        output.contains("error: unknown command '.bogus'"),
+        "{output}"
+    );
+    assert_eq!(std::fs::read_to_string(&db_path)?, "(1 1 1)\n");
+    assert_eq!(
+        std::fs::read_to_string(Repl::make_history_filename(&db_path))?,
+        "() ((1 1))\n.structure 1\n.bogus\n.exit\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn applied_queries_are_recorded_for_clink_undo() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("history.links");
+    let history_path = ChangeHistory::make_history_filename(&db_path);
+    let storage = NamedTypesDecorator::new(&db_path, false)?;
+    Repl::new(storage)
+        .with_auto_create_missing_references(true)
+        .with_change_history(Some(ChangeHistory::load(&history_path)?))
+        .run(
+            Cursor::new("() ((a: a a))\n() (\n  (b: b b)\n)\n.undo\n"),
+            &mut Vec::new(),
+        )?;
+
+    let mut history = ChangeHistory::load(&history_path)?;
+    assert_eq!(
+        ["() ((a: a a))"],
+        history
+            .applied()
+            .iter()
+            .map(|entry| entry.query.as_str())
+            .collect::<Vec<_>>()[..]
+    );
+    assert_eq!(history.undone()[0].query, "() ( (b: b b) )");
+
+    let mut storage = NamedTypesDecorator::new(&db_path, false)?;
+    history.undo(&mut storage, 1)?;
+    assert!(storage.lino_lines()?.is_empty());
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use link_cli::sequences::{LeftSequenceWalker, RightSequenceWalker, Sequences};
+use link_cli::{LinkStorage, PinnedTypes};
+use tempfile::NamedTempFile;
+
+fn with_sequences(test: impl FnOnce(&mut LinkStorage, Sequences) -> Result<()>) -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    let mut links = LinkStorage::new(db_path, false)?;
+    let mut pinned_types = PinnedTypes::new(&mut links);
+    let _type_type = pinned_types.next_type()?;
+    let element_type = pinned_types.next_type()?;
+    let sequence_type = pinned_types.next_type()?;
+    test(&mut links, Sequences::new(element_type, sequence_type))
+}
+
+#[test]
+fn lists_of_links_are_stored_once_and_read_back() -> Result<()> {
+    with_sequences(|links, sequences| {
+        let items = [links.create(0, 0), links.create(0, 0), links.create(0, 0)];
+        let list = [items[0], items[1], items[2], items[0]];
+
+        let sequence = sequences.create(links, &list);
+        let count = links.all().len();
+
+        assert!(sequences.is_sequence(links, sequence));
+        assert_eq!(sequence, sequences.create(links, &list));
+        assert_eq!(count, links.all().len());
+        assert_eq!(list.to_vec(), sequences.read(links, sequence)?);
+        assert_eq!(
+            list.iter().rev().copied().collect::<Vec<_>>(),
+            sequences.read_reversed(links, sequence)?
+        );
+        assert!(sequences.contains(links, sequence, items[2])?);
+        assert!(!sequences.contains(links, sequence, sequence)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn sequences_are_searched_without_creating_links() -> Result<()> {
+    with_sequences(|links, sequences| {
+        let (first, second) = (links.create(0, 0), links.create(0, 0));
+        let sequence = sequences.create(links, &[first, second, first]);
+        let count = links.all().len();
+
+        assert_eq!(
+            Some(sequence),
+            sequences.search(links, &[first, second, first])
+        );
+        assert_eq!(None, sequences.search(links, &[second, first]));
+        assert_eq!(None, sequences.search(links, &[first, second, second]));
+        assert_eq!(count, links.all().len());
+
+        let empty = sequences.create(links, &[]);
+        assert_eq!(Some(empty), sequences.search(links, &[]));
+        assert!(sequences.read(links, empty)?.is_empty());
+        assert!(sequences.read(links, first).is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn sequences_containing_a_subsequence_are_found() -> Result<()> {
+    with_sequences(|links, sequences| {
+        let items = (0..4).map(|_| links.create(0, 0)).collect::<Vec<_>>();
+        let abcd = sequences.create(links, &[items[0], items[1], items[2], items[3]]);
+        let bca = sequences.create(links, &[items[1], items[2], items[0]]);
+        let db = sequences.create(links, &[items[3], items[1]]);
+        let count = links.all().len();
+
+        assert_eq!(
+            vec![abcd, bca, db],
+            sequences.containing(links, &[items[1]])
+        );
+        assert_eq!(
+            vec![abcd, bca],
+            sequences.containing(links, &[items[1], items[2]])
+        );
+        assert_eq!(vec![db], sequences.containing(links, &[items[3], items[1]]));
+        assert!(sequences
+            .containing(links, &[items[0], items[2]])
+            .is_empty());
+        assert!(sequences.containing(links, &[abcd]).is_empty());
+        assert_eq!(vec![abcd, bca, db], sequences.containing(links, &[]));
+        assert_eq!(count, links.all().len());
+        Ok(())
+    })
+}
+
+#[test]
+fn walkers_accept_any_element_criterion() -> Result<()> {
+    with_sequences(|links, _| {
+        let leaves = (0..5).map(|_| links.create(0, 0)).collect::<Vec<_>>();
+        let left = links.create(leaves[0], leaves[1]);
+        let inner = links.create(leaves[3], leaves[4]);
+        let right = links.create(leaves[2], inner);
+        let root = links.create(left, right);
+        let is_leaf = |links: &LinkStorage, link: u32| {
+            links
+                .get(link)
+                .is_some_and(|leaf| leaf.source == 0 && leaf.target == 0)
+        };
+
+        assert_eq!(leaves, RightSequenceWalker::new(is_leaf).walk(links, root));
+        assert_eq!(
+            leaves.iter().rev().copied().collect::<Vec<_>>(),
+            LeftSequenceWalker::new(is_leaf).walk(links, root)
+        );
+        Ok(())
+    })
+}
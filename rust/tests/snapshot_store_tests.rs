@@ -0,0 +1,147 @@
+use anyhow::Result;
+use link_cli::{
+    DatabaseDiff, Link, ListedSnapshot, NamedTypeLinks, NamedTypesDecorator, SnapshotStore,
+};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+mod common;
+
+use common::apply;
+
+fn chunk_count(db_path: &Path) -> Result<usize> {
+    let chunks = SnapshotStore::make_snapshots_directory(db_path).join("chunks");
+    Ok(fs::read_dir(chunks)?.count())
+}
+
+#[test]
+fn restore_brings_back_both_databases() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("db.links");
+    let names_path = temp_dir.path().join("db.names.links");
+    apply(&db_path, "() ((child: father mother))")?;
+    let links = fs::read_to_string(&db_path)?;
+    let names = fs::read_to_string(&names_path)?;
+
+    let store = SnapshotStore::new(&db_path);
+    let info = store.create("pre-migration")?;
+    assert_eq!(info.links, 3);
+    assert!(store.create("pre-migration").is_err());
+
+    apply(&db_path, "((* *)) ()")?;
+    apply(&db_path, "() ((other: other other))")?;
+    store.restore("pre-migration")?;
+
+    assert_eq!(fs::read_to_string(&db_path)?, links);
+    assert_eq!(fs::read_to_string(&names_path)?, names);
+    Ok(())
+}
+
+#[test]
+fn snapshots_share_unchanged_chunks() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("db.links");
+    let lines = (1..=500)
+        .map(|id| format!("({id} {id} {id})\n"))
+        .collect::<String>();
+    fs::write(&db_path, &lines)?;
+
+    let store = SnapshotStore::new(&db_path);
+    store.create("first")?;
+    let first_chunks = chunk_count(&db_path)?;
+    assert!(first_chunks > 1);
+
+    store.create("second")?;
+    assert_eq!(chunk_count(&db_path)?, first_chunks);
+
+    // Removing a line only replaces the chunk that held it.
+    fs::write(&db_path, lines.replacen("(250 250 250)\n", "", 1))?;
+    store.create("third")?;
+    assert_eq!(chunk_count(&db_path)?, first_chunks + 1);
+
+    let names = store
+        .list()?
+        .into_iter()
+        .map(|snapshot| snapshot.name().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["first", "second", "third"]);
+    Ok(())
+}
+
+#[test]
+fn diff_reports_changes_since_a_snapshot() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("db.links");
+    apply(&db_path, "() ((child: father mother))")?;
+    let store = SnapshotStore::new(&db_path);
+    store.create("before")?;
+
+    apply(
+        &db_path,
+        "((child: father mother)) ((child: mother father))",
+    )?;
+    apply(&db_path, "() ((4 4))")?;
+
+    let mut before = store.open("before")?;
+    let mut after = NamedTypesDecorator::new(&db_path, false)?;
+    let mut diff = DatabaseDiff::new(&mut before, &mut after);
+    assert_eq!(
+        diff.changes()?,
+        vec![
+            (Some(Link::new(3, 1, 2)), Some(Link::new(3, 2, 1))),
+            (None, Some(Link::new(4, 4, 4))),
+        ]
+    );
+    assert_eq!(
+        diff.lino_lines()?,
+        vec![
+            "((child: father mother)) ((child: mother father))",
+            "() ((4: 4 4))",
+        ]
+    );
+    assert_eq!(before.get_by_name("child")?, Some(3));
+    Ok(())
+}
+
+#[test]
+fn corrupt_manifests_are_listed_and_refused() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let db_path = temp_dir.path().join("db.links");
+    apply(&db_path, "() ((a: a a))")?;
+    let store = SnapshotStore::new(&db_path);
+    store.create("good")?;
+    store.create("escaping")?;
+
+    let snapshots = SnapshotStore::make_snapshots_directory(&db_path);
+    let manifest_path = snapshots.join("escaping.snapshot.json");
+    let mut manifest: Value = serde_json::from_str(&fs::read_to_string(&manifest_path)?)?;
+    manifest["links"]["chunks"][0] = json!("../../db.links");
+    fs::write(&manifest_path, manifest.to_string())?;
+    fs::write(snapshots.join("broken.snapshot.json"), "{ not json")?;
+
+    let listed = store.list()?;
+    let names = listed
+        .iter()
+        .map(|snapshot| snapshot.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["good", "broken", "escaping"]);
+    assert!(matches!(listed[0], ListedSnapshot::Readable(_)));
+    assert!(listed[2].to_string().contains("Invalid chunk hash"));
+
+    let error = store.restore("escaping").unwrap_err();
+    assert!(error.to_string().contains("Invalid chunk hash"), "{error}");
+    assert!(store.open::<u32>("escaping").is_err());
+    Ok(())
+}
+
+#[test]
+fn snapshot_names_must_be_file_name_safe() {
+    let temp_dir = tempdir().unwrap();
+    let store = SnapshotStore::new(temp_dir.path().join("db.links"));
+    for name in ["", "../escape", ".hidden", "with space"] {
+        assert!(store.create(name).is_err(), "{name:?} should be rejected");
+    }
+    assert!(store.restore("missing").is_err());
+}
@@ -0,0 +1,121 @@
+//! Tests for cycle-safe and depth-limited structure rendering.
+
+use anyhow::Result;
+use link_cli::{
+    LinkStorage, NamedTypeLinks, NamedTypesDecorator, QueryProcessor, StructureFormat,
+    StructureLayout,
+};
+use tempfile::NamedTempFile;
+
+fn with_decorator(test: impl FnOnce(&mut NamedTypesDecorator) -> Result<()>) -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let names_file = NamedTempFile::new()?;
+    let db_path = db_file.path().to_str().unwrap();
+    let names_path = names_file.path().to_str().unwrap();
+    let mut storage = NamedTypesDecorator::with_names_database_path(db_path, names_path, false)?;
+    test(&mut storage)
+}
+
+#[test]
+fn cyclic_links_are_printed_as_back_references() -> Result<()> {
+    with_decorator(|storage| {
+        QueryProcessor::new(false)
+            .with_auto_create_missing_references(true)
+            .process_query(storage, "() ((2: 3 3) (3: 2 2))")?;
+
+        assert_eq!("((^2 ^2) ^3)", storage.format_structure(2)?);
+        assert_eq!("((^3 ^3) ^2)", storage.format_structure(3)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn link_storage_structure_handles_cycles() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let mut storage = LinkStorage::new(db_file.path().to_str().unwrap(), false)?;
+    let first = storage.create(0, 0);
+    let second = storage.create(first, first);
+    storage.update(first, second, second)?;
+
+    assert_eq!("((^1 ^1) ^2)", storage.format_structure(first)?);
+    Ok(())
+}
+
+#[test]
+fn shared_links_are_expanded_only_once() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let mut storage = LinkStorage::new(db_file.path().to_str().unwrap(), false)?;
+    let mut previous = storage.create(0, 0);
+    storage.update(previous, previous, previous)?;
+    for _ in 1..31 {
+        previous = storage.create(previous, previous);
+    }
+
+    let structure = storage.format_structure(previous)?;
+
+    let back_references = (2..=30).map(|id| format!(" ^{id})")).collect::<String>();
+    assert_eq!(
+        format!("{}(1 1){back_references}", "(".repeat(29)),
+        structure
+    );
+    Ok(())
+}
+
+#[test]
+fn deep_chains_do_not_overflow_the_stack() -> Result<()> {
+    let db_file = NamedTempFile::new()?;
+    let mut storage = LinkStorage::new(db_file.path().to_str().unwrap(), false)?;
+    let mut previous = storage.create(0, 0);
+    storage.update(previous, previous, previous)?;
+    for _ in 1..200_000 {
+        previous = storage.create(previous, 1);
+    }
+
+    let structure = storage.format_structure(previous)?;
+
+    assert_eq!(199_999, structure.matches('(').count());
+    assert!(structure.ends_with(" 1)"));
+    Ok(())
+}
+
+#[test]
+fn depth_limit_stops_expanding_nested_links() -> Result<()> {
+    with_decorator(|storage| {
+        let first = storage.create(0, 0);
+        storage.update(first, first, first)?;
+        let second = storage.create(0, 0);
+        storage.update(second, second, second)?;
+        let inner = storage.get_or_create(first, second);
+        let outer = storage.get_or_create(inner, first);
+        let root = storage.get_or_create(outer, inner);
+
+        let unlimited = storage.format_structure(root)?;
+        let depth_zero =
+            storage.format_structure_with(root, &StructureFormat::new().with_max_depth(Some(0)))?;
+        let depth_one =
+            storage.format_structure_with(root, &StructureFormat::new().with_max_depth(Some(1)))?;
+
+        assert_eq!(format!("(((1 2) 1) ^{inner})"), unlimited);
+        assert_eq!(format!("({outer} {inner})"), depth_zero);
+        assert_eq!(format!("(({inner} 1) (1 2))"), depth_one);
+        Ok(())
+    })
+}
+
+#[test]
+fn tree_layout_prints_indented_lino() -> Result<()> {
+    with_decorator(|storage| {
+        QueryProcessor::new(false)
+            .with_auto_create_missing_references(true)
+            .process_query(storage, "() ((child: father mother))")?;
+        let child = storage.get_by_name("child")?.unwrap();
+
+        let tree = storage.format_structure_with(
+            child,
+            &StructureFormat::new().with_layout(StructureLayout::Tree),
+        )?;
+
+        assert_eq!("child:\n  father\n  mother", tree);
+        Ok(())
+    })
+}
@@ -0,0 +1,320 @@
+use anyhow::Result;
+use link_cli::{
+    merge_link_files, merge_separate_files, Doublet, Link, LinkStorage, MergeConflict, NamedTypes,
+    NamedTypesDecorator, NamesLayout, Parser,
+};
+use std::path::Path;
+use tempfile::tempdir;
+
+const BASE: &str = "(1 1 1 \"a\")\n(2 2 2 \"b\")\n(3 1 2)\n";
+
+#[test]
+fn merge_takes_changes_made_on_one_side() -> Result<()> {
+    let ours = "(1 1 1 \"a\")\n(2 2 2 \"b\")\n(3 2 1)\n";
+    let theirs = "(1 1 1 \"alpha\")\n(2 2 2 \"b\")\n(3 1 2)\n";
+
+    let result = merge_link_files(BASE, ours, theirs)?;
+
+    assert!(result.is_clean());
+    assert_eq!(result.text, "(1 1 1 \"alpha\")\n(2 2 2 \"b\")\n(3 2 1)\n");
+    Ok(())
+}
+
+#[test]
+fn links_added_on_both_sides_are_kept_apart_or_unified_by_name() -> Result<()> {
+    let ours = format!("{BASE}(4 1 2)\n(5 5 5 \"c\")\n");
+    let theirs = format!("{BASE}(4 4 4 \"c\")\n(5 2 2)\n(6 5 4)\n");
+
+    let result = merge_link_files(BASE, &ours, &theirs)?;
+
+    assert!(result.is_clean());
+    assert_eq!(result.renumbered, vec![(5, 7)]);
+    assert_eq!(
+        result.text,
+        format!("{BASE}(4 1 2)\n(5 5 5 \"c\")\n(6 7 5)\n(7 2 2)\n")
+    );
+    Ok(())
+}
+
+#[test]
+fn conflicts_keep_our_version_and_stay_loadable() -> Result<()> {
+    let ours = "(1 1 1 \"x\")\n(2 2 2 \"b\")\n(3 2 1)\n";
+    let theirs = "(1 1 1 \"a\")\n(2 2 2 \"x\")\n(3 2 2)\n";
+
+    let result = merge_link_files(BASE, ours, theirs)?;
+
+    assert_eq!(
+        result.conflicts,
+        vec![
+            MergeConflict::Link {
+                index: 3,
+                base: Some((Doublet::new(3, 1, 2), None)),
+                ours: Some((Doublet::new(3, 2, 1), None)),
+                theirs: Some((Doublet::new(3, 2, 2), None)),
+            },
+            MergeConflict::Name {
+                name: "x".to_string(),
+                ours: 1,
+                theirs: 2,
+            },
+        ]
+    );
+    assert_eq!(
+        result.text,
+        concat!(
+            "(1 1 1 \"x\")\n",
+            "(2 2 2)\n",
+            "# conflict: name \"x\" ours on link 1 theirs on link 2\n",
+            "(3 2 1)\n",
+            "# conflict: link 3 base (3 1 2) ours (3 2 1) theirs (3 2 2)\n",
+        )
+    );
+
+    let temp_dir = tempdir()?;
+    let path = temp_dir.path().join("merged.links");
+    std::fs::write(&path, &result.text)?;
+    let storage = LinkStorage::new(path.to_str().unwrap(), false)?;
+    assert_eq!(storage.get(3), Some(&Link::new(3, 2, 1)));
+    Ok(())
+}
+
+/// Saves `text` as a database, applies `edit` to it and returns the saved
+/// database file, followed by its names file in the separate layout.
+fn edit_database(
+    dir: &Path,
+    text: &str,
+    layout: NamesLayout,
+    edit: impl FnOnce(&mut NamedTypesDecorator) -> Result<()>,
+) -> Result<(String, String)> {
+    let path = dir.join("edited.links");
+    let names_path = NamedTypesDecorator::make_names_database_filename(&path);
+    let _ = std::fs::remove_file(&names_path);
+    std::fs::write(&path, text)?;
+    let mut storage = NamedTypesDecorator::new(&path, false)?;
+    storage.set_names_layout(layout)?;
+    edit(&mut storage)?;
+    storage.save()?;
+    let names = std::fs::read_to_string(&names_path).unwrap_or_default();
+    Ok((std::fs::read_to_string(&path)?, names))
+}
+
+fn single_file(
+    dir: &Path,
+    text: &str,
+    edit: impl FnOnce(&mut NamedTypesDecorator) -> Result<()>,
+) -> Result<String> {
+    Ok(edit_database(dir, text, NamesLayout::SingleFile, edit)?.0)
+}
+
+fn open_merged(dir: &Path, text: &str) -> Result<NamedTypesDecorator> {
+    let path = dir.join("merged.links");
+    std::fs::write(&path, text)?;
+    NamedTypesDecorator::new(&path, false)
+}
+
+/// Returns the link with `name`. Single-file databases keep their names
+/// among the links, so the ids of the links depend on the names before them.
+fn named(storage: &mut NamedTypesDecorator, name: &str) -> Result<u32> {
+    Ok(storage
+        .get_by_name(name)?
+        .unwrap_or_else(|| panic!("no link named {name}")))
+}
+
+#[test]
+fn names_follow_links_moved_to_new_ids() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir = temp_dir.path();
+    let base = single_file(dir, "", |storage| {
+        let link = storage.create(1, 1);
+        storage.set_name(link, "a")?;
+        Ok(())
+    })?;
+    let add_named = |name: &'static str| {
+        move |storage: &mut NamedTypesDecorator| {
+            let link = storage.create(1, 1);
+            storage.set_name(link, name)?;
+            Ok(())
+        }
+    };
+    let ours = single_file(dir, &base, add_named("alpha"))?;
+    let theirs = single_file(dir, &base, add_named("beta"))?;
+
+    let result = merge_link_files(&base, &ours, &theirs)?;
+
+    assert!(result.is_clean(), "{:?}", result.conflicts);
+    assert!(result.text.starts_with("#! names\n"), "{}", result.text);
+    let mut merged = open_merged(dir, &result.text)?;
+    let (a, alpha, beta) = (
+        named(&mut merged, "a")?,
+        named(&mut merged, "alpha")?,
+        named(&mut merged, "beta")?,
+    );
+    assert_eq!(result.renumbered, vec![(alpha.into(), beta.into())]);
+    assert_eq!(merged.get(beta), Some(&Link::new(beta, 1, 1)));
+    let mut links = link_cli::NamedTypeLinks::all_links(&mut merged)
+        .into_iter()
+        .map(|link| link.index)
+        .collect::<Vec<_>>();
+    links.sort_unstable();
+    assert_eq!(links, vec![a, alpha, beta]);
+    Ok(())
+}
+
+#[test]
+fn names_and_aliases_are_merged_per_link() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir = temp_dir.path();
+    let base = single_file(dir, "", |storage| {
+        for name in ["a", "b"] {
+            let link = storage.create(1, 1);
+            storage.set_name(link, name)?;
+        }
+        let b = named(storage, "b")?;
+        storage.add_alias(b, "old")?;
+        Ok(())
+    })?;
+    let (a, b) = {
+        let mut storage = open_merged(dir, &base)?;
+        (named(&mut storage, "a")?, named(&mut storage, "b")?)
+    };
+    let ours = single_file(dir, &base, |storage| {
+        storage.add_alias(a, "first")?;
+        storage.set_name(b, "ours")?;
+        Ok(())
+    })?;
+    let theirs = single_file(dir, &base, |storage| {
+        storage.set_name(a, "alpha")?;
+        storage.remove_alias(b, "old")?;
+        storage.set_name(b, "theirs")?;
+        Ok(())
+    })?;
+
+    let result = merge_link_files(&base, &ours, &theirs)?;
+
+    assert_eq!(
+        result.conflicts,
+        vec![MergeConflict::LinkName {
+            index: b.into(),
+            base: Some("b".to_string()),
+            ours: Some("ours".to_string()),
+            theirs: Some("theirs".to_string()),
+        }]
+    );
+    assert!(result.text.contains(&format!(
+        "# conflict: name of link {b} base \"b\" ours \"ours\" theirs \"theirs\"\n"
+    )));
+    let mut merged = open_merged(dir, &result.text)?;
+    assert_eq!(merged.get_name(a)?.as_deref(), Some("alpha"));
+    assert_eq!(merged.aliases(a)?, vec!["first".to_string()]);
+    assert_eq!(merged.get_name(b)?.as_deref(), Some("ours"));
+    assert!(merged.aliases(b)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn separate_names_files_are_merged_with_their_links() -> Result<()> {
+    let temp_dir = tempdir()?;
+    let dir = temp_dir.path();
+    let separate = |text: &str, name: &'static str| {
+        edit_database(dir, text, NamesLayout::Separate, move |storage| {
+            let link = storage.create(1, 1);
+            storage.set_name(link, name)?;
+            Ok(())
+        })
+    };
+    let base = separate("", "a")?;
+    let ours = separate(&base.0, "alpha")?;
+    let theirs = separate(&base.0, "beta")?;
+
+    let (result, names) = merge_separate_files(
+        [&base.0, &base.1],
+        [&ours.0, &ours.1],
+        [&theirs.0, &theirs.1],
+    )?;
+
+    assert!(result.is_clean(), "{:?}", result.conflicts);
+    assert!(!result.text.contains("#! names"));
+    let path = dir.join("merged.links");
+    std::fs::write(&path, &result.text)?;
+    std::fs::write(
+        NamedTypesDecorator::make_names_database_filename(&path),
+        names,
+    )?;
+    let mut merged = NamedTypesDecorator::new(&path, false)?;
+    assert_eq!(merged.get_name(2)?.as_deref(), Some("alpha"));
+    assert_eq!(merged.get_name(3)?.as_deref(), Some("beta"));
+    Ok(())
+}
+
+#[test]
+fn free_ids_of_both_sides_are_merged() -> Result<()> {
+    let base = "#! free-ids\n(1 1 1)\n(2 1 1)\n(3 1 1)\n(4 1 1)\n";
+    let ours = "#! free-ids 2\n(1 1 1)\n(3 1 1)\n(4 1 1)\n";
+    let theirs = "#! free-ids 3\n(1 1 1)\n(2 1 1)\n(4 1 1)\n";
+
+    let result = merge_link_files(base, ours, theirs)?;
+
+    assert!(result.is_clean(), "{:?}", result.conflicts);
+    assert_eq!(result.text, "#! free-ids 2 3\n(1 1 1)\n(4 1 1)\n");
+    Ok(())
+}
+
+#[test]
+fn wide_databases_are_merged_with_their_address_width() -> Result<()> {
+    let base = "#! address-width 64\n(1 1 1)\n";
+    let ours = "#! address-width 64\n(1 1 1)\n(5000000000 1 1)\n";
+    let theirs = "#! address-width 64\n(1 1 1)\n(2 1 1)\n";
+
+    let result = merge_link_files(base, ours, theirs)?;
+
+    assert!(result.is_clean(), "{:?}", result.conflicts);
+    assert_eq!(
+        result.text,
+        "#! address-width 64\n(1 1 1)\n(2 1 1)\n(5000000000 1 1)\n"
+    );
+    let error = merge_link_files(base, "(1 1 1)\n", theirs).unwrap_err();
+    assert!(error.to_string().contains("different address widths"));
+    Ok(())
+}
+
+#[test]
+fn lino_exports_are_merged_by_name_and_number() -> Result<()> {
+    let base = "(a: a a)\n(2: a a)\n";
+    let ours = "(a: a a)\n(2: a a)\n(3: a 2)\n";
+    let theirs = "(a: a a)\n(2: a a)\n(3: 2 a)\n('b c': 3 3)\n";
+
+    let result = merge_link_files(base, ours, theirs)?;
+
+    assert!(result.is_clean());
+    assert_eq!(result.renumbered, vec![(3, 4)]);
+    assert_eq!(
+        result.text,
+        "(a: a a)\n(2: a a)\n(3: a 2)\n(4: 2 a)\n('b c': 4 4)\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn lino_conflicts_are_written_as_lino_links() -> Result<()> {
+    let base = "(a: a a)\n(2: a a)\n";
+    let ours = "(a: a 2)\n";
+    let theirs = "(a: 2 a)\n(2: a a)\n";
+
+    let result = merge_link_files(base, ours, theirs)?;
+
+    assert_eq!(
+        result.conflicts,
+        vec![MergeConflict::LinoLink {
+            id: "a".to_string(),
+            base: Some("(a: a a)".to_string()),
+            ours: Some("(a: a 2)".to_string()),
+            theirs: Some("(a: 2 a)".to_string()),
+        }]
+    );
+    assert_eq!(
+        result.text,
+        "(a: a 2)\n(conflict: (base: (a: a a)) (ours: (a: a 2)) (theirs: (a: 2 a)))\n"
+    );
+    assert_eq!(Parser::new().parse(&result.text)?.len(), 2);
+    Ok(())
+}
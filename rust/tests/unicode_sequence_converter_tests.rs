@@ -1,11 +1,13 @@
 use anyhow::Result;
 use link_cli::sequences::{
-    AddressToRawNumberConverter, BalancedVariantConverter, CachingConverterDecorator,
+    AddressToRawNumberConverter, BalancedVariantConverter, CacheStats, CachingConverterDecorator,
     CharToUnicodeSymbolConverter, RawNumberToAddressConverter, RightSequenceWalker,
     StringToUnicodeSequenceConverter, TargetMatcher, UnicodeSequenceToStringConverter,
-    UnicodeSymbolToCharConverter,
+    UnicodeSymbolEncoding, UnicodeSymbolToCharConverter,
+};
+use link_cli::{
+    external_reference, DoubletStorage, Hybrid, HybridReference, LinkStorage, PinnedTypes,
 };
-use link_cli::{external_reference, HybridReference, LinkStorage, PinnedTypes};
 use std::cell::Cell;
 use tempfile::NamedTempFile;
 
@@ -36,6 +38,9 @@ fn raw_number_converters_match_hybrid_external_reference_encoding() {
     let zero = HybridReference::external(0);
     assert!(zero.is_external());
     assert_eq!(Some(0), zero.absolute_value());
+
+    assert_eq!(u64::MAX, Hybrid::<u64>::external(1).encoded());
+    assert_eq!(1u64, number_to_address.convert(u64::MAX));
 }
 
 #[test]
@@ -119,6 +124,81 @@ fn string_and_unicode_sequence_converters_round_trip_utf16_text() -> Result<()>
     })
 }
 
+#[test]
+fn unicode_sequence_converters_work_with_u64_addresses() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    let mut links = DoubletStorage::<u64>::new(db_path, false)?;
+    let unicode_symbol_type = links.get_or_create_named("UnicodeSymbol");
+    let unicode_sequence_type = links.get_or_create_named("UnicodeSequence");
+    let symbol_matcher = TargetMatcher::new(unicode_symbol_type);
+    let string_to_sequence = StringToUnicodeSequenceConverter::new(
+        CharToUnicodeSymbolConverter::new(AddressToRawNumberConverter::new(), unicode_symbol_type),
+        BalancedVariantConverter::new(),
+        unicode_sequence_type,
+    );
+    let sequence_to_string = UnicodeSequenceToStringConverter::new(
+        TargetMatcher::new(unicode_sequence_type),
+        RightSequenceWalker::new(symbol_matcher),
+        UnicodeSymbolToCharConverter::new(RawNumberToAddressConverter::new(), symbol_matcher),
+        unicode_sequence_type,
+    );
+
+    let sequence = string_to_sequence.convert(&mut links, "wide");
+
+    assert_eq!("wide", sequence_to_string.convert(&links, sequence)?);
+    Ok(())
+}
+
+#[test]
+fn symbol_encodings_split_strings_into_code_units_or_code_points() -> Result<()> {
+    assert_eq!(
+        vec![0x61, 0xD83D, 0xDE00],
+        UnicodeSymbolEncoding::Utf16.symbols("a😀")
+    );
+    assert_eq!(
+        vec![0x61, 0x1F600],
+        UnicodeSymbolEncoding::CodePoints.symbols("a😀")
+    );
+    assert_eq!(
+        vec![0x65, 0x301],
+        UnicodeSymbolEncoding::CodePoints.symbols("e\u{301}")
+    );
+    assert_eq!(
+        vec![0xE9],
+        UnicodeSymbolEncoding::NfcCodePoints.symbols("e\u{301}")
+    );
+
+    with_links(|links| {
+        let (unicode_symbol_type, unicode_sequence_type) = allocate_unicode_types(links)?;
+        let symbol_matcher = TargetMatcher::new(unicode_symbol_type);
+        let string_to_sequence = StringToUnicodeSequenceConverter::new(
+            CharToUnicodeSymbolConverter::new(
+                AddressToRawNumberConverter::new(),
+                unicode_symbol_type,
+            ),
+            BalancedVariantConverter::new(),
+            unicode_sequence_type,
+        );
+        let sequence_to_string = UnicodeSequenceToStringConverter::new(
+            TargetMatcher::new(unicode_sequence_type),
+            RightSequenceWalker::new(symbol_matcher),
+            UnicodeSymbolToCharConverter::new(RawNumberToAddressConverter::new(), symbol_matcher),
+            unicode_sequence_type,
+        );
+
+        let utf16 = string_to_sequence.convert(links, "😀😀");
+        let code_points = string_to_sequence
+            .with_encoding(UnicodeSymbolEncoding::CodePoints)
+            .convert(links, "😀😀");
+
+        assert_ne!(utf16, code_points);
+        assert_eq!("😀😀", sequence_to_string.convert(links, utf16)?);
+        assert_eq!("😀😀", sequence_to_string.convert(links, code_points)?);
+        Ok(())
+    })
+}
+
 #[test]
 fn caching_converter_decorator_reuses_cached_values() -> Result<()> {
     let calls = Cell::new(0);
@@ -139,3 +219,30 @@ fn caching_converter_decorator_reuses_cached_values() -> Result<()> {
     assert_eq!(1, cache.len());
     Ok(())
 }
+
+#[test]
+fn caching_converter_decorator_evicts_least_recently_used_entries() {
+    let mut cache = CachingConverterDecorator::<String, u32>::new().with_capacity(2);
+    cache.insert("first".to_string(), 1);
+    cache.insert("second".to_string(), 2);
+    assert_eq!(Some(1), cache.get(&"first".to_string()));
+
+    cache.insert("third".to_string(), 3);
+    assert_eq!(None, cache.get(&"second".to_string()));
+    assert_eq!(Some(1), cache.get(&"first".to_string()));
+
+    cache.insert("again".to_string(), 3);
+    assert_eq!(None, cache.get(&"third".to_string()));
+    cache.invalidate_value(&3);
+    assert_eq!(None, cache.get(&"again".to_string()));
+    assert_eq!(Some(1), cache.get(&"first".to_string()));
+    assert_eq!(
+        CacheStats {
+            hits: 3,
+            misses: 3,
+            evictions: 2,
+            invalidations: 1,
+        },
+        cache.stats()
+    );
+}
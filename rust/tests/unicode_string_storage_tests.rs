@@ -1,5 +1,6 @@
 use anyhow::Result;
-use link_cli::{external_reference, LinkStorage, NamedLinks, UnicodeStringStorage};
+use link_cli::sequences::UnicodeSymbolEncoding;
+use link_cli::{external_reference, LinkStorage, NameSearch, NamedLinks, UnicodeStringStorage};
 use tempfile::NamedTempFile;
 
 fn with_storage(test: impl FnOnce(&mut UnicodeStringStorage<'_>) -> Result<()>) -> Result<()> {
@@ -52,6 +53,92 @@ fn create_and_retrieve_unicode_string_as_utf16_sequence() -> Result<()> {
     })
 }
 
+#[test]
+fn code_point_encoding_stores_one_symbol_per_character() -> Result<()> {
+    let temp_file = NamedTempFile::new()?;
+    let db_path = temp_file.path().to_str().unwrap();
+    let mut links = LinkStorage::new(db_path, false)?;
+    let mut storage = UnicodeStringStorage::new(&mut links)?;
+    assert_eq!(UnicodeSymbolEncoding::Utf16, storage.symbol_encoding());
+    let plain = storage.create_string("plain")?;
+
+    storage.set_symbol_encoding(UnicodeSymbolEncoding::CodePoints)?;
+    let content = "smile 😀";
+    let link = storage.create_string(content)?;
+    assert_eq!(content, storage.get_string(link)?);
+    assert_eq!(
+        content.chars().map(u32::from).collect::<Vec<_>>(),
+        storage.unicode_sequence_code_points(link)?
+    );
+    assert_eq!(
+        content.encode_utf16().collect::<Vec<_>>(),
+        storage.unicode_sequence_code_units(link)?
+    );
+    assert_eq!(plain, storage.create_string("plain")?);
+
+    let error = storage
+        .set_symbol_encoding(UnicodeSymbolEncoding::Utf16)
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("would be stored differently as utf-16"));
+
+    let mut storage = UnicodeStringStorage::new(&mut links)?;
+    assert_eq!(UnicodeSymbolEncoding::CodePoints, storage.symbol_encoding());
+    assert_eq!(link, storage.create_string(content)?);
+    Ok(())
+}
+
+#[test]
+fn nfc_encoding_stores_composed_strings() -> Result<()> {
+    with_storage(|storage| {
+        storage.set_symbol_encoding(UnicodeSymbolEncoding::NfcCodePoints)?;
+        let decomposed = storage.create_string("cafe\u{301}")?;
+
+        assert_eq!(decomposed, storage.create_string("caf\u{e9}")?);
+        assert_eq!("caf\u{e9}", storage.get_string(decomposed)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn deleted_sequences_are_not_returned_from_the_cache() -> Result<()> {
+    with_storage(|storage| {
+        let string = storage.create_string("gone")?;
+        let sequence = storage.links().get(string).unwrap().target;
+        storage.links_mut().delete(string)?;
+        storage.links_mut().delete(sequence)?;
+
+        let recreated = storage.create_string("gone")?;
+        assert!(storage
+            .links()
+            .exists(storage.links().get(recreated).unwrap().target));
+        assert_eq!("gone", storage.get_string(recreated)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn strings_containing_text_are_found() -> Result<()> {
+    with_storage(|storage| {
+        let banana = storage.create_string("banana")?;
+        let bandana = storage.create_string("bandana")?;
+        let cabana = storage.create_string("cabana")?;
+        let empty = storage.create_string("")?;
+
+        assert_eq!(
+            vec![banana, bandana, cabana],
+            storage.strings_containing("ana")
+        );
+        assert_eq!(vec![banana, cabana], storage.strings_containing("bana"));
+        assert_eq!(vec![bandana], storage.strings_containing("nda"));
+        assert!(storage.strings_containing("nab").is_empty());
+        assert!(storage.strings_containing("xyz").is_empty());
+        assert!(storage.strings_containing("").contains(&empty));
+        Ok(())
+    })
+}
+
 #[test]
 fn pinned_types_are_created_and_named() -> Result<()> {
     with_storage(|storage| {
@@ -168,6 +255,63 @@ fn name_is_removed_when_external_reference_is_deleted() -> Result<()> {
     })
 }
 
+#[test]
+fn names_are_searched_by_prefix_and_substring() -> Result<()> {
+    with_storage(|storage| {
+        let names = ["father", "mother", "Fatherland", "grandfather", "f", "fat"];
+        for (link, name) in (1..).zip(names) {
+            storage.set_name_for_external_reference(link, name)?;
+        }
+        storage.add_alias_for_external_reference(2, "mom")?;
+        let found = |storage: &UnicodeStringStorage<'_>, search: NameSearch| -> Result<Vec<u32>> {
+            Ok(storage
+                .search_names(&search)?
+                .into_iter()
+                .map(|(link, _)| link)
+                .collect())
+        };
+        let links = |ids: &[u32]| {
+            let mut links = ids
+                .iter()
+                .map(|&id| external_reference(id))
+                .collect::<Vec<_>>();
+            links.sort();
+            links
+        };
+
+        assert_eq!(found(storage, NameSearch::prefix("f"))?, links(&[1, 5, 6]));
+        assert_eq!(found(storage, NameSearch::prefix("fat"))?, links(&[1, 6]));
+        assert_eq!(
+            found(storage, NameSearch::prefix("FAT").with_ignore_case(true))?,
+            links(&[1, 3, 6])
+        );
+        assert_eq!(
+            storage.search_names(&NameSearch::prefix("mo"))?,
+            vec![
+                (external_reference(2), "mom".to_string()),
+                (external_reference(2), "mother".to_string())
+            ]
+        );
+        assert_eq!(
+            found(storage, NameSearch::substring("ther"))?,
+            links(&[1, 2, 3, 4])
+        );
+        assert_eq!(
+            found(
+                storage,
+                NameSearch::substring("LAND").with_ignore_case(true)
+            )?,
+            links(&[3])
+        );
+        assert!(found(storage, NameSearch::prefix("fatherly"))?.is_empty());
+        assert!(found(storage, NameSearch::substring("xyz"))?.is_empty());
+
+        storage.remove_name_by_external_reference(6)?;
+        assert_eq!(found(storage, NameSearch::prefix("fat"))?, links(&[1]));
+        Ok(())
+    })
+}
+
 #[test]
 fn named_links_facade_matches_csharp_named_links_role() -> Result<()> {
     let temp_file = NamedTempFile::new()?;
@@ -250,7 +250,10 @@ impl NamedTypeLinks for BrowserStorage {
     }
 
     fn update(&mut self, id: u32, source: u32, target: u32) -> Result<Link> {
-        let link = self.links.get_mut(&id).ok_or(LinkError::NotFound(id))?;
+        let link = self
+            .links
+            .get_mut(&id)
+            .ok_or(LinkError::NotFound(id.into()))?;
         let before = *link;
         link.source = source;
         link.target = target;
@@ -259,7 +262,9 @@ impl NamedTypeLinks for BrowserStorage {
 
     fn delete(&mut self, id: u32) -> Result<Link> {
         self.remove_name(id)?;
-        self.links.remove(&id).ok_or(LinkError::NotFound(id).into())
+        self.links
+            .remove(&id)
+            .ok_or(LinkError::NotFound(id.into()).into())
     }
 
     fn all_links(&mut self) -> Vec<Link> {